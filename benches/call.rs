@@ -0,0 +1,68 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/benches/call.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// Compares calling a loaded function through `Symbol::with`, as the `snek!`
+// macro used to on every call, with calling through the function pointer it
+// now stores at load time. Run with `cargo bench --bench call`.
+
+#[macro_use]
+extern crate snek;
+
+use snek::Snek;
+
+use std::hint::black_box;
+use std::os::raw::c_int;
+use std::time::{Duration, Instant};
+
+include!("../tests/common/fixture.rs");
+
+const CALLS: u32 = 10_000_000;
+
+snek! {
+    Fixture {
+        add: (a: c_int, b: c_int) -> c_int
+    }
+}
+
+fn time<F>(name: &str, mut f: F) where F: FnMut(c_int) -> c_int {
+    // Warm up before timing
+    for i in 0..CALLS as c_int / 10 {
+        black_box(f(black_box(i)));
+    }
+
+    let start = Instant::now();
+    for i in 0..CALLS as c_int {
+        black_box(f(black_box(i)));
+    }
+
+    let elapsed = start.elapsed();
+    println!("{:<24} {:>8.2} ns/call", name, nanos(elapsed) / f64::from(CALLS));
+}
+
+fn nanos(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1e9 + f64::from(duration.subsec_nanos())
+}
+
+fn main() {
+    let snek = Snek::load(fixture()).unwrap();
+    let symbol = snek.symbol("add").unwrap();
+    time("Symbol::with", |i| unsafe { symbol.with(|add: extern "C" fn(c_int, c_int) -> c_int| add(i, 1)) });
+
+    let fixture = Fixture::load(fixture()).unwrap();
+    time("snek! method", |i| unsafe { fixture.add(i, 1) });
+}
@@ -0,0 +1,66 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/benches/lookup.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// Compares looking a symbol up by a `&str`, which is copied to add a NUL
+// unless it already ends with one, with looking it up by a `&CStr`, which is
+// passed to the platform as it is, with and without the symbol cache. Run
+// with `cargo bench --bench lookup`.
+
+extern crate snek;
+
+use snek::{LoadOptions, Snek, SymbolName};
+
+use std::ffi::CStr;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+include!("../tests/common/fixture.rs");
+
+const LOOKUPS: u32 = 1_000_000;
+
+fn time<N>(name: &str, snek: &Snek, symbol: N) where N: SymbolName + Copy {
+    // Warm up before timing
+    for _ in 0..LOOKUPS / 10 {
+        black_box(snek.symbol(black_box(symbol)).unwrap());
+    }
+
+    let start = Instant::now();
+    for _ in 0..LOOKUPS {
+        black_box(snek.symbol(black_box(symbol)).unwrap());
+    }
+
+    let elapsed = start.elapsed();
+    println!("{:<24} {:>8.2} ns/lookup", name, nanos(elapsed) / f64::from(LOOKUPS));
+}
+
+fn nanos(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1e9 + f64::from(duration.subsec_nanos())
+}
+
+fn main() {
+    let name = CStr::from_bytes_with_nul(b"add\0").unwrap();
+
+    let uncached = LoadOptions::new().cache_symbols(false).load(fixture()).unwrap();
+    time("uncached &str", &uncached, "add");
+    time("uncached &str with NUL", &uncached, "add\0");
+    time("uncached &CStr", &uncached, name);
+
+    let cached = Snek::load(fixture()).unwrap();
+    time("cached &str", &cached, "add");
+    time("cached &CStr", &cached, name);
+}
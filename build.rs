@@ -0,0 +1,39 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/build.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::env;
+
+fn main() {
+    // The tests look up functions the test binaries define through the
+    // process's own handle, which only finds those the executable exports
+    let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    let target_env = env::var("CARGO_CFG_TARGET_ENV").unwrap_or_default();
+
+    // MSVC's linker has no flag to export everything, and a flag naming a
+    // function would reach every test binary, so tests/current.rs asks for
+    // its own export instead
+    if target_os == "windows" && target_env == "msvc" {
+        return;
+    }
+
+    if target_os == "windows" {
+        println!("cargo:rustc-link-arg-tests=-Wl,--export-all-symbols");
+    } else if target_os != "macos" && target_os != "ios" && env::var("CARGO_CFG_UNIX").is_ok() {
+        println!("cargo:rustc-link-arg-tests=-rdynamic");
+    }
+}
@@ -0,0 +1,79 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/fixture/build.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    // Replace NULL_SYMBOL's address with zero, which dlsym returns as it is
+    if env::var("CARGO_CFG_TARGET_OS").map(|os| os == "linux").unwrap_or(false) {
+        println!("cargo:rustc-cdylib-link-arg=-Wl,--defsym=NULL_SYMBOL=0");
+    }
+
+    if env::var("CARGO_CFG_TARGET_OS").map(|os| os == "windows").unwrap_or(false) {
+        version_resource();
+    }
+}
+
+/// Compiles version.rc and links it into the library, with the resource
+/// compiler of whichever toolchain is in use, which can be overridden with
+/// `RC`. Without one the library is built without the resource, so that the
+/// other tests can still run, and only the version tests fail.
+fn version_resource() {
+    let manifest = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let script = Path::new(&manifest).join("version.rc");
+    let out = env::var("OUT_DIR").unwrap();
+    println!("cargo:rerun-if-changed={}", script.display());
+    println!("cargo:rerun-if-env-changed=RC");
+
+    let msvc = env::var("CARGO_CFG_TARGET_ENV").map(|target_env| target_env == "msvc").unwrap_or(false);
+    let (compiler, output) = if msvc {
+        ("rc", Path::new(&out).join("version.res"))
+    } else {
+        ("windres", Path::new(&out).join("version.o"))
+    };
+
+    // MinGW's tools are prefixed by their target when cross compiling
+    let cross = env::var("HOST").map(|host| !host.contains("windows")).unwrap_or(false);
+    let compiler = match env::var("RC") {
+        Ok(compiler) => compiler,
+        Err(_) if cross && !msvc => {
+            let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap();
+            format!("{}-w64-mingw32-{}", if arch == "x86" { "i686" } else { &arch }, compiler)
+        },
+        Err(_) => compiler.to_owned()
+    };
+
+    let mut command = Command::new(&compiler);
+    if msvc {
+        command.arg("/nologo").arg("/fo").arg(&output).arg(&script);
+    } else {
+        command.arg("--input").arg(&script).arg("--output-format=coff").arg("--output").arg(&output);
+    }
+
+    match command.status() {
+        Ok(status) => assert!(status.success(), "failed to compile {}", script.display()),
+        Err(err) => {
+            println!("cargo:warning=building without a version resource, as {} could not be run ({}); set RC to the resource compiler", compiler, err);
+            return;
+        }
+    }
+
+    println!("cargo:rustc-cdylib-link-arg={}", output.display());
+}
@@ -0,0 +1,42 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/fixture/consumer/build.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::env;
+use std::path::Path;
+
+fn main() {
+    // The macOS linker refuses undefined symbols in a library unless told
+    // they will be found when it is loaded
+    if env::var("CARGO_CFG_TARGET_OS").map(|os| os == "macos").unwrap_or(false) {
+        println!("cargo:rustc-cdylib-link-arg=-undefined");
+        println!("cargo:rustc-cdylib-link-arg=dynamic_lookup");
+    }
+
+    // A Windows library cannot be linked with undefined symbols, so there it
+    // imports add from the fixture, built first by the tests, making the
+    // fixture a dependency which has to be found when it is loaded
+    if env::var("CARGO_CFG_TARGET_OS").map(|os| os == "windows").unwrap_or(false) {
+        let manifest = env::var("CARGO_MANIFEST_DIR").unwrap();
+        let fixture = Path::new(&manifest).join("../../target/fixture/debug");
+        println!("cargo:rustc-link-search=native={}", fixture.display());
+
+        // MSVC names the import library after the whole file name
+        let msvc = env::var("CARGO_CFG_TARGET_ENV").map(|target_env| target_env == "msvc").unwrap_or(false);
+        println!("cargo:rustc-link-lib=dylib={}", if msvc { "snek_test_fixture.dll" } else { "snek_test_fixture" });
+    }
+}
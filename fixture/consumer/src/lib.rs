@@ -0,0 +1,35 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/fixture/consumer/lib.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! A library for the snek integration tests which uses a symbol it does not
+//! define, `add` from `snek-test-fixture`, so that on unix it can only be
+//! loaded once the fixture has been loaded with its symbols made global. On
+//! Windows it imports `add` from the fixture, so that it can only be loaded
+//! once the fixture can be found.
+
+use std::os::raw::c_int;
+
+extern "C" {
+    fn add(a: c_int, b: c_int) -> c_int;
+}
+
+/// Calls `add` from the fixture, doubling the result.
+#[no_mangle]
+pub extern "C" fn add_twice(a: c_int, b: c_int) -> c_int {
+    unsafe { add(a, b) * 2 }
+}
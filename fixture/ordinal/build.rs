@@ -0,0 +1,37 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/fixture/ordinal/build.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::env;
+use std::path::Path;
+
+fn main() {
+    // The module definition file gives ordinal_add a fixed ordinal, rather
+    // than whichever the linker would choose
+    if env::var("CARGO_CFG_TARGET_OS").map(|os| os == "windows").unwrap_or(false) {
+        let manifest = env::var("CARGO_MANIFEST_DIR").unwrap();
+        let def = Path::new(&manifest).join("ordinal.def");
+        println!("cargo:rerun-if-changed={}", def.display());
+
+        let msvc = env::var("CARGO_CFG_TARGET_ENV").map(|target_env| target_env == "msvc").unwrap_or(false);
+        if msvc {
+            println!("cargo:rustc-cdylib-link-arg=/DEF:{}", def.display());
+        } else {
+            println!("cargo:rustc-cdylib-link-arg={}", def.display());
+        }
+    }
+}
@@ -0,0 +1,28 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/fixture/ordinal/src/lib.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! A library for the snek integration tests which on Windows exports its
+//! function at a fixed ordinal, as given in `ordinal.def`.
+
+use std::os::raw::c_int;
+
+/// Adds two numbers, exported at ordinal 42 on Windows.
+#[no_mangle]
+pub extern "C" fn ordinal_add(a: c_int, b: c_int) -> c_int {
+    a + b
+}
@@ -0,0 +1,233 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/fixture/lib.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! A small library exporting symbols for the snek integration tests to load.
+//! It is built by the tests themselves, so it is not part of the published
+//! crate.
+
+#[macro_use]
+extern crate snek;
+
+use std::ffi::{CStr, CString};
+use std::ptr;
+use std::os::raw::{c_char, c_int};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+declare_plugin_info! {
+    name: "fixture",
+    version: [0, 2, 0],
+    author: "snek",
+    capabilities: 0
+}
+
+/// A data symbol.
+#[no_mangle]
+pub static ANSWER: c_int = 42;
+
+#[no_mangle]
+pub extern "C" fn add(a: c_int, b: c_int) -> c_int {
+    a + b
+}
+
+/// Uses the system calling convention, which is `__stdcall` on 32-bit
+/// Windows and the C convention everywhere else.
+#[no_mangle]
+pub extern "system" fn multiply(a: c_int, b: c_int) -> c_int {
+    a * b
+}
+
+/// Exported under the decorated name a 32-bit MinGW build gives a
+/// `__stdcall` function, which rustc itself leaves undecorated in its exports.
+/// The convention is kept plain, as only the name matters to the lookup.
+#[cfg(all(windows, target_arch = "x86"))]
+#[export_name = "add_stdcall@8"]
+pub extern "C" fn add_stdcall(a: c_int, b: c_int) -> c_int {
+    a + b
+}
+
+/// Returns a static NUL-terminated greeting.
+#[no_mangle]
+pub extern "C" fn hello() -> *const c_char {
+    b"hello\0".as_ptr() as *const c_char
+}
+
+/// Returns the length of a NUL-terminated string.
+///
+/// # Safety
+/// The pointer must be to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn string_length(string: *const c_char) -> usize {
+    CStr::from_ptr(string).to_bytes().len()
+}
+
+static FREED: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns a newly allocated name for the given id, to be freed with
+/// `free_string`: null for 0, and invalid UTF-8 for 2.
+#[no_mangle]
+pub extern "C" fn name_of(id: c_int) -> *mut c_char {
+    let name: &[u8] = match id {
+        0 => return ptr::null_mut(),
+        2 => b"tw\xffo",
+        _ => b"one"
+    };
+
+    CString::new(name).unwrap().into_raw()
+}
+
+/// Frees a string returned from `name_of`.
+///
+/// # Safety
+/// The pointer must be null or returned from `name_of`, and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn free_string(string: *mut c_char) {
+    if !string.is_null() {
+        FREED.fetch_add(1, Ordering::SeqCst);
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Returns the number of strings freed with `free_string`.
+#[no_mangle]
+pub extern "C" fn freed_strings() -> usize {
+    FREED.load(Ordering::SeqCst)
+}
+
+/// Returns the number of times this has been called since the library was
+/// loaded, which starts again from one if the library is really unloaded.
+#[no_mangle]
+pub extern "C" fn count() -> usize {
+    CALLS.fetch_add(1, Ordering::SeqCst) + 1
+}
+
+static INITS: AtomicUsize = AtomicUsize::new(0);
+static FINIS: AtomicUsize = AtomicUsize::new(0);
+
+/// An init function, which counts how many times it has been called.
+#[no_mangle]
+pub extern "C" fn fixture_init() -> c_int {
+    INITS.fetch_add(1, Ordering::SeqCst);
+    0
+}
+
+/// An init function which always fails.
+#[no_mangle]
+pub extern "C" fn fixture_init_fails() -> c_int {
+    3
+}
+
+/// A fini function, which counts how many times it has been called.
+#[no_mangle]
+pub extern "C" fn fixture_fini() {
+    FINIS.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Returns the number of times `fixture_init` has been called.
+#[no_mangle]
+pub extern "C" fn init_calls() -> usize {
+    INITS.load(Ordering::SeqCst)
+}
+
+/// Returns the number of times `fixture_fini` has been called.
+#[no_mangle]
+pub extern "C" fn fini_calls() -> usize {
+    FINIS.load(Ordering::SeqCst)
+}
+
+/// Returns the version of the library the fixture was built as.
+#[no_mangle]
+pub extern "C" fn version() -> c_int {
+    if cfg!(feature = "v1") { 1 } else { 2 }
+}
+
+/// A symbol which only exists in the second version of the library.
+#[cfg(not(feature = "v1"))]
+#[no_mangle]
+pub extern "C" fn added_in_v2() -> c_int {
+    2
+}
+
+/// A thread-local variable. Stable Rust cannot declare one to export, so this
+/// is an ordinary static placed in the thread-local data section, and must only
+/// be reached through `fixture_errno_address`.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[no_mangle]
+#[link_section = ".tdata"]
+#[allow(non_upper_case_globals)]
+pub static fixture_errno: c_int = 0;
+
+// Returns the calling thread's instance of `fixture_errno` as the library's
+// own code finds it
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+::std::arch::global_asm!(
+    ".text",
+    ".type fixture_errno_address, @function",
+    "fixture_errno_address:",
+    "pushq %rbp",
+    ".byte 0x66",
+    "leaq fixture_errno@tlsgd(%rip), %rdi",
+    ".word 0x6666",
+    "rex64",
+    "call __tls_get_addr@PLT",
+    "popq %rbp",
+    "ret",
+    ".size fixture_errno_address, . - fixture_errno_address",
+    options(att_syntax)
+);
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+extern "C" {
+    fn fixture_errno_address() -> *mut c_int;
+}
+
+/// Sets the calling thread's `fixture_errno`.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[no_mangle]
+pub extern "C" fn set_errno(value: c_int) {
+    unsafe { *fixture_errno_address() = value }
+}
+
+/// Panics, which aborts the process as the panic cannot unwind out of an
+/// `extern "C"` function.
+#[no_mangle]
+pub extern "C" fn panics() {
+    panic!("snek-test-fixture panicked on purpose");
+}
+
+/// Command handlers, found by their shared prefix.
+#[no_mangle]
+pub extern "C" fn cmd_start() -> c_int {
+    1
+}
+
+#[no_mangle]
+pub extern "C" fn cmd_stop() -> c_int {
+    2
+}
+
+#[no_mangle]
+pub extern "C" fn cmd_status() -> c_int {
+    3
+}
+
+/// Exported with the null address on Linux, where the build script defines it
+/// as an absolute symbol, much as an undefined weak symbol appears.
+#[no_mangle]
+pub static NULL_SYMBOL: u8 = 0;
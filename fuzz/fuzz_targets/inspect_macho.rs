@@ -0,0 +1,39 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/fuzz/fuzz_targets/inspect_macho.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+#![no_main]
+
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate snek;
+
+// The first input byte picks a thin or universal magic to prefix, so that
+// every input reaches one of the Mach-O parsers
+const MAGICS: [&[u8]; 2] = [&[0xcf, 0xfa, 0xed, 0xfe], &[0xca, 0xfe, 0xba, 0xbe]];
+
+fuzz_target!(|data: &[u8]| {
+    let (choice, data) = match data.split_first() {
+        Some((&choice, data)) => (choice, data),
+        None => return
+    };
+
+    let mut file = MAGICS[(choice & 1) as usize].to_vec();
+    file.extend_from_slice(data);
+
+    let _ = snek::inspect::inspect(&file);
+});
@@ -0,0 +1,33 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/fuzz/fuzz_targets/inspect_pe.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+#![no_main]
+
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate snek;
+
+// Prefix the MZ magic so that every input reaches the PE parser
+const MAGIC: &[u8] = b"MZ";
+
+fuzz_target!(|data: &[u8]| {
+    let mut file = MAGIC.to_vec();
+    file.extend_from_slice(data);
+
+    let _ = snek::inspect::inspect(&file);
+});
@@ -0,0 +1,121 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/address.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::snek::backend;
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use libc::c_void;
+
+/// Find the library containing an address, and the nearest symbol before it,
+/// such as for turning the instruction pointers in a crash report into
+/// something readable. This works for addresses in any library loaded by the
+/// process, whether by a [`Snek`](struct.Snek.html) or not, and in the
+/// executable itself.
+///
+/// * On unix, this uses `dladdr`, which only knows the symbols a library
+///   exports, so an address in a function it does not export is reported
+///   from the nearest exported one before it, or from the library's base.
+/// * On Windows, this uses DbgHelp's `SymFromAddrW`, which also finds the
+///   symbols in any program database found beside the module.
+///
+/// Returns `None` if the address is not in any loaded library.
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::Snek;
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+/// # fn main() {
+/// # let libexample = fixture();
+/// let snek = Snek::load(libexample).unwrap();
+/// let add = snek.symbol("add").unwrap();
+///
+/// let info = snek::resolve_address(add.as_ptr()).unwrap();
+/// assert_eq!(info.symbol(), Some("add"));
+/// assert_eq!(info.offset(), 0);
+/// println!("{}", info);
+/// # }
+/// ```
+pub fn resolve_address(address: *const c_void) -> Option<AddressInfo> {
+    backend::resolve_address(address)
+}
+
+/// The library and symbol an address belongs to, returned from
+/// [`resolve_address`](fn.resolve_address.html).
+///
+/// This formats as the library's path followed by the symbol and the offset
+/// from it, such as `/usr/lib/libexample.so!example_init+0x1c`, or by the
+/// offset from the library's base if no symbol was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressInfo {
+    path: PathBuf,
+    base: usize,
+    symbol: Option<(String, usize)>,
+    address: usize
+}
+
+impl AddressInfo {
+    #[cfg_attr(not(any(unix, windows)), allow(dead_code))]
+    pub(crate) fn new(path: PathBuf, base: *const c_void, symbol: Option<(String, *const c_void)>, address: *const c_void) -> AddressInfo {
+        AddressInfo {
+            path,
+            base: base as usize,
+            symbol: symbol.map(|(name, address)| (name, address as usize)),
+            address: address as usize
+        }
+    }
+
+    /// Returns the path of the library containing the address, as the
+    /// platform reports it.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the address the library is loaded at.
+    pub fn base(&self) -> *const c_void {
+        self.base as *const c_void
+    }
+
+    /// Returns the name of the nearest symbol at or before the address, if
+    /// one was found.
+    pub fn symbol(&self) -> Option<&str> {
+        self.symbol.as_ref().map(|(name, _)| name.as_str())
+    }
+
+    /// Returns the address of the nearest symbol, if one was found.
+    pub fn symbol_address(&self) -> Option<*const c_void> {
+        self.symbol.as_ref().map(|&(_, address)| address as *const c_void)
+    }
+
+    /// Returns the offset of the address from the nearest symbol, or from the
+    /// library's base if no symbol was found.
+    pub fn offset(&self) -> usize {
+        let start = self.symbol.as_ref().map(|&(_, address)| address).unwrap_or(self.base);
+        self.address.wrapping_sub(start)
+    }
+}
+
+impl fmt::Display for AddressInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.symbol() {
+            Some(symbol) => write!(f, "{}!{}+{:#x}", self.path.display(), symbol, self.offset()),
+            None => write!(f, "{}+{:#x}", self.path.display(), self.offset())
+        }
+    }
+}
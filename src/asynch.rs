@@ -0,0 +1,162 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/asynch.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! This module provides versions of the library loading functions which run
+//! the load on tokio's blocking thread pool, so that a slow load does not
+//! stall the executor. This requires the `tokio` feature.
+//!
+//! Loading a library can block for a long time, such as while the file is
+//! read from a network filesystem or while its initialisers run, so calling
+//! [`Snek::load`](../struct.Snek.html#method.load) directly from an async task
+//! holds up every other task on the same worker thread.
+//!
+//! # Cancellation
+//! The load starts when the returned [`Load`](struct.Load.html) is first
+//! polled, and nothing is loaded if it is dropped before then. Once a load
+//! has started it cannot be interrupted, so if the future is dropped it runs
+//! to completion on the blocking thread anyway, and the library is then
+//! unloaded again on that thread.
+//!
+//! # Threads
+//! The library is loaded on a blocking thread and the resulting
+//! [`Snek`](../struct.Snek.html) is handed back to the task awaiting it. Both
+//! the [`Load`](struct.Load.html) future and the `Snek` it resolves to are
+//! `Send`, so the `Snek` can be held across an `.await` in a task spawned with
+//! `tokio::spawn`.
+//!
+//! # Example
+//! ```
+//! # extern crate snek;
+//! # extern crate tokio;
+//! # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+//! # fn main() {
+//! # let libexample = fixture();
+//! let runtime = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+//! let snek = runtime.block_on(snek::asynch::load(libexample)).unwrap();
+//! # assert!(snek.symbol("add").is_ok());
+//! # }
+//! ```
+
+use ::{Error, LoadOptions, Snek};
+use ::failure::{Class, Failure};
+
+use std::fmt;
+use std::pin::Pin;
+use std::future::Future;
+use std::path::Path;
+use std::task::{Context, Poll};
+
+use tokio::task::{self, JoinHandle};
+
+/// Load a dynamic library on the blocking thread pool, as with
+/// [`Snek::load`](../struct.Snek.html#method.load).
+///
+/// The returned future must be polled from within a tokio runtime.
+pub fn load<P>(path: P) -> Load where P: AsRef<Path> {
+    load_with(path, &LoadOptions::new())
+}
+
+/// Load a dynamic library from the first of the given paths which loads, on
+/// the blocking thread pool, as with [`Snek::load_any`](../struct.Snek.html#method.load_any).
+///
+/// The returned future must be polled from within a tokio runtime.
+pub fn load_any<I, P>(paths: I) -> Load where I: IntoIterator<Item = P>, P: AsRef<Path> {
+    let paths = paths.into_iter().map(|path| path.as_ref().to_owned()).collect::<Vec<_>>();
+    Load::new(move || LoadOptions::new().load_any(paths))
+}
+
+/// Load a dynamic library on the blocking thread pool with the given options,
+/// as with [`LoadOptions::load`](../struct.LoadOptions.html#method.load).
+///
+/// The returned future must be polled from within a tokio runtime.
+pub fn load_with<P>(path: P, options: &LoadOptions) -> Load where P: AsRef<Path> {
+    let path = path.as_ref().to_owned();
+    let options = options.clone();
+    Load::new(move || options.load(path))
+}
+
+/// A future which resolves to a library loaded on the blocking thread pool,
+/// returned by the functions in this module.
+///
+/// See the [module documentation](index.html#cancellation) for what happens
+/// when this is dropped before it completes.
+pub struct Load {
+    state: State
+}
+
+enum State {
+    Start(Box<dyn FnOnce() -> Result<Snek, Error> + Send>),
+    Running(JoinHandle<Result<Snek, Error>>),
+    Finished
+}
+
+impl Load {
+    fn new<F>(load: F) -> Load where F: FnOnce() -> Result<Snek, Error> + Send + 'static {
+        Load {
+            state: State::Start(Box::new(load))
+        }
+    }
+}
+
+impl Future for Load {
+    type Output = Result<Snek, Error>;
+
+    fn poll(self: Pin<&mut Load>, cx: &mut Context) -> Poll<Result<Snek, Error>> {
+        let this = self.get_mut();
+
+        loop {
+            match this.state {
+                State::Start(_) => {
+                    let load = match ::std::mem::replace(&mut this.state, State::Finished) {
+                        State::Start(load) => load,
+                        _ => unreachable!()
+                    };
+
+                    this.state = State::Running(task::spawn_blocking(load));
+                },
+
+                State::Running(ref mut handle) => {
+                    let result = match Pin::new(handle).poll(cx) {
+                        Poll::Pending => return Poll::Pending,
+                        Poll::Ready(Ok(result)) => result,
+                        Poll::Ready(Err(err)) => Err(Error::LibraryLoadError(
+                            Failure::new(format!("the load task failed: {}", err), None, Class::Other)
+                        ))
+                    };
+
+                    this.state = State::Finished;
+                    return Poll::Ready(result);
+                },
+
+                State::Finished => panic!("Load polled after it completed")
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Load {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = match self.state {
+            State::Start(_) => "Start",
+            State::Running(_) => "Running",
+            State::Finished => "Finished"
+        };
+
+        f.debug_struct("Load").field("state", &state).finish()
+    }
+}
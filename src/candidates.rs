@@ -0,0 +1,109 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/candidates.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::Error;
+
+use std::fmt;
+use std::slice;
+use std::path::{Path, PathBuf};
+
+/// A single path which was tried while loading a library from several
+/// candidates, and the reason it could not be loaded.
+#[derive(Debug)]
+pub struct Candidate {
+    path: PathBuf,
+    error: Error
+}
+
+impl Candidate {
+    /// Returns the path which was tried.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the reason the path could not be loaded.
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+}
+
+/// The failures of every candidate path tried by a load which tries several
+/// paths in turn, such as [`Snek::load_any`](struct.Snek.html#method.load_any),
+/// in the order they were tried. This is held by
+/// [`Error::CandidatesError`](enum.Error.html).
+///
+/// Formatting this lists each candidate on its own line, along with the
+/// reason it failed.
+#[derive(Debug, Default)]
+pub struct CandidateErrors {
+    candidates: Vec<Candidate>
+}
+
+impl CandidateErrors {
+    pub(crate) fn new() -> CandidateErrors {
+        CandidateErrors::default()
+    }
+
+    pub(crate) fn push(&mut self, path: PathBuf, error: Error) {
+        self.candidates.push(Candidate { path, error });
+    }
+
+    /// Returns the number of candidates which were tried.
+    pub fn len(&self) -> usize {
+        self.candidates.len()
+    }
+
+    /// Returns true if there were no candidates to try.
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+
+    /// Returns an iterator over the candidates in the order they were tried.
+    pub fn iter<'a>(&'a self) -> slice::Iter<'a, Candidate> {
+        self.candidates.iter()
+    }
+
+    /// Returns the paths of the candidates in the order they were tried.
+    pub fn paths(&self) -> Vec<&Path> {
+        self.candidates.iter().map(Candidate::path).collect()
+    }
+}
+
+impl<'a> IntoIterator for &'a CandidateErrors {
+    type Item = &'a Candidate;
+    type IntoIter = slice::Iter<'a, Candidate>;
+
+    fn into_iter(self) -> slice::Iter<'a, Candidate> {
+        self.iter()
+    }
+}
+
+impl fmt::Display for CandidateErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.candidates.is_empty() {
+            return f.write_str("no candidate paths were given");
+        }
+
+        write!(f, "none of {} candidate paths could be loaded:", self.candidates.len())?;
+        for candidate in &self.candidates {
+            write!(f, "\n    {}: {}", candidate.path.display(), candidate.error.message())?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/diagnostics.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! This module reads the platform's error for the last failed dynamic
+//! loading call, for code which makes its own calls to `dlopen` or
+//! `LoadLibrary` alongside this crate. The crate's own errors are built from
+//! the same [`OsError`](struct.OsError.html), so both report a failure the
+//! same way.
+//!
+//! # Example
+//! ```no_run
+//! # extern crate libc;
+//! # extern crate snek;
+//! # fn main() {
+//! # #[cfg(unix)]
+//! # {
+//! let handle = unsafe { libc::dlopen(b"libexample.so\0".as_ptr() as *const _, libc::RTLD_NOW) };
+//! if handle.is_null() {
+//!     println!("failed to load: {}", snek::diagnostics::last_os_error());
+//! }
+//! # }
+//! # }
+//! ```
+
+use ::failure::{Class, Failure};
+use ::snek;
+
+use std::fmt;
+
+/// The platform's error for a failed dynamic loading call, returned from
+/// [`last_os_error`](fn.last_os_error.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OsError {
+    code: Option<i32>,
+    message: String
+}
+
+impl OsError {
+    pub(crate) fn new<S>(code: Option<i32>, message: S) -> OsError where S: Into<String> {
+        OsError {
+            code,
+            message: message.into()
+        }
+    }
+
+    /// Returns the error code, which on Windows is the value of
+    /// `GetLastError()`. This is `None` on unix, where `dlerror()` only
+    /// reports a message.
+    pub fn code(&self) -> Option<i32> {
+        self.code
+    }
+
+    /// Returns the platform's message for the error. On Windows this ends
+    /// with the code, such as `(os error 126)`.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Convert an error from a load into the crate's own failure, classified
+    /// by its code where it has one, and otherwise as given.
+    #[cfg_attr(not(any(unix, windows)), allow(dead_code))]
+    pub(crate) fn into_failure(self, class: Class) -> Failure {
+        let class = self.code.map(snek::classify).unwrap_or(class);
+        Failure::new(self.message, self.code, class)
+    }
+}
+
+impl fmt::Display for OsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Returns the error from the last failed dynamic loading call made by the
+/// calling thread.
+///
+/// * On unix, this takes the message from `dlerror()`, which is cleared by
+///   reading it, so only the first call after a failure returns it.
+/// * On Windows, this formats the code from `GetLastError()`, which any later
+///   failing system call on the thread replaces, so this should be called
+///   straight after the failure.
+/// * On platforms without dynamic libraries, this returns an error saying so.
+pub fn last_os_error() -> OsError {
+    snek::last_error()
+}
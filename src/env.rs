@@ -0,0 +1,357 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/env.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! This module exposes information about the environment the platform's
+//! dynamic loader searches when a library is loaded by a bare file name.
+
+use std::env;
+use std::fs;
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+
+/// Returns the directories the platform's dynamic loader searches for a
+/// library loaded by a bare file name, in the order they are searched, as best
+/// as it can be determined from the current process.
+///
+/// Relative entries in search path variables are resolved against the current
+/// directory, and empty entries (which the loaders treat as the current
+/// directory) are included as the current directory. Each directory only
+/// appears once, at its first position.
+///
+/// * On Linux and other unix platforms, this is the directories in
+///   `LD_LIBRARY_PATH`, followed by those configured in `/etc/ld.so.conf` and
+///   the default library directories. Runpaths embedded in the executable are
+///   not included.
+/// * On macOS, this is the directories in `DYLD_LIBRARY_PATH`, followed by those
+///   in `DYLD_FALLBACK_LIBRARY_PATH`, or the default fallback directories if it
+///   is not set.
+/// * On Windows, this is the directory of the executable, the directory set by
+///   `SetDllDirectory` if any, the system and Windows directories, the current
+///   directory (unless `SetDllDirectory` has replaced it) and the directories in
+///   `PATH`. Directories added with `AddDllDirectory` cannot be queried, and so
+///   are not included.
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # fn main() {
+/// for directory in snek::env::search_paths() {
+///     println!("{}", directory.display());
+/// }
+/// # }
+/// ```
+pub fn search_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+
+    for path in platform_search_paths() {
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+
+    paths
+}
+
+/// Returns the paths at which a library with the given bare file name exists in
+/// the directories returned by [`search_paths`](fn.search_paths.html), in search
+/// order.
+pub fn locate<S>(name: S) -> Vec<PathBuf> where S: AsRef<OsStr> {
+    search_paths().into_iter()
+        .map(|directory| directory.join(name.as_ref()))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Returns true if the given path is a bare file name, which the dynamic loader
+/// looks for in its search paths rather than relative to the current directory.
+pub fn is_bare_name<P>(path: P) -> bool where P: AsRef<Path> {
+    let mut components = path.as_ref().components();
+
+    matches!((components.next(), components.next()), (Some(Component::Normal(_)), None))
+}
+
+/// Returns the file the dynamic loader would load for the given path, which
+/// is the first match in the search paths for a bare file name.
+pub(crate) fn library_file(path: &Path) -> Option<PathBuf> {
+    if is_bare_name(path) {
+        locate(path).into_iter().next()
+    } else {
+        Some(path.to_owned())
+    }
+}
+
+/// Returns the file names tried, in order, when loading a library by its
+/// logical name with [`Snek::load_name`](../struct.Snek.html#method.load_name),
+/// such as `foo` for `libfoo.so`. Versioned names are only included if they
+/// are found in the directories returned by [`search_paths`](fn.search_paths.html).
+///
+/// * On macOS, this is `libfoo.dylib`, then any versioned `libfoo.N.dylib`
+///   names, highest version first, then `libfoo.so` as installed by some
+///   ports, and finally `foo.framework/foo`, which the loader looks for in
+///   its framework directories.
+/// * On Windows, this is `foo.dll`, then `libfoo.dll` as built by MinGW.
+/// * On other unix platforms, this is `libfoo.so`, then any soname-versioned
+///   `libfoo.so.N` names, highest version first, as the unversioned name is
+///   usually only installed with a library's development package.
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # fn main() {
+/// for name in snek::env::library_names("z") {
+///     println!("{}", name.display());
+/// }
+/// # }
+/// ```
+pub fn library_names(name: &str) -> Vec<PathBuf> {
+    platform_library_names(name)
+}
+
+/// Returns the major versions of a library installed under soname-versioned
+/// names, such as 3 for `libfoo.so.3` or `libfoo.so.3.1.4`, found in the
+/// search paths for a bare file name or alongside it otherwise.
+pub(crate) fn installed_majors(path: &Path) -> Vec<u32> {
+    let prefix = match path.file_name().and_then(OsStr::to_str) {
+        Some(name) => format!("{}.", name),
+        None => return Vec::new()
+    };
+
+    let directories = if is_bare_name(path) {
+        search_paths()
+    } else {
+        vec![path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or_else(|| Path::new(".")).to_owned()]
+    };
+
+    directories.iter()
+        .filter_map(|directory| fs::read_dir(directory).ok())
+        .flat_map(|entries| entries.filter_map(Result::ok))
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let version = name.strip_prefix(&prefix)?;
+            version.split('.').next()?.parse::<u32>().ok()
+        })
+        .collect()
+}
+
+// Only used by the platforms with dynamic libraries
+#[cfg_attr(not(any(unix, windows)), allow(dead_code))]
+fn split_variable(name: &str) -> Vec<PathBuf> {
+    let value = match env::var_os(name) {
+        Some(value) => value,
+        None => return Vec::new()
+    };
+
+    let current = env::current_dir().ok();
+
+    env::split_paths(&value)
+        .filter_map(|path| match current {
+            Some(ref current) if path.as_os_str().is_empty() => Some(current.clone()),
+            Some(ref current) => Some(current.join(path)),
+            None if path.is_absolute() => Some(path),
+            None => None
+        })
+        .collect()
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn platform_search_paths() -> Vec<PathBuf> {
+    let mut paths = split_variable("LD_LIBRARY_PATH");
+
+    read_ld_so_conf(Path::new("/etc/ld.so.conf"), &mut paths, 0);
+
+    for default in &["/lib64", "/usr/lib64", "/lib", "/usr/lib"] {
+        let default = Path::new(default);
+        if default.is_dir() {
+            paths.push(default.to_path_buf());
+        }
+    }
+
+    paths
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn platform_library_names(name: &str) -> Vec<PathBuf> {
+    let unversioned = format!("lib{}.so", name);
+
+    let mut majors = installed_majors(Path::new(&unversioned));
+    majors.sort_unstable_by(|a, b| b.cmp(a));
+    majors.dedup();
+
+    let mut names = vec![PathBuf::from(&unversioned)];
+    names.extend(majors.into_iter().map(|major| PathBuf::from(format!("{}.{}", unversioned, major))));
+    names
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn read_ld_so_conf(path: &Path, paths: &mut Vec<PathBuf>, depth: usize) {
+    // Guards against include cycles in broken configurations
+    if depth > 8 {
+        return;
+    }
+
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return
+    };
+
+    for line in contents.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+
+        if line.starts_with("include") && line["include".len()..].starts_with(char::is_whitespace) {
+            for pattern in line["include".len()..].split_whitespace() {
+                let pattern = path.parent().unwrap_or(Path::new("/")).join(pattern);
+                for include in expand_glob(&pattern) {
+                    read_ld_so_conf(&include, paths, depth + 1);
+                }
+            }
+        } else if line.starts_with('/') {
+            paths.push(PathBuf::from(line));
+        }
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn expand_glob(pattern: &Path) -> Vec<PathBuf> {
+    let name = match pattern.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => return Vec::new()
+    };
+
+    if !name.contains('*') && !name.contains('?') {
+        return vec![pattern.to_path_buf()];
+    }
+
+    let directory = pattern.parent().unwrap_or(Path::new("/"));
+    let mut matches: Vec<PathBuf> = match fs::read_dir(directory) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.file_name()
+                .map(|file| ::scan::glob_match(&name, file))
+                .unwrap_or(false))
+            .collect(),
+
+        Err(_) => Vec::new()
+    };
+
+    matches.sort();
+    matches
+}
+
+#[cfg(target_os = "macos")]
+fn platform_search_paths() -> Vec<PathBuf> {
+    let mut paths = split_variable("DYLD_LIBRARY_PATH");
+
+    if env::var_os("DYLD_FALLBACK_LIBRARY_PATH").is_some() {
+        paths.extend(split_variable("DYLD_FALLBACK_LIBRARY_PATH"));
+    } else {
+        if let Some(home) = env::var_os("HOME") {
+            paths.push(Path::new(&home).join("lib"));
+        }
+
+        paths.push(PathBuf::from("/usr/local/lib"));
+        paths.push(PathBuf::from("/usr/lib"));
+    }
+
+    paths
+}
+
+#[cfg(target_os = "macos")]
+fn platform_library_names(name: &str) -> Vec<PathBuf> {
+    // Versioned names put the version before the extension, as in
+    // libfoo.3.dylib or libfoo.3.1.dylib
+    let prefix = format!("lib{}.", name);
+    let mut versioned = search_paths().iter()
+        .filter_map(|directory| fs::read_dir(directory).ok())
+        .flat_map(|entries| entries.filter_map(Result::ok))
+        .filter_map(|entry| {
+            let file = entry.file_name().into_string().ok()?;
+            let version = file.strip_prefix(&prefix)?.strip_suffix(".dylib")?;
+            let parts = version.split('.').map(|part| part.parse::<u32>().ok()).collect::<Option<Vec<_>>>()?;
+            Some((parts, file))
+        })
+        .collect::<Vec<_>>();
+
+    versioned.sort_unstable_by(|a, b| b.cmp(a));
+    versioned.dedup();
+
+    let mut names = vec![PathBuf::from(format!("lib{}.dylib", name))];
+    names.extend(versioned.into_iter().map(|(_, file)| PathBuf::from(file)));
+    names.push(PathBuf::from(format!("lib{}.so", name)));
+    names.push(Path::new(&format!("{}.framework", name)).join(name));
+    names
+}
+
+#[cfg(windows)]
+fn platform_library_names(name: &str) -> Vec<PathBuf> {
+    vec![PathBuf::from(format!("{}.dll", name)), PathBuf::from(format!("lib{}.dll", name))]
+}
+
+#[cfg(not(any(unix, windows)))]
+fn platform_library_names(name: &str) -> Vec<PathBuf> {
+    vec![PathBuf::from(name)]
+}
+
+#[cfg(windows)]
+fn platform_search_paths() -> Vec<PathBuf> {
+    use std::ffi::OsString;
+    use std::os::windows::ffi::OsStringExt;
+    use ::win32;
+
+    fn wide_path<F>(f: F) -> Option<PathBuf> where F: Fn(*mut u16, u32) -> u32 {
+        let mut buffer = vec![0u16; 260];
+
+        loop {
+            let length = f(buffer.as_mut_ptr(), buffer.len() as u32) as usize;
+
+            if length == 0 {
+                return None;
+            } else if length < buffer.len() {
+                return Some(PathBuf::from(OsString::from_wide(&buffer[..length])));
+            }
+
+            // The buffer was too small, and length is the size required
+            buffer.resize(length, 0);
+        }
+    }
+
+    let mut paths = Vec::new();
+
+    if let Some(directory) = env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)) {
+        paths.push(directory);
+    }
+
+    let dll_directory = wide_path(|buffer, length| unsafe { win32::GetDllDirectoryW(length, buffer) })
+        .filter(|directory| !directory.as_os_str().is_empty());
+
+    let replaces_current = dll_directory.is_some();
+    paths.extend(dll_directory);
+    paths.extend(wide_path(|buffer, length| unsafe { win32::GetSystemDirectoryW(buffer, length) }));
+    paths.extend(wide_path(|buffer, length| unsafe { win32::GetWindowsDirectoryW(buffer, length) }));
+
+    if !replaces_current {
+        paths.extend(env::current_dir().ok());
+    }
+
+    paths.extend(split_variable("PATH"));
+    paths
+}
+
+#[cfg(not(any(unix, windows)))]
+fn platform_search_paths() -> Vec<PathBuf> {
+    Vec::new()
+}
@@ -0,0 +1,193 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/failure.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::io;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The category of a failed library load or symbol lookup, as determined from
+/// the OS error code and from inspecting the library file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Class {
+    NotFound,
+    PermissionDenied,
+    WrongArchitecture,
+    BadImage,
+    MissingDependency,
+    InvalidName,
+    RelativePath,
+    NullSymbol,
+    Unsupported,
+    Other
+}
+
+/// The reason a library load or symbol lookup failed, returned from
+/// [`Error::kind`](enum.Error.html#method.kind).
+///
+/// This is worked out from the platform's error code or message, and from
+/// checking the library file, as described for
+/// [`LoadOptions::diagnose`](struct.LoadOptions.html#method.diagnose), which
+/// adds the most detail. More kinds may be added, so a failure which cannot
+/// be told apart yet is `Other`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The library file, or the symbol, does not exist.
+    NotFound,
+
+    /// The library file exists but is not a valid library, such as an empty
+    /// or text file.
+    NotALibrary,
+
+    /// The library was built for a different architecture to the current
+    /// process.
+    WrongArchitecture,
+
+    /// The library file exists, but a library it depends on could not be
+    /// found or loaded.
+    MissingDependency,
+
+    /// The library file exists but could not be read.
+    PermissionDenied,
+
+    /// A symbol name was rejected before looking it up.
+    InvalidName,
+
+    /// A library path or symbol name contains a NUL byte.
+    InvalidInput,
+
+    /// A relative path was given where absolute paths are required.
+    RelativePath,
+
+    /// The symbol exists, but its address is null, as described for
+    /// [`Snek::nullable_symbol`](struct.Snek.html#method.nullable_symbol).
+    NullSymbol,
+
+    /// The operation is not supported by the platform.
+    Unsupported,
+
+    /// Any other failure.
+    Other
+}
+
+impl From<Class> for ErrorKind {
+    fn from(class: Class) -> ErrorKind {
+        match class {
+            Class::NotFound => ErrorKind::NotFound,
+            Class::PermissionDenied => ErrorKind::PermissionDenied,
+            Class::WrongArchitecture => ErrorKind::WrongArchitecture,
+            Class::BadImage => ErrorKind::NotALibrary,
+            Class::MissingDependency => ErrorKind::MissingDependency,
+            Class::InvalidName => ErrorKind::InvalidName,
+            Class::RelativePath => ErrorKind::RelativePath,
+            Class::NullSymbol => ErrorKind::NullSymbol,
+            Class::Unsupported => ErrorKind::Unsupported,
+            Class::Other => ErrorKind::Other
+        }
+    }
+}
+
+/// The details of a failed library load or symbol lookup, held by
+/// [`Error::LibraryLoadError`](enum.Error.html) and
+/// [`Error::SymbolLoadError`](enum.Error.html).
+///
+/// This formats as the platform's error message, along with any explanation
+/// found by inspecting the library file. The library path or symbol name
+/// which failed is also kept, as the platform's message does not always
+/// mention it.
+#[derive(Debug, Clone)]
+pub struct Failure {
+    pub(crate) message: String,
+    pub(crate) os_code: Option<i32>,
+    pub(crate) class: Class,
+    pub(crate) path: Option<PathBuf>,
+    pub(crate) symbol: Option<String>,
+
+    // The OS error behind the failure, returned as the source of the `Error`
+    pub(crate) source: Option<Arc<io::Error>>
+}
+
+impl Failure {
+    pub(crate) fn new<S>(message: S, os_code: Option<i32>, class: Class) -> Failure where S: Into<String> {
+        Failure {
+            message: message.into(),
+            os_code,
+            class,
+            path: None,
+            symbol: None,
+            source: os_code.map(|code| Arc::new(io::Error::from_raw_os_error(code)))
+        }
+    }
+
+    /// Record the library path which was being loaded, unless one already is.
+    pub(crate) fn with_path(mut self, path: &Path) -> Failure {
+        self.path = self.path.or_else(|| Some(path.to_owned()));
+        self
+    }
+
+    /// Record the symbol which was being looked up, unless one already is.
+    pub(crate) fn with_symbol(mut self, symbol: &str) -> Failure {
+        self.symbol = self.symbol.or_else(|| Some(symbol.to_owned()));
+        self
+    }
+
+    pub(crate) fn from_io<S>(message: S, err: &io::Error) -> Failure where S: Into<String> {
+        let code = err.raw_os_error();
+        let mut failure = Failure::new(message, code, code.map(::snek::classify).unwrap_or(Class::Other));
+
+        // An error made by std rather than the OS has no code to recreate it from
+        if failure.source.is_none() {
+            failure.source = Some(Arc::new(io::Error::new(err.kind(), err.to_string())));
+        }
+
+        failure
+    }
+
+    /// Returns the error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the OS error code for the failure, if one is known.
+    ///
+    /// On Windows this is the value of `GetLastError()` from the failed call.
+    /// On unix the dynamic loader only reports a message, so this is the
+    /// `errno` from checking the library file when it cannot be opened, such
+    /// as `ENOENT` for a path which does not exist, and otherwise `None`.
+    pub fn os_code(&self) -> Option<i32> {
+        self.os_code
+    }
+
+    /// Returns the path of the library which was being loaded, as it was
+    /// given.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Returns the name of the symbol which was being looked up.
+    pub fn symbol(&self) -> Option<&str> {
+        self.symbol.as_deref()
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
@@ -0,0 +1,142 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/ffi.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::ptr::NonNull;
+use std::str::Utf8Error;
+
+/// A type which can be passed to or returned from a C function, checked for
+/// every parameter and return type in a [`snek!`](macro.snek!.html) block
+/// marked `#[deny_non_ffi]`.
+///
+/// This is implemented for the integer and floating point types (and so the
+/// `std::os::raw` and `libc` aliases of them), `bool`, `()`, raw pointers,
+/// `NonNull`, and `extern "C"` function pointers taking and returning
+/// `FfiSafe` types, along with `Option`s of `NonNull` and function pointers,
+/// which are represented as nullable pointers.
+///
+/// # Safety
+/// The type must have the same layout and calling convention as a C type,
+/// which usually means a `#[repr(C)]` struct of `FfiSafe` fields, or a
+/// `#[repr(C)]` or `#[repr(<integer>)]` enum whose every value the library
+/// can really pass. Implementing this for any other type brings back the
+/// memory corruption the check exists to prevent.
+///
+/// # Example
+/// ```
+/// # #[macro_use] extern crate snek;
+/// # use std::os::raw::c_int;
+/// #[repr(C)]
+/// #[derive(Clone, Copy)]
+/// pub struct Point {
+///     x: c_int,
+///     y: c_int
+/// }
+///
+/// unsafe impl snek::FfiSafe for Point {}
+///
+/// snek! {
+///     #[deny_non_ffi]
+///     Example {
+///         add: (x: c_int, y: c_int) -> c_int,
+///         origin: () -> Point
+///     }
+/// }
+/// # fn main() {}
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be passed to or returned from a C function",
+    label = "not FFI-safe",
+    note = "a #[repr(C)] type can be marked safe with `unsafe impl snek::FfiSafe`"
+)]
+pub unsafe trait FfiSafe {}
+
+macro_rules! ffi_safe {
+    ($($ty:ty),*) => {
+        $(unsafe impl FfiSafe for $ty {})*
+    }
+}
+
+ffi_safe!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64, bool, ());
+
+unsafe impl<T> FfiSafe for *const T {}
+unsafe impl<T> FfiSafe for *mut T {}
+unsafe impl<T> FfiSafe for NonNull<T> {}
+unsafe impl<T> FfiSafe for Option<NonNull<T>> {}
+
+macro_rules! ffi_safe_fn {
+    ($($arg:ident),*) => {
+        unsafe impl<R: FfiSafe $(, $arg: FfiSafe)*> FfiSafe for extern "C" fn($($arg),*) -> R {}
+        unsafe impl<R: FfiSafe $(, $arg: FfiSafe)*> FfiSafe for unsafe extern "C" fn($($arg),*) -> R {}
+        unsafe impl<R: FfiSafe $(, $arg: FfiSafe)*> FfiSafe for Option<extern "C" fn($($arg),*) -> R> {}
+        unsafe impl<R: FfiSafe $(, $arg: FfiSafe)*> FfiSafe for Option<unsafe extern "C" fn($($arg),*) -> R> {}
+    }
+}
+
+ffi_safe_fn!();
+ffi_safe_fn!(A);
+ffi_safe_fn!(A, B);
+ffi_safe_fn!(A, B, C);
+ffi_safe_fn!(A, B, C, D);
+ffi_safe_fn!(A, B, C, D, E);
+ffi_safe_fn!(A, B, C, D, E, F);
+ffi_safe_fn!(A, B, C, D, E, F, G);
+ffi_safe_fn!(A, B, C, D, E, F, G, H);
+
+/// A type which a C string returned from a function can be copied into, used
+/// by a [`snek!`](macro.snek!.html) entry whose return type is marked
+/// `as String`, or another type implementing this.
+///
+/// This is implemented for `String`, which replaces invalid UTF-8 with
+/// `U+FFFD` and takes a null pointer as an empty string, `Option<String>`,
+/// which takes a null pointer as `None`, and `Result<String, Utf8Error>`,
+/// which fails on invalid UTF-8 rather than replacing it.
+pub trait FromCString: Sized {
+    /// Copy the string at the given address.
+    ///
+    /// # Safety
+    /// The pointer must be null or point to a NUL-terminated string.
+    unsafe fn from_c_string(string: *const c_char) -> Self;
+}
+
+impl FromCString for String {
+    unsafe fn from_c_string(string: *const c_char) -> String {
+        Option::<String>::from_c_string(string).unwrap_or_default()
+    }
+}
+
+impl FromCString for Option<String> {
+    unsafe fn from_c_string(string: *const c_char) -> Option<String> {
+        if string.is_null() {
+            None
+        } else {
+            Some(CStr::from_ptr(string).to_string_lossy().into_owned())
+        }
+    }
+}
+
+impl FromCString for Result<String, Utf8Error> {
+    unsafe fn from_c_string(string: *const c_char) -> Result<String, Utf8Error> {
+        if string.is_null() {
+            Ok(String::new())
+        } else {
+            CStr::from_ptr(string).to_str().map(str::to_owned)
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/handle.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::ptr::NonNull;
+use libc::c_void;
+
+/// An opaque handle to a loaded dynamic library, as returned by
+/// [`load_library`](fn.load_library.html) and used to load symbols from the
+/// library with [`load_symbol`](fn.load_symbol.html) until it is unloaded by
+/// [`drop_library`](fn.drop_library.html).
+///
+/// Unlike a [`Snek`](struct.Snek.html), a `Handle` does not unload the library
+/// when it is dropped. It can be converted to and from the platform's raw
+/// handle, a `void *` from `dlopen` or an `HMODULE`, for use with other
+/// foreign code.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Handle(NonNull<c_void>);
+
+// A handle is only passed back to the loader, whose calls are safe from any
+// thread on every supported platform: `dlopen` and friends are thread-safe
+// under POSIX, with the crate serialising them where `dlerror` is shared, and
+// so are `LoadLibrary` and friends on Windows. Registered and testing handles
+// point at statics which are only read, under a lock.
+unsafe impl Send for Handle {}
+unsafe impl Sync for Handle {}
+
+impl Handle {
+    pub(crate) fn new(handle: NonNull<c_void>) -> Handle {
+        Handle(handle)
+    }
+
+    pub(crate) fn as_non_null(&self) -> NonNull<c_void> {
+        self.0
+    }
+
+    /// Wrap a raw platform handle, returning `None` if it is null.
+    ///
+    /// # Safety
+    /// The pointer must be a handle to a loaded library, as returned by
+    /// `dlopen` or `LoadLibrary`, which stays loaded for as long as the
+    /// `Handle` is used.
+    pub unsafe fn from_raw(handle: *mut c_void) -> Option<Handle> {
+        NonNull::new(handle).map(Handle)
+    }
+
+    /// Returns the raw platform handle, without giving up ownership of it.
+    pub fn as_raw(&self) -> *mut c_void {
+        self.0.as_ptr()
+    }
+
+    /// Returns the raw platform handle, leaving the caller responsible for
+    /// unloading the library.
+    pub fn into_raw(self) -> *mut c_void {
+        self.0.as_ptr()
+    }
+}
@@ -0,0 +1,148 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/inspect/bytes.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::Error;
+use super::invalid;
+
+use std::str;
+use std::cell::Cell;
+
+/// The longest string read from a file, such as a library or symbol name.
+pub const MAX_STRING: usize = 4096;
+
+/// The most entries of any one kind read from a file. A file which claims to
+/// have more than this is refused rather than partially read.
+pub const MAX_ENTRIES: u64 = 1 << 20;
+
+/// The most bytes of strings copied out of a single file, which bounds the
+/// memory used by a file whose entries all share one long string.
+pub const MAX_STRING_BYTES: usize = 1 << 26;
+
+/// Check a count of entries read from a file against [`MAX_ENTRIES`].
+pub fn entries(count: u64) -> Result<u64, Error> {
+    if count > MAX_ENTRIES {
+        Err(invalid(&format!("the file claims {} entries, more than the limit of {}", count, MAX_ENTRIES)))
+    } else {
+        Ok(count)
+    }
+}
+
+/// Tracks the bytes of strings copied out of a file against [`MAX_STRING_BYTES`].
+pub struct Budget {
+    remaining: Cell<usize>
+}
+
+impl Budget {
+    pub fn new() -> Budget {
+        Budget {
+            remaining: Cell::new(MAX_STRING_BYTES)
+        }
+    }
+
+    pub fn own(&self, string: &str) -> Result<String, Error> {
+        match self.remaining.get().checked_sub(string.len()) {
+            Some(remaining) => {
+                self.remaining.set(remaining);
+                Ok(string.to_owned())
+            },
+
+            None => Err(invalid("the file's strings exceed the size limit"))
+        }
+    }
+}
+
+/// Bounds-checked reads from a file held in memory. Every read returns `None`
+/// rather than panicking if it would fall outside the data.
+#[derive(Clone, Copy)]
+pub struct Bytes<'a> {
+    data: &'a [u8],
+    big_endian: bool
+}
+
+impl<'a> Bytes<'a> {
+    pub fn new(data: &'a [u8], big_endian: bool) -> Bytes<'a> {
+        Bytes {
+            data,
+            big_endian
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn slice(&self, offset: u64, len: u64) -> Option<&'a [u8]> {
+        let start = to_usize(offset)?;
+        let end = start.checked_add(to_usize(len)?)?;
+        self.data.get(start..end)
+    }
+
+    pub fn u8(&self, offset: u64) -> Option<u8> {
+        self.data.get(to_usize(offset)?).cloned()
+    }
+
+    pub fn u16(&self, offset: u64) -> Option<u16> {
+        let bytes = self.slice(offset, 2)?;
+        let bytes = [bytes[0], bytes[1]];
+
+        Some(if self.big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+    }
+
+    pub fn u32(&self, offset: u64) -> Option<u32> {
+        let bytes = self.slice(offset, 4)?;
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+
+        Some(if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+
+    pub fn u64(&self, offset: u64) -> Option<u64> {
+        let bytes = self.slice(offset, 8)?;
+        let mut array = [0; 8];
+        array.copy_from_slice(bytes);
+
+        Some(if self.big_endian { u64::from_be_bytes(array) } else { u64::from_le_bytes(array) })
+    }
+
+    /// Reads a 64-bit value if `wide`, otherwise a 32-bit value.
+    pub fn word(&self, offset: u64, wide: bool) -> Option<u64> {
+        if wide {
+            self.u64(offset)
+        } else {
+            self.u32(offset).map(u64::from)
+        }
+    }
+
+    /// Reads a NUL-terminated string of at most `limit` bytes (and never more
+    /// than [`MAX_STRING`]) which must be valid UTF-8.
+    pub fn string(&self, offset: u64, limit: u64) -> Option<&'a str> {
+        let start = to_usize(offset)?;
+        let available = self.data.get(start..)?;
+        let limit = to_usize(limit).unwrap_or(MAX_STRING).min(MAX_STRING).min(available.len());
+
+        let length = available[..limit].iter().position(|&byte| byte == 0)?;
+        str::from_utf8(&available[..length]).ok()
+    }
+}
+
+pub fn to_usize(value: u64) -> Option<usize> {
+    if value > usize::MAX as u64 {
+        None
+    } else {
+        Some(value as usize)
+    }
+}
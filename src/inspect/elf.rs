@@ -0,0 +1,253 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/inspect/elf.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::Error;
+use super::{Format, Image, Machine, invalid, truncated};
+use super::bytes::{entries, Budget, Bytes};
+
+const PT_LOAD: u32 = 1;
+const PT_DYNAMIC: u32 = 2;
+
+const DT_NULL: u64 = 0;
+const DT_NEEDED: u64 = 1;
+const DT_STRTAB: u64 = 5;
+const DT_STRSZ: u64 = 10;
+const DT_RPATH: u64 = 15;
+const DT_RUNPATH: u64 = 29;
+
+const SHT_DYNSYM: u32 = 11;
+const SHN_UNDEF: u16 = 0;
+const STB_GLOBAL: u8 = 1;
+const STB_WEAK: u8 = 2;
+const STB_GNU_UNIQUE: u8 = 10;
+const STV_HIDDEN: u8 = 2;
+const STV_INTERNAL: u8 = 1;
+
+struct Segment {
+    address: u64,
+    offset: u64,
+    size: u64
+}
+
+pub fn parse(data: &[u8], budget: &Budget) -> Result<Image, Error> {
+    let wide = match data.get(4) {
+        Some(&1) => false,
+        Some(&2) => true,
+        _ => return Err(invalid("the ELF file has an invalid class"))
+    };
+
+    let bytes = match data.get(5) {
+        Some(&1) => Bytes::new(data, false),
+        Some(&2) => Bytes::new(data, true),
+        _ => return Err(invalid("the ELF file has an invalid byte order"))
+    };
+
+    let machine = machine(bytes.u16(0x12).ok_or_else(truncated)?, wide);
+
+    let (phoff, phentsize, phnum) = if wide {
+        (bytes.u64(0x20), bytes.u16(0x36), bytes.u16(0x38))
+    } else {
+        (bytes.u32(0x1c).map(u64::from), bytes.u16(0x2a), bytes.u16(0x2c))
+    };
+
+    let phoff = phoff.ok_or_else(truncated)?;
+    let phentsize = u64::from(phentsize.ok_or_else(truncated)?);
+    let phnum = phnum.ok_or_else(truncated)?;
+
+    let mut loads = Vec::new();
+    let mut dynamic = None;
+
+    for index in 0..u64::from(phnum) {
+        let header = index.checked_mul(phentsize).and_then(|offset| offset.checked_add(phoff)).ok_or_else(truncated)?;
+        let kind = bytes.u32(header).ok_or_else(truncated)?;
+
+        let segment = if wide {
+            Segment {
+                offset: bytes.u64(header + 8).ok_or_else(truncated)?,
+                address: bytes.u64(header + 16).ok_or_else(truncated)?,
+                size: bytes.u64(header + 32).ok_or_else(truncated)?
+            }
+        } else {
+            Segment {
+                offset: u64::from(bytes.u32(header + 4).ok_or_else(truncated)?),
+                address: u64::from(bytes.u32(header + 8).ok_or_else(truncated)?),
+                size: u64::from(bytes.u32(header + 16).ok_or_else(truncated)?)
+            }
+        };
+
+        match kind {
+            PT_LOAD => loads.push(segment),
+            PT_DYNAMIC => dynamic = Some(segment),
+            _ => ()
+        }
+    }
+
+    let mut image = Image {
+        format: Format::Elf,
+        machines: vec![machine],
+        dependencies: Vec::new(),
+        search_paths: Vec::new(),
+        exports: exports(&bytes, wide, budget)?
+    };
+
+    // Executables and libraries without a dynamic segment have no dependencies
+    let dynamic = match dynamic {
+        Some(dynamic) => dynamic,
+        None => return Ok(image)
+    };
+
+    let entry_size = if wide { 16 } else { 8 };
+    let mut needed = Vec::new();
+    let mut paths = Vec::new();
+    let mut strtab = None;
+    let mut strsz = None;
+
+    for index in 0..entries(dynamic.size / entry_size)? {
+        let entry = dynamic.offset.checked_add(index * entry_size).ok_or_else(truncated)?;
+        let tag = bytes.word(entry, wide).ok_or_else(truncated)?;
+        let value = bytes.word(entry + entry_size / 2, wide).ok_or_else(truncated)?;
+
+        match tag {
+            DT_NULL => break,
+            DT_NEEDED => needed.push(value),
+            DT_STRTAB => strtab = Some(value),
+            DT_STRSZ => strsz = Some(value),
+            DT_RPATH | DT_RUNPATH => paths.push(value),
+            _ => ()
+        }
+    }
+
+    if needed.is_empty() && paths.is_empty() {
+        return Ok(image);
+    }
+
+    let strtab = strtab.and_then(|address| to_offset(&loads, address))
+        .ok_or_else(|| invalid("the ELF file has no dynamic string table"))?;
+
+    let strsz = strsz.unwrap_or(bytes.len() as u64);
+    let string = |offset: u64| -> Result<String, Error> {
+        if offset >= strsz {
+            return Err(truncated());
+        }
+
+        strtab.checked_add(offset)
+            .and_then(|absolute| bytes.string(absolute, strsz - offset))
+            .ok_or_else(truncated)
+            .and_then(|string| budget.own(string))
+    };
+
+    for offset in needed {
+        image.dependencies.push(string(offset)?);
+    }
+
+    for offset in paths {
+        for path in string(offset)?.split(':').filter(|path| !path.is_empty()) {
+            image.search_paths.push(path.to_owned());
+        }
+    }
+
+    Ok(image)
+}
+
+fn exports(bytes: &Bytes, wide: bool, budget: &Budget) -> Result<Vec<String>, Error> {
+    let (shoff, shentsize, shnum) = if wide {
+        (bytes.u64(0x28), bytes.u16(0x3a), bytes.u16(0x3c))
+    } else {
+        (bytes.u32(0x20).map(u64::from), bytes.u16(0x2e), bytes.u16(0x30))
+    };
+
+    let shoff = shoff.ok_or_else(truncated)?;
+    let shentsize = u64::from(shentsize.ok_or_else(truncated)?);
+    let shnum = u64::from(shnum.ok_or_else(truncated)?);
+
+    let section = |index: u64| index.checked_mul(shentsize).and_then(|offset| offset.checked_add(shoff));
+
+    // Reads the type, offset, size, link and entry size of a section header
+    let header = |header: u64| -> Option<(u32, u64, u64, u32, u64)> {
+        if wide {
+            Some((bytes.u32(header + 4)?, bytes.u64(header + 24)?, bytes.u64(header + 32)?, bytes.u32(header + 40)?, bytes.u64(header + 56)?))
+        } else {
+            Some((bytes.u32(header + 4)?, u64::from(bytes.u32(header + 16)?), u64::from(bytes.u32(header + 20)?), bytes.u32(header + 24)?, u64::from(bytes.u32(header + 36)?)))
+        }
+    };
+
+    let mut exports = Vec::new();
+    if shoff == 0 {
+        return Ok(exports);
+    }
+
+    for index in 0..shnum {
+        let (kind, offset, size, link, entry_size) = section(index).and_then(&header).ok_or_else(truncated)?;
+        if kind != SHT_DYNSYM || entry_size == 0 {
+            continue;
+        }
+
+        let (_, strtab, strsz, _, _) = section(u64::from(link)).and_then(&header).ok_or_else(truncated)?;
+
+        for symbol in 1..entries(size / entry_size)? {
+            let symbol = offset.checked_add(symbol * entry_size).ok_or_else(truncated)?;
+
+            let (name, info, other, shndx) = if wide {
+                (bytes.u32(symbol), bytes.u8(symbol + 4), bytes.u8(symbol + 5), bytes.u16(symbol + 6))
+            } else {
+                (bytes.u32(symbol), bytes.u8(symbol + 12), bytes.u8(symbol + 13), bytes.u16(symbol + 14))
+            };
+
+            let name = u64::from(name.ok_or_else(truncated)?);
+            let info = info.ok_or_else(truncated)?;
+            let visibility = other.ok_or_else(truncated)? & 3;
+
+            let exported = matches!(info >> 4, STB_GLOBAL | STB_WEAK | STB_GNU_UNIQUE);
+
+            if !exported || shndx.ok_or_else(truncated)? == SHN_UNDEF || visibility == STV_HIDDEN || visibility == STV_INTERNAL || name >= strsz {
+                continue;
+            }
+
+            if let Some(name) = strtab.checked_add(name).and_then(|offset| bytes.string(offset, strsz - name)) {
+                if !name.is_empty() {
+                    exports.push(budget.own(name)?);
+                }
+            }
+        }
+    }
+
+    Ok(exports)
+}
+
+fn to_offset(loads: &[Segment], address: u64) -> Option<u64> {
+    loads.iter()
+        .find(|segment| address >= segment.address && address - segment.address < segment.size)
+        .and_then(|segment| segment.offset.checked_add(address - segment.address))
+}
+
+fn machine(value: u16, wide: bool) -> Machine {
+    match (value, wide) {
+        (3, _) => Machine::X86,
+        (62, _) => Machine::X86_64,
+        (40, _) => Machine::Arm,
+        (183, _) => Machine::AArch64,
+        (20, _) => Machine::PowerPc,
+        (21, _) => Machine::PowerPc64,
+        (8, false) => Machine::Mips,
+        (8, true) => Machine::Mips64,
+        (243, false) => Machine::RiscV32,
+        (243, true) => Machine::RiscV64,
+        (22, _) => Machine::S390x,
+        (value, _) => Machine::Unknown(u32::from(value))
+    }
+}
@@ -0,0 +1,188 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/inspect/macho.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::Error;
+use super::{Format, Image, Machine, invalid, truncated};
+use super::bytes::{entries, Budget, Bytes};
+
+const MH_MAGIC: u32 = 0xfeed_face;
+const MH_MAGIC_64: u32 = 0xfeed_facf;
+const FAT_MAGIC: u32 = 0xcafe_babe;
+const FAT_MAGIC_64: u32 = 0xcafe_babf;
+
+const LC_SYMTAB: u32 = 0x2;
+const LC_LOAD_DYLIB: u32 = 0xc;
+const LC_RPATH: u32 = 0x8000_001c;
+const LC_REEXPORT_DYLIB: u32 = 0x8000_001f;
+
+const N_STAB: u8 = 0xe0;
+const N_TYPE: u8 = 0x0e;
+const N_EXT: u8 = 0x01;
+const N_UNDF: u8 = 0x00;
+
+pub fn is_macho(magic: &[u8]) -> bool {
+    let big = Bytes::new(magic, true).u32(0);
+    let little = Bytes::new(magic, false).u32(0);
+
+    [MH_MAGIC, MH_MAGIC_64, FAT_MAGIC, FAT_MAGIC_64].iter()
+        .any(|&value| big == Some(value) || little == Some(value))
+}
+
+pub fn parse(data: &[u8], budget: &Budget) -> Result<Image, Error> {
+    match Bytes::new(data, true).u32(0) {
+        Some(FAT_MAGIC) => parse_universal(data, false, budget),
+        Some(FAT_MAGIC_64) => parse_universal(data, true, budget),
+        _ => parse_single(data, budget)
+    }
+}
+
+fn parse_universal(data: &[u8], wide: bool, budget: &Budget) -> Result<Image, Error> {
+    let bytes = Bytes::new(data, true);
+    let count = entries(u64::from(bytes.u32(4).ok_or_else(truncated)?))?;
+    let entry_size = if wide { 32 } else { 20 };
+
+    let mut slices = Vec::new();
+    for index in 0..count {
+        let entry = 8 + index * entry_size;
+        let machine = machine(bytes.u32(entry).ok_or_else(truncated)?);
+
+        let (offset, size) = if wide {
+            (bytes.u64(entry + 8), bytes.u64(entry + 16))
+        } else {
+            (bytes.u32(entry + 8).map(u64::from), bytes.u32(entry + 12).map(u64::from))
+        };
+
+        slices.push((machine, offset.ok_or_else(truncated)?, size.ok_or_else(truncated)?));
+    }
+
+    let current = Machine::current();
+    let &(_, offset, size) = slices.iter()
+        .find(|&&(machine, _, _)| machine == current)
+        .or_else(|| slices.first())
+        .ok_or_else(|| invalid("the universal binary has no slices"))?;
+
+    let slice = bytes.slice(offset, size).ok_or_else(truncated)?;
+    let mut image = parse_single(slice, budget)?;
+
+    // Put the inspected slice first, so machine() describes it. Subtypes of
+    // the same architecture (such as x86_64h) are only listed once.
+    image.format = Format::MachOUniversal;
+    image.machines = vec![image.machines[0]];
+
+    for &(machine, _, _) in &slices {
+        if !image.machines.contains(&machine) {
+            image.machines.push(machine);
+        }
+    }
+
+    Ok(image)
+}
+
+fn parse_single(data: &[u8], budget: &Budget) -> Result<Image, Error> {
+    let (bytes, wide) = match (Bytes::new(data, false).u32(0), Bytes::new(data, true).u32(0)) {
+        (Some(MH_MAGIC), _) => (Bytes::new(data, false), false),
+        (Some(MH_MAGIC_64), _) => (Bytes::new(data, false), true),
+        (_, Some(MH_MAGIC)) => (Bytes::new(data, true), false),
+        (_, Some(MH_MAGIC_64)) => (Bytes::new(data, true), true),
+        _ => return Err(invalid("the file is not a Mach-O file"))
+    };
+
+    let machine = machine(bytes.u32(4).ok_or_else(truncated)?);
+    let count = entries(u64::from(bytes.u32(16).ok_or_else(truncated)?))?;
+
+    let mut image = Image {
+        format: Format::MachO,
+        machines: vec![machine],
+        dependencies: Vec::new(),
+        search_paths: Vec::new(),
+        exports: Vec::new()
+    };
+
+    let mut command = if wide { 32 } else { 28 };
+    for _ in 0..count {
+        let kind = bytes.u32(command).ok_or_else(truncated)?;
+        let size = u64::from(bytes.u32(command + 4).ok_or_else(truncated)?);
+
+        if size < 8 {
+            return Err(truncated());
+        }
+
+        // Weak dependencies are deliberately skipped, as they may be missing
+        match kind {
+            LC_LOAD_DYLIB | LC_REEXPORT_DYLIB => image.dependencies.push(string(&bytes, command, size, budget)?),
+            LC_RPATH => image.search_paths.push(string(&bytes, command, size, budget)?),
+            LC_SYMTAB => image.exports = symbols(&bytes, command, wide, budget)?,
+            _ => ()
+        }
+
+        command = command.checked_add(size).ok_or_else(truncated)?;
+    }
+
+    Ok(image)
+}
+
+fn string(bytes: &Bytes, command: u64, size: u64, budget: &Budget) -> Result<String, Error> {
+    let offset = u64::from(bytes.u32(command + 8).ok_or_else(truncated)?);
+    if offset >= size {
+        return Err(truncated());
+    }
+
+    bytes.string(command + offset, size - offset)
+        .ok_or_else(truncated)
+        .and_then(|string| budget.own(string))
+}
+
+fn symbols(bytes: &Bytes, command: u64, wide: bool, budget: &Budget) -> Result<Vec<String>, Error> {
+    let symoff = u64::from(bytes.u32(command + 8).ok_or_else(truncated)?);
+    let nsyms = entries(u64::from(bytes.u32(command + 12).ok_or_else(truncated)?))?;
+    let stroff = u64::from(bytes.u32(command + 16).ok_or_else(truncated)?);
+    let strsize = u64::from(bytes.u32(command + 20).ok_or_else(truncated)?);
+    let entry_size = if wide { 16 } else { 12 };
+
+    let mut exports = Vec::new();
+    for index in 0..nsyms {
+        let entry = symoff + index * entry_size;
+        let name = u64::from(bytes.u32(entry).ok_or_else(truncated)?);
+        let kind = bytes.u8(entry + 4).ok_or_else(truncated)?;
+
+        if kind & N_STAB != 0 || kind & N_EXT == 0 || kind & N_TYPE == N_UNDF || name >= strsize {
+            continue;
+        }
+
+        if let Some(name) = bytes.string(stroff + name, strsize - name) {
+            let name = name.strip_prefix('_').unwrap_or(name);
+            if !name.is_empty() {
+                exports.push(budget.own(name)?);
+            }
+        }
+    }
+
+    Ok(exports)
+}
+
+fn machine(value: u32) -> Machine {
+    match value {
+        7 => Machine::X86,
+        0x0100_0007 => Machine::X86_64,
+        12 => Machine::Arm,
+        0x0100_000c => Machine::AArch64,
+        18 => Machine::PowerPc,
+        0x0100_0012 => Machine::PowerPc64,
+        value => Machine::Unknown(value)
+    }
+}
@@ -0,0 +1,244 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/inspect/mod.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! This module reads the headers of dynamic library files without loading
+//! them, supporting ELF, PE and Mach-O (including universal) files regardless
+//! of the current platform.
+//!
+//! This is used to explain why a library failed to load, but can also be used
+//! directly to check a file before attempting to load it.
+
+use ::Error;
+
+use self::bytes::Budget;
+
+use std::fs;
+use std::fmt;
+use std::path::Path;
+
+/// The largest file [`inspect_file`](fn.inspect_file.html) will read.
+const MAX_FILE_SIZE: u64 = 1 << 30;
+
+mod bytes;
+mod elf;
+mod pe;
+mod macho;
+
+/// The file format of an inspected library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Elf,
+    Pe,
+    MachO,
+
+    /// A Mach-O universal binary, holding slices for several architectures.
+    MachOUniversal
+}
+
+/// The machine architecture an inspected library was built for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Machine {
+    X86,
+    X86_64,
+    Arm,
+    AArch64,
+    PowerPc,
+    PowerPc64,
+    Mips,
+    Mips64,
+    RiscV32,
+    RiscV64,
+    S390x,
+
+    /// An architecture this crate does not recognise, holding the raw value
+    /// from the file header.
+    Unknown(u32)
+}
+
+impl Machine {
+    /// Returns the architecture the current process was compiled for.
+    pub fn current() -> Machine {
+        if cfg!(target_arch = "x86") {
+            Machine::X86
+        } else if cfg!(target_arch = "x86_64") {
+            Machine::X86_64
+        } else if cfg!(target_arch = "arm") {
+            Machine::Arm
+        } else if cfg!(target_arch = "aarch64") {
+            Machine::AArch64
+        } else if cfg!(target_arch = "powerpc") {
+            Machine::PowerPc
+        } else if cfg!(target_arch = "powerpc64") {
+            Machine::PowerPc64
+        } else if cfg!(target_arch = "mips") {
+            Machine::Mips
+        } else if cfg!(target_arch = "mips64") {
+            Machine::Mips64
+        } else if cfg!(target_arch = "riscv32") {
+            Machine::RiscV32
+        } else if cfg!(target_arch = "riscv64") {
+            Machine::RiscV64
+        } else if cfg!(target_arch = "s390x") {
+            Machine::S390x
+        } else {
+            Machine::Unknown(0)
+        }
+    }
+}
+
+impl fmt::Display for Machine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Machine::X86 => "x86",
+            Machine::X86_64 => "x86_64",
+            Machine::Arm => "arm",
+            Machine::AArch64 => "aarch64",
+            Machine::PowerPc => "powerpc",
+            Machine::PowerPc64 => "powerpc64",
+            Machine::Mips => "mips",
+            Machine::Mips64 => "mips64",
+            Machine::RiscV32 => "riscv32",
+            Machine::RiscV64 => "riscv64",
+            Machine::S390x => "s390x",
+            Machine::Unknown(value) => return write!(f, "unknown ({:#x})", value)
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// The information read from the headers of a library file.
+#[derive(Debug, Clone)]
+pub struct Image {
+    format: Format,
+    machines: Vec<Machine>,
+    dependencies: Vec<String>,
+    search_paths: Vec<String>,
+    exports: Vec<String>
+}
+
+impl Image {
+    /// Returns the format of the file.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Returns the architecture the file was built for. For a universal binary
+    /// this is the architecture of the slice which was inspected: the one for
+    /// the current architecture if present, or the first one otherwise.
+    pub fn machine(&self) -> Machine {
+        self.machines[0]
+    }
+
+    /// Returns every architecture the file was built for. This only holds more
+    /// than one architecture for a universal binary.
+    pub fn machines(&self) -> &[Machine] {
+        &self.machines
+    }
+
+    /// Returns true if the file can be loaded by the current process as far as
+    /// its architecture is concerned.
+    pub fn matches_current(&self) -> bool {
+        self.machines.contains(&Machine::current())
+    }
+
+    /// Returns the names of the libraries the file requires at load time, as
+    /// written in the file. Dependencies which are allowed to be missing (weak
+    /// or delay-loaded dependencies) are not included.
+    pub fn dependencies(&self) -> &[String] {
+        &self.dependencies
+    }
+
+    /// Returns the search paths embedded in the file for finding dependencies
+    /// (`DT_RUNPATH` or `DT_RPATH` entries for ELF, and `LC_RPATH` entries for
+    /// Mach-O), as written in the file.
+    pub fn search_paths(&self) -> &[String] {
+        &self.search_paths
+    }
+
+    /// Returns the names of the symbols the file exports in sorted order, as
+    /// they would be passed to [`Snek::symbol`](../struct.Snek.html#method.symbol). The
+    /// leading underscore Mach-O adds to C names is removed, and PE exports
+    /// which are only available by ordinal are not included.
+    ///
+    /// For an ELF file these are read from the section headers, so a file
+    /// which has had them stripped will report no exports.
+    pub fn exports(&self) -> &[String] {
+        &self.exports
+    }
+}
+
+/// Inspect the library file at the given path.
+///
+/// If the file cannot be read, is larger than 1GiB, or is not a valid library
+/// file, this will return [`Error::InspectError`](../enum.Error.html).
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::inspect;
+/// # fn main() {
+/// if let Ok(image) = inspect::inspect_file("libexample.so") {
+///     println!("{} library depending on {:?}", image.machine(), image.dependencies());
+/// }
+/// # }
+/// ```
+pub fn inspect_file<P>(path: P) -> Result<Image, Error> where P: AsRef<Path> {
+    let path = path.as_ref();
+    let failed = |err| Error::InspectError(format!("failed to read {}: {}", path.display(), err));
+
+    if fs::metadata(path).map_err(failed)?.len() > MAX_FILE_SIZE {
+        return Err(invalid("the file is too large to be inspected"));
+    }
+
+    let data = fs::read(path).map_err(failed)?;
+
+    inspect(&data)
+}
+
+/// Inspect a library file which has been read into memory.
+///
+/// If the data is not a valid library file, this will return
+/// [`Error::InspectError`](../enum.Error.html). This never panics, whatever
+/// the data holds, and files which claim implausibly large tables are refused
+/// so that the memory used stays bounded.
+pub fn inspect(data: &[u8]) -> Result<Image, Error> {
+    let budget = Budget::new();
+
+    let mut image = match data.get(..4) {
+        Some(b"\x7fELF") => elf::parse(data, &budget)?,
+        Some(magic) if &magic[..2] == b"MZ" => pe::parse(data, &budget)?,
+        Some(magic) if macho::is_macho(magic) => macho::parse(data, &budget)?,
+        Some(_) => return Err(invalid("the file is not an ELF, PE or Mach-O file")),
+        None => return Err(invalid("the file is too small to be a library"))
+    };
+
+    // ELF files list versioned symbols once per version
+    image.exports.sort();
+    image.exports.dedup();
+
+    Ok(image)
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::InspectError(reason.into())
+}
+
+fn truncated() -> Error {
+    invalid("the file is truncated or malformed")
+}
@@ -0,0 +1,139 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/inspect/pe.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::Error;
+use super::{Format, Image, Machine, invalid, truncated};
+use super::bytes::{entries, Budget, Bytes, MAX_STRING};
+
+const EXPORT_DIRECTORY: u64 = 0;
+const IMPORT_DIRECTORY: u64 = 1;
+
+struct Section {
+    address: u64,
+    size: u64,
+    offset: u64
+}
+
+pub fn parse(data: &[u8], budget: &Budget) -> Result<Image, Error> {
+    let bytes = Bytes::new(data, false);
+
+    let header = u64::from(bytes.u32(0x3c).ok_or_else(truncated)?);
+    if bytes.slice(header, 4) != Some(b"PE\0\0") {
+        return Err(invalid("the file has an MZ header but no PE header"));
+    }
+
+    let coff = header + 4;
+    let machine = machine(bytes.u16(coff).ok_or_else(truncated)?);
+    let section_count = u64::from(bytes.u16(coff + 2).ok_or_else(truncated)?);
+    let optional_size = u64::from(bytes.u16(coff + 16).ok_or_else(truncated)?);
+
+    let optional = coff + 20;
+    let (directory_count, directories) = match bytes.u16(optional).ok_or_else(truncated)? {
+        0x10b => (optional + 92, optional + 96),
+        0x20b => (optional + 108, optional + 112),
+        _ => return Err(invalid("the PE file has an invalid optional header"))
+    };
+
+    let directory_count = u64::from(bytes.u32(directory_count).ok_or_else(truncated)?);
+
+    let mut sections = Vec::new();
+    let table = optional + optional_size;
+
+    for index in 0..section_count {
+        let section = table + index * 40;
+
+        sections.push(Section {
+            size: u64::from(bytes.u32(section + 8).ok_or_else(truncated)?),
+            address: u64::from(bytes.u32(section + 12).ok_or_else(truncated)?),
+            offset: u64::from(bytes.u32(section + 20).ok_or_else(truncated)?)
+        });
+    }
+
+    let mut image = Image {
+        format: Format::Pe,
+        machines: vec![machine],
+        dependencies: Vec::new(),
+        search_paths: Vec::new(),
+        exports: Vec::new()
+    };
+
+    if directory_count > EXPORT_DIRECTORY {
+        let exports = u64::from(bytes.u32(directories + EXPORT_DIRECTORY * 8).ok_or_else(truncated)?);
+        if exports != 0 {
+            let exports = to_offset(&sections, exports).ok_or_else(truncated)?;
+            let count = entries(u64::from(bytes.u32(exports + 24).ok_or_else(truncated)?))?;
+            let names = u64::from(bytes.u32(exports + 32).ok_or_else(truncated)?);
+            let names = to_offset(&sections, names).ok_or_else(truncated)?;
+
+            for index in 0..count {
+                let name = u64::from(bytes.u32(names + index * 4).ok_or_else(truncated)?);
+                let name = to_offset(&sections, name)
+                    .and_then(|name| bytes.string(name, MAX_STRING as u64))
+                    .ok_or_else(truncated)?;
+
+                image.exports.push(budget.own(name)?);
+            }
+        }
+    }
+
+    if directory_count <= IMPORT_DIRECTORY {
+        return Ok(image);
+    }
+
+    let imports = u64::from(bytes.u32(directories + IMPORT_DIRECTORY * 8).ok_or_else(truncated)?);
+    if imports == 0 {
+        return Ok(image);
+    }
+
+    let imports = to_offset(&sections, imports).ok_or_else(truncated)?;
+
+    // The import table has no count, ending at an entry of zeroes instead
+    for index in 0.. {
+        let descriptor = imports + entries(index)? * 20;
+        let descriptor_bytes = bytes.slice(descriptor, 20).ok_or_else(truncated)?;
+
+        if descriptor_bytes.iter().all(|&byte| byte == 0) {
+            break;
+        }
+
+        let name = u64::from(bytes.u32(descriptor + 12).ok_or_else(truncated)?);
+        let name = to_offset(&sections, name)
+            .and_then(|name| bytes.string(name, MAX_STRING as u64))
+            .ok_or_else(truncated)?;
+
+        image.dependencies.push(budget.own(name)?);
+    }
+
+    Ok(image)
+}
+
+fn to_offset(sections: &[Section], address: u64) -> Option<u64> {
+    sections.iter()
+        .find(|section| address >= section.address && address - section.address < section.size)
+        .and_then(|section| section.offset.checked_add(address - section.address))
+}
+
+fn machine(value: u16) -> Machine {
+    match value {
+        0x14c => Machine::X86,
+        0x8664 => Machine::X86_64,
+        0x1c0 | 0x1c4 => Machine::Arm,
+        0xaa64 => Machine::AArch64,
+        value => Machine::Unknown(u32::from(value))
+    }
+}
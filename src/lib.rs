@@ -23,6 +23,9 @@
 //! also possible to manually load libraries and symbols using a [`Snek`](struct.Snek.html)
 //! instance.
 //!
+//! The most commonly used types can be imported at once with
+//! `use snek::prelude::*;`.
+//!
 //! # Safety
 //! There is no way of verifying the type of loaded symbols, so both methods of
 //! using them assume that the given type is correct - this library should be used
@@ -33,51 +36,578 @@
 //! #[macro_use] extern crate snek;
 //! extern crate libc;
 //!
-//! use libc::c_int;
+//! use std::ffi::CStr;
+//! use libc::{c_char, c_int};
 //!
 //! snek! {
 //!     Example {
-//!         hello: () -> (),
+//!         hello: () -> *const c_char,
 //!         add: (x: c_int, y: c_int) -> c_int
 //!     }
 //! }
+//! # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
 //!
 //! fn main() {
-//!     if let Ok(example) = Example::load("libexample.so") {
-//!         unsafe { example.hello() };
-//!         println!("2 + 4 = {}", unsafe { example.add(2, 4) });
-//!     }
+//! # let libexample = fixture();
+//!     let example = Example::load(libexample).unwrap();
+//!
+//!     let greeting = unsafe { CStr::from_ptr(example.hello()) };
+//!     assert_eq!(greeting.to_str(), Ok("hello"));
+//!     assert_eq!(unsafe { example.add(2, 4) }, 6);
 //! }
 
 extern crate libc;
 
-#[cfg(windows)]
-extern crate winapi;
-#[cfg(windows)]
-extern crate kernel32;
+#[cfg(feature = "notify")]
+extern crate notify;
+
+#[cfg(feature = "tokio")]
+extern crate tokio;
 
-pub use snek::{Snek, load_library, load_symbol, drop_library};
-pub use symbol::Symbol;
+pub use snek::{Snek, is_supported, load_library, load_library_with_flags, load_symbol, load_symbol_version, global_symbol, drop_library};
+#[cfg(unix)]
+pub use snek::next_symbol;
+#[cfg(any(unix, windows))]
+pub use snek::load_library_with_raw_flags;
+pub use handle::Handle;
+pub use options::{set_require_absolute_paths, LoadFlags, LoadOptions};
+pub use candidates::{Candidate, CandidateErrors};
+pub use multiple::Errors;
+pub use failure::{ErrorKind, Failure};
+pub use symbol::{OwnedSymbol, RawSymbol, SharedSymbol, Symbol, TypedSymbol};
+pub use tls::TlsData;
+pub use name::SymbolName;
+pub use resolver::{Resolver, ResolverPosition};
+pub use ffi::{FfiSafe, FromCString};
+pub use registry::Registry;
+pub use link::LinkGroup;
+pub use scan::{scan_dir, ScanOptions, ScanResult};
+pub use preflight::{preflight, PreflightMethod, PreflightOptions, PreflightReport};
+pub use reload::ReloadableSnek;
+pub use shared::SharedSnek;
+#[cfg(feature = "notify")]
+pub use reload::Watch;
+pub use address::{resolve_address, AddressInfo};
+pub use loaded::{loaded_libraries, LoadedLibrary};
+
+use failure::Class;
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+#[cfg(feature = "tokio")]
+pub mod asynch;
+pub mod diagnostics;
+pub mod env;
+#[cfg(windows)]
+pub mod windows;
+#[cfg(feature = "static-registry")]
+pub mod statics;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod inspect;
+pub mod plugin;
+pub mod prelude;
 
 mod snek;
+mod handle;
+mod options;
+mod candidates;
+mod multiple;
+mod failure;
 mod symbol;
+mod tls;
+mod name;
+mod resolver;
+mod ffi;
+mod registry;
+mod link;
+mod scan;
+mod preflight;
+mod reload;
+mod shared;
+mod address;
+mod loaded;
+#[cfg(windows)]
+mod win32;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use libc::c_void;
+    pub use snek::{call_init, init_failed, set_fini, wrap_handle, wrap_process};
+    pub use symbol::{cast_function, SymbolAddress};
+    pub use std::error::Error as StdError;
+}
 
 /// This enum stores information about the error returned when loading a library
-/// or symbol fails. On unix platforms, it hold the result of `dlerror()`.
+/// or symbol fails. On unix platforms, the [`Failure`](struct.Failure.html)
+/// holds the result of `dlerror()`, and on Windows the message for the error
+/// code from `GetLastError()`.
+///
+/// Rather than matching on the message, the `is_` methods can be used to
+/// find out why a load failed.
+///
+/// `RegistryError` is returned when an operation on a [`Registry`](struct.Registry.html)
+/// would break its dependency ordering, and `PluginError` when a plugin
+/// library does not follow the [`plugin`](plugin/index.html) convention.
+/// `ReloadError` is returned when a [`ReloadableSnek`](struct.ReloadableSnek.html)
+/// cannot be reloaded, in which case the previously loaded copy is kept.
+/// `InspectError` is returned when a file cannot be read by the
+/// [`inspect`](inspect/index.html) module, and `CandidatesError` when none of
+/// several candidate paths could be loaded, holding the failure for each.
+/// `Multiple` is returned when an operation attempts several things, and
+/// more than one of them fails, holding every failure.
+/// `UnloadError` is returned by [`Snek::close`](struct.Snek.html#method.close)
+/// when a library could not be unloaded.
+/// `InvalidInput` is returned for a library path or symbol name which
+/// cannot be passed to the platform at all, as it contains a NUL byte.
+/// `Unsupported` is returned by every load on a platform without dynamic
+/// libraries, as reported by [`is_supported`](fn.is_supported.html).
+///
+/// Formatting the error gives a message for showing to a user, such as
+/// `failed to load library: libexample.so: cannot open shared object file`,
+/// the detail of which comes from the platform.
 #[derive(Debug)]
 pub enum Error {
-    LibraryLoadError(String),
-    SymbolLoadError(String)
+    LibraryLoadError(Failure),
+    SymbolLoadError(Failure),
+    RegistryError(String),
+    PluginError(String),
+    ReloadError(String),
+    InspectError(String),
+    CandidatesError(CandidateErrors),
+    InvalidInput(String),
+    UnloadError(String),
+    Multiple(Errors),
+    Unsupported
+}
+
+impl Error {
+    /// Returns the reason the load failed. For a `CandidatesError` or
+    /// `Multiple` this is the reason shared by every failure, if there is
+    /// one, and otherwise `Other`.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::{ErrorKind, Snek};
+    /// # fn main() {
+    /// for path in &["plugins/libfirst.so", "plugins/libsecond.so"] {
+    ///     match Snek::load(path) {
+    ///         Ok(_) => break,
+    ///         Err(ref err) if err.kind() == ErrorKind::NotFound => continue,
+    ///         Err(err) => println!("{}", err)
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn kind(&self) -> ErrorKind {
+        match *self {
+            Error::InvalidInput(_) => ErrorKind::InvalidInput,
+            Error::CandidatesError(_) | Error::Multiple(_) if self.is_invalid_input() => ErrorKind::InvalidInput,
+            _ => self.class().map(ErrorKind::from).unwrap_or(ErrorKind::Other)
+        }
+    }
+
+    /// Returns true if the library file, or the symbol, does not exist. A
+    /// library which exists but depends on one which does not is not counted.
+    pub fn is_not_found(&self) -> bool {
+        self.class() == Some(Class::NotFound)
+    }
+
+    /// Returns true if the library file exists but could not be read.
+    pub fn is_permission_denied(&self) -> bool {
+        self.class() == Some(Class::PermissionDenied)
+    }
+
+    /// Returns true if the library was built for a different architecture to
+    /// the current process.
+    pub fn is_wrong_architecture(&self) -> bool {
+        self.class() == Some(Class::WrongArchitecture)
+    }
+
+    /// Returns true if the library file exists but is not a valid library.
+    pub fn is_bad_image(&self) -> bool {
+        self.class() == Some(Class::BadImage)
+    }
+
+    /// Returns true if a symbol name was rejected before looking it up, as
+    /// described for [`Snek::symbol`](struct.Snek.html#method.symbol). This
+    /// is only checked in debug builds.
+    pub fn is_invalid_name(&self) -> bool {
+        self.class() == Some(Class::InvalidName)
+    }
+
+    /// Returns true if a library path or symbol name could not be passed to
+    /// the platform, as it contains a NUL byte. This is checked in release
+    /// builds too.
+    pub fn is_invalid_input(&self) -> bool {
+        match *self {
+            Error::InvalidInput(_) => true,
+            Error::CandidatesError(ref candidates) => !candidates.is_empty() && candidates.iter().all(|candidate| candidate.error().is_invalid_input()),
+            Error::Multiple(ref errors) => errors.iter().all(Error::is_invalid_input),
+            _ => false
+        }
+    }
+
+    /// Returns true if a library was not loaded because its path is relative
+    /// or a bare file name, and absolute paths are required, as described
+    /// for [`LoadOptions::require_absolute_paths`](struct.LoadOptions.html#method.require_absolute_paths).
+    pub fn is_relative_path(&self) -> bool {
+        self.class() == Some(Class::RelativePath)
+    }
+
+    /// Returns true if the symbol exists but its address is null, as
+    /// described for [`Snek::nullable_symbol`](struct.Snek.html#method.nullable_symbol).
+    pub fn is_null_symbol(&self) -> bool {
+        self.class() == Some(Class::NullSymbol)
+    }
+
+    /// Returns true if the operation is not supported by the platform,
+    /// including every load on a platform without dynamic libraries.
+    pub fn is_unsupported(&self) -> bool {
+        self.class() == Some(Class::Unsupported)
+    }
+
+    /// Returns the OS error code for a `LibraryLoadError` or
+    /// `SymbolLoadError`, if one is known, as described for
+    /// [`Failure::os_code`](struct.Failure.html#method.os_code).
+    pub fn os_code(&self) -> Option<i32> {
+        match *self {
+            Error::LibraryLoadError(ref failure) | Error::SymbolLoadError(ref failure) => failure.os_code(),
+            _ => None
+        }
+    }
+
+    /// Returns the path of the library which could not be loaded, for a
+    /// `LibraryLoadError`.
+    pub fn path(&self) -> Option<&Path> {
+        match *self {
+            Error::LibraryLoadError(ref failure) => failure.path(),
+            _ => None
+        }
+    }
+
+    /// Returns the name of the symbol which could not be loaded, for a
+    /// `SymbolLoadError`.
+    pub fn symbol(&self) -> Option<&str> {
+        match *self {
+            Error::SymbolLoadError(ref failure) => failure.symbol(),
+            _ => None
+        }
+    }
+
+    pub(crate) fn with_path(self, path: &Path) -> Error {
+        match self {
+            Error::LibraryLoadError(failure) => Error::LibraryLoadError(failure.with_path(path)),
+            err => err
+        }
+    }
+
+    pub(crate) fn with_symbol(self, symbol: &str) -> Error {
+        match self {
+            Error::SymbolLoadError(failure) => Error::SymbolLoadError(failure.with_symbol(symbol)),
+            err => err
+        }
+    }
+
+    // For a CandidatesError or Multiple, the class shared by every error, if any
+    fn class(&self) -> Option<Class> {
+        match *self {
+            Error::LibraryLoadError(ref failure) | Error::SymbolLoadError(ref failure) => Some(failure.class),
+            Error::Unsupported => Some(Class::Unsupported),
+
+            Error::CandidatesError(ref candidates) => {
+                let mut classes = candidates.iter().map(|candidate| candidate.error().class());
+                let first = classes.next()?;
+                if classes.all(|class| class == first) { first } else { None }
+            },
+
+            Error::Multiple(ref errors) => {
+                let mut classes = errors.iter().map(Error::class);
+                let first = classes.next()?;
+                if classes.all(|class| class == first) { first } else { None }
+            },
+
+            _ => None
+        }
+    }
+
+    pub(crate) fn message(&self) -> String {
+        match *self {
+            Error::LibraryLoadError(ref failure) |
+            Error::SymbolLoadError(ref failure) => failure.message.clone(),
+            Error::RegistryError(ref message) |
+            Error::PluginError(ref message) |
+            Error::ReloadError(ref message) |
+            Error::InspectError(ref message) |
+            Error::InvalidInput(ref message) |
+            Error::UnloadError(ref message) => message.clone(),
+            Error::CandidatesError(ref candidates) => candidates.to_string(),
+            Error::Multiple(ref errors) => errors.to_string(),
+            Error::Unsupported => "dynamic libraries are not supported on this platform".into()
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // The name is only added where the platform's message lacks it, as
+        // unix messages already start with it
+        let named = |failure: &Failure, name: Option<String>| match name {
+            Some(ref name) if !failure.message.contains(name.as_str()) => format!(" {}", name),
+            _ => String::new()
+        };
+
+        match *self {
+            Error::LibraryLoadError(ref failure) => {
+                let name = named(failure, failure.path().map(|path| path.display().to_string()));
+                write!(f, "failed to load library{}: {}", name, failure)
+            },
+
+            Error::SymbolLoadError(ref failure) => {
+                let name = named(failure, failure.symbol().map(str::to_owned));
+                write!(f, "failed to load symbol{}: {}", name, failure)
+            },
+
+            Error::RegistryError(ref message) => write!(f, "library registry error: {}", message),
+            Error::PluginError(ref message) => write!(f, "invalid plugin: {}", message),
+            Error::ReloadError(ref message) => write!(f, "failed to reload library: {}", message),
+            Error::InspectError(ref message) => write!(f, "failed to inspect library: {}", message),
+            Error::InvalidInput(ref message) => write!(f, "invalid input: {}", message),
+            Error::UnloadError(ref message) => write!(f, "failed to unload library: {}", message),
+            Error::Multiple(ref errors) => write!(f, "{}", errors),
+            Error::CandidatesError(ref candidates) => write!(f, "failed to load library: {}", candidates),
+            Error::Unsupported => f.write_str(&self.message())
+        }
+    }
+}
+
+impl error::Error for Error {
+    // The message of the OS error is already part of the failure's, but its
+    // code and kind are kept with it
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::LibraryLoadError(ref failure) | Error::SymbolLoadError(ref failure) => {
+                failure.source.as_ref().map(|err| &**err as &(dyn error::Error + 'static))
+            },
+
+            _ => None
+        }
+    }
+}
+
+/// Converts into an `io::Error` of the closest `io::ErrorKind`, which holds
+/// the `Error` itself, so it can be recovered with `get_ref` and
+/// `downcast_ref`, or `into_inner` and `downcast`.
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::Snek;
+/// # use std::io;
+/// # fn main() {
+/// fn load() -> io::Result<Snek> {
+///     Ok(Snek::load("libexample.so")?)
+/// }
+///
+/// let err = load().unwrap_err();
+/// assert_eq!(err.kind(), io::ErrorKind::NotFound);
+/// assert!(err.get_ref().unwrap().downcast_ref::<snek::Error>().is_some());
+/// # }
+/// ```
+impl From<Error> for io::Error {
+    fn from(err: Error) -> io::Error {
+        let kind = match err.kind() {
+            ErrorKind::NotFound => io::ErrorKind::NotFound,
+            ErrorKind::PermissionDenied => io::ErrorKind::PermissionDenied,
+            ErrorKind::InvalidName | ErrorKind::InvalidInput => io::ErrorKind::InvalidInput,
+            ErrorKind::Unsupported => io::ErrorKind::Unsupported,
+            _ => io::ErrorKind::Other
+        };
+
+        io::Error::new(kind, err)
+    }
+}
+
+/// This macro is used on the plugin side to export a
+/// [`plugin::Info`](plugin/struct.Info.html) structure under the name
+/// [`plugin::INFO_SYMBOL`](plugin/constant.INFO_SYMBOL.html), so that a host
+/// can read it with [`Snek::plugin_info`](struct.Snek.html#method.plugin_info).
+///
+/// # Example
+/// ```
+/// # #[macro_use] extern crate snek;
+/// declare_plugin_info! {
+///     name: "example",
+///     version: [1, 2, 0],
+///     author: "Samuel Sleight",
+///     capabilities: 0b11
+/// }
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! declare_plugin_info {
+    (name: $name:expr, version: $version:expr, author: $author:expr, capabilities: $capabilities:expr) => {
+        #[no_mangle]
+        pub static PLUGIN_INFO: $crate::plugin::Info = $crate::plugin::Info::new($name, $version, $author, $capabilities);
+    }
+}
+
+/// This macro is used to register a library whose functions are linked
+/// statically into the program with the [`statics`](statics/index.html)
+/// registry, so that loading the given path uses them. Each function is
+/// registered under its own name, and must be in scope. This requires the
+/// `static-registry` feature.
+///
+/// # Example
+/// ```
+/// # #[macro_use] extern crate snek;
+/// # use std::os::raw::c_int;
+/// # use snek::Snek;
+/// extern "C" fn add(x: c_int, y: c_int) -> c_int {
+///     x + y
+/// }
+///
+/// extern "C" fn sub(x: c_int, y: c_int) -> c_int {
+///     x - y
+/// }
+///
+/// fn main() {
+///     register_static_library! {
+///         "libexample.so" {
+///             add,
+///             sub
+///         }
+///     }
+///
+///     let snek = Snek::load("libexample.so").unwrap();
+///     assert!(snek.symbol("sub").is_ok());
+/// }
+/// ```
+#[cfg(feature = "static-registry")]
+#[macro_export]
+macro_rules! register_static_library {
+    ($path:tt {
+        $($symbol:ident),* $(,)*
+    }) => {
+        $crate::statics::register($path, &[$((stringify!($symbol), $symbol as *const $crate::__private::c_void)),*])
+    }
+}
+
+/// This macro calls a symbol as a C function of the given type, given any of
+/// a [`Symbol`](struct.Symbol.html), an [`OwnedSymbol`](struct.OwnedSymbol.html),
+/// a [`RawSymbol`](struct.RawSymbol.html), or a raw `*mut c_void` address.
+///
+/// The type is written as `fn(<parameter types>) -> <return type>`, and the
+/// symbol is called with the `extern "C"` ABI, unless another is given as in
+/// `extern "system" fn(...)`. As with [`Symbol::with`](struct.Symbol.html#method.with),
+/// nothing can check that the symbol really has this type, so the macro must
+/// be used inside an `unsafe` block.
+///
+/// # Panics
+/// This panics if the address is null.
+///
+/// # Example
+/// ```
+/// # #[macro_use] extern crate snek;
+/// # use snek::Snek;
+/// # use std::os::raw::c_int;
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+/// # fn main() {
+/// # let libexample = fixture();
+/// let snek = Snek::load(libexample).unwrap();
+/// let add = snek.symbol("add").unwrap();
+///
+/// let result = unsafe { call_symbol!(add, fn(c_int, c_int) -> c_int, (3, 7)) };
+/// assert_eq!(result, 10);
+/// # }
+/// ```
+///
+/// Anything other than a function type is rejected:
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate snek;
+/// # use snek::Snek;
+/// # use std::os::raw::c_int;
+/// # fn main() {
+/// # let snek = Snek::load("libexample.so").unwrap();
+/// # let add = snek.symbol("add").unwrap();
+/// let result = unsafe { call_symbol!(add, c_int, (3, 7)) };
+/// # }
+/// ```
+///
+/// As is calling it outside of an `unsafe` block:
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate snek;
+/// # use snek::Snek;
+/// # use std::os::raw::c_int;
+/// # fn main() {
+/// # let snek = Snek::load("libexample.so").unwrap();
+/// # let add = snek.symbol("add").unwrap();
+/// let result = call_symbol!(add, fn(c_int, c_int) -> c_int, (3, 7));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! call_symbol {
+    ($symbol:expr, fn($($param:ty),* $(,)?) $(-> $ret:ty)?, ($($arg:expr),* $(,)?)) => {
+        $crate::call_symbol!($symbol, extern "C" fn($($param),*) $(-> $ret)?, ($($arg),*))
+    };
+
+    ($symbol:expr, extern $abi:tt fn($($param:ty),* $(,)?) $(-> $ret:ty)?, ($($arg:expr),* $(,)?)) => {
+        $crate::__private::cast_function::<unsafe extern $abi fn($($param),*) $(-> $ret)?>(&$symbol)($($arg),*)
+    };
 }
 
 /// This macro is used to generate a struct that wraps a dynamic library with
 /// generated loading code. Each defined function will be loaded as a symbol
 /// from the library when an instance of the struct is constructed, and can be
-/// called via functions of the same name attached to the struct.
+/// called via functions of the same name attached to the struct. Each symbol
+/// is cast to its function type once when it is loaded, so a call is a direct
+/// call through a function pointer.
+///
+/// The struct is constructed with `load`, which takes the path of the library,
+/// or `from_handle`, which takes a [`Handle`](struct.Handle.html) to a library
+/// which is already loaded, such as one from [`load_library`](fn.load_library.html).
 ///
 /// In the same way as a [`Snek`](struct.Snek.html) instance, when an instance
 /// of a struct defined by this macro is dropped, the library is unloaded.
 ///
+/// The struct wraps a [`Snek`](struct.Snek.html), which it can be borrowed as
+/// through `AsRef`, and symbols not declared in the block can be loaded from
+/// the same library with `extra_symbol`. As with the declared functions, they
+/// can only be used while the struct is borrowed:
+///
+/// ```
+/// # #[macro_use] extern crate snek;
+/// # extern crate libc;
+/// # use libc::c_int;
+/// snek! {
+///     Example {
+///         add: (x: c_int, y: c_int) -> c_int
+///     }
+/// }
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+///
+/// fn main() {
+/// # let libexample = fixture();
+///     let example = Example::load(libexample).unwrap();
+///     assert_eq!(unsafe { example.add(3, 7) }, 10);
+///
+///     // An export which is not part of the declared interface yet
+///     if let Ok(version) = example.extra_symbol("version") {
+///         let version = unsafe { version.with(|version: extern fn() -> c_int| version()) };
+///         println!("loaded version {}", version);
+///     }
+///
+///     let snek: &snek::Snek = example.as_ref();
+///     assert!(snek.symbol("hello").is_ok());
+/// }
+/// ```
+///
 /// # Safety
 /// As with [`Symbol::with`](struct.Symbol.html#method.with), there is no way
 /// of verifying the types of the functions so care should be taken to ensure
@@ -97,11 +627,12 @@ pub enum Error {
 ///         add: (x: c_int, y: c_int) -> c_int
 ///     }
 /// }
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
 ///
 /// fn main() {
-///     if let Ok(example) = Example::load("libexample.so") {
-///         println!("{}", unsafe { example.add(3, 7) })
-///     }
+/// # let libexample = fixture();
+///     let example = Example::load(libexample).unwrap();
+///     assert_eq!(unsafe { example.add(3, 7) }, 10);
 /// }
 /// ```
 ///
@@ -111,49 +642,614 @@ pub enum Error {
 /// # #[macro_use] extern crate snek;
 /// # extern crate libc;
 /// # use libc::c_int;
+/// # use libc::c_char;
+/// snek! {
+///     Example {
+///         add: (x: c_int, y: c_int) -> c_int,
+///         hello: () -> *const c_char
+///     }
+/// }
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+/// # fn main() {
+/// # let libexample = fixture();
+/// # let example = Example::load(libexample).unwrap();
+/// # assert!(!unsafe { example.hello() }.is_null());
+/// # }
+/// ```
+///
+/// A parameter can be given a Rust type to accept as well as the C type passed
+/// to the function, as `name: &str as *const c_char`, in which case the
+/// generated function takes the Rust type, converts it to a `CString` which
+/// lives until the call returns, and passes its pointer. Any type accepted by
+/// `CString::new` can be used. A string containing a NUL byte panics, unless
+/// the block is marked `#[fallible_strings]`, in which case every function in
+/// it returns a `Result` which is `Err(NulError)` instead:
+///
+/// ```
+/// # #[macro_use] extern crate snek;
+/// # extern crate libc;
+/// # use libc::c_char;
+/// snek! {
+///     #[fallible_strings]
+///     Example {
+///         string_length: (string: &str as *const c_char) -> usize
+///     }
+/// }
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+///
+/// fn main() {
+/// # let libexample = fixture();
+///     let example = Example::load(libexample).unwrap();
+///     assert_eq!(unsafe { example.string_length("hello") }, Ok(5));
+///     assert!(unsafe { example.string_length("hel\0lo") }.is_err());
+/// }
+/// ```
+///
+/// Similarly, a return type can be given a Rust type to convert to, as
+/// `-> *mut c_char as String`, in which case the generated function copies the
+/// returned C string into a [`FromCString`](trait.FromCString.html) type, such
+/// as `String` or `Option<String>`. Following this with `=> free_string` also
+/// loads `free_string` from the library along with the function, and calls it
+/// with the returned pointer once it has been copied, whether or not the copy
+/// succeeded:
+///
+/// ```
+/// # #[macro_use] extern crate snek;
+/// # extern crate libc;
+/// # use libc::{c_char, c_int};
+/// snek! {
+///     Example {
+///         hello: () -> *const c_char as String,
+///         name_of: (id: c_int) -> *mut c_char as Option<String> => free_string
+///     }
+/// }
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+///
+/// fn main() {
+/// # let libexample = fixture();
+///     let example = Example::load(libexample).unwrap();
+///     assert_eq!(unsafe { example.hello() }, "hello");
+///     assert_eq!(unsafe { example.name_of(1) }, Some("one".into()));
+/// }
+/// ```
+///
+/// Naming an init function after the functions calls it once every symbol
+/// has been loaded, taking no arguments and returning an `int`. If it returns
+/// non-zero, loading fails with [`Error::LibraryLoadError`](enum.Error.html)
+/// and the library is unloaded again. Naming a fini function, taking no
+/// arguments and returning nothing, calls it when the struct is dropped, just
+/// before the library is unloaded. It is looked up before the init function
+/// is called, and is only called if the init function succeeded, so as with
+/// [`LoadOptions::fini`](struct.LoadOptions.html#method.fini) a library is
+/// never initialised without being shut down. Both are called by `from_handle`
+/// as well as `load`:
+///
+/// ```
+/// # #[macro_use] extern crate snek;
+/// # extern crate libc;
+/// # use libc::c_int;
 /// snek! {
 ///     Example {
+///         add: (x: c_int, y: c_int) -> c_int
+///     }
+///
+///     init fixture_init;
+///     fini fixture_fini;
+/// }
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+/// # fn main() {
+/// # let libexample = fixture();
+/// # let example = Example::load(libexample).unwrap();
+/// # assert_eq!(unsafe { example.add(3, 7) }, 10);
+/// # }
+/// ```
+///
+/// Marking the block `#[load_error(Name)]` also generates an error enum with
+/// that name, which `load` and `from_handle` return in place of
+/// [`Error`](enum.Error.html), saying which stage of loading failed: `Library`
+/// holding the error from loading the library, `Symbol` holding the name of
+/// the symbol (or init or fini function) which could not be loaded along with
+/// the error, or `InitFailed` holding the init function's return code. It
+/// implements `std::error::Error`, and converts into an `Error` for callers
+/// which do not need the detail:
+///
+/// ```
+/// # #[macro_use] extern crate snek;
+/// # extern crate libc;
+/// # use libc::c_int;
+/// snek! {
+///     #[load_error(ExampleLoadError)]
+///     Example {
 ///         add: (x: c_int, y: c_int) -> c_int,
-///         hello: () -> ()
+///         subtract: (x: c_int, y: c_int) -> c_int
+///     }
+/// }
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+///
+/// fn main() {
+/// # let libexample = fixture();
+///     match Example::load(libexample) {
+///         Err(ExampleLoadError::Symbol { name, .. }) => assert_eq!(name, "subtract"),
+///         _ => unreachable!()
+///     }
+/// }
+/// ```
+///
+/// Marking the block `#[deny_non_ffi]` checks that every parameter and return
+/// type implements [`FfiSafe`](trait.FfiSafe.html), so that a type which
+/// cannot cross the C ABI, such as `String`, is a compile error at the entry
+/// using it rather than memory corruption at the first call:
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate snek;
+/// snek! {
+///     #[deny_non_ffi]
+///     Example {
+///         greet: (name: String) -> Vec<u8>
 ///     }
 /// }
-/// # fn main () {}
+/// # fn main() {}
+/// ```
+///
+/// Marking the block `#[self_process]` also generates a `load_self` function,
+/// which resolves the symbols against the current process rather than a
+/// library file, using `dlopen(NULL)` on unix and `GetModuleHandle(NULL)` on
+/// Windows, for calling back into functions a host executable exports (as
+/// `#[no_mangle] pub extern "C"` functions, linked with `-rdynamic` on
+/// Linux). The process's handle is never released when the struct is dropped.
+/// On unix the lookups also search every library loaded with global
+/// visibility, such as the C library:
+///
+/// ```
+/// # #[macro_use] extern crate snek;
+/// # extern crate libc;
+/// # use libc::{c_char, size_t};
+/// snek! {
+///     #[self_process]
+///     Host {
+///         strlen: (s: *const c_char) -> size_t
+///     }
+/// }
+///
+/// fn main() {
+/// # #[cfg(unix)] {
+///     let host = Host::load_self().unwrap();
+///     assert_eq!(unsafe { host.strlen(b"host\0".as_ptr() as *const c_char) }, 4);
+/// # }
+/// }
+/// ```
+///
+/// Naming a vtable after the functions also generates a `#[repr(C)]` struct
+/// with a public field for each function, in the order they are declared,
+/// which can be passed to foreign code expecting a table of function
+/// pointers. The loaded struct gains a `vtable` function returning one
+/// filled in with its symbols, and the vtable can also be filled in by hand
+/// with functions defined in Rust, so that the same declaration describes the
+/// interface whichever side implements it:
+///
+/// ```
+/// # #[macro_use] extern crate snek;
+/// # extern crate libc;
+/// # use libc::c_int;
+/// snek! {
+///     Example {
+///         add: (x: c_int, y: c_int) -> c_int
+///     }
+///
+///     vtable ExampleVtable;
+/// }
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+///
+/// extern "C" fn subtract(x: c_int, y: c_int) -> c_int {
+///     x - y
+/// }
+///
+/// fn main() {
+/// # let libexample = fixture();
+///     let example = Example::load(libexample).unwrap();
+///     let loaded = example.vtable();
+///     assert_eq!(unsafe { (loaded.add)(3, 7) }, 10);
+///
+///     let host = ExampleVtable { add: subtract };
+///     assert_eq!(unsafe { (host.add)(3, 7) }, -4);
+/// }
 /// ```
 #[macro_export]
 macro_rules! snek {
-    ($sname:ident { 
-        $($symbol:ident : ($($pn: ident : $pt:ty),*) -> $ot:ty),*
-    }) => {
+    (#[deny_non_ffi]
+    $(#[$($attr:tt)*])*
+    $(@strings $strings:tt)?
+    $(@error $error:tt)?
+    $sname:ident {
+        $($symbol:ident : ($($pn: ident : $pt:ty $(as $ct:ty)?),*) -> $ot:ty $(as $rt:ty $(=> $free:ident)?)?),*
+    }
+
+    $($rest:tt)*) => {
+        $crate::snek! {
+            $(#[$($attr)*])*
+            $(@strings $strings)?
+            $(@error $error)?
+            $sname {
+                $($symbol: ($($pn: $pt $(as $ct)?),*) -> $ot $(as $rt $(=> $free)?)?),*
+            }
+
+            $($rest)*
+        }
+
+        // A function per symbol, so that an error names the entry it is in
+        const _: () = {
+            fn ffi_safe<T: $crate::FfiSafe>() {}
+
+            $(#[allow(dead_code)]
+            fn $symbol() {
+                $(ffi_safe::<$crate::snek!(@ctype $pt $(as $ct)?)>();)*
+                ffi_safe::<$ot>();
+            })*
+        };
+    };
+
+    // This is handled after every other attribute, as it needs their options
+    (#[self_process]
+    #[$($next:tt)*]
+    $($rest:tt)*) => {
+        $crate::snek! {
+            #[$($next)*]
+            #[self_process]
+            $($rest)*
+        }
+    };
+
+    (#[self_process]
+    $(@strings $strings:tt)?
+    $(@error $error:tt)?
+    $sname:ident {
+        $($symbol:ident : ($($pn: ident : $pt:ty $(as $ct:ty)?),*) -> $ot:ty $(as $rt:ty $(=> $free:ident)?)?),*
+    }
+
+    $($rest:tt)*) => {
+        $crate::snek! {
+            $(@strings $strings)?
+            $(@error $error)?
+            $sname {
+                $($symbol: ($($pn: $pt $(as $ct)?),*) -> $ot $(as $rt $(=> $free)?)?),*
+            }
+
+            $($rest)*
+        }
+
+        impl<'a> $sname<'a> {
+            /// Resolve the symbols against the current process, finding
+            /// functions exported by the executable itself rather than a
+            /// library file. The process's handle is never released.
+            pub fn load_self() -> ::std::result::Result<$sname<'a>, $crate::snek!(@error_type $($error)?)> {
+                match $crate::__private::wrap_process() {
+                    Ok(snek) => $sname::from_snek(snek),
+                    Err(err) => Err($crate::snek!(@library_error $($error)? err))
+                }
+            }
+        }
+    };
+
+    (#[fallible_strings]
+    $(#[$($attr:tt)*])*
+    $(@error $error:tt)?
+    $sname:ident {
+        $($symbol:ident : ($($pn: ident : $pt:ty $(as $ct:ty)?),*) -> $ot:ty $(as $rt:ty $(=> $free:ident)?)?),*
+    }
+
+    $($rest:tt)*) => {
+        $crate::snek! {
+            $(#[$($attr)*])*
+            @strings [result]
+            $(@error $error)?
+            $sname {
+                $($symbol: ($($pn: $pt $(as $ct)?),*) -> $ot $(as $rt $(=> $free)?)?),*
+            }
+
+            $($rest)*
+        }
+    };
+
+    (#[load_error($ename:ident)]
+    $(#[$($attr:tt)*])*
+    $(@strings $strings:tt)?
+    $sname:ident {
+        $($symbol:ident : ($($pn: ident : $pt:ty $(as $ct:ty)?),*) -> $ot:ty $(as $rt:ty $(=> $free:ident)?)?),*
+    }
+
+    $($rest:tt)*) => {
+        $crate::snek! {
+            $(#[$($attr)*])*
+            $(@strings $strings)?
+            @error [$ename]
+            $sname {
+                $($symbol: ($($pn: $pt $(as $ct)?),*) -> $ot $(as $rt $(=> $free)?)?),*
+            }
+
+            $($rest)*
+        }
+
+        /// The ways loading the library can fail.
+        #[derive(Debug)]
+        pub enum $ename {
+            /// The library itself could not be loaded.
+            Library($crate::Error),
+
+            /// A symbol, or the init or fini function, could not be loaded.
+            Symbol {
+                name: &'static str,
+                source: $crate::Error
+            },
+
+            /// The init function returned the given non-zero code.
+            InitFailed(i32)
+        }
+
+        impl ::std::fmt::Display for $ename {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                match *self {
+                    $ename::Library(ref err) => write!(f, "{}", err),
+
+                    $ename::Symbol { name, ref source } => match *source {
+                        $crate::Error::SymbolLoadError(ref failure) => write!(f, "failed to load symbol {}: {}", name, failure),
+                        ref source => write!(f, "failed to load symbol {}: {}", name, source)
+                    },
+
+                    $ename::InitFailed(code) => write!(f, "init function returned {}", code)
+                }
+            }
+        }
+
+        impl ::std::error::Error for $ename {
+            fn source(&self) -> Option<&(dyn $crate::__private::StdError + 'static)> {
+                match *self {
+                    $ename::Library(ref err) => ::std::error::Error::source(err),
+                    $ename::Symbol { ref source, .. } => Some(source),
+                    $ename::InitFailed(_) => None
+                }
+            }
+        }
+
+        impl ::std::convert::From<$ename> for $crate::Error {
+            fn from(err: $ename) -> $crate::Error {
+                match err {
+                    $ename::Library(err) => err,
+                    $ename::Symbol { source, .. } => source,
+                    $ename::InitFailed(code) => $crate::__private::init_failed(code)
+                }
+            }
+        }
+    };
+
+    (@strings $strings:tt
+    @error $error:tt
+    $sname:ident {
+        $($symbol:ident : ($($pn: ident : $pt:ty $(as $ct:ty)?),*) -> $ot:ty $(as $rt:ty $(=> $free:ident)?)?),*
+    }
+
+    $(init $init:ident;)*
+    $(fini $fini:ident;)*
+    vtable $vname:ident;) => {
+        $crate::snek! {
+            @strings $strings
+            @error $error
+            $sname {
+                $($symbol: ($($pn: $pt $(as $ct)?),*) -> $ot $(as $rt $(=> $free)?)?),*
+            }
+
+            $(init $init;)*
+            $(fini $fini;)*
+        }
+
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        pub struct $vname {
+            $(pub $symbol: unsafe extern "C" fn($($crate::snek!(@ctype $pt $(as $ct)?)),*) -> $ot,)*
+        }
+
+        // Every field is a function pointer, so padding or a changed field
+        // type would change the size
+        const _: () = assert!(
+            ::std::mem::size_of::<$vname>() == [$(stringify!($symbol)),*].len() * ::std::mem::size_of::<usize>()
+        );
+
+        impl<'a> $sname<'a> {
+            /// Returns a vtable filled in with the loaded symbols, which is
+            /// only valid while this library is loaded.
+            pub fn vtable(&self) -> $vname {
+                $vname {
+                    $($symbol: $crate::snek!(@function self.$symbol; $ot $(as $rt $(=> $free)?)?),)*
+                }
+            }
+        }
+    };
+
+    (@strings $strings:tt
+    @error $error:tt
+    $sname:ident {
+        $($symbol:ident : ($($pn: ident : $pt:ty $(as $ct:ty)?),*) -> $ot:ty $(as $rt:ty $(=> $free:ident)?)?),*
+    }
+
+    $(init $init:ident;)*
+    $(fini $fini:ident;)*) => {
         pub struct $sname<'a> {
-            handle: *mut libc::c_void,
-            $($symbol: snek::Symbol<'a>),*
+            __snek: $crate::Snek,
+            $($symbol: $crate::snek!(@field unsafe extern "C" fn($($crate::snek!(@ctype $pt $(as $ct)?)),*) -> $ot; $ot $(as $rt $(=> $free)?)?),)*
+            _life: ::std::marker::PhantomData<&'a ()>
         }
 
         impl<'a> $sname<'a> {
-            pub fn load<P>(path: P) -> Result<$sname<'a>, snek::Error> where P: AsRef<std::path::Path> {
-                let handle = match snek::load_library(path) {
-                    Ok(result) => result,
-                    Err(err) => return Err(err)
-                };
+            pub fn load<P>(path: P) -> ::std::result::Result<$sname<'a>, $crate::snek!(@error_type $error)> where P: ::std::convert::AsRef<::std::path::Path> {
+                match $crate::load_library(path.as_ref()) {
+                    Ok(handle) => $sname::from_snek($crate::__private::wrap_handle(handle, path.as_ref())),
+                    Err(err) => Err($crate::snek!(@library_error $error err))
+                }
+            }
 
+            pub fn from_handle(handle: $crate::Handle) -> ::std::result::Result<$sname<'a>, $crate::snek!(@error_type $error)> {
+                $sname::from_snek($crate::__private::wrap_handle(handle, ::std::path::Path::new("")))
+            }
+
+            fn from_snek(mut snek: $crate::Snek) -> ::std::result::Result<$sname<'a>, $crate::snek!(@error_type $error)> {
+                // The symbols are cast to their function types once here, so
+                // that each call is a plain call through a function pointer.
+                // If one is missing the library is unloaded again as the
+                // Snek is dropped, before any fini function is set.
+                $(let $symbol = unsafe {
+                    $crate::snek!(@load $error snek $symbol unsafe extern "C" fn($($crate::snek!(@ctype $pt $(as $ct)?)),*) -> $ot; $ot $(as $rt $(=> $free)?)?)
+                };)*
+
+                // The fini function is found before init is called, so that
+                // nothing can fail once the library is initialised
+                let fini: ::std::option::Option<unsafe extern "C" fn()> = ::std::option::Option::None
+                    $(.or(::std::option::Option::Some(unsafe {
+                        ::std::mem::transmute::<*mut $crate::__private::c_void, unsafe extern "C" fn()>($crate::snek!(@symbol $error snek $fini))
+                    })))*;
+
+                $($crate::snek!(@init $error snek $init);)*
+
+                $crate::__private::set_fini(&mut snek, fini);
                 Ok($sname {
-                    handle: handle,
-                    $($symbol: match snek::load_symbol(handle, stringify!($symbol)) {
-                        Ok(result) => snek::Symbol::new(result),
-                        Err(err) => return Err(err)
-                    }),*
+                    __snek: snek,
+                    $($symbol: $symbol,)*
+                    _life: ::std::marker::PhantomData
                 })
             }
 
-            $(pub unsafe fn $symbol(&self, $($pn: $pt),*) -> $ot {
-                self.$symbol.with(|f: extern fn($($pt),*) -> $ot| f($($pn),*))
+            /// Load a symbol not declared in the block from the same
+            /// library, as with `snek::Snek::symbol`, which is valid for as
+            /// long as this is borrowed.
+            pub fn extra_symbol<N>(&self, symbol: N) -> ::std::result::Result<$crate::Symbol<'_>, $crate::Error> where N: $crate::SymbolName {
+                self.__snek.symbol(symbol)
+            }
+
+            // Each converted string is kept alive until the call returns
+            $(#[inline]
+            pub unsafe fn $symbol(&self, $($pn: $pt),*) -> $crate::snek!(@result $strings $crate::snek!(@rtype $ot $(as $rt)?)) {
+                $(let $pn = $crate::snek!(@convert $strings $pn $(as $ct)?);)*
+                $crate::snek!(@wrap $strings $crate::snek!(@return self.$symbol; ($($crate::snek!(@pass $pn $(as $ct)?)),*); $ot $(as $rt $(=> $free)?)?))
             })*
         }
 
-        impl<'a> Drop for $sname<'a> {
-            fn drop(&mut self) {
-                snek::drop_library(self.handle)
+        impl<'a> ::std::convert::AsRef<$crate::Snek> for $sname<'a> {
+            fn as_ref(&self) -> &$crate::Snek {
+                &self.__snek
             }
         }
-    }
+    };
+
+    // Any options not given by an attribute take their defaults
+    (@strings $strings:tt $sname:ident $($rest:tt)*) => {
+        $crate::snek! {
+            @strings $strings
+            @error []
+            $sname $($rest)*
+        }
+    };
+
+    (@error $error:tt $sname:ident $($rest:tt)*) => {
+        $crate::snek! {
+            @strings []
+            @error $error
+            $sname $($rest)*
+        }
+    };
+
+    ($sname:ident $($rest:tt)*) => {
+        $crate::snek! {
+            @strings []
+            @error []
+            $sname $($rest)*
+        }
+    };
+
+    // The type passed to the C function for a parameter
+    (@ctype $pt:ty) => { $pt };
+    (@ctype $pt:ty as $ct:ty) => { $ct };
+
+    (@convert $strings:tt $pn:ident) => { $pn };
+    (@convert [] $pn:ident as $ct:ty) => {
+        ::std::ffi::CString::new($pn).expect(concat!("string argument ", stringify!($pn), " contains a NUL byte"))
+    };
+    (@convert [result] $pn:ident as $ct:ty) => { ::std::ffi::CString::new($pn)? };
+
+    (@pass $pn:ident) => { $pn };
+    (@pass $pn:ident as $ct:ty) => { $pn.as_ptr() };
+
+    (@result [] $ot:ty) => { $ot };
+    (@result [result] $ot:ty) => { ::std::result::Result<$ot, ::std::ffi::NulError> };
+
+    // The type returned by the generated function, converted if marked
+    (@rtype $ot:ty) => { $ot };
+    (@rtype $ot:ty as $rt:ty) => { $rt };
+
+    // A symbol with a free function is stored along with it
+    (@field $fn:ty; $ot:ty $(as $rt:ty)?) => { $fn };
+    (@field $fn:ty; $ot:ty as $rt:ty => $free:ident) => { ($fn, unsafe extern "C" fn($ot)) };
+
+    (@load $error:tt $snek:ident $symbol:ident $fn:ty; $ot:ty $(as $rt:ty)?) => {
+        ::std::mem::transmute::<*mut $crate::__private::c_void, $fn>($crate::snek!(@symbol $error $snek $symbol))
+    };
+    (@load $error:tt $snek:ident $symbol:ident $fn:ty; $ot:ty as $rt:ty => $free:ident) => {
+        (
+            $crate::snek!(@load $error $snek $symbol $fn; $ot),
+            ::std::mem::transmute::<*mut $crate::__private::c_void, unsafe extern "C" fn($ot)>($crate::snek!(@symbol $error $snek $free))
+        )
+    };
+
+    (@function $field:expr; $ot:ty $(as $rt:ty)?) => { $field };
+    (@function $field:expr; $ot:ty as $rt:ty => $free:ident) => { $field.0 };
+
+    (@return $field:expr; $args:tt; $ot:ty) => { ($field) $args };
+    (@return $field:expr; $args:tt; $ot:ty as $rt:ty) => {
+        <$rt as $crate::FromCString>::from_c_string(($field) $args as *const ::std::os::raw::c_char)
+    };
+    (@return $field:expr; $args:tt; $ot:ty as $rt:ty => $free:ident) => {{
+        // The string is copied before it is freed, so it is freed whether or
+        // not the conversion succeeds
+        let result = ($field.0) $args;
+        let value = <$rt as $crate::FromCString>::from_c_string(result as *const ::std::os::raw::c_char);
+
+        ($field.1)(result);
+        value
+    }};
+
+    (@wrap [] $call:expr) => { $call };
+    (@wrap [result] $call:expr) => { ::std::result::Result::Ok($call) };
+
+    // The error returned from loading, which is a generated enum when the
+    // block is marked #[load_error]
+    (@error_type) => { $crate::Error };
+    (@error_type []) => { $crate::Error };
+    (@error_type [$ename:ident]) => { $ename };
+
+    (@library_error $err:ident) => { $err };
+    (@library_error [] $err:ident) => { $err };
+    (@library_error [$ename:ident] $err:ident) => { $ename::Library($err) };
+
+    // The address of a symbol, returning from the function if it is missing.
+    // The name is NUL-terminated here, so that it is never copied to look up.
+    (@symbol [] $snek:ident $symbol:ident) => {
+        $snek.symbol(concat!(stringify!($symbol), "\0"))?.as_ptr()
+    };
+    (@symbol [$ename:ident] $snek:ident $symbol:ident) => {
+        match $snek.symbol(concat!(stringify!($symbol), "\0")) {
+            Ok(symbol) => symbol.as_ptr(),
+            Err(source) => return Err($ename::Symbol { name: stringify!($symbol), source })
+        }
+    };
+
+    (@init [] $snek:ident $init:ident) => {
+        unsafe { $crate::__private::call_init($crate::snek!(@symbol [] $snek $init), stringify!($init))? }
+    };
+    (@init [$ename:ident] $snek:ident $init:ident) => {
+        let init = unsafe {
+            ::std::mem::transmute::<*mut $crate::__private::c_void, unsafe extern "C" fn() -> i32>($crate::snek!(@symbol [$ename] $snek $init))
+        };
+
+        match unsafe { init() } {
+            0 => (),
+            code => return Err($ename::InitFailed(code))
+        }
+    };
 }
@@ -0,0 +1,161 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/link.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::{Error, LoadFlags, LoadOptions, Snek};
+
+use std::path::Path;
+
+/// This loads a set of libraries where some, the consumers, use symbols
+/// which they do not define themselves but expect to be provided by others,
+/// the providers.
+///
+/// For a consumer's undefined symbols to be resolved against a provider, the
+/// provider has to be loaded first, with its symbols made visible to libraries
+/// loaded after it. A `LinkGroup` takes care of this: providers are loaded
+/// with global visibility (`RTLD_GLOBAL`), and consumers are loaded with every
+/// symbol bound immediately (`RTLD_NOW`), so that a symbol none of the
+/// providers export fails the load rather than a later call. Such a failure
+/// names the consumer and the missing symbol, where the platform says which
+/// symbol it was.
+///
+/// On Windows, a library's imports name the library they come from, and are
+/// resolved against a library of that name if one is already loaded, so
+/// loading the providers first has the same effect, as long as their file
+/// names are those the consumers were linked against. Symbols cannot be
+/// provided to a library which does not import them by name.
+///
+/// The group owns every library it loads. When it is dropped, the consumers
+/// are unloaded before the providers, each in the reverse order they were
+/// loaded.
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::LinkGroup;
+/// # fn main() {
+/// let mut group = LinkGroup::new();
+///
+/// if group.load_provider("libcore.so").is_ok() {
+///     // Symbols which libextension.so uses from libcore.so are resolved
+///     let _ = group.load_consumer("libextension.so");
+/// }
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct LinkGroup {
+    providers: Vec<Snek>,
+    consumers: Vec<Snek>
+}
+
+impl LinkGroup {
+    /// Construct a new, empty `LinkGroup`.
+    pub fn new() -> LinkGroup {
+        LinkGroup::default()
+    }
+
+    /// Attempt to load a library whose symbols are made available to the
+    /// consumers loaded after it.
+    ///
+    /// If the load fails, this will return [`Error::LibraryLoadError`](enum.Error.html).
+    pub fn load_provider<P>(&mut self, path: P) -> Result<&Snek, Error> where P: AsRef<Path> {
+        let mut options = LoadOptions::new();
+        options.flags(LoadFlags::new().now().global());
+
+        self.providers.push(options.load(path)?);
+        Ok(&self.providers[self.providers.len() - 1])
+    }
+
+    /// Attempt to load a library whose undefined symbols are resolved against
+    /// the providers loaded so far.
+    ///
+    /// If the load fails, this will return [`Error::LibraryLoadError`](enum.Error.html),
+    /// naming the symbol which could not be resolved if the platform says.
+    pub fn load_consumer<P>(&mut self, path: P) -> Result<&Snek, Error> where P: AsRef<Path> {
+        let path = path.as_ref();
+
+        let mut options = LoadOptions::new();
+        options.flags(LoadFlags::new().now());
+
+        let snek = options.load(path).map_err(|err| self.explain(path, err))?;
+        self.consumers.push(snek);
+        Ok(&self.consumers[self.consumers.len() - 1])
+    }
+
+    /// Returns the providers in the order they were loaded.
+    pub fn providers(&self) -> &[Snek] {
+        &self.providers
+    }
+
+    /// Returns the consumers in the order they were loaded.
+    pub fn consumers(&self) -> &[Snek] {
+        &self.consumers
+    }
+
+    fn explain(&self, path: &Path, err: Error) -> Error {
+        match err {
+            Error::LibraryLoadError(mut failure) => {
+                if let Some(symbol) = undefined_symbol(&failure.message) {
+                    let providers = self.providers.iter()
+                        .map(|provider| provider.path.display().to_string())
+                        .collect::<Vec<_>>();
+
+                    let provided = if providers.is_empty() {
+                        "no providers are loaded".to_owned()
+                    } else {
+                        format!("none of {} provide it", providers.join(", "))
+                    };
+
+                    failure.message = format!("{} (consumer {} needs symbol {}, but {})", failure.message, path.display(), symbol, provided);
+                }
+
+                Error::LibraryLoadError(failure)
+            },
+
+            err => err
+        }
+    }
+}
+
+impl Drop for LinkGroup {
+    fn drop(&mut self) {
+        while self.consumers.pop().is_some() {}
+        while self.providers.pop().is_some() {}
+    }
+}
+
+/// Find the name of the undefined symbol in a message from the dynamic
+/// loader, as worded by glibc, musl and both old and new versions of dyld.
+fn undefined_symbol(message: &str) -> Option<&str> {
+    let symbol = if let Some(index) = message.find("undefined symbol: ") {
+        &message[index + "undefined symbol: ".len()..]
+    } else if let Some(index) = message.find("symbol not found in flat namespace '") {
+        let symbol = &message[index + "symbol not found in flat namespace '".len()..];
+        let symbol = &symbol[..symbol.find('\'')?];
+        symbol.strip_prefix('_').unwrap_or(symbol)
+    } else if let Some(index) = message.find("Symbol not found: ") {
+        let symbol = &message[index + "Symbol not found: ".len()..];
+        symbol.strip_prefix('_').unwrap_or(symbol)
+    } else if let Some(index) = message.find(": symbol not found") {
+        let before = &message[..index];
+        &before[before.rfind(": ").map(|start| start + 2)?..]
+    } else {
+        return None;
+    };
+
+    symbol.split(|c: char| c.is_whitespace() || c == ',' || c == '(').next().filter(|symbol| !symbol.is_empty())
+}
@@ -0,0 +1,101 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/loaded.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::snek::backend;
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use libc::c_void;
+
+/// List every library currently loaded in the process, along with the main
+/// program, whether they were loaded by a [`Snek`](struct.Snek.html) or not,
+/// such as for a diagnostics page.
+///
+/// * On unix, other than Apple platforms, this uses `dl_iterate_phdr`, which
+///   holds the loader's lock for the whole listing, so the list is a
+///   consistent snapshot even while other threads load and unload libraries.
+///   Libraries the system maps itself are included as it names them, such as
+///   Linux's `linux-vdso.so.1`.
+/// * On macOS and iOS, this asks dyld for each image it has loaded in turn,
+///   so a library loaded or unloaded by another thread while the list is
+///   being made may or may not be in it, though every library loaded
+///   throughout is.
+/// * On Windows, this uses `EnumProcessModulesEx`, skipping any module
+///   unloaded before its path could be asked for.
+///
+/// Libraries registered with the static registry are part of the program, so
+/// are never listed separately. On platforms without dynamic libraries the
+/// list is empty.
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::Snek;
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+/// # fn main() {
+/// # let libexample = fixture();
+/// let snek = Snek::load(libexample).unwrap();
+///
+/// for library in snek::loaded_libraries() {
+///     println!("{}", library);
+/// }
+/// # }
+/// ```
+pub fn loaded_libraries() -> Vec<LoadedLibrary> {
+    backend::loaded_libraries()
+}
+
+/// A library currently loaded in the process, returned from
+/// [`loaded_libraries`](fn.loaded_libraries.html).
+///
+/// This formats as the library's base address followed by its path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedLibrary {
+    path: PathBuf,
+    base: usize
+}
+
+impl LoadedLibrary {
+    #[cfg_attr(not(any(unix, windows)), allow(dead_code))]
+    pub(crate) fn new(path: PathBuf, base: *const c_void) -> LoadedLibrary {
+        LoadedLibrary {
+            path,
+            base: base as usize
+        }
+    }
+
+    /// Returns the path of the library, as the platform reports it.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the address the library is loaded at. On unix platforms using
+    /// ELF this is the difference between the addresses the library was
+    /// linked for and those it was loaded at, which for a shared library is
+    /// where it begins, though for an executable not built as
+    /// position-independent it is zero.
+    pub fn base(&self) -> *const c_void {
+        self.base as *const c_void
+    }
+}
+
+impl fmt::Display for LoadedLibrary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#x} {}", self.base, self.path.display())
+    }
+}
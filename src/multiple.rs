@@ -0,0 +1,133 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/multiple.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::Error;
+
+use std::fmt;
+use std::slice;
+use std::vec;
+
+/// The failures of an operation which attempts several things, each of which
+/// may fail by itself, in the order they were attempted. This is held by
+/// [`Error::Multiple`](enum.Error.html).
+///
+/// There is always at least one error, as an operation with none did not
+/// fail, and an `Error::Multiple` added to this has its errors added in its
+/// place, so they are never nested.
+///
+/// Formatting this lists each error on its own line.
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::{Error, Errors};
+/// # fn main() {
+/// let mut errors = Errors::new(Error::InspectError("first".into()));
+/// errors.push(Error::InspectError("second".into()));
+///
+/// assert_eq!(errors.iter().count(), 2);
+/// assert!(Errors::from_vec(Vec::new()).is_none());
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Errors {
+    errors: Vec<Error>
+}
+
+impl Errors {
+    /// Construct a new `Errors` holding the given error.
+    pub fn new(error: Error) -> Errors {
+        let mut errors = Errors {
+            errors: Vec::new()
+        };
+
+        errors.push(error);
+        errors
+    }
+
+    /// Construct a new `Errors` holding each of the given errors, or `None`
+    /// if there are none.
+    pub fn from_vec(errors: Vec<Error>) -> Option<Errors> {
+        let mut errors = errors.into_iter();
+        let mut result = Errors::new(errors.next()?);
+
+        for error in errors {
+            result.push(error);
+        }
+
+        Some(result)
+    }
+
+    /// Add an error to the end of the list.
+    pub fn push(&mut self, error: Error) {
+        match error {
+            Error::Multiple(errors) => self.errors.extend(errors.errors),
+            error => self.errors.push(error)
+        }
+    }
+
+    /// Returns the first error.
+    pub fn first(&self) -> &Error {
+        &self.errors[0]
+    }
+
+    /// Returns an iterator over the errors in the order they occurred.
+    pub fn iter(&self) -> slice::Iter<'_, Error> {
+        self.errors.iter()
+    }
+
+    /// Returns the errors in the order they occurred.
+    pub fn into_vec(self) -> Vec<Error> {
+        self.errors
+    }
+}
+
+impl<'a> IntoIterator for &'a Errors {
+    type Item = &'a Error;
+    type IntoIter = slice::Iter<'a, Error>;
+
+    fn into_iter(self) -> slice::Iter<'a, Error> {
+        self.iter()
+    }
+}
+
+impl IntoIterator for Errors {
+    type Item = Error;
+    type IntoIter = vec::IntoIter<Error>;
+
+    fn into_iter(self) -> vec::IntoIter<Error> {
+        self.errors.into_iter()
+    }
+}
+
+impl fmt::Display for Errors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let [ref error] = self.errors[..] {
+            return write!(f, "{}", error);
+        }
+
+        write!(f, "{} errors occurred:", self.errors.len())?;
+        for error in &self.errors {
+            // A candidates error lists its candidates on lines of its own,
+            // which are indented to match
+            write!(f, "\n    {}", error.to_string().replace('\n', "\n    "))?;
+        }
+
+        Ok(())
+    }
+}
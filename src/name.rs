@@ -0,0 +1,247 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/name.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::Error;
+
+use std::borrow::Cow;
+use std::ffi::{CStr, CString};
+#[cfg(any(unix, windows))]
+use std::path::Path;
+
+/// A symbol name which can be looked up in a library, accepted by
+/// [`Snek::symbol`](struct.Snek.html#method.symbol) and
+/// [`load_symbol`](fn.load_symbol.html).
+///
+/// This is implemented for `str`, `String`, `CStr`, `CString` and `[u8]`,
+/// and references to any of them. The platform needs a NUL-terminated name,
+/// so a `CStr`, or a string or byte slice which already ends with a single
+/// NUL byte, is passed through as it is, and any other name is copied to add
+/// one. A name containing a NUL byte before its end cannot be looked up, and
+/// fails with [`Error::InvalidInput`](enum.Error.html).
+///
+/// This trait is sealed, so it cannot be implemented outside of this crate.
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::Snek;
+/// # use std::ffi::CStr;
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+/// # fn main() {
+/// # let libexample = fixture();
+/// let snek = Snek::load(libexample).unwrap();
+///
+/// // Neither of these copies the name
+/// assert!(snek.symbol("add\0").is_ok());
+/// assert!(snek.symbol(CStr::from_bytes_with_nul(b"add\0").unwrap()).is_ok());
+///
+/// assert!(snek.symbol("add").is_ok());
+/// assert!(snek.symbol(String::from("add")).is_ok());
+/// assert!(snek.symbol(&b"add"[..]).is_ok());
+/// # }
+/// ```
+pub trait SymbolName: private::Sealed {
+    /// Returns the name as a NUL-terminated string, borrowed if it already
+    /// is one.
+    #[doc(hidden)]
+    fn c_name(&self) -> Result<Cow<'_, CStr>, Error>;
+
+    /// Returns the bytes of the name, without a terminating NUL, without
+    /// copying them.
+    #[doc(hidden)]
+    fn name_bytes(&self) -> &[u8];
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+fn from_bytes(bytes: &[u8]) -> Result<Cow<'_, CStr>, Error> {
+    if let Ok(name) = CStr::from_bytes_with_nul(bytes) {
+        return Ok(Cow::Borrowed(name));
+    }
+
+    CString::new(bytes).map(Cow::Owned).map_err(|err| Error::InvalidInput(format!(
+        "invalid symbol name \"{}\": contains a NUL byte at byte {}", String::from_utf8_lossy(bytes).escape_default(), err.nul_position()
+    )))
+}
+
+fn without_nul(bytes: &[u8]) -> &[u8] {
+    bytes.strip_suffix(b"\0").unwrap_or(bytes)
+}
+
+/// Returns a library path as a NUL-terminated string for the platform.
+#[cfg(unix)]
+pub(crate) fn c_path(path: &Path) -> Result<CString, Error> {
+    let path = path.to_string_lossy();
+
+    CString::new(path.as_bytes()).map_err(|err| Error::InvalidInput(format!(
+        "invalid library path \"{}\": contains a NUL byte at byte {}", path.escape_default(), err.nul_position()
+    )))
+}
+
+/// Returns a library path as a NUL-terminated wide string for the platform.
+#[cfg(windows)]
+pub(crate) fn wide_path(path: &Path) -> Result<Vec<u16>, Error> {
+    use std::os::windows::ffi::OsStrExt;
+
+    let wide = path.as_os_str().encode_wide().collect::<Vec<_>>();
+    match wide.iter().position(|&unit| unit == 0) {
+        Some(position) => Err(Error::InvalidInput(format!(
+            "invalid library path \"{}\": contains a NUL character at character {}", path.display().to_string().escape_default(), position
+        ))),
+
+        None => Ok(wide.into_iter().chain(Some(0)).collect())
+    }
+}
+
+/// The longest path the loader accepts without the `\\?\` prefix, which is
+/// that of a directory, leaving room for an 8.3 file name.
+#[cfg(windows)]
+const MAX_DIRECTORY_PATH: usize = 248;
+
+/// Returns a library path which the loader can open whatever its length. A
+/// path too long for the loader is made absolute and given the `\\?\` prefix,
+/// which turns off the processing of `.` and `..` components and forward
+/// slashes, so the path is normalised first. Shorter paths, and bare file
+/// names, are left for the loader to search for as it would.
+#[cfg(windows)]
+pub(crate) fn extended_path(path: &Path) -> Cow<'_, Path> {
+    use std::env;
+    use std::ffi::OsString;
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::{Component, PathBuf, Prefix};
+
+    if ::env::is_bare_name(path) {
+        return Cow::Borrowed(path);
+    }
+
+    let absolute = match (path.is_absolute(), env::current_dir()) {
+        (true, _) => Cow::Borrowed(path),
+        (false, Ok(current)) => Cow::Owned(current.join(path)),
+        (false, Err(_)) => return Cow::Borrowed(path)
+    };
+
+    // A shorter path is passed as it is, so that a relative one is still
+    // searched for
+    if absolute.as_os_str().encode_wide().count() < MAX_DIRECTORY_PATH {
+        return Cow::Borrowed(path);
+    }
+
+    let mut components = absolute.components();
+    let mut extended = match components.next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::Disk(disk) => PathBuf::from(format!(r"\\?\{}:", disk as char)),
+            Prefix::UNC(server, share) => {
+                let mut unc = OsString::from(r"\\?\UNC\");
+                unc.push(server);
+                unc.push(r"\");
+                unc.push(share);
+                PathBuf::from(unc)
+            },
+
+            // Already verbatim, or a device path, which is used as it is
+            _ => return absolute
+        },
+
+        _ => return absolute
+    };
+
+    for component in components {
+        match component {
+            Component::RootDir => extended.push(r"\"),
+            Component::CurDir => (),
+            Component::ParentDir => {
+                // Never above the root, as after the prefix is the root
+                if extended.components().count() > 2 {
+                    extended.pop();
+                }
+            },
+            Component::Normal(name) => extended.push(name),
+            Component::Prefix(_) => ()
+        }
+    }
+
+    Cow::Owned(extended)
+}
+
+impl private::Sealed for str {}
+impl SymbolName for str {
+    fn c_name(&self) -> Result<Cow<'_, CStr>, Error> {
+        from_bytes(self.as_bytes())
+    }
+
+    fn name_bytes(&self) -> &[u8] {
+        without_nul(self.as_bytes())
+    }
+}
+
+impl private::Sealed for String {}
+impl SymbolName for String {
+    fn c_name(&self) -> Result<Cow<'_, CStr>, Error> {
+        from_bytes(self.as_bytes())
+    }
+
+    fn name_bytes(&self) -> &[u8] {
+        without_nul(self.as_bytes())
+    }
+}
+
+impl private::Sealed for [u8] {}
+impl SymbolName for [u8] {
+    fn c_name(&self) -> Result<Cow<'_, CStr>, Error> {
+        from_bytes(self)
+    }
+
+    fn name_bytes(&self) -> &[u8] {
+        without_nul(self)
+    }
+}
+
+impl private::Sealed for CStr {}
+impl SymbolName for CStr {
+    fn c_name(&self) -> Result<Cow<'_, CStr>, Error> {
+        Ok(Cow::Borrowed(self))
+    }
+
+    fn name_bytes(&self) -> &[u8] {
+        self.to_bytes()
+    }
+}
+
+impl private::Sealed for CString {}
+impl SymbolName for CString {
+    fn c_name(&self) -> Result<Cow<'_, CStr>, Error> {
+        Ok(Cow::Borrowed(self.as_c_str()))
+    }
+
+    fn name_bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl<N> private::Sealed for &N where N: SymbolName + ?Sized {}
+impl<N> SymbolName for &N where N: SymbolName + ?Sized {
+    fn c_name(&self) -> Result<Cow<'_, CStr>, Error> {
+        (**self).c_name()
+    }
+
+    fn name_bytes(&self) -> &[u8] {
+        (**self).name_bytes()
+    }
+}
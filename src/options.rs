@@ -0,0 +1,466 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/options.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::{env, Error, Snek};
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static REQUIRE_ABSOLUTE_PATHS: AtomicBool = AtomicBool::new(false);
+
+/// Set whether every load in the process requires an absolute path by
+/// default, as described for
+/// [`LoadOptions::require_absolute_paths`](struct.LoadOptions.html#method.require_absolute_paths).
+///
+/// This is the default taken by each `LoadOptions` when it is constructed,
+/// including those used by [`Snek::load`](struct.Snek.html#method.load) and
+/// the other `Snek` constructors, so it should be set at startup, before any
+/// library is loaded. Options which set it explicitly are not affected.
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::Snek;
+/// # fn main() {
+/// snek::set_require_absolute_paths(true);
+///
+/// let err = Snek::load("libexample.so").unwrap_err();
+/// assert!(err.is_relative_path());
+/// # }
+/// ```
+pub fn set_require_absolute_paths(require: bool) {
+    REQUIRE_ABSOLUTE_PATHS.store(require, Ordering::SeqCst);
+}
+
+/// This configures how [`LoadOptions::load`](struct.LoadOptions.html#method.load)
+/// loads a library. The default options are those used by
+/// [`Snek::load`](struct.Snek.html#method.load).
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::LoadOptions;
+/// # fn main() {
+/// let result = LoadOptions::new()
+///     .diagnose(false)
+///     .load("libexample.so");
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LoadOptions {
+    pub(crate) diagnose: bool,
+    pub(crate) flags: LoadFlags,
+    pub(crate) init: Option<String>,
+    pub(crate) fini: Option<String>,
+    pub(crate) ascii_symbols: bool,
+    pub(crate) cache_symbols: bool,
+    pub(crate) absolute_paths: bool
+}
+
+/// How the symbols of a library are bound when it is loaded, passed to
+/// [`Snek::load_with_flags`](struct.Snek.html#method.load_with_flags),
+/// [`LoadOptions::flags`](struct.LoadOptions.html#method.flags) and
+/// [`load_library_with_flags`](fn.load_library_with_flags.html).
+///
+/// The default flags bind now and are local:
+///
+/// * A library bound now has every undefined symbol resolved while it is
+///   loaded, failing the load if any cannot be, while a [`lazy`](#method.lazy)
+///   library's undefined functions are resolved when each is first called,
+///   so one which cannot be resolved only fails then, by crashing the
+///   process. A library which is meant to load with optional symbols left
+///   unresolved has to be loaded lazily.
+/// * A local library's symbols are only found through its own handle, while
+///   a [`global`](#method.global) library's symbols are also used to resolve
+///   the undefined symbols of libraries loaded after it, and are found by
+///   lookups which search every global library.
+///
+/// These map to `RTLD_NOW` or `RTLD_LAZY`, and `RTLD_LOCAL` or `RTLD_GLOBAL`,
+/// on unix. Windows resolves every import when a library is loaded, and only
+/// finds a library's exports through its own handle, so both flags are
+/// ignored there.
+///
+/// Whatever the flags, Windows is kept from showing a dialog box when a load
+/// fails, unless [`error_dialogs`](#method.error_dialogs) are allowed.
+///
+/// Earlier versions of this crate loaded every library lazily, and on macOS
+/// left it global, so a library which relied on that now needs its flags
+/// given explicitly.
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::{LoadFlags, Snek};
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+/// # fn main() {
+/// # let libexample = fixture();
+/// let snek = Snek::load_with_flags(libexample, LoadFlags::new().now().global()).unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LoadFlags {
+    pub(crate) now: bool,
+    pub(crate) global: bool,
+
+    // Passed to the platform in place of the portable flags
+    pub(crate) raw: Option<RawFlags>,
+
+    // Loads the library into a new namespace with dlmopen
+    pub(crate) new_namespace: bool,
+
+    // Lets Windows show a dialog box when the load fails
+    pub(crate) error_dialogs: bool,
+
+    // The LOAD_LIBRARY_SEARCH_* and LOAD_WITH_ALTERED_SEARCH_PATH flags
+    pub(crate) search: u32
+}
+
+// The LoadLibraryExW flags for the search order, which winapi 0.2 lacks
+const LOAD_WITH_ALTERED_SEARCH_PATH: u32 = 0x8;
+const LOAD_LIBRARY_SEARCH_DLL_LOAD_DIR: u32 = 0x100;
+const LOAD_LIBRARY_SEARCH_APPLICATION_DIR: u32 = 0x200;
+const LOAD_LIBRARY_SEARCH_USER_DIRS: u32 = 0x400;
+const LOAD_LIBRARY_SEARCH_SYSTEM32: u32 = 0x800;
+const LOAD_LIBRARY_SEARCH_DEFAULT_DIRS: u32 = 0x1000;
+
+/// The platform's own load flags: a `dlopen` mode on unix, and the flags for
+/// `LoadLibraryExW` on Windows.
+#[cfg(unix)]
+pub(crate) type RawFlags = ::libc::c_int;
+
+#[cfg(not(unix))]
+pub(crate) type RawFlags = u32;
+
+impl LoadFlags {
+    /// Construct the default `LoadFlags`, which bind now and are local.
+    pub const fn new() -> LoadFlags {
+        LoadFlags {
+            now: true,
+            global: false,
+            raw: None,
+            new_namespace: false,
+            error_dialogs: false,
+            search: 0
+        }
+    }
+
+    /// Construct `LoadFlags` which pass the given value straight to the
+    /// platform, in place of any portable flags, as described for
+    /// [`Snek::load_with_raw_flags`](struct.Snek.html#method.load_with_raw_flags).
+    #[cfg(any(unix, windows))]
+    pub(crate) const fn from_raw(raw: RawFlags) -> LoadFlags {
+        LoadFlags {
+            raw: Some(raw),
+            ..LoadFlags::new()
+        }
+    }
+
+    /// Load the library into a new namespace, as described for
+    /// [`Snek::load_in_new_namespace`](struct.Snek.html#method.load_in_new_namespace).
+    pub(crate) const fn in_new_namespace(self) -> LoadFlags {
+        LoadFlags { new_namespace: true, ..self }
+    }
+
+    /// Resolve each undefined function when it is first called.
+    pub const fn lazy(self) -> LoadFlags {
+        LoadFlags { now: false, ..self }
+    }
+
+    /// Resolve every undefined symbol while the library is loaded.
+    pub const fn now(self) -> LoadFlags {
+        LoadFlags { now: true, ..self }
+    }
+
+    /// Only find the library's symbols through its own handle.
+    pub const fn local(self) -> LoadFlags {
+        LoadFlags { global: false, ..self }
+    }
+
+    /// Make the library's symbols available to libraries loaded after it.
+    pub const fn global(self) -> LoadFlags {
+        LoadFlags { global: true, ..self }
+    }
+
+    /// Let Windows show its own dialog box when the load fails, such as for a
+    /// missing dependency, rather than only returning the error.
+    ///
+    /// By default the thread's error mode has `SEM_FAILCRITICALERRORS` and
+    /// `SEM_NOOPENFILEERRORBOX` added around the load, with the previous mode
+    /// restored afterwards, so that a failure never waits on someone to
+    /// dismiss a dialog, which would hang a service with no one to click it.
+    /// This has no effect on other platforms, which have no such dialogs.
+    pub const fn error_dialogs(self) -> LoadFlags {
+        LoadFlags { error_dialogs: true, ..self }
+    }
+
+    /// Search the directory of the library being loaded for its dependencies,
+    /// with `LOAD_LIBRARY_SEARCH_DLL_LOAD_DIR`, which needs an absolute path.
+    ///
+    /// Setting any of the `search_*` flags on Windows replaces the legacy
+    /// search order, which includes the current directory and `PATH`, with
+    /// only the directories asked for, defending against a planted library
+    /// being loaded in place of the real one. They are passed to
+    /// `LoadLibraryExW`, and an invalid combination, such as with
+    /// [`altered_search_path`](#method.altered_search_path), fails the load
+    /// with the system's message. They have no effect on other platforms.
+    pub const fn search_dll_load_dir(self) -> LoadFlags {
+        LoadFlags { search: self.search | LOAD_LIBRARY_SEARCH_DLL_LOAD_DIR, ..self }
+    }
+
+    /// Search the directory of the executable, with
+    /// `LOAD_LIBRARY_SEARCH_APPLICATION_DIR`.
+    pub const fn search_application_dir(self) -> LoadFlags {
+        LoadFlags { search: self.search | LOAD_LIBRARY_SEARCH_APPLICATION_DIR, ..self }
+    }
+
+    /// Search the directories added with `AddDllDirectory` or
+    /// `SetDllDirectory`, with `LOAD_LIBRARY_SEARCH_USER_DIRS`.
+    pub const fn search_user_dirs(self) -> LoadFlags {
+        LoadFlags { search: self.search | LOAD_LIBRARY_SEARCH_USER_DIRS, ..self }
+    }
+
+    /// Search the system directory, with `LOAD_LIBRARY_SEARCH_SYSTEM32`.
+    pub const fn search_system32(self) -> LoadFlags {
+        LoadFlags { search: self.search | LOAD_LIBRARY_SEARCH_SYSTEM32, ..self }
+    }
+
+    /// Search the application, system and user directories, with
+    /// `LOAD_LIBRARY_SEARCH_DEFAULT_DIRS`.
+    pub const fn search_default_dirs(self) -> LoadFlags {
+        LoadFlags { search: self.search | LOAD_LIBRARY_SEARCH_DEFAULT_DIRS, ..self }
+    }
+
+    /// Search the directory of the library being loaded for its dependencies
+    /// in place of the executable's, keeping the rest of the legacy search
+    /// order, with `LOAD_WITH_ALTERED_SEARCH_PATH`. This suits a library which
+    /// ships its own dependencies beside it, and needs an absolute path.
+    pub const fn altered_search_path(self) -> LoadFlags {
+        LoadFlags { search: self.search | LOAD_WITH_ALTERED_SEARCH_PATH, ..self }
+    }
+
+    /// Returns true if every undefined symbol is resolved at load time.
+    pub const fn is_now(&self) -> bool {
+        self.now
+    }
+
+    /// Returns true if the library's symbols are made available to libraries
+    /// loaded after it.
+    pub const fn is_global(&self) -> bool {
+        self.global
+    }
+
+    /// Returns true if Windows may show a dialog box when the load fails.
+    pub const fn shows_error_dialogs(&self) -> bool {
+        self.error_dialogs
+    }
+}
+
+impl LoadOptions {
+    /// Construct the default `LoadOptions`.
+    pub fn new() -> LoadOptions {
+        LoadOptions {
+            diagnose: true,
+            flags: LoadFlags::new(),
+            init: None,
+            fini: None,
+            ascii_symbols: true,
+            cache_symbols: true,
+            absolute_paths: REQUIRE_ABSOLUTE_PATHS.load(Ordering::SeqCst)
+        }
+    }
+
+    /// Set whether a failed load is diagnosed by inspecting the library file.
+    ///
+    /// When enabled, if the platform fails to load a library file which
+    /// exists, its headers are read to check whether it was built for the
+    /// architecture of the current process, and whether each library it
+    /// depends on can be found, with any problems added to the returned
+    /// error. This only has any cost when a load fails.
+    pub fn diagnose(&mut self, diagnose: bool) -> &mut LoadOptions {
+        self.diagnose = diagnose;
+        self
+    }
+
+    /// Set how the library's symbols are bound, as described for
+    /// [`LoadFlags`](struct.LoadFlags.html).
+    pub fn flags(&mut self, flags: LoadFlags) -> &mut LoadOptions {
+        self.flags = flags;
+        self
+    }
+
+    /// Set whether debug builds reject symbol names containing non-ASCII
+    /// characters, as described for [`Snek::symbol`](struct.Snek.html#method.symbol).
+    /// This is enabled by default, and should be disabled for a library which
+    /// really exports non-ASCII names.
+    pub fn ascii_symbols(&mut self, ascii: bool) -> &mut LoadOptions {
+        self.ascii_symbols = ascii;
+        self
+    }
+
+    /// Set whether [`Snek::symbol`](struct.Snek.html#method.symbol) remembers
+    /// the address the library returns for each name, so that looking the
+    /// same name up again neither copies it nor asks the platform. This is
+    /// enabled by default, and can be disabled to save the memory for a
+    /// library whose symbols are each only looked up once, for which it
+    /// also saves copying each name, so that looking up a `CStr` allocates
+    /// nothing.
+    ///
+    /// Only the library's own answers are kept, for as long as the `Snek`
+    /// exists, so resolvers are still consulted before it on every lookup,
+    /// and a [`ReloadableSnek`](struct.ReloadableSnek.html) starts afresh with
+    /// each copy it loads.
+    pub fn cache_symbols(&mut self, cache: bool) -> &mut LoadOptions {
+        self.cache_symbols = cache;
+        self
+    }
+
+    /// Set whether a library may only be loaded from an absolute path.
+    ///
+    /// A bare file name is looked for in the platform's search paths, which
+    /// on Windows can include the current directory, and a relative path is
+    /// resolved against the current directory, so either can pick up a
+    /// planted copy of the library. When enabled, both are rejected before
+    /// anything is loaded, with an error for which
+    /// [`Error::is_relative_path`](enum.Error.html#method.is_relative_path)
+    /// is true. This includes every name tried by [`load_name`](#method.load_name)
+    /// and [`load_versioned`](#method.load_versioned), so a library found in
+    /// the search paths has to be located explicitly, such as with
+    /// [`env::locate`](env/fn.locate.html), and then loaded from the path
+    /// found.
+    ///
+    /// A library loaded from an absolute path is never searched for, so the
+    /// current directory is not consulted for it, though it may still be for
+    /// the libraries it depends on.
+    ///
+    /// This is disabled by default, unless enabled for the whole process with
+    /// [`set_require_absolute_paths`](fn.set_require_absolute_paths.html).
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::LoadOptions;
+    /// # fn main() {
+    /// let err = LoadOptions::new()
+    ///     .require_absolute_paths(true)
+    ///     .load("libexample.so")
+    ///     .unwrap_err();
+    ///
+    /// assert!(err.is_relative_path());
+    /// # }
+    /// ```
+    pub fn require_absolute_paths(&mut self, require: bool) -> &mut LoadOptions {
+        self.absolute_paths = require;
+        self
+    }
+
+    /// Set a function exported by the library to call once it is loaded,
+    /// taking no arguments and returning an `int`.
+    ///
+    /// If the function cannot be found, the load fails with
+    /// [`Error::SymbolLoadError`](enum.Error.html), and if it returns non-zero
+    /// the load fails with [`Error::LibraryLoadError`](enum.Error.html). In
+    /// either case the library is unloaded again without calling the
+    /// [`fini`](#method.fini) function.
+    pub fn init<S>(&mut self, symbol: S) -> &mut LoadOptions where S: Into<String> {
+        self.init = Some(symbol.into());
+        self
+    }
+
+    /// Set a function exported by the library to call just before it is
+    /// unloaded, taking no arguments and returning nothing.
+    ///
+    /// The function is looked up before the [`init`](#method.init) function
+    /// is called, so that a library is never initialised without a way to shut
+    /// it down, and is only called if the init function succeeded.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::LoadOptions;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let snek = LoadOptions::new()
+    ///     .init("fixture_init")
+    ///     .fini("fixture_fini")
+    ///     .load(libexample)
+    ///     .unwrap();
+    ///
+    /// // fixture_fini is called here, before the library is unloaded
+    /// drop(snek);
+    /// # }
+    /// ```
+    pub fn fini<S>(&mut self, symbol: S) -> &mut LoadOptions where S: Into<String> {
+        self.fini = Some(symbol.into());
+        self
+    }
+
+    /// Attempt to load a dynamic library from the given path with these options.
+    ///
+    /// If the load fails, this will return [`Error::LibraryLoadError`](enum.Error.html)
+    pub fn load<P>(&self, path: P) -> Result<Snek, Error> where P: AsRef<Path> {
+        Snek::open(path.as_ref(), self)
+    }
+
+    /// Attempt to load a dynamic library by its logical name with these
+    /// options, as with [`Snek::load_name`](struct.Snek.html#method.load_name).
+    pub fn load_name(&self, name: &str) -> Result<Snek, Error> {
+        Snek::open_any(env::library_names(name), self)
+    }
+
+    /// Attempt to load a dynamic library by its unversioned name with these
+    /// options, falling back to soname-versioned names if that fails.
+    ///
+    /// Distributions usually only install the unversioned `libfoo.so` link
+    /// with a library's development package, so it is missing on most users'
+    /// machines while `libfoo.so.3` is present. When the given path cannot be
+    /// loaded, this tries it with each of the given major versions appended,
+    /// highest first. If no versions are given, the search paths (for a bare
+    /// file name) or the path's directory are scanned for versioned names, and
+    /// each major version found is tried, highest first.
+    ///
+    /// If no name can be loaded, this will return
+    /// [`Error::CandidatesError`](enum.Error.html), holding each name tried
+    /// and the reason it failed. Nothing but [`load_versioned`](#method.load_versioned)
+    /// and [`Snek::load_versioned`](struct.Snek.html#method.load_versioned)
+    /// ever tries a name other than the one given.
+    pub fn load_versioned<P>(&self, path: P, majors: &[u32]) -> Result<Snek, Error> where P: AsRef<Path> {
+        Snek::open_versioned(path.as_ref(), majors, self)
+    }
+
+    /// Attempt to load a dynamic library from each of the given paths in turn
+    /// with these options, as with [`Snek::load_any`](struct.Snek.html#method.load_any).
+    ///
+    /// If none of the paths can be loaded, this will return
+    /// [`Error::CandidatesError`](enum.Error.html)
+    pub fn load_any<I, P>(&self, paths: I) -> Result<Snek, Error> where I: IntoIterator<Item = P>, P: AsRef<Path> {
+        Snek::open_any(paths, self)
+    }
+}
+
+impl Default for LoadFlags {
+    fn default() -> LoadFlags {
+        LoadFlags::new()
+    }
+}
+
+impl Default for LoadOptions {
+    fn default() -> LoadOptions {
+        LoadOptions::new()
+    }
+}
@@ -0,0 +1,389 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/plugin.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! This module codifies a common convention for plugin libraries: the library
+//! exports a create function returning a pointer to some plugin context, and a
+//! destroy function which releases it. Every other function the plugin exports
+//! takes that context as its first argument.
+//!
+//! By default the create and destroy functions are expected to be named
+//! [`CREATE_SYMBOL`](constant.CREATE_SYMBOL.html) and
+//! [`DESTROY_SYMBOL`](constant.DESTROY_SYMBOL.html), with the signatures
+//! `extern "C" fn() -> *mut T` and `extern "C" fn(*mut T)` respectively.
+//!
+//! Plugins may also export an [`Info`](struct.Info.html) structure describing
+//! themselves, which the host can read with
+//! [`Snek::plugin_info`](../struct.Snek.html#method.plugin_info) before
+//! calling anything.
+//!
+//! Plugins which are hot reloaded with
+//! [`ReloadableSnek::reload_with_state`](../struct.ReloadableSnek.html#method.reload_with_state)
+//! hand their state from the old copy to the new one through a [`Buffer`](struct.Buffer.html).
+
+use ::{Error, Snek, Symbol};
+
+use std::{mem, ptr, slice, str};
+use libc::c_void;
+
+/// The default name of the function called to create the plugin context.
+pub const CREATE_SYMBOL: &str = "plugin_create";
+
+/// The default name of the function called to destroy the plugin context.
+pub const DESTROY_SYMBOL: &str = "plugin_destroy";
+
+/// The name of the function called on the old copy of a plugin to serialize
+/// its state during a reload, with the signature `extern "C" fn(*mut Buffer) -> c_int`.
+pub const SERIALIZE_SYMBOL: &str = "plugin_serialize";
+
+/// The name of the function called on the new copy of a plugin to deserialize
+/// the old copy's state during a reload, with the signature
+/// `extern "C" fn(*const Buffer) -> c_int`.
+pub const DESERIALIZE_SYMBOL: &str = "plugin_deserialize";
+
+/// The default name of the exported [`Info`](struct.Info.html) structure, as
+/// declared by [`declare_plugin_info!`](../macro.declare_plugin_info!.html).
+pub const INFO_SYMBOL: &str = "PLUGIN_INFO";
+
+/// The value of [`Info::magic`](struct.Info.html#structfield.magic) in a valid
+/// plugin information structure.
+pub const INFO_MAGIC: u32 = 0x534e_454b;
+
+const HEADER_SIZE: usize = 8;
+const TEXT_SIZE: usize = 64;
+
+/// This is the layout of the plugin information structure shared between a
+/// host and its plugins.
+///
+/// The `size` field holds the size of the structure as the plugin was compiled,
+/// so that fields can be appended in later versions of this crate: when reading
+/// an older, smaller structure the missing fields are zeroed, and when reading
+/// a newer, larger one the unknown fields are ignored.
+///
+/// Text fields are NUL-padded UTF-8, truncated to fit.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Info {
+    /// Always [`INFO_MAGIC`](constant.INFO_MAGIC.html).
+    pub magic: u32,
+
+    /// The size of the structure in bytes, as seen by the plugin.
+    pub size: u32,
+
+    /// The name of the plugin.
+    pub name: [u8; TEXT_SIZE],
+
+    /// The version of the plugin as major, minor and patch numbers.
+    pub version: [u32; 3],
+
+    /// The author of the plugin.
+    pub author: [u8; TEXT_SIZE],
+
+    /// Plugin-defined capability bits.
+    pub capabilities: u64
+}
+
+impl Info {
+    /// Construct a new `Info` with the magic number and size filled in. This is
+    /// usable in a `static`, though [`declare_plugin_info!`](../macro.declare_plugin_info!.html)
+    /// is the more convenient way of exporting one.
+    pub const fn new(name: &str, version: [u32; 3], author: &str, capabilities: u64) -> Info {
+        Info {
+            magic: INFO_MAGIC,
+            size: mem::size_of::<Info>() as u32,
+            name: text(name),
+            version,
+            author: text(author),
+            capabilities
+        }
+    }
+
+    /// Returns the name of the plugin.
+    pub fn name(&self) -> &str {
+        untext(&self.name)
+    }
+
+    /// Returns the author of the plugin.
+    pub fn author(&self) -> &str {
+        untext(&self.author)
+    }
+
+    /// Read plugin information from the address of a loaded symbol, checking
+    /// the magic number and size before reading anything else.
+    ///
+    /// # Safety
+    /// The symbol must point to at least eight readable bytes, and to at least
+    /// as many as its `size` field claims if the magic number matches.
+    pub unsafe fn read(symbol: &Symbol) -> Result<Info, Error> {
+        let base = symbol.as_ptr() as *const u8;
+        if base.is_null() {
+            return Err(Error::PluginError("plugin information symbol is null".into()));
+        }
+
+        let magic = ptr::read_unaligned(base as *const u32);
+        if magic != INFO_MAGIC {
+            return Err(Error::PluginError(format!("plugin information has magic number {:#x}, expected {:#x}", magic, INFO_MAGIC)));
+        }
+
+        let size = ptr::read_unaligned(base.offset(4) as *const u32) as usize;
+        if size < HEADER_SIZE {
+            return Err(Error::PluginError(format!("plugin information claims an invalid size of {} bytes", size)));
+        }
+
+        let mut info: Info = mem::zeroed();
+        let available = if size < mem::size_of::<Info>() { size } else { mem::size_of::<Info>() };
+        ptr::copy_nonoverlapping(base, &mut info as *mut Info as *mut u8, available);
+
+        Ok(info)
+    }
+}
+
+impl ::std::fmt::Debug for Info {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("Info")
+            .field("size", &self.size)
+            .field("name", &self.name())
+            .field("version", &self.version)
+            .field("author", &self.author())
+            .field("capabilities", &self.capabilities)
+            .finish()
+    }
+}
+
+const fn text(value: &str) -> [u8; TEXT_SIZE] {
+    let bytes = value.as_bytes();
+    let mut result = [0; TEXT_SIZE];
+    let mut index = 0;
+
+    // Leave room for at least one NUL so C plugins can treat it as a string
+    while index < bytes.len() && index < TEXT_SIZE - 1 {
+        result[index] = bytes[index];
+        index += 1;
+    }
+
+    result
+}
+
+fn untext(value: &[u8]) -> &str {
+    let length = value.iter().position(|&byte| byte == 0).unwrap_or(value.len());
+    match str::from_utf8(&value[..length]) {
+        Ok(text) => text,
+        Err(err) => str::from_utf8(&value[..err.valid_up_to()]).unwrap_or("")
+    }
+}
+
+/// This is the buffer state is handed over in when a plugin is reloaded. The
+/// memory is owned by the host for the whole reload: the old copy appends to
+/// it by calling `write`, and the new copy reads `len` bytes from `data`.
+///
+/// `data` and `len` always describe the current contents, and `data` may
+/// change after each call to `write`.
+#[repr(C)]
+#[derive(Debug)]
+pub struct Buffer {
+    /// The start of the buffer contents.
+    pub data: *const u8,
+
+    /// The length of the buffer contents in bytes.
+    pub len: usize,
+
+    /// Appends `len` bytes from `data` to the buffer.
+    pub write: extern "C" fn(buffer: *mut Buffer, data: *const u8, len: usize),
+
+    state: *mut c_void
+}
+
+impl Buffer {
+    pub(crate) fn new(state: &mut Vec<u8>) -> Buffer {
+        Buffer {
+            data: state.as_ptr(),
+            len: state.len(),
+            write: write_buffer,
+            state: state as *mut Vec<u8> as *mut c_void
+        }
+    }
+
+    /// Returns the buffer contents, for plugins reading their state.
+    ///
+    /// # Safety
+    /// The buffer must be one passed to a plugin by this crate, and must not be
+    /// written to while the returned slice is alive.
+    pub unsafe fn bytes(&self) -> &[u8] {
+        if self.len == 0 {
+            &[]
+        } else {
+            slice::from_raw_parts(self.data, self.len)
+        }
+    }
+
+    /// Appends the given bytes to the buffer, for plugins writing their state.
+    pub fn append(&mut self, bytes: &[u8]) {
+        (self.write)(self, bytes.as_ptr(), bytes.len())
+    }
+}
+
+extern "C" fn write_buffer(buffer: *mut Buffer, data: *const u8, len: usize) {
+    if buffer.is_null() || data.is_null() || len == 0 {
+        return;
+    }
+
+    unsafe {
+        let buffer = &mut *buffer;
+        let state = &mut *(buffer.state as *mut Vec<u8>);
+
+        state.extend_from_slice(slice::from_raw_parts(data, len));
+        buffer.data = state.as_ptr();
+        buffer.len = state.len();
+    }
+}
+
+/// This is implemented by structures of function pointers which are loaded
+/// from a plugin library alongside the create and destroy functions. The unit
+/// type implements it for plugins which are only used through
+/// [`PluginHandle::library`](struct.PluginHandle.html#method.library).
+///
+/// # Safety
+/// Implementors must only load symbols whose types are actually those of the
+/// fields they are stored in, as with [`Symbol::with`](../struct.Symbol.html#method.with).
+pub unsafe trait Vtable<T>: Sized {
+    /// Load every function in the vtable from the given library.
+    fn load(snek: &Snek) -> Result<Self, Error>;
+}
+
+unsafe impl<T> Vtable<T> for () {
+    fn load(_: &Snek) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// This wraps a plugin library which follows the create/destroy convention,
+/// holding the context returned by the create function and passing it to
+/// every call made through the plugin's [`Vtable`](trait.Vtable.html).
+///
+/// When a `PluginHandle` is dropped, the destroy function is called with the
+/// context before the library is unloaded. If loading the plugin fails after
+/// the create function has been called, the same ordering is used before the
+/// error is returned.
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::{Snek, Error};
+/// # use snek::plugin::{PluginHandle, Vtable};
+/// enum Context {}
+///
+/// struct Api {
+///     update: extern "C" fn(*mut Context, f32)
+/// }
+///
+/// unsafe impl Vtable<Context> for Api {
+///     fn load(snek: &Snek) -> Result<Api, Error> {
+///         let update = snek.symbol("plugin_update")?;
+///         Ok(Api { update: unsafe { update.with(|f: extern "C" fn(*mut Context, f32)| f) } })
+///     }
+/// }
+///
+/// # fn main() {
+/// if let Ok(snek) = Snek::load("libplugin.so") {
+///     if let Ok(plugin) = unsafe { PluginHandle::<Context, Api>::load(snek) } {
+///         plugin.call(|api, context| (api.update)(context, 0.16));
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct PluginHandle<T, V = ()> {
+    context: *mut T,
+    destroy: extern "C" fn(*mut T),
+    vtable: V,
+
+    // Declared last so the library is unloaded after everything above
+    snek: Snek
+}
+
+impl<T, V> PluginHandle<T, V> where V: Vtable<T> {
+    /// Attempt to create the plugin context using the conventionally named
+    /// create and destroy functions, then load the vtable.
+    ///
+    /// # Safety
+    /// The create and destroy functions must have the signatures described in
+    /// the [module documentation](index.html).
+    pub unsafe fn load(snek: Snek) -> Result<PluginHandle<T, V>, Error> {
+        PluginHandle::load_with_names(snek, CREATE_SYMBOL, DESTROY_SYMBOL)
+    }
+
+    /// Attempt to create the plugin context using the given create and destroy
+    /// functions, then load the vtable.
+    ///
+    /// Both functions are resolved before the create function is called, so a
+    /// missing destroy function never leaves a context behind. If the create
+    /// function returns a null pointer this returns
+    /// [`Error::PluginError`](../enum.Error.html).
+    ///
+    /// # Safety
+    /// The create and destroy functions must have the signatures described in
+    /// the [module documentation](index.html).
+    pub unsafe fn load_with_names(snek: Snek, create: &str, destroy: &str) -> Result<PluginHandle<T, V>, Error> {
+        let create = snek.symbol(create)?.with(|f: extern "C" fn() -> *mut T| f);
+        let destroy = snek.symbol(destroy)?.with(|f: extern "C" fn(*mut T)| f);
+
+        let context = create();
+        if context.is_null() {
+            return Err(Error::PluginError("plugin create function returned a null context".into()));
+        }
+
+        let vtable = match V::load(&snek) {
+            Ok(vtable) => vtable,
+            Err(err) => {
+                destroy(context);
+                return Err(err);
+            }
+        };
+
+        Ok(PluginHandle {
+            context,
+            destroy,
+            vtable,
+            snek
+        })
+    }
+
+    /// Call into the plugin, passing the loaded vtable and the plugin context
+    /// to the given function.
+    ///
+    /// The function pointers in the vtable must not be kept beyond the call, as
+    /// the library is unloaded when the handle is dropped.
+    pub fn call<F, U>(&self, f: F) -> U where F: FnOnce(&V, *mut T) -> U {
+        f(&self.vtable, self.context)
+    }
+
+    /// Returns the context returned by the plugin's create function.
+    pub fn context(&self) -> *mut T {
+        self.context
+    }
+
+    /// Returns the underlying library, for loading symbols outside the vtable.
+    pub fn library(&self) -> &Snek {
+        &self.snek
+    }
+}
+
+impl<T, V> Drop for PluginHandle<T, V> {
+    fn drop(&mut self) {
+        (self.destroy)(self.context)
+    }
+}
@@ -0,0 +1,258 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/preflight.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::{env, is_supported, Error, SymbolName};
+use ::inspect::{self, Format, Image, Machine};
+use ::options::LoadFlags;
+use ::snek::{backend, diagnose};
+
+use std::path::{Path, PathBuf};
+
+/// Check whether the library at the given path can be loaded by the current
+/// process, without leaving it loaded, using the default
+/// [`PreflightOptions`](struct.PreflightOptions.html).
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+/// # fn main() {
+/// # let libplugin = fixture();
+/// let report = snek::preflight(libplugin).unwrap();
+/// assert!(report.is_loadable());
+/// # }
+/// ```
+pub fn preflight<P>(path: P) -> Result<PreflightReport, Error> where P: AsRef<Path> {
+    PreflightOptions::new().run(path)
+}
+
+/// How a [`PreflightReport`](struct.PreflightReport.html) was produced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreflightMethod {
+    /// The library was loaded, resolving all of its symbols immediately, and
+    /// then unloaded again. This runs its initialisers.
+    Loaded,
+
+    /// Only the library file's headers were read, and each of its direct
+    /// dependencies looked for, so none of its code was run. A library which
+    /// passes this check can still fail to load, such as when a dependency
+    /// lacks a symbol it needs.
+    Inspected
+}
+
+/// This configures how [`PreflightOptions::run`](struct.PreflightOptions.html#method.run)
+/// checks a library.
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::{PreflightMethod, PreflightOptions};
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+/// # fn main() {
+/// # let libplugin = fixture();
+/// let report = PreflightOptions::new()
+///     .load(false)
+///     .require("plugin_main")
+///     .run(libplugin)
+///     .unwrap();
+///
+/// assert_eq!(report.method(), PreflightMethod::Inspected);
+/// assert_eq!(report.missing_exports(), ["plugin_main"]);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PreflightOptions {
+    load: bool,
+    required: Vec<String>
+}
+
+impl PreflightOptions {
+    /// Construct the default `PreflightOptions`, which load the library where
+    /// the platform supports it and require no exports.
+    pub fn new() -> PreflightOptions {
+        PreflightOptions {
+            load: true,
+            required: Vec::new()
+        }
+    }
+
+    /// Set whether the library is really loaded, which gives the most
+    /// certain answer but runs its initialisers. When disabled, or on a
+    /// platform without dynamic libraries, the library is only inspected, as
+    /// described for [`PreflightMethod::Inspected`](enum.PreflightMethod.html).
+    pub fn load(&mut self, load: bool) -> &mut PreflightOptions {
+        self.load = load;
+        self
+    }
+
+    /// Add a symbol the library must export to be considered loadable.
+    pub fn require<S>(&mut self, symbol: S) -> &mut PreflightOptions where S: Into<String> {
+        self.required.push(symbol.into());
+        self
+    }
+
+    /// Check the library at the given path with these options.
+    ///
+    /// A library which fails to load still produces a report, holding the
+    /// error. This only returns an error if the library was to be inspected
+    /// and its file cannot be found or read, in which case it will return
+    /// [`Error::InspectError`](enum.Error.html).
+    pub fn run<P>(&self, path: P) -> Result<PreflightReport, Error> where P: AsRef<Path> {
+        let path = path.as_ref();
+
+        let file = env::library_file(path);
+
+        let image = file.as_ref().map(inspect::inspect_file);
+
+        let mut report = PreflightReport {
+            method: PreflightMethod::Inspected,
+            file: file.clone(),
+            format: None,
+            machine: None,
+            matches_current: true,
+            missing_dependencies: Vec::new(),
+            missing_exports: Vec::new(),
+            error: None
+        };
+
+        if let (Some(file), Some(Ok(image))) = (&file, &image) {
+            report.format = Some(image.format());
+            report.machine = Some(image.machine());
+            report.matches_current = image.matches_current();
+            report.missing_dependencies = diagnose::missing_dependencies(file, image);
+        }
+
+        if self.load && is_supported() {
+            report.method = PreflightMethod::Loaded;
+            self.load_and_close(path, &mut report);
+        } else {
+            match image {
+                Some(Ok(image)) => report.missing_exports = self.missing_exports(&image),
+                Some(Err(err)) => return Err(err),
+                None => return Err(Error::InspectError(format!("{} not found in any search directory", path.display())))
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn load_and_close(&self, path: &Path, report: &mut PreflightReport) {
+        // Binding everything now catches a missing symbol in a dependency,
+        // and keeping the symbols local leaves nothing behind once closed
+        match backend::load_library(path, LoadFlags::new().now().local()) {
+            Ok(handle) => {
+                report.missing_exports = self.required.iter()
+                    .filter(|symbol| symbol.c_name().and_then(|symbol| unsafe { backend::load_symbol(handle, &symbol) }).is_err())
+                    .cloned()
+                    .collect();
+
+                let _ = backend::drop_library(handle);
+            },
+
+            Err(err) => report.error = Some(diagnose::diagnose(path, err, true))
+        }
+    }
+
+    fn missing_exports(&self, image: &Image) -> Vec<String> {
+        self.required.iter()
+            .filter(|symbol| !image.exports().contains(symbol))
+            .cloned()
+            .collect()
+    }
+}
+
+impl Default for PreflightOptions {
+    fn default() -> PreflightOptions {
+        PreflightOptions::new()
+    }
+}
+
+/// The result of checking whether a library can be loaded, returned from
+/// [`preflight`](fn.preflight.html) and [`PreflightOptions::run`](struct.PreflightOptions.html#method.run).
+///
+/// The details read from the library file are only present if it could be
+/// found and inspected, which it may not be even when it loads, such as for
+/// a macOS system library which only exists in the dyld shared cache.
+#[derive(Debug)]
+pub struct PreflightReport {
+    method: PreflightMethod,
+    file: Option<PathBuf>,
+    format: Option<Format>,
+    machine: Option<Machine>,
+    matches_current: bool,
+    missing_dependencies: Vec<String>,
+    missing_exports: Vec<String>,
+    error: Option<Error>
+}
+
+impl PreflightReport {
+    /// Returns true if the library can be loaded and exports every required
+    /// symbol, as far as the [`method`](#method.method) used can tell.
+    pub fn is_loadable(&self) -> bool {
+        let loaded = match self.method {
+            PreflightMethod::Loaded => self.error.is_none(),
+            PreflightMethod::Inspected => self.matches_current && self.missing_dependencies.is_empty()
+        };
+
+        loaded && self.missing_exports.is_empty()
+    }
+
+    /// Returns how the library was checked.
+    pub fn method(&self) -> PreflightMethod {
+        self.method
+    }
+
+    /// Returns the library file which was inspected, if one was found.
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    /// Returns the format of the library file.
+    pub fn format(&self) -> Option<Format> {
+        self.format
+    }
+
+    /// Returns the architecture the library file was built for.
+    pub fn machine(&self) -> Option<Machine> {
+        self.machine
+    }
+
+    /// Returns true if the library file was built for the architecture of
+    /// the current process, or if it could not be inspected.
+    pub fn matches_current(&self) -> bool {
+        self.matches_current
+    }
+
+    /// Returns the direct dependencies of the library file which cannot be
+    /// found, as written in the file.
+    pub fn missing_dependencies(&self) -> &[String] {
+        &self.missing_dependencies
+    }
+
+    /// Returns the required symbols which the library does not export. When
+    /// the library was loaded this is only checked if the load succeeded.
+    pub fn missing_exports(&self) -> &[String] {
+        &self.missing_exports
+    }
+
+    /// Returns the error from loading the library, if it was loaded and
+    /// failed, explained as for [`LoadOptions::diagnose`](struct.LoadOptions.html#method.diagnose).
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+}
@@ -0,0 +1,32 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/prelude.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! This module re-exports the most commonly used parts of the crate, so that
+//! they can all be imported with a single glob import.
+//!
+//! # Example
+//! ```
+//! # extern crate snek;
+//! use snek::prelude::*;
+//!
+//! # fn main() {
+//! let result: Result<Snek, Error> = Snek::load("libexample.so");
+//! # }
+//! ```
+
+pub use ::{Snek, Symbol, SymbolName, Error, LoadOptions, LoadFlags, Registry, ReloadableSnek, SharedSnek};
@@ -0,0 +1,239 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/registry.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::{Error, Snek};
+
+use std::path::Path;
+
+/// This manages a set of named libraries which may depend on each other,
+/// making sure that a library is never unloaded while another library that
+/// depends on it is still loaded.
+///
+/// Dependencies are declared when a library is registered, and may refer to
+/// names which have not been registered yet. Registering a library which would
+/// introduce a dependency cycle fails with
+/// [`Error::RegistryError`](enum.Error.html), as does unloading a library
+/// that others still depend on unless
+/// [`unload_cascade`](#method.unload_cascade) is used.
+///
+/// When the registry is dropped (including while unwinding from a panic) the
+/// remaining libraries are unloaded in reverse dependency order, so every
+/// library is unloaded before anything it depends on.
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::Registry;
+/// # fn main() {
+/// let mut registry = Registry::new();
+///
+/// if registry.load("core", "libcore.so", &[]).is_ok() {
+///     // "render" is unloaded before "core" when the registry is dropped
+///     let _ = registry.load("render", "librender.so", &["core"]);
+/// }
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct Registry {
+    entries: Vec<Entry>
+}
+
+#[derive(Debug)]
+struct Entry {
+    name: String,
+    dependencies: Vec<String>,
+    snek: Snek
+}
+
+impl Registry {
+    /// Construct a new, empty `Registry`.
+    pub fn new() -> Registry {
+        Registry {
+            entries: Vec::new()
+        }
+    }
+
+    /// Attempt to load a dynamic library from the given path and register it
+    /// under the given name, depending on the libraries named in `dependencies`.
+    ///
+    /// If the registration would be invalid this fails before the library is
+    /// loaded, otherwise it fails with any error from [`Snek::load`](struct.Snek.html#method.load).
+    pub fn load<P>(&mut self, name: &str, path: P, dependencies: &[&str]) -> Result<&Snek, Error> where P: AsRef<Path> {
+        self.check(name, dependencies)?;
+        let snek = Snek::load(path)?;
+        Ok(self.push(name, snek, dependencies))
+    }
+
+    /// Register an already loaded library under the given name, depending on
+    /// the libraries named in `dependencies`.
+    ///
+    /// If the registration would be invalid the library is dropped along with
+    /// the returned error.
+    pub fn insert(&mut self, name: &str, snek: Snek, dependencies: &[&str]) -> Result<&Snek, Error> {
+        self.check(name, dependencies)?;
+        Ok(self.push(name, snek, dependencies))
+    }
+
+    /// Retrieve the library registered under the given name.
+    pub fn get(&self, name: &str) -> Option<&Snek> {
+        self.position(name).map(|index| &self.entries[index].snek)
+    }
+
+    /// Register a callback to run just before the library registered under
+    /// the given name is unloaded, as described for
+    /// [`Snek::on_unload`](struct.Snek.html#method.on_unload).
+    ///
+    /// If no library is registered under the name, this will return
+    /// [`Error::RegistryError`](enum.Error.html).
+    pub fn on_unload(&self, name: &str, callback: Box<dyn FnOnce() + Send>) -> Result<(), Error> {
+        self.get(name).ok_or_else(|| unknown(name)).map(|snek| snek.on_unload(callback))
+    }
+
+    /// Returns true if a library is registered under the given name.
+    pub fn contains(&self, name: &str) -> bool {
+        self.position(name).is_some()
+    }
+
+    /// Returns the names of the registered libraries which directly depend on
+    /// the library with the given name.
+    pub fn dependents(&self, name: &str) -> Vec<&str> {
+        self.entries.iter()
+            .filter(|entry| entry.dependencies.iter().any(|dependency| dependency == name))
+            .map(|entry| entry.name.as_str())
+            .collect()
+    }
+
+    /// Unload the library registered under the given name.
+    ///
+    /// If any other registered library depends on it, this will return
+    /// [`Error::RegistryError`](enum.Error.html) and nothing is unloaded.
+    pub fn unload(&mut self, name: &str) -> Result<(), Error> {
+        let index = self.position(name).ok_or_else(|| unknown(name))?;
+
+        let dependents = self.dependents(name);
+        if !dependents.is_empty() {
+            return Err(Error::RegistryError(format!(
+                "cannot unload '{}' while it is required by {}", name, dependents.join(", ")
+            )));
+        }
+
+        self.entries.remove(index);
+        Ok(())
+    }
+
+    /// Unload the library registered under the given name along with every
+    /// library that depends on it, directly or indirectly. Dependents are always
+    /// unloaded before the libraries they depend on.
+    pub fn unload_cascade(&mut self, name: &str) -> Result<(), Error> {
+        if !self.contains(name) {
+            return Err(unknown(name));
+        }
+
+        let mut doomed = vec![name.to_owned()];
+        let mut index = 0;
+
+        while index < doomed.len() {
+            let dependents: Vec<String> = self.dependents(&doomed[index]).into_iter()
+                .filter(|dependent| !doomed.iter().any(|name| name == dependent))
+                .map(|dependent| dependent.to_owned())
+                .collect();
+
+            doomed.extend(dependents);
+            index += 1;
+        }
+
+        while !doomed.is_empty() {
+            let next = doomed.iter()
+                .position(|name| self.dependents(name).is_empty())
+                .unwrap_or(doomed.len() - 1);
+
+            let name = doomed.remove(next);
+            if let Some(index) = self.position(&name) {
+                self.entries.remove(index);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Unload every registered library, each one before anything it depends on.
+    pub fn unload_all(&mut self) {
+        while !self.entries.is_empty() {
+            // Registration rejects cycles, so there is always a library which
+            // nothing depends on - the fallback only guards the loop.
+            let next = (0..self.entries.len()).rev()
+                .find(|&index| self.dependents(&self.entries[index].name).is_empty())
+                .unwrap_or(self.entries.len() - 1);
+
+            self.entries.remove(next);
+        }
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.name == name)
+    }
+
+    fn check(&self, name: &str, dependencies: &[&str]) -> Result<(), Error> {
+        if self.contains(name) {
+            return Err(Error::RegistryError(format!("a library named '{}' is already registered", name)));
+        }
+
+        // The new library closes a cycle if anything it depends on already
+        // (transitively) depends on it.
+        let mut pending: Vec<&str> = dependencies.to_vec();
+        let mut visited: Vec<&str> = Vec::new();
+
+        while let Some(current) = pending.pop() {
+            if current == name {
+                return Err(Error::RegistryError(format!("registering '{}' would create a dependency cycle", name)));
+            }
+
+            if visited.contains(&current) {
+                continue;
+            }
+
+            visited.push(current);
+
+            if let Some(index) = self.position(current) {
+                pending.extend(self.entries[index].dependencies.iter().map(|dependency| dependency.as_str()));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push(&mut self, name: &str, snek: Snek, dependencies: &[&str]) -> &Snek {
+        self.entries.push(Entry {
+            name: name.to_owned(),
+            dependencies: dependencies.iter().map(|&dependency| dependency.to_owned()).collect(),
+            snek
+        });
+
+        &self.entries[self.entries.len() - 1].snek
+    }
+}
+
+impl Drop for Registry {
+    fn drop(&mut self) {
+        self.unload_all()
+    }
+}
+
+fn unknown(name: &str) -> Error {
+    Error::RegistryError(format!("no library named '{}' is registered", name))
+}
@@ -0,0 +1,436 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/reload.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::{Error, Snek};
+use ::plugin::{self, Buffer};
+
+use std::{env, fs, io, process};
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use std::os::raw::c_int;
+
+#[cfg(feature = "notify")]
+use std::thread;
+#[cfg(feature = "notify")]
+use std::time::Duration;
+#[cfg(feature = "notify")]
+use std::sync::Arc;
+#[cfg(feature = "notify")]
+use std::sync::atomic::AtomicBool;
+#[cfg(feature = "notify")]
+use std::sync::mpsc::{self, RecvTimeoutError};
+#[cfg(feature = "notify")]
+use notify::{self, Watcher};
+
+static SHADOW_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// This wraps a dynamic library which can be reloaded from disk while the
+/// program is running.
+///
+/// Rather than loading the library from its path directly, each load copies
+/// the file to a shadow copy in a new directory in the temporary directory,
+/// which only the current user can enter, and loads that. This means the
+/// original file can be replaced (by a compiler, for example) while it is in
+/// use, and that a new copy can be loaded while the old one is still live.
+/// Shadow copies are removed when they are unloaded.
+/// Where unloading does nothing, as described for
+/// [`Snek::unload_is_noop`](struct.Snek.html#method.unload_is_noop), each
+/// previous version stays mapped, though its shadow copy is still removed.
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::ReloadableSnek;
+/// # fn main() {
+/// if let Ok(mut library) = ReloadableSnek::load("libgame.so") {
+///     loop {
+///         if library.has_changed() {
+///             if let Err(err) = unsafe { library.reload_with_state() } {
+///                 println!("reload failed, keeping the old version: {:?}", err);
+///             }
+///         }
+///
+///         // ... call into library.library() ...
+/// #       break;
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ReloadableSnek {
+    path: PathBuf,
+    generation: usize,
+    loaded: Loaded
+}
+
+#[derive(Debug)]
+struct Loaded {
+    snek: Snek,
+    modified: Option<SystemTime>,
+
+    // Declared after the library so the copy is removed once it is unloaded
+    shadow: Shadow
+}
+
+#[derive(Debug)]
+struct Shadow {
+    path: PathBuf,
+
+    // Made for the copy alone, and removed along with it
+    dir: PathBuf
+}
+
+impl ReloadableSnek {
+    /// Attempt to load a shadow copy of the dynamic library at the given path.
+    ///
+    /// If copying the library fails, this will return [`Error::ReloadError`](enum.Error.html),
+    /// otherwise it fails with any error from [`Snek::load`](struct.Snek.html#method.load).
+    pub fn load<P>(path: P) -> Result<ReloadableSnek, Error> where P: AsRef<Path> {
+        let path = path.as_ref().to_path_buf();
+        let loaded = Loaded::load(&path)?;
+
+        Ok(ReloadableSnek {
+            path,
+            generation: 0,
+            loaded
+        })
+    }
+
+    /// Returns the currently loaded copy of the library.
+    ///
+    /// Anything obtained from it is invalidated by the next successful reload.
+    pub fn library(&self) -> &Snek {
+        &self.loaded.snek
+    }
+
+    /// Returns the path the library is reloaded from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the path of the shadow copy which is currently loaded.
+    pub fn loaded_path(&self) -> &Path {
+        &self.loaded.shadow.path
+    }
+
+    /// Returns the number of successful reloads so far.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Returns true if the modification time of the library on disk differs
+    /// from the time it had when the current copy was loaded.
+    pub fn has_changed(&self) -> bool {
+        modified(&self.path) != self.loaded.modified
+    }
+
+    /// Unload the current copy of the library and load a new one from disk.
+    ///
+    /// The new copy is loaded before the old one is unloaded, so if loading it
+    /// fails the old copy is kept and the error is returned.
+    pub fn reload(&mut self) -> Result<(), Error> {
+        let loaded = Loaded::load(&self.path)?;
+        self.swap(loaded);
+        Ok(())
+    }
+
+    /// Reload the library, handing its state over from the old copy to the new
+    /// one.
+    ///
+    /// After the new copy is loaded, the old copy's
+    /// [`plugin::SERIALIZE_SYMBOL`](plugin/constant.SERIALIZE_SYMBOL.html) function
+    /// is called to write its state into a [`plugin::Buffer`](plugin/struct.Buffer.html),
+    /// which is then passed to the new copy's
+    /// [`plugin::DESERIALIZE_SYMBOL`](plugin/constant.DESERIALIZE_SYMBOL.html) function.
+    /// Only once both have returned zero is the old copy unloaded.
+    ///
+    /// If either copy does not export its function this will return
+    /// [`Error::SymbolLoadError`](enum.Error.html), and if either function returns
+    /// a non-zero value (such as when the new version cannot parse the state of
+    /// the old one) this will return [`Error::ReloadError`](enum.Error.html). In
+    /// both cases the new copy is unloaded and the old copy stays live.
+    ///
+    /// # Safety
+    /// The functions must have the signatures `extern "C" fn(*mut Buffer) -> c_int`
+    /// and `extern "C" fn(*const Buffer) -> c_int` respectively, and a failing
+    /// serialize function must leave the old copy's state intact.
+    pub unsafe fn reload_with_state(&mut self) -> Result<(), Error> {
+        let loaded = Loaded::load(&self.path)?;
+
+        let serialize = self.loaded.snek.symbol(plugin::SERIALIZE_SYMBOL)?
+            .with(|f: extern "C" fn(*mut Buffer) -> c_int| f);
+
+        let deserialize = loaded.snek.symbol(plugin::DESERIALIZE_SYMBOL)?
+            .with(|f: extern "C" fn(*const Buffer) -> c_int| f);
+
+        let mut state = Vec::new();
+
+        let result = serialize(&mut Buffer::new(&mut state));
+        if result != 0 {
+            return Err(Error::ReloadError(format!("old copy failed to serialize its state (returned {})", result)));
+        }
+
+        let result = deserialize(&Buffer::new(&mut state));
+        if result != 0 {
+            return Err(Error::ReloadError(format!("new copy failed to deserialize the old state (returned {})", result)));
+        }
+
+        self.swap(loaded);
+        Ok(())
+    }
+
+    /// Start watching the library on disk for changes, calling the given
+    /// function on a background thread each time it is replaced or modified.
+    /// This requires the `notify` feature.
+    ///
+    /// Linkers tend to write their output in several steps, so the function is
+    /// only called once the file has not changed for the given quiet period.
+    /// Each call also marks the returned [`Watch`](struct.Watch.html) as pending,
+    /// so that the owner of the library can reload it automatically with
+    /// [`reload_if_pending`](#method.reload_if_pending) - the reload itself is
+    /// never performed on the background thread, as that would invalidate
+    /// symbols which are in use.
+    ///
+    /// The background thread is stopped when the returned `Watch` is dropped.
+    /// If the watch cannot be set up, this will return [`Error::ReloadError`](enum.Error.html).
+    #[cfg(feature = "notify")]
+    pub fn watch<F>(&self, quiet_period: Duration, mut callback: F) -> Result<Watch, Error> where F: FnMut() + Send + 'static {
+        let path = fs::canonicalize(&self.path)
+            .map_err(|err| Error::ReloadError(format!("failed to resolve {}: {}", self.path.display(), err)))?;
+
+        let name = path.file_name().map(|name| name.to_os_string())
+            .ok_or_else(|| Error::ReloadError(format!("{} does not name a file", path.display())))?;
+
+        let (sender, receiver) = mpsc::channel();
+        let events = sender.clone();
+
+        // The file itself is usually replaced rather than written to in place,
+        // so the directory is watched for events naming it instead.
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            if let Ok(event) = event {
+                if !event.kind.is_access() && event.paths.iter().any(|path| path.file_name() == Some(&name)) {
+                    let _ = events.send(WatchMessage::Changed);
+                }
+            }
+        }).map_err(|err| Error::ReloadError(format!("failed to create a file watcher: {}", err)))?;
+
+        let directory = path.parent().unwrap_or(&path);
+        watcher.watch(directory, notify::RecursiveMode::NonRecursive)
+            .map_err(|err| Error::ReloadError(format!("failed to watch {}: {}", directory.display(), err)))?;
+
+        let pending = Arc::new(AtomicBool::new(false));
+        let thread_pending = pending.clone();
+
+        let thread = thread::spawn(move || loop {
+            match receiver.recv() {
+                Ok(WatchMessage::Changed) => (),
+                _ => return
+            }
+
+            loop {
+                match receiver.recv_timeout(quiet_period) {
+                    Ok(WatchMessage::Changed) => (),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    _ => return
+                }
+            }
+
+            thread_pending.store(true, Ordering::SeqCst);
+            callback();
+        });
+
+        Ok(Watch {
+            pending,
+            sender,
+            watcher: Some(watcher),
+            thread: Some(thread)
+        })
+    }
+
+    /// Reload the library if the given [`Watch`](struct.Watch.html) has seen a
+    /// change since it was last checked, returning whether a reload happened.
+    /// This requires the `notify` feature.
+    ///
+    /// If the reload fails the change is still considered handled, and the old
+    /// copy is kept as with [`reload`](#method.reload).
+    #[cfg(feature = "notify")]
+    pub fn reload_if_pending(&mut self, watch: &Watch) -> Result<bool, Error> {
+        if watch.take_pending() {
+            self.reload().map(|_| true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn swap(&mut self, loaded: Loaded) {
+        self.loaded = loaded;
+        self.generation += 1;
+    }
+}
+
+/// This is a running file watch started by
+/// [`ReloadableSnek::watch`](struct.ReloadableSnek.html#method.watch). Dropping
+/// it stops watching and shuts down the background thread. This requires the
+/// `notify` feature.
+#[cfg(feature = "notify")]
+pub struct Watch {
+    pending: Arc<AtomicBool>,
+    sender: mpsc::Sender<WatchMessage>,
+    watcher: Option<notify::RecommendedWatcher>,
+    thread: Option<thread::JoinHandle<()>>
+}
+
+#[cfg(feature = "notify")]
+enum WatchMessage {
+    Changed,
+    Stop
+}
+
+#[cfg(feature = "notify")]
+impl Watch {
+    /// Returns true if the library has changed since this was last called, and
+    /// resets the pending state.
+    pub fn take_pending(&self) -> bool {
+        self.pending.swap(false, Ordering::SeqCst)
+    }
+}
+
+#[cfg(feature = "notify")]
+impl ::std::fmt::Debug for Watch {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("Watch")
+            .field("pending", &self.pending.load(Ordering::SeqCst))
+            .finish()
+    }
+}
+
+#[cfg(feature = "notify")]
+impl Drop for Watch {
+    fn drop(&mut self) {
+        self.watcher.take();
+        let _ = self.sender.send(WatchMessage::Stop);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Loaded {
+    fn load(path: &Path) -> Result<Loaded, Error> {
+        let modified = modified(path);
+        let shadow = Shadow::copy(path)?;
+        let snek = Snek::load(&shadow.path)?;
+
+        Ok(Loaded {
+            snek,
+            modified,
+            shadow
+        })
+    }
+}
+
+impl Shadow {
+    /// Copy the library into a directory of its own in the temporary
+    /// directory, which only this user can enter. The directory and the file
+    /// are both created afresh, failing rather than following anything
+    /// already at their paths, so no other user can plant or replace the
+    /// file which is loaded.
+    fn copy(path: &Path) -> Result<Shadow, Error> {
+        let name = path.file_name()
+            .ok_or_else(|| Error::ReloadError(format!("{} does not name a file", path.display())))?;
+
+        let dir = private_dir()?;
+        let shadow = Shadow {
+            path: dir.join(name),
+            dir
+        };
+
+        let copy = |shadow: &Path| -> io::Result<()> {
+            let mut source = File::open(path)?;
+            let mut file = new_file(shadow)?;
+
+            io::copy(&mut source, &mut file)?;
+            file.sync_all()
+        };
+
+        copy(&shadow.path).map_err(|err| Error::ReloadError(format!("failed to copy {}: {}", path.display(), err)))?;
+        Ok(shadow)
+    }
+}
+
+impl Drop for Shadow {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+        let _ = fs::remove_dir(&self.dir);
+    }
+}
+
+/// The most names tried for a shadow directory, in case others already exist.
+const SHADOW_ATTEMPTS: usize = 16;
+
+/// Create a new directory for a shadow copy, named for the process, a counter
+/// and the time, so that a name someone else has taken is skipped rather than
+/// used.
+fn private_dir() -> Result<PathBuf, Error> {
+    let nanos = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|since| since.subsec_nanos()).unwrap_or(0);
+
+    for _ in 0..SHADOW_ATTEMPTS {
+        let name = format!("snek-{}-{}-{:08x}", process::id(), SHADOW_COUNTER.fetch_add(1, Ordering::SeqCst), nanos);
+        let dir = env::temp_dir().join(name);
+
+        match new_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(Error::ReloadError(format!("failed to create {}: {}", dir.display(), err)))
+        }
+    }
+
+    Err(Error::ReloadError(format!("failed to create a shadow directory in {}", env::temp_dir().display())))
+}
+
+#[cfg(unix)]
+fn new_dir(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    fs::DirBuilder::new().mode(0o700).create(dir)
+}
+
+#[cfg(not(unix))]
+fn new_dir(dir: &Path) -> io::Result<()> {
+    fs::DirBuilder::new().create(dir)
+}
+
+#[cfg(unix)]
+fn new_file(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    OpenOptions::new().write(true).create_new(true).mode(0o700).open(path)
+}
+
+#[cfg(not(unix))]
+fn new_file(path: &Path) -> io::Result<File> {
+    OpenOptions::new().write(true).create_new(true).open(path)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -0,0 +1,107 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/resolver.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use libc::c_void;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ptr::NonNull;
+use std::sync::{Mutex, MutexGuard};
+
+/// A function consulted by [`Snek::symbol`](struct.Snek.html#method.symbol)
+/// to find a symbol, added with [`Snek::push_resolver`](struct.Snek.html#method.push_resolver).
+/// It returns the address to use for the name, or `None` (or a null pointer)
+/// to leave it to the rest of the chain.
+pub type Resolver = Box<dyn Fn(&str) -> Option<*mut c_void> + Send + Sync>;
+
+/// Where a [`Resolver`](type.Resolver.html) is consulted, relative to the
+/// lookup in the library itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolverPosition {
+    /// Consulted before the library, so that the resolver overrides any
+    /// symbol the library exports, such as with a test double or a patch.
+    Before,
+
+    /// Consulted only once the library has failed to find the symbol, so
+    /// that the resolver provides a fallback for it.
+    After
+}
+
+/// The resolvers added to a `Snek`, in the order they were added, along with
+/// the names each has satisfied, so that they show up in its `Debug` output.
+#[derive(Default)]
+pub(crate) struct Resolvers {
+    chain: Vec<(ResolverPosition, Resolver)>,
+    resolved: Mutex<BTreeMap<String, usize>>
+}
+
+impl Resolvers {
+    pub(crate) fn push(&mut self, position: ResolverPosition, resolver: Resolver) -> usize {
+        self.chain.push((position, resolver));
+        self.chain.len() - 1
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.chain.is_empty()
+    }
+
+    /// Consult each resolver at the given position in turn, recording which
+    /// one satisfied the name, if any.
+    pub(crate) fn resolve(&self, symbol: &str, position: ResolverPosition) -> Option<NonNull<c_void>> {
+        let (index, address) = self.chain.iter()
+            .enumerate()
+            .filter(|&(_, &(at, _))| at == position)
+            .filter_map(|(index, (_, resolver))| resolver(symbol).and_then(NonNull::new).map(|address| (index, address)))
+            .next()?;
+
+        self.resolved().insert(symbol.to_owned(), index);
+        Some(address)
+    }
+
+    /// Record that the library itself satisfied the name.
+    pub(crate) fn resolved_by_library(&self, symbol: &str) {
+        self.resolved().remove(symbol);
+    }
+
+    pub(crate) fn resolved_by(&self, symbol: &str) -> Option<usize> {
+        self.resolved().get(symbol).cloned()
+    }
+
+    fn resolved(&self) -> MutexGuard<'_, BTreeMap<String, usize>> {
+        // Each name is only inserted or removed while locked, which cannot
+        // leave the map half updated
+        self.resolved.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl fmt::Debug for Resolvers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let resolved = self.resolved();
+
+        f.debug_list()
+            .entries(self.chain.iter().enumerate().map(|(index, &(position, _))| {
+                let names = resolved.iter()
+                    .filter(|&(_, &by)| by == index)
+                    .map(|(name, _)| name.as_str())
+                    .collect::<Vec<_>>();
+
+                (index, position, names)
+            }))
+            .finish()
+    }
+}
@@ -0,0 +1,292 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/scan.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::{Error, Snek};
+use ::failure::Failure;
+
+use std::fs;
+use std::env::consts::DLL_EXTENSION;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// This configures which files [`scan_dir`](fn.scan_dir.html) attempts to
+/// load. By default, only files in the given directory itself with the
+/// platform's dynamic library extension are loaded, and the scan continues
+/// past any failures.
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    extensions: Vec<String>,
+    pattern: Option<String>,
+    depth: usize,
+    stop_on_error: bool
+}
+
+impl ScanOptions {
+    /// Construct the default `ScanOptions`.
+    pub fn new() -> ScanOptions {
+        ScanOptions {
+            extensions: vec![DLL_EXTENSION.into()],
+            pattern: None,
+            depth: 0,
+            stop_on_error: false
+        }
+    }
+
+    /// Set the file extensions which are loaded. An empty list allows any
+    /// extension.
+    pub fn extensions(&mut self, extensions: &[&str]) -> &mut ScanOptions {
+        self.extensions = extensions.iter().map(|&extension| extension.into()).collect();
+        self
+    }
+
+    /// Only load files whose names match the given pattern, in which `*` matches
+    /// any sequence of characters and `?` matches any single character. This is
+    /// applied in addition to the extension filter.
+    ///
+    /// Any part of a file name which is not valid Unicode is left as it is, so
+    /// each byte of it (or on Windows, each unpaired surrogate) is only matched
+    /// by a `?` or `*`.
+    pub fn pattern(&mut self, pattern: &str) -> &mut ScanOptions {
+        self.pattern = Some(pattern.into());
+        self
+    }
+
+    /// Set how many levels of subdirectories are scanned. A depth of zero only
+    /// scans the given directory itself.
+    pub fn depth(&mut self, depth: usize) -> &mut ScanOptions {
+        self.depth = depth;
+        self
+    }
+
+    /// Set whether the scan stops after the first file that fails to load.
+    pub fn stop_on_error(&mut self, stop_on_error: bool) -> &mut ScanOptions {
+        self.stop_on_error = stop_on_error;
+        self
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        let extension_matches = self.extensions.is_empty() || path.extension()
+            .map(|extension| self.extensions.iter().any(|allowed| extension == allowed.as_str()))
+            .unwrap_or(false);
+
+        let pattern_matches = match (self.pattern.as_ref(), path.file_name()) {
+            (None, _) => true,
+            (Some(pattern), Some(name)) => glob_match(pattern, name),
+            (Some(_), None) => false
+        };
+
+        extension_matches && pattern_matches
+    }
+}
+
+impl Default for ScanOptions {
+    fn default() -> ScanOptions {
+        ScanOptions::new()
+    }
+}
+
+/// The outcome of attempting to load a single file during a
+/// [`scan_dir`](fn.scan_dir.html).
+#[derive(Debug)]
+pub struct ScanResult {
+    /// The path of the file, as found while scanning.
+    pub path: PathBuf,
+
+    /// The loaded library, or the reason loading it failed.
+    pub result: Result<Snek, Error>
+}
+
+/// Attempt to load every dynamic library in the given directory which matches
+/// the given options.
+///
+/// A failure to load one file does not prevent the others from being loaded
+/// unless [`ScanOptions::stop_on_error`](struct.ScanOptions.html#method.stop_on_error)
+/// is set. A directory which cannot be read is reported as a failed result for
+/// that directory, which is ordered and stops the scan just as a file which
+/// fails to load does. Results are ordered by path, and files which resolve to
+/// the same canonical path (such as via symlinks) are only loaded once.
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::{scan_dir, ScanOptions};
+/// # fn main() {
+/// for plugin in scan_dir("plugins", ScanOptions::new().depth(1)) {
+///     match plugin.result {
+///         Ok(_) => println!("loaded {}", plugin.path.display()),
+///         Err(err) => println!("failed to load {}: {:?}", plugin.path.display(), err)
+///     }
+/// }
+/// # }
+/// ```
+pub fn scan_dir<P>(dir: P, options: &ScanOptions) -> Vec<ScanResult> where P: AsRef<Path> {
+    let mut candidates = Vec::new();
+    let mut results = Vec::new();
+
+    collect(dir.as_ref(), options, options.depth, &mut candidates);
+    candidates.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut seen = Vec::new();
+    for (path, unreadable) in candidates {
+        let result = match unreadable {
+            Some(err) => Err(err),
+
+            None => {
+                let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+                if seen.contains(&canonical) {
+                    continue;
+                }
+
+                seen.push(canonical);
+                Snek::load(&path)
+            }
+        };
+
+        let failed = result.is_err();
+
+        results.push(ScanResult {
+            path,
+            result
+        });
+
+        if failed && options.stop_on_error {
+            break;
+        }
+    }
+
+    results
+}
+
+// Each candidate is a file to load, or a directory which could not be read
+// along with the error to report for it
+fn collect(dir: &Path, options: &ScanOptions, depth: usize, candidates: &mut Vec<(PathBuf, Option<Error>)>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            let err = Error::LibraryLoadError(Failure::from_io(format!("failed to read directory: {}", err), &err));
+            candidates.push((dir.into(), Some(err)));
+
+            return;
+        }
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+
+        if path.is_dir() {
+            if depth > 0 {
+                collect(&path, options, depth - 1, candidates);
+            }
+        } else if options.matches(&path) {
+            candidates.push((path, None));
+        }
+    }
+}
+
+/// Returns whether a file name matches a pattern in which `*` matches any
+/// sequence of characters and `?` matches any single character.
+///
+/// This takes time linear in the length of the name for a given pattern,
+/// however many `*`s it has.
+pub(crate) fn glob_match(pattern: &str, name: &OsStr) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = units(name);
+
+    let (mut p, mut n) = (0, 0);
+
+    // The position of the last `*` reached in the pattern, and of the name
+    // from which it is currently matching
+    let mut star = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(&'*') => {
+                star = Some((p, n));
+                p += 1;
+            },
+
+            Some(&'?') => {
+                p += 1;
+                n += 1;
+            },
+
+            Some(&c) if name[n] == Unit::Char(c) => {
+                p += 1;
+                n += 1;
+            },
+
+            // Let the last `*` match one more unit of the name and try again
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                },
+
+                None => return false
+            }
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// A single character of a file name, or a byte (or on Windows, a UTF-16 code
+// unit) which is not part of a valid character. These are kept as they are
+// rather than replaced, so that only a wildcard can match them.
+#[derive(PartialEq)]
+enum Unit {
+    Char(char),
+
+    #[cfg(any(unix, windows))]
+    Raw(u32)
+}
+
+fn units(name: &OsStr) -> Vec<Unit> {
+    match name.to_str() {
+        Some(name) => name.chars().map(Unit::Char).collect(),
+        None => raw_units(name)
+    }
+}
+
+#[cfg(unix)]
+fn raw_units(name: &OsStr) -> Vec<Unit> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut units = Vec::new();
+    for chunk in name.as_bytes().utf8_chunks() {
+        units.extend(chunk.valid().chars().map(Unit::Char));
+        units.extend(chunk.invalid().iter().map(|&byte| Unit::Raw(byte as u32)));
+    }
+
+    units
+}
+
+#[cfg(windows)]
+fn raw_units(name: &OsStr) -> Vec<Unit> {
+    use std::os::windows::ffi::OsStrExt;
+
+    char::decode_utf16(name.encode_wide())
+        .map(|unit| unit.map(Unit::Char).unwrap_or_else(|err| Unit::Raw(err.unpaired_surrogate() as u32)))
+        .collect()
+}
+
+#[cfg(not(any(unix, windows)))]
+fn raw_units(name: &OsStr) -> Vec<Unit> {
+    name.to_string_lossy().chars().map(Unit::Char).collect()
+}
@@ -0,0 +1,90 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/shared.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::{Error, SharedSymbol, Snek, SymbolName};
+
+use std::sync::Arc;
+
+/// This wraps a [`Snek`](struct.Snek.html) so that it can be cloned cheaply,
+/// for a library shared by several parts of a program which each hold their
+/// own handle to it, returned from [`Snek::into_shared`](struct.Snek.html#method.into_shared).
+///
+/// Every clone refers to the same loaded library, and each
+/// [`SharedSymbol`](struct.SharedSymbol.html) loaded through one holds a
+/// count on it, so the library is unloaded once, when the last clone and the
+/// last of its symbols have both been dropped. Clones can be sent to other
+/// threads, and the library is unloaded on whichever drops the last of them.
+///
+/// # Example
+/// ```
+/// # extern crate libc;
+/// # extern crate snek;
+/// # use snek::Snek;
+/// # use libc::c_int;
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+/// # fn main() {
+/// # let libexample = fixture();
+/// let shared = Snek::load(libexample).unwrap().into_shared();
+/// let add = shared.symbol("add").unwrap();
+///
+/// // The symbol keeps the library loaded by itself
+/// drop(shared);
+/// assert_eq!(unsafe { add.with(|add: extern fn(c_int, c_int) -> c_int| add(3, 7)) }, 10);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SharedSnek {
+    snek: Arc<Snek>
+}
+
+impl SharedSnek {
+    pub(crate) fn new(snek: Snek) -> SharedSnek {
+        SharedSnek {
+            snek: Arc::new(snek)
+        }
+    }
+
+    /// Returns the shared library, for anything else a `Snek` can do.
+    ///
+    /// A symbol loaded through it with [`Snek::symbol`](struct.Snek.html#method.symbol)
+    /// borrows from this clone rather than keeping the library loaded.
+    pub fn library(&self) -> &Snek {
+        &self.snek
+    }
+
+    /// Attempt to load a symbol from the library, as with
+    /// [`Snek::symbol`](struct.Snek.html#method.symbol), returning a
+    /// [`SharedSymbol`](struct.SharedSymbol.html) which keeps the library
+    /// loaded for as long as it exists.
+    pub fn symbol<N>(&self, symbol: N) -> Result<SharedSymbol, Error> where N: SymbolName {
+        self.snek.symbol(symbol).map(|symbol| SharedSymbol::new(symbol.as_non_null(), self.snek.clone()))
+    }
+
+    /// Returns the number of clones of this `SharedSnek` and of the
+    /// [`SharedSymbol`](struct.SharedSymbol.html)s loaded through them which
+    /// still exist, including this one.
+    pub fn strong_count(&self) -> usize {
+        Arc::strong_count(&self.snek)
+    }
+}
+
+impl From<Snek> for SharedSnek {
+    fn from(snek: Snek) -> SharedSnek {
+        SharedSnek::new(snek)
+    }
+}
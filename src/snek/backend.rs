@@ -0,0 +1,382 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/snek/backend.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// Libraries registered with the static registry are found before asking the
+// platform, so that the same loading code works against either, and handles
+// to testing symbol tables are recognised before being passed to it.
+
+use ::{AddressInfo, Error, LoadedLibrary};
+#[cfg(any(feature = "static-registry", feature = "testing"))]
+use ::failure::{Class, Failure};
+use ::options::LoadFlags;
+
+#[cfg(any(feature = "static-registry", feature = "testing"))]
+use std::env;
+use std::ptr::NonNull;
+use std::path::{Path, PathBuf};
+use std::ffi::CStr;
+use libc::c_void;
+
+use super::platform;
+
+#[cfg(feature = "static-registry")]
+use ::statics;
+
+#[cfg(feature = "testing")]
+use ::testing;
+
+pub fn load_library(path: &Path, flags: LoadFlags) -> Result<NonNull<c_void>, Error> {
+    #[cfg(feature = "static-registry")]
+    {
+        // A registered library is part of the process, so is never in a
+        // namespace of its own
+        if let Some(library) = statics::find(path).filter(|_| !flags.new_namespace) {
+            return Ok(library);
+        }
+    }
+
+    platform::load_library(path, flags).map_err(|err| err.with_path(path))
+}
+
+/// Registered and testing libraries are part of the program itself, so are
+/// found in the executable.
+pub fn library_path(handle: NonNull<c_void>) -> Result<PathBuf, Error> {
+    #[cfg(feature = "static-registry")]
+    {
+        if statics::is_static(handle) {
+            return current_exe();
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    {
+        if testing::is_table(handle) {
+            return current_exe();
+        }
+    }
+
+    platform::library_path(handle)
+}
+
+#[cfg(any(feature = "static-registry", feature = "testing"))]
+fn current_exe() -> Result<PathBuf, Error> {
+    env::current_exe().map_err(|err| Error::LibraryLoadError(Failure::new(err.to_string(), err.raw_os_error(), Class::Other)))
+}
+
+/// Registered libraries are always loaded, so are found as they are by
+/// `load_library`.
+pub fn open_existing(path: &Path) -> Result<NonNull<c_void>, Error> {
+    #[cfg(feature = "static-registry")]
+    {
+        if let Some(library) = statics::find(path) {
+            return Ok(library);
+        }
+    }
+
+    platform::open_existing(path).map_err(|err| err.with_path(path))
+}
+
+/// Registered libraries are never unloaded, so need no more references, but
+/// a testing symbol table is freed with its one owner, so cannot be shared.
+pub fn add_reference(handle: NonNull<c_void>) -> Result<NonNull<c_void>, Error> {
+    #[cfg(feature = "static-registry")]
+    {
+        if statics::is_static(handle) {
+            return Ok(handle);
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    {
+        if testing::is_table(handle) {
+            return Err(Error::LibraryLoadError(Failure::new("testing symbol tables cannot be shared", None, Class::Unsupported)));
+        }
+    }
+
+    platform::add_reference(handle)
+}
+
+/// Returns a handle to the current process, which must never be dropped.
+pub fn process_handle() -> Result<NonNull<c_void>, Error> {
+    platform::process_handle()
+}
+
+/// Registered libraries and testing symbol tables are part of the program
+/// itself, so their addresses are found in the executable.
+pub fn resolve_address(address: *const c_void) -> Option<AddressInfo> {
+    platform::resolve_address(address)
+}
+
+/// Registered libraries and testing symbol tables are never listed, as they
+/// are not loaded by the platform.
+pub fn loaded_libraries() -> Vec<LoadedLibrary> {
+    platform::loaded_libraries()
+}
+
+/// Registered and testing symbols are named by strings, which a name that
+/// is not UTF-8 never matches once its invalid bytes are replaced.
+pub unsafe fn load_symbol(handle: NonNull<c_void>, symbol: &CStr) -> Result<NonNull<c_void>, Error> {
+    #[cfg(feature = "static-registry")]
+    {
+        if let Some(result) = statics::symbol(handle, &symbol.to_string_lossy()) {
+            return result.map_err(|err| err.with_symbol(&symbol.to_string_lossy()));
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    {
+        if let Some(result) = testing::symbol(handle, &symbol.to_string_lossy()) {
+            return result.map_err(|err| err.with_symbol(&symbol.to_string_lossy()));
+        }
+    }
+
+    platform::load_symbol(handle, symbol).map_err(|err| err.with_symbol(&symbol.to_string_lossy()))
+}
+
+/// A registered or testing symbol with the null address is still present,
+/// as on the platform.
+pub unsafe fn has_symbol(handle: NonNull<c_void>, symbol: &CStr) -> bool {
+    #[cfg(feature = "static-registry")]
+    {
+        if let Some(result) = statics::symbol(handle, &symbol.to_string_lossy()) {
+            return is_present(result);
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    {
+        if let Some(result) = testing::symbol(handle, &symbol.to_string_lossy()) {
+            return is_present(result);
+        }
+    }
+
+    unsafe { platform::has_symbol(handle, symbol) }
+}
+
+#[cfg(any(feature = "static-registry", feature = "testing"))]
+fn is_present(result: Result<NonNull<c_void>, Error>) -> bool {
+    match result {
+        Ok(_) => true,
+        Err(err) => err.is_null_symbol()
+    }
+}
+
+/// Registered and testing symbols belong to no object the platform knows
+/// of, so are never found by a search of the whole process.
+pub unsafe fn global_symbol(symbol: &CStr) -> Result<NonNull<c_void>, Error> {
+    unsafe { platform::global_symbol(symbol) }.map_err(|err| err.with_symbol(&symbol.to_string_lossy()))
+}
+
+#[cfg(unix)]
+pub unsafe fn next_symbol(symbol: &CStr) -> Result<NonNull<c_void>, Error> {
+    unsafe { platform::next_symbol(symbol) }.map_err(|err| err.with_symbol(&symbol.to_string_lossy()))
+}
+
+/// Registered and testing symbols are only named, so have no ordinals.
+#[cfg(windows)]
+pub unsafe fn load_symbol_ordinal(handle: NonNull<c_void>, ordinal: u16) -> Result<NonNull<c_void>, Error> {
+    let name = format!("ordinal {}", ordinal);
+
+    #[cfg(feature = "static-registry")]
+    {
+        if statics::is_static(handle) {
+            return Err(unnumbered(&name));
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    {
+        if testing::is_table(handle) {
+            return Err(unnumbered(&name));
+        }
+    }
+
+    platform::load_symbol_ordinal(handle, ordinal).map_err(|err| err.with_symbol(&name))
+}
+
+#[cfg(all(windows, any(feature = "static-registry", feature = "testing")))]
+fn unnumbered(name: &str) -> Error {
+    Error::SymbolLoadError(Failure::new("registered symbols have no ordinals", None, Class::Unsupported)).with_symbol(name)
+}
+
+/// Registered and testing symbols are not in any export table.
+#[cfg(windows)]
+pub unsafe fn export_info(handle: NonNull<c_void>, symbol: &CStr) -> Option<(u16, ::windows::ExportKind)> {
+    #[cfg(feature = "static-registry")]
+    {
+        if statics::is_static(handle) {
+            return None;
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    {
+        if testing::is_table(handle) {
+            return None;
+        }
+    }
+
+    unsafe { platform::export_info(handle, symbol) }
+}
+
+/// Registered and testing symbols have no versions, so looking one up is no
+/// more supported than on a platform without them.
+pub unsafe fn load_symbol_version(handle: NonNull<c_void>, symbol: &CStr, version: &CStr) -> Result<NonNull<c_void>, Error> {
+    #[cfg(feature = "static-registry")]
+    {
+        if statics::is_static(handle) {
+            return Err(unversioned(symbol));
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    {
+        if testing::is_table(handle) {
+            return Err(unversioned(symbol));
+        }
+    }
+
+    platform::load_symbol_version(handle, symbol, version).map_err(|err| err.with_symbol(&symbol.to_string_lossy()))
+}
+
+#[cfg(any(feature = "static-registry", feature = "testing"))]
+fn unversioned(symbol: &CStr) -> Error {
+    Error::SymbolLoadError(Failure::new("registered symbols have no versions", None, Class::Unsupported)).with_symbol(&symbol.to_string_lossy())
+}
+
+/// Returns up to the given number of the names a library exports, where the
+/// platform can read them from the library as it is loaded, so that they
+/// need not be read from its file. Registered and testing symbols are read
+/// from neither.
+#[cfg_attr(not(feature = "suggestions"), allow(dead_code))]
+pub unsafe fn exported_names(handle: NonNull<c_void>, limit: usize) -> Option<Vec<String>> {
+    #[cfg(feature = "static-registry")]
+    {
+        if statics::is_static(handle) {
+            return None;
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    {
+        if testing::is_table(handle) {
+            return None;
+        }
+    }
+
+    unsafe { platform::exported_names(handle, limit) }
+}
+
+/// Returns the decorated name a lookup for the symbol falls back to. Registered
+/// and testing symbols are never decorated.
+pub unsafe fn decorated_name(handle: NonNull<c_void>, symbol: &str) -> Option<String> {
+    #[cfg(feature = "static-registry")]
+    {
+        if statics::is_static(handle) {
+            return None;
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    {
+        if testing::is_table(handle) {
+            return None;
+        }
+    }
+
+    platform::decorated_name(handle, symbol)
+}
+
+/// Find the calling thread's instance of a thread-local variable from its
+/// symbol. Registered and testing symbols are plain addresses.
+#[cfg_attr(not(any(feature = "static-registry", feature = "testing")), allow(unused_variables))]
+pub unsafe fn thread_local(handle: NonNull<c_void>, symbol: NonNull<c_void>) -> Result<NonNull<c_void>, Error> {
+    #[cfg(feature = "static-registry")]
+    {
+        if statics::is_static(handle) {
+            return Ok(symbol);
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    {
+        if testing::is_table(handle) {
+            return Ok(symbol);
+        }
+    }
+
+    platform::thread_local(symbol)
+}
+
+/// Registered and testing libraries have no namespace of their own.
+pub fn namespace_id(handle: NonNull<c_void>) -> Result<i64, Error> {
+    #[cfg(feature = "static-registry")]
+    {
+        if statics::is_static(handle) {
+            return Err(Error::Unsupported);
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    {
+        if testing::is_table(handle) {
+            return Err(Error::Unsupported);
+        }
+    }
+
+    platform::namespace_id(handle)
+}
+
+/// Registered libraries are never unloaded, so are already as good as pinned.
+/// Testing tables are freed with their last reference, which pinning cannot
+/// prevent, so they cannot be pinned.
+pub fn pin(handle: NonNull<c_void>, path: &Path) -> Result<(), Error> {
+    #[cfg(feature = "static-registry")]
+    {
+        if statics::is_static(handle) {
+            return Ok(());
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    {
+        if testing::is_table(handle) {
+            return Err(Error::Unsupported);
+        }
+    }
+
+    platform::pin(handle).map_err(|err| err.with_path(path))
+}
+
+pub fn drop_library(handle: NonNull<c_void>) -> Result<(), Error> {
+    #[cfg(feature = "static-registry")]
+    {
+        if statics::is_static(handle) {
+            return Ok(());
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    {
+        if testing::drop_table(handle) {
+            return Ok(());
+        }
+    }
+
+    platform::drop_library(handle)
+}
@@ -0,0 +1,71 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/snek/cache.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ptr::NonNull;
+use std::sync::{Mutex, MutexGuard};
+use libc::c_void;
+
+/// The addresses a `Snek`'s library has returned for each name, so that
+/// looking a name up again neither copies it nor asks the platform. Only
+/// successful lookups are kept, as a missing symbol does not stay missing
+/// where a resolver can stand in for it.
+pub(crate) struct SymbolCache {
+    enabled: bool,
+    symbols: Mutex<HashMap<Box<[u8]>, usize>>
+}
+
+impl SymbolCache {
+    pub(crate) fn new(enabled: bool) -> SymbolCache {
+        SymbolCache {
+            enabled,
+            symbols: Mutex::default()
+        }
+    }
+
+    pub(crate) fn get(&self, symbol: &[u8]) -> Option<NonNull<c_void>> {
+        if !self.enabled {
+            return None;
+        }
+
+        self.symbols().get(symbol).and_then(|&address| NonNull::new(address as *mut c_void))
+    }
+
+    pub(crate) fn insert(&self, symbol: &[u8], address: NonNull<c_void>) {
+        if self.enabled {
+            self.symbols().insert(symbol.into(), address.as_ptr() as usize);
+        }
+    }
+
+    fn symbols(&self) -> MutexGuard<'_, HashMap<Box<[u8]>, usize>> {
+        // Each entry is only inserted while locked, which cannot leave the
+        // map half updated
+        self.symbols.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl fmt::Debug for SymbolCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.enabled {
+            write!(f, "{} symbols", self.symbols().len())
+        } else {
+            write!(f, "disabled")
+        }
+    }
+}
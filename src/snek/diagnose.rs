@@ -0,0 +1,259 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/snek/diagnose.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::{env, Error};
+use ::failure::{Class, Failure};
+use ::inspect::{self, Format, Image, Machine};
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// How many levels of dependencies are followed when looking for one which
+/// is missing.
+const MAX_DEPTH: usize = 8;
+
+/// Classify the error from a failed library load by checking whether the
+/// library file exists and can be opened. If `explain` is set, the file is
+/// also inspected to refine the classification, and an explanation of why
+/// the load failed is added to the message if one can be found.
+pub fn diagnose(path: &Path, err: Error, explain: bool) -> Error {
+    match err {
+        Error::LibraryLoadError(mut failure) => {
+            let mut findings = Vec::new();
+
+            let file = if env::is_bare_name(path) {
+                let located = env::locate(path);
+                if located.is_empty() {
+                    findings.push(format!("looked in {} directories, file present in none", env::search_paths().len()));
+                    failure.class = Class::NotFound;
+                }
+
+                located.into_iter().next()
+            } else {
+                Some(path.to_owned())
+            };
+
+            if let Some(file) = file {
+                match File::open(&file) {
+                    Err(err) => {
+                        let checked = Failure::from_io("", &err);
+                        if failure.os_code.is_none() {
+                            failure.os_code = checked.os_code;
+                            failure.source = checked.source;
+                        }
+                        failure.class = checked.class;
+                    },
+
+                    Ok(_) => {
+                        // The file exists, so whatever was not found must be something it needs
+                        if failure.class == Class::NotFound {
+                            failure.class = Class::MissingDependency;
+                        }
+
+                        if explain {
+                            let mut visited = Vec::new();
+                            if let Some(class) = self::explain(&file, 0, &mut visited, &mut findings) {
+                                failure.class = class;
+                            }
+                        }
+                    }
+                }
+            }
+
+            if explain && !findings.is_empty() {
+                failure.message = format!("{} ({})", failure.message, findings.join("; "));
+            }
+
+            Error::LibraryLoadError(failure)
+        },
+
+        err => err
+    }
+}
+
+/// Inspect a library file and its dependencies, adding any problems found to
+/// `findings` and returning the class of the first problem.
+fn explain(file: &Path, depth: usize, visited: &mut Vec<PathBuf>, findings: &mut Vec<String>) -> Option<Class> {
+    visited.push(file.to_owned());
+
+    // A file which cannot be inspected is left to the platform's own message
+    let image = match inspect::inspect_file(file) {
+        Ok(image) => image,
+        Err(_) if depth == 0 => return Some(Class::BadImage),
+        Err(_) => return None
+    };
+
+    let current = Machine::current();
+    if !image.matches_current() {
+        let subject = describe(file, depth);
+
+        if image.format() == Format::MachOUniversal {
+            let machines = image.machines().iter().map(|machine| machine.to_string()).collect::<Vec<_>>();
+            findings.push(format!("{} contains {} but process is {}", subject, machines.join(", "), current));
+        } else {
+            findings.push(format!("{} is {} but process is {}", subject, image.machine(), current));
+        }
+
+        // A dependency for the wrong architecture is skipped by the loader, so
+        // is as good as missing
+        return Some(if depth == 0 { Class::WrongArchitecture } else { Class::MissingDependency });
+    }
+
+    let mut class = None;
+    for dependency in image.dependencies() {
+        let found = match resolve(file, &image, dependency) {
+            Resolved::Found(found) => if depth < MAX_DEPTH && !visited.contains(&found) {
+                explain(&found, depth + 1, visited, findings)
+            } else {
+                None
+            },
+
+            Resolved::Missing(searched) => {
+                let mut finding = format!("dependency {} not found in any of {} search directories", dependency, searched);
+                if depth > 0 {
+                    finding = format!("{} (required by {})", finding, file.display());
+                }
+
+                findings.push(finding);
+                Some(Class::MissingDependency)
+            },
+
+            Resolved::Unknown => None
+        };
+
+        class = class.or(found);
+    }
+
+    class
+}
+
+/// Returns the direct dependencies of a library file which cannot be found.
+pub fn missing_dependencies(file: &Path, image: &Image) -> Vec<String> {
+    image.dependencies().iter()
+        .filter(|dependency| matches!(resolve(file, image, dependency), Resolved::Missing(_)))
+        .cloned()
+        .collect()
+}
+
+fn describe(file: &Path, depth: usize) -> String {
+    if depth == 0 {
+        "library".into()
+    } else {
+        format!("dependency {}", file.display())
+    }
+}
+
+enum Resolved {
+    Found(PathBuf),
+    Missing(usize),
+
+    /// The dependency cannot be checked from the file alone, such as a
+    /// system library which is not present on disk or a path relative to the
+    /// executable.
+    Unknown
+}
+
+fn resolve(file: &Path, image: &Image, dependency: &str) -> Resolved {
+    let directory = file.parent().unwrap_or_else(|| Path::new(""));
+
+    match image.format() {
+        Format::Elf => {
+            if dependency.contains('/') {
+                return exists(Path::new(dependency));
+            }
+
+            let mut directories = image.search_paths().iter()
+                .map(|path| PathBuf::from(expand_origin(path, directory)))
+                .collect::<Vec<_>>();
+
+            directories.extend(env::search_paths());
+            search(directories, dependency)
+        },
+
+        Format::MachO | Format::MachOUniversal => {
+            if dependency.starts_with("/usr/lib/") || dependency.starts_with("/System/") {
+                // These are usually only present in the dyld shared cache
+                Resolved::Unknown
+            } else if let Some(rest) = dependency.strip_prefix("@loader_path/") {
+                exists(&directory.join(rest))
+            } else if let Some(rest) = dependency.strip_prefix("@rpath/") {
+                let directories = image.search_paths().iter()
+                    .filter_map(|path| if let Some(relative) = path.strip_prefix("@loader_path") {
+                        Some(directory.join(relative.trim_start_matches('/')))
+                    } else if path.starts_with('@') {
+                        None
+                    } else {
+                        Some(PathBuf::from(path))
+                    })
+                    .collect::<Vec<_>>();
+
+                if directories.is_empty() {
+                    Resolved::Unknown
+                } else {
+                    search(directories, rest)
+                }
+            } else if dependency.starts_with('@') {
+                Resolved::Unknown
+            } else if dependency.contains('/') {
+                exists(Path::new(dependency))
+            } else {
+                search(env::search_paths(), dependency)
+            }
+        },
+
+        Format::Pe => {
+            let lower = dependency.to_ascii_lowercase();
+            if lower.starts_with("api-ms-") || lower.starts_with("ext-ms-") {
+                // API sets are resolved by the loader rather than found on disk
+                return Resolved::Unknown;
+            }
+
+            let mut directories = vec![directory.to_owned()];
+            directories.extend(env::search_paths());
+            search(directories, dependency)
+        }
+    }
+}
+
+fn expand_origin(path: &str, directory: &Path) -> String {
+    let directory = directory.to_string_lossy();
+    path.replace("${ORIGIN}", &directory).replace("$ORIGIN", &directory)
+}
+
+fn exists(path: &Path) -> Resolved {
+    if path.is_file() {
+        Resolved::Found(path.to_owned())
+    } else {
+        Resolved::Missing(1)
+    }
+}
+
+fn search(mut directories: Vec<PathBuf>, name: &str) -> Resolved {
+    let mut unique = Vec::with_capacity(directories.len());
+    for directory in directories.drain(..) {
+        if !unique.contains(&directory) {
+            unique.push(directory);
+        }
+    }
+
+    unique.iter()
+        .map(|directory| directory.join(name))
+        .find(|candidate| candidate.is_file())
+        .map(Resolved::Found)
+        .unwrap_or_else(|| Resolved::Missing(unique.len()))
+}
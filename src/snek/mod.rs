@@ -18,79 +18,1756 @@
 
 extern crate libc;
 
-use ::{Error, Symbol};
+use ::{env, inspect, CandidateErrors, Error, Errors, Handle, LoadOptions, OwnedSymbol, RawSymbol, Resolver, ResolverPosition, SharedSnek, Symbol, SymbolName, TlsData, TypedSymbol};
+use ::plugin::Info;
+use ::options::LoadFlags;
+use ::failure::{Class, Failure};
+use ::resolver::Resolvers;
+use self::cache::SymbolCache;
 
-use std::path::Path;
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::str::FromStr;
+use std::convert::TryFrom;
+use std::mem;
+use std::ptr::NonNull;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::os::raw::c_int;
 use libc::c_void;
 
 #[cfg(unix)]
-pub use self::unix::{load_library, load_symbol, drop_library};
+use self::unix as platform;
 
 #[cfg(windows)]
-pub use self::windows::{load_library, load_symbol, drop_library};
+use self::windows as platform;
+
+#[cfg(not(any(unix, windows)))]
+use self::unsupported as platform;
+
+pub(crate) use self::platform::{classify, last_error};
 
 mod unix;
 mod windows;
+mod unsupported;
+pub(crate) mod backend;
+pub(crate) mod diagnose;
+mod cache;
+#[cfg(feature = "suggestions")]
+mod suggest;
+#[cfg(debug_assertions)]
+mod validate;
+
+/// Returns true if dynamic libraries can be loaded on the current platform.
+/// Where they cannot, such as on `wasm32-unknown-unknown`, every load fails
+/// with [`Error::Unsupported`](enum.Error.html).
+pub const fn is_supported() -> bool {
+    cfg!(any(unix, windows))
+}
+
+/// Attempt to load a dynamic library from the given path, returning a
+/// [`Handle`](struct.Handle.html) to it. This is used by
+/// [`Snek::load`](struct.Snek.html#method.load) and the
+/// [`snek!`](macro.snek!.html) macro.
+///
+/// If the load fails, this will return [`Error::LibraryLoadError`](enum.Error.html).
+/// When the path is a bare file name which does not exist in any of the
+/// directories returned by [`env::search_paths`](env/fn.search_paths.html),
+/// the error message says so, and when the file exists the message explains
+/// whether it was built for another architecture or depends on a library
+/// which cannot be found.
+pub fn load_library<P>(path: P) -> Result<Handle, Error> where P: AsRef<Path> {
+    load_library_with_flags(path, LoadFlags::new())
+}
+
+/// Attempt to load a library from the given path, passing the given mode
+/// straight to `dlopen`, as described for
+/// [`Snek::load_with_raw_flags`](struct.Snek.html#method.load_with_raw_flags).
+#[cfg(unix)]
+pub fn load_library_with_raw_flags<P>(path: P, mode: libc::c_int) -> Result<Handle, Error> where P: AsRef<Path> {
+    load_library_with_flags(path, LoadFlags::from_raw(mode))
+}
+
+/// Attempt to load a library from the given path, passing the given flags to
+/// `LoadLibraryExW`, as described for
+/// [`Snek::load_with_raw_flags`](struct.Snek.html#method.load_with_raw_flags).
+#[cfg(windows)]
+pub fn load_library_with_raw_flags<P>(path: P, flags: u32) -> Result<Handle, Error> where P: AsRef<Path> {
+    load_library_with_flags(path, LoadFlags::from_raw(flags))
+}
+
+/// Attempt to load a dynamic library from the given path with the given
+/// [`LoadFlags`](struct.LoadFlags.html), as for [`load_library`](fn.load_library.html).
+pub fn load_library_with_flags<P>(path: P, flags: LoadFlags) -> Result<Handle, Error> where P: AsRef<Path> {
+    let path = path.as_ref();
+    backend::load_library(path, flags)
+        .map(Handle::new)
+        .map_err(|err| diagnose::diagnose(path, err, true))
+}
+
+/// Attempt to load a symbol from a library, returning its address. This is
+/// used by the [`snek!`](macro.snek!.html) macro.
+///
+/// If the load fails, this will return [`Error::SymbolLoadError`](enum.Error.html).
+/// The name can be any [`SymbolName`](trait.SymbolName.html), and in debug
+/// builds is first checked as for [`Snek::symbol`](struct.Snek.html#method.symbol),
+/// always rejecting non-ASCII names.
+///
+/// # Safety
+/// The library must still be loaded. A `Handle` returned by
+/// [`load_library`](fn.load_library.html) stays valid until it is passed to
+/// [`drop_library`](fn.drop_library.html), but one made with
+/// [`Handle::from_raw`](struct.Handle.html#method.from_raw) may be unloaded
+/// elsewhere. The returned [`RawSymbol`](struct.RawSymbol.html) is not tied to
+/// the handle, and must not be used once the library is unloaded.
+pub unsafe fn load_symbol<N>(handle: &Handle, symbol: N) -> Result<RawSymbol, Error> where N: SymbolName {
+    let symbol = symbol.c_name()?;
+
+    #[cfg(debug_assertions)]
+    validate::validate(&symbol.to_string_lossy(), true)?;
+
+    backend::load_symbol(handle.as_non_null(), &symbol).map(RawSymbol::new)
+}
+
+/// Attempt to load a particular version of a symbol from a library, returning
+/// its address, as described for [`Snek::symbol_version`](struct.Snek.html#method.symbol_version).
+///
+/// # Safety
+/// The library must still be loaded, as for [`load_symbol`](fn.load_symbol.html).
+pub unsafe fn load_symbol_version<N>(handle: &Handle, symbol: N, version: &str) -> Result<RawSymbol, Error> where N: SymbolName {
+    let symbol = symbol.c_name()?;
+    let version = version.c_name()?;
+
+    #[cfg(debug_assertions)]
+    validate::validate(&symbol.to_string_lossy(), true)?;
+
+    backend::load_symbol_version(handle.as_non_null(), &symbol, &version).map(RawSymbol::new)
+}
+
+/// Attempt to find a symbol anywhere in the process, rather than in one
+/// library, returning [`Error::SymbolLoadError`](enum.Error.html) if no loaded
+/// object exports it.
+///
+/// On unix this searches the global scope, as `dlsym(RTLD_DEFAULT, ...)`
+/// does, which holds the executable and each library loaded with
+/// [`LoadFlags::global`](struct.LoadFlags.html#method.global), but not those
+/// loaded locally. Windows has no such scope, so each loaded module is tried
+/// in turn, the executable first. Symbols of libraries registered with the
+/// `static-registry` or `testing` features are never found. The name is
+/// checked as for [`load_symbol`](fn.load_symbol.html).
+///
+/// The returned symbol belongs to no [`Snek`](struct.Snek.html), so is given
+/// the `'static` lifetime, but nothing keeps the object defining it loaded.
+/// One which can be unloaded, such as a library loaded by a `Snek` which is
+/// later dropped, must not have its symbol used after that.
+///
+/// # Example
+/// ```
+/// # extern crate libc;
+/// # extern crate snek;
+/// # use libc::{c_char, size_t};
+/// # fn main() {
+/// # if cfg!(unix) {
+/// let strlen = snek::global_symbol("strlen").unwrap();
+/// let len = unsafe { strlen.with(|strlen: extern fn(*const c_char) -> size_t| strlen(b"snek\0".as_ptr() as *const c_char)) };
+/// assert_eq!(len, 4);
+/// # }
+/// # }
+/// ```
+pub fn global_symbol<N>(symbol: N) -> Result<Symbol<'static>, Error> where N: SymbolName {
+    let symbol = symbol.c_name()?;
+
+    #[cfg(debug_assertions)]
+    validate::validate(&symbol.to_string_lossy(), true)?;
+
+    unsafe { backend::global_symbol(&symbol) }.map(Symbol::from_non_null)
+}
+
+/// Attempt to find the next definition of a symbol after the object this
+/// crate is linked into, as `dlsym(RTLD_NEXT, ...)` does, returning
+/// [`Error::SymbolLoadError`](enum.Error.html) if no later object defines it.
+///
+/// This is how an interposing shim, such as a library preloaded to wrap
+/// `malloc`, finds the definition it wraps rather than its own. The objects
+/// searched are those after the executable or library which this crate is
+/// statically linked into, in load order, so it must be linked into the shim
+/// itself. The name is checked and the symbol's lifetime is as described for
+/// [`global_symbol`](fn.global_symbol.html).
+#[cfg(unix)]
+pub fn next_symbol<N>(symbol: N) -> Result<Symbol<'static>, Error> where N: SymbolName {
+    let symbol = symbol.c_name()?;
+
+    #[cfg(debug_assertions)]
+    validate::validate(&symbol.to_string_lossy(), true)?;
+
+    unsafe { backend::next_symbol(&symbol) }.map(Symbol::from_non_null)
+}
+
+/// Unload a library loaded by [`load_library`](fn.load_library.html).
+pub fn drop_library(handle: Handle) {
+    let _ = backend::drop_library(handle.as_non_null());
+}
+
+/// Call a library's init function at the given address, returning
+/// [`Error::LibraryLoadError`](enum.Error.html) if it returns non-zero. This
+/// is used by the [`snek!`](macro.snek!.html) macro.
+#[doc(hidden)]
+pub unsafe fn call_init(init: *mut c_void, symbol: &str) -> Result<(), Error> {
+    let init = unsafe { mem::transmute::<*mut c_void, unsafe extern "C" fn() -> c_int>(init) };
+
+    match unsafe { init() } {
+        0 => Ok(()),
+        code => Err(Error::LibraryLoadError(Failure::new(format!("init function {} returned {}", symbol, code), None, Class::Other)))
+    }
+}
+
+/// Returns the error for an init function which returned the given code, for
+/// converting a [`snek!`](macro.snek!.html) block's generated error, which
+/// does not record the function's name.
+#[doc(hidden)]
+pub fn init_failed(code: i32) -> Error {
+    Error::LibraryLoadError(Failure::new(format!("init function returned {}", code), None, Class::Other))
+}
+
+/// Wrap a handle in a `Snek` which unloads the library when dropped. This is
+/// used by the [`snek!`](macro.snek!.html) macro.
+#[doc(hidden)]
+pub fn wrap_handle(handle: Handle, path: &Path) -> Snek {
+    Snek::from_handle(handle, path)
+}
+
+/// Returns [`Snek::current`](struct.Snek.html#method.current). This is used by
+/// the [`snek!`](macro.snek!.html) macro for a block marked `#[self_process]`.
+#[doc(hidden)]
+pub fn wrap_process() -> Result<Snek, Error> {
+    Snek::current()
+}
+
+fn null_handle() -> Error {
+    Error::InvalidInput("library handle is null".to_owned())
+}
+
+/// Set the function called just before the library wrapped by a `Snek` is
+/// unloaded. This is used by the [`snek!`](macro.snek!.html) macro, once its
+/// init function has succeeded.
+#[doc(hidden)]
+pub fn set_fini(snek: &mut Snek, fini: Option<unsafe extern "C" fn()>) {
+    Arc::get_mut(&mut snek.library).expect("library is shared before it is loaded").fini = fini;
+}
 
 /// This provides an interface for manually loading a dynamic library and
 /// symbols from it. While this exists, it is more recommended to use the 
 /// [`snek!`](macro.snek!.html) macro to generate a wrapper for a library 
 /// automatically.
 ///
-/// A `Snek` instance contains nothing but a handle to the loaded library,
-/// and provides a single method for loading symbols from the library. When
+/// A `Snek` instance contains a handle to the loaded library and the path it
+/// was loaded from, and provides methods for loading symbols from it. When
 /// the instance is dropped, it unloads the library, so the lifetime of
 /// any loaded symbols is tied to the lifetime of the `Snek` instance.
 ///
+/// Where a symbol must outlive the `Snek`, such as one stored in a struct or
+/// a cache, [`owned_symbol`](#method.owned_symbol) returns an
+/// [`OwnedSymbol`](struct.OwnedSymbol.html) instead, which keeps the library
+/// loaded until it is dropped.
+///
 /// For more information about using the loaded symbols see the 
 /// [`Symbol`](struct.Symbol.html)  documentation.
 ///
+/// A `Snek` is `Send` and `Sync`, so a library can be loaded on one thread
+/// and used on another, or shared between threads through a
+/// [`SharedSnek`](struct.SharedSnek.html). The platform loaders are safe to
+/// call from any thread, and unloading is counted atomically, so the library
+/// is still unloaded once. Whether a library's own functions can be called
+/// from several threads at once is up to the library.
+///
 /// # Example
 /// ```
 /// # extern crate libc;
 /// # extern crate snek;
 /// # use snek::Snek;
 /// # use libc::c_int;
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
 /// # fn main() {
-/// match Snek::load("libexample.so") {
-///     Ok(snek) => match snek.symbol("add") {
-///         Ok(symbol) => println!("{}", unsafe { symbol.with(
-///             |add: extern fn(c_int, c_int) -> c_int| add(3, 7)
-///         ) }),
-///
-///         _ => ()
-///     },
+/// # let libexample = fixture();
+/// let snek = Snek::load(libexample).unwrap();
+/// let symbol = snek.symbol("add").unwrap();
 ///
-///     _ => ()
-/// }
+/// let result = unsafe { symbol.with(|add: extern fn(c_int, c_int) -> c_int| add(3, 7)) };
+/// assert_eq!(result, 10);
 /// # }
 /// ```
 #[derive(Debug)]
 pub struct Snek {
-    handle: *mut c_void
+    library: Arc<Library>,
+
+    // The path as it was given to load the library
+    pub(crate) path: PathBuf,
+
+    // Whether debug builds reject non-ASCII symbol names
+    ascii_symbols: bool,
+
+    // Consulted by symbol around the lookup in the library
+    resolvers: Resolvers,
+
+    // The addresses the library has returned to symbol
+    symbols: SymbolCache
+}
+
+/// The loaded library itself, shared by a `Snek` and any `OwnedSymbol`s
+/// loaded from it, and unloaded once they have all been dropped.
+#[derive(Debug)]
+pub(crate) struct Library {
+    handle: Handle,
+
+    // Called before the library is unloaded
+    fini: Option<unsafe extern "C" fn()>,
+
+    // Called before the fini function, in reverse order of registration
+    unload_callbacks: UnloadCallbacks,
+
+    // Set once the library is unloaded, which may be before this is dropped
+    // if it was forced
+    closed: AtomicBool,
+
+    // Unset for a handle owned by other code, or the handle to the process
+    // itself, neither of which is released when this is closed
+    owned: bool,
+
+    // Set for the handle to the process itself, which is never unloaded
+    process: bool,
+
+    // Set once the module is pinned, after which it is never unloaded
+    pinned: AtomicBool
+}
+
+impl Library {
+    fn close(&self) -> Result<(), Error> {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        // Unloading would succeed without unloading anything, and the fini
+        // function must not shut down a library which stays loaded
+        if self.pinned.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        // A panicking callback is caught here rather than left to unwind, so
+        // that the others still run and the library is still unloaded
+        for callback in self.unload_callbacks.take().into_iter().rev() {
+            let _ = panic::catch_unwind(AssertUnwindSafe(callback));
+        }
+
+        if let Some(fini) = self.fini {
+            unsafe { fini() }
+        }
+
+        if self.owned {
+            backend::drop_library(self.handle.as_non_null())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Default)]
+struct UnloadCallbacks(Mutex<Vec<Box<dyn FnOnce() + Send>>>);
+
+impl UnloadCallbacks {
+    fn lock(&self) -> MutexGuard<'_, Vec<Box<dyn FnOnce() + Send>>> {
+        // Callbacks are caught when run, and only pushed or taken while
+        // locked, so the list is never left half updated
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn take(&self) -> Vec<Box<dyn FnOnce() + Send>> {
+        mem::take(&mut *self.lock())
+    }
+}
+
+impl fmt::Debug for UnloadCallbacks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} callbacks", self.lock().len())
+    }
+}
+
+// Dropping is best-effort, as there is nowhere to report a failed unload;
+// Snek::close reports it instead
+impl Drop for Library {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
 }
 
 impl Snek {
     /// Attempt to load a dynamic library from the given path, returning a `Snek`
     /// instance wrapping the handle. 
     ///
-    /// If the load fails, this will return [`Error::LibraryLoadError`](enum.Error.html)
+    /// The library's symbols are bound with the default [`LoadFlags`](struct.LoadFlags.html),
+    /// so every undefined symbol is resolved while it is loaded. A library
+    /// which cannot resolve one fails to load, rather than crashing the
+    /// process when it is first called, unless loaded lazily with
+    /// [`load_with_flags`](#method.load_with_flags).
+    ///
+    /// On macOS, a bare file name such as `libfoo.dylib` which dyld does not
+    /// find is tried again as `@rpath/libfoo.dylib`, so that the runpaths of
+    /// the executable are searched as they are by the loaders of other unix
+    /// platforms, and then in each of the directories from
+    /// [`env::search_paths`](env/fn.search_paths.html). A universal binary
+    /// without a slice for the process's architecture fails with a message
+    /// naming the architectures it does contain.
+    ///
+    /// If the load fails, this will return [`Error::LibraryLoadError`](enum.Error.html),
+    /// with an explanation of the failure as described for
+    /// [`LoadOptions::diagnose`](struct.LoadOptions.html#method.diagnose).
     pub fn load<P>(path: P) -> Result<Snek, Error> where P: AsRef<Path> {
-        load_library(path).map(|result| Snek { handle: result })
+        LoadOptions::new().load(path)
+    }
+
+    /// Attempt to load a dynamic library from the given path, binding its
+    /// symbols as described by the given [`LoadFlags`](struct.LoadFlags.html).
+    ///
+    /// If the load fails, this will return [`Error::LibraryLoadError`](enum.Error.html),
+    /// as for [`load`](#method.load).
+    pub fn load_with_flags<P>(path: P, flags: LoadFlags) -> Result<Snek, Error> where P: AsRef<Path> {
+        LoadOptions::new().flags(flags).load(path)
+    }
+
+    /// Attempt to load a dynamic library from the given path, passing the
+    /// given mode straight to `dlopen`, such as `RTLD_NOW | RTLD_DEEPBIND` on
+    /// glibc to resolve the library's own symbols before those of the
+    /// process.
+    ///
+    /// The values are defined by the platform, and can differ between unix
+    /// platforms, so should be taken from the `libc` crate. The mode replaces
+    /// the portable [`LoadFlags`](struct.LoadFlags.html) entirely, so it must
+    /// include `RTLD_NOW` or `RTLD_LAZY`. If the load fails, this will return
+    /// [`Error::LibraryLoadError`](enum.Error.html), as for [`load`](#method.load).
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate libc;
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let snek = Snek::load_with_raw_flags(libexample, libc::RTLD_NOW | libc::RTLD_LOCAL).unwrap();
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn load_with_raw_flags<P>(path: P, mode: libc::c_int) -> Result<Snek, Error> where P: AsRef<Path> {
+        LoadOptions::new().flags(LoadFlags::from_raw(mode)).load(path)
+    }
+
+    /// Attempt to load a dynamic library from the given path with
+    /// `LoadLibraryExW`, passing it the given flags, such as
+    /// `LOAD_LIBRARY_SEARCH_DLL_LOAD_DIR` to find the library's dependencies
+    /// beside it.
+    ///
+    /// The values are defined by Windows, and each is only supported by the
+    /// versions which introduced it. If the load fails, this will return
+    /// [`Error::LibraryLoadError`](enum.Error.html), as for [`load`](#method.load).
+    #[cfg(windows)]
+    pub fn load_with_raw_flags<P>(path: P, flags: u32) -> Result<Snek, Error> where P: AsRef<Path> {
+        LoadOptions::new().flags(LoadFlags::from_raw(flags)).load(path)
+    }
+
+    /// Attempt to load a dynamic library from the given path into a new
+    /// namespace of its own, with glibc's `dlmopen(LM_ID_NEWLM, ...)`.
+    ///
+    /// A library in its own namespace gets its own copy of every library it
+    /// depends on, and its symbols are never used to resolve those of
+    /// libraries in other namespaces, so that two versions of the same
+    /// library, or two copies of one, can be loaded side by side without
+    /// interfering, and each unloaded without affecting the other. glibc
+    /// only supports a handful of namespaces, fewer still where the libraries
+    /// use static thread-local storage, so this suits a few isolated plugins
+    /// rather than every one.
+    ///
+    /// This is only supported on Linux with glibc, and elsewhere returns
+    /// [`Error::Unsupported`](enum.Error.html). If the load fails, this will
+    /// return [`Error::LibraryLoadError`](enum.Error.html), as for
+    /// [`load`](#method.load).
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// match Snek::load_in_new_namespace(libexample) {
+    ///     Ok(snek) => println!("loaded into namespace {}", snek.namespace_id().unwrap()),
+    ///     Err(ref err) if err.is_unsupported() => println!("namespaces are not supported"),
+    ///     Err(err) => panic!("{}", err)
+    /// }
+    /// # }
+    /// ```
+    pub fn load_in_new_namespace<P>(path: P) -> Result<Snek, Error> where P: AsRef<Path> {
+        LoadOptions::new().flags(LoadFlags::new().in_new_namespace()).load(path)
+    }
+
+    /// Returns the namespace the library was loaded into, as described for
+    /// [`load_in_new_namespace`](#method.load_in_new_namespace), which is zero
+    /// for a library loaded into the process's own namespace.
+    ///
+    /// This is only supported on Linux with glibc, and elsewhere, or for a
+    /// library registered with the static registry, returns
+    /// [`Error::Unsupported`](enum.Error.html).
+    pub fn namespace_id(&self) -> Result<i64, Error> {
+        backend::namespace_id(self.library.handle.as_non_null())
+    }
+
+    /// Returns the absolute path of the file the library was loaded from, as
+    /// found by the loader, rather than the path it was loaded by, which may
+    /// have been a bare file name searched for, or relative to the working
+    /// directory. Symbolic links in the path are resolved.
+    ///
+    /// * On Linux with glibc, this reads the library's `link_map` from
+    ///   `dlinfo`.
+    /// * On macOS and iOS, this finds the image dyld loaded for the library.
+    /// * On other unix platforms, including Linux with musl, which has no
+    ///   `RTLD_DI_LINKMAP`, this finds the file mapped into the process for
+    ///   the library, listed in `/proc/self/maps`, and without procfs
+    ///   returns [`Error::Unsupported`](enum.Error.html).
+    /// * On Windows, this is the file name the loader keeps for the module,
+    ///   from `GetModuleFileNameW`, which may have the `\\?\` prefix if the
+    ///   library was loaded by a long path.
+    ///
+    /// A library registered with the static registry, or a testing symbol
+    /// table, is part of the program, so its path is the executable's.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let snek = Snek::load(libexample).unwrap();
+    /// println!("loaded from {}", snek.path().unwrap().display());
+    /// # }
+    /// ```
+    pub fn path(&self) -> Result<PathBuf, Error> {
+        backend::library_path(self.library.handle.as_non_null())
+    }
+
+    /// Read the version resource of the library's file, found as for
+    /// [`path`](#method.path), returning `None` if it has none, as described
+    /// for [`windows::file_version`](windows/fn.file_version.html).
+    #[cfg(windows)]
+    pub fn file_version(&self) -> Result<Option<::windows::FileVersion>, Error> {
+        ::windows::file_version(self.path()?)
+    }
+
+    /// Return a `Snek` for the running program itself, whose symbols are those
+    /// the executable exports, as with `dlopen(NULL)` on unix and
+    /// `GetModuleHandleW(NULL)` on Windows.
+    ///
+    /// On unix this also finds the symbols of every library loaded with
+    /// global visibility, including those the executable links against, such
+    /// as the C library. On Windows only the executable's own exports are
+    /// found. An executable only exports a function it is linked to export,
+    /// such as with `-rdynamic` on Linux, or `/EXPORT` or a module definition
+    /// file on Windows.
+    ///
+    /// The handle belongs to the process, so dropping the `Snek` never
+    /// releases it. Its [`path`](#method.path) is that of the executable.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # fn main() {
+    /// let current = Snek::current().unwrap();
+    /// # #[cfg(unix)]
+    /// assert!(current.symbol("strlen").is_ok());
+    /// # }
+    /// ```
+    pub fn current() -> Result<Snek, Error> {
+        let handle = backend::process_handle()?;
+        let mut snek = Snek::from_handle(Handle::new(handle), std::env::current_exe().unwrap_or_default());
+
+        let library = Arc::get_mut(&mut snek.library).expect("library is shared before it is loaded");
+        library.owned = false;
+        library.process = true;
+
+        Ok(snek)
+    }
+
+    /// Give up the library's raw platform handle, leaving the caller
+    /// responsible for unloading it, such as by passing it back to
+    /// [`from_raw_handle`](#method.from_raw_handle) or to C code which closes
+    /// it.
+    ///
+    /// The library is no longer unloaded by the `Snek`, nor by any
+    /// [`OwnedSymbol`](struct.OwnedSymbol.html)s loaded from it, which stay
+    /// valid for as long as the caller keeps it loaded. Its unload callbacks
+    /// are dropped without being run, and its fini function is never called.
+    /// A `Snek` which never owned its handle, such as one from
+    /// [`current`](#method.current) or [`from_raw_handle_unowned`](#method.from_raw_handle_unowned),
+    /// gives up a handle which must still not be closed.
+    pub fn into_raw_handle(self) -> *mut c_void {
+        self.library.closed.store(true, Ordering::SeqCst);
+        self.library.unload_callbacks.take();
+
+        self.library.handle.as_raw()
+    }
+
+    /// Open a dynamic library which the process has already loaded, such as
+    /// one the host application links against, without ever loading it.
+    ///
+    /// The library is found by its path or file name as the platform would
+    /// find it when loading it, using `RTLD_NOLOAD` on unix and
+    /// `GetModuleHandleExW` on Windows, so that a second copy is never loaded
+    /// from elsewhere. The returned `Snek` holds its own reference to the
+    /// library, which dropping it releases, so the library stays loaded until
+    /// both it and whatever loaded the library first have released theirs.
+    ///
+    /// If the library is not loaded, this will return
+    /// [`Error::LibraryLoadError`](enum.Error.html), for which
+    /// [`Error::is_not_found`](enum.Error.html#method.is_not_found) is true.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let snek = Snek::load(&libexample).unwrap();
+    /// let existing = Snek::open_existing(&libexample).unwrap();
+    ///
+    /// // The library stays loaded for the second handle
+    /// drop(snek);
+    /// assert!(existing.symbol("add").is_ok());
+    /// # }
+    /// ```
+    pub fn open_existing<P>(path: P) -> Result<Snek, Error> where P: AsRef<Path> {
+        let path = path.as_ref();
+        backend::open_existing(path).map(|handle| Snek::from_handle(Handle::new(handle), path))
+    }
+
+    /// Wrap a raw platform handle, taking ownership of it, so that dropping
+    /// the `Snek` unloads the library as though it had loaded it. This is the
+    /// counterpart of [`into_raw_handle`](#method.into_raw_handle), and the
+    /// `Snek` records the path the library was loaded from, if it can be
+    /// found.
+    ///
+    /// If the handle is null, this will return [`Error::InvalidInput`](enum.Error.html).
+    ///
+    /// # Safety
+    /// The handle must be a handle to a loaded library, as returned by
+    /// `dlopen` or `LoadLibrary`, whose reference is given up to the `Snek`,
+    /// so that nothing else closes it.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let handle = Snek::load(libexample).unwrap().into_raw_handle();
+    ///
+    /// // The Snek now unloads the library once dropped
+    /// let snek = unsafe { Snek::from_raw_handle(handle) }.unwrap();
+    /// assert!(snek.symbol("add").is_ok());
+    /// # }
+    /// ```
+    pub unsafe fn from_raw_handle(handle: *mut c_void) -> Result<Snek, Error> {
+        let handle = NonNull::new(handle).ok_or_else(null_handle)?;
+
+        Ok(Snek::from_handle(Handle::new(handle), backend::library_path(handle).unwrap_or_default()))
+    }
+
+    /// Wrap a raw platform handle owned by other code, such as an `HMODULE`
+    /// passed to a callback, taking a reference of its own so that dropping
+    /// the `Snek` only releases that reference.
+    ///
+    /// On Windows the reference is taken with `GetModuleHandleExW`, given the
+    /// handle as an address inside the module. On unix the library is opened
+    /// again with `RTLD_NOLOAD` by the path it was loaded from, as found by
+    /// [`path`](#method.path), which only counts another reference to it. The
+    /// `Snek` records that path.
+    ///
+    /// If the handle is null, this will return [`Error::InvalidInput`](enum.Error.html),
+    /// and if another reference cannot be taken, [`Error::LibraryLoadError`](enum.Error.html).
+    ///
+    /// # Safety
+    /// The handle must be a handle to a loaded library, as returned by
+    /// `dlopen` or `LoadLibrary`, until this returns.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let handle = snek::load_library(libexample).unwrap();
+    ///
+    /// // Dropping the Snek leaves the handle loaded
+    /// let snek = unsafe { Snek::from_raw_handle_ref(handle.as_raw()) }.unwrap();
+    /// drop(snek);
+    ///
+    /// assert!(unsafe { snek::load_symbol(&handle, "add") }.is_ok());
+    /// snek::drop_library(handle);
+    /// # }
+    /// ```
+    pub unsafe fn from_raw_handle_ref(handle: *mut c_void) -> Result<Snek, Error> {
+        let handle = NonNull::new(handle).ok_or_else(null_handle)?;
+        let reference = backend::add_reference(handle)?;
+
+        Ok(Snek::from_handle(Handle::new(reference), backend::library_path(reference).unwrap_or_default()))
+    }
+
+    /// Wrap a raw platform handle owned by other code without taking a
+    /// reference, so that the library is never unloaded by the `Snek`. The
+    /// `Snek` records the path the library was loaded from, if it can be
+    /// found.
+    ///
+    /// If the handle is null, this will return [`Error::InvalidInput`](enum.Error.html).
+    ///
+    /// # Safety
+    /// The handle must be a handle to a loaded library, as returned by
+    /// `dlopen` or `LoadLibrary`, which its owner keeps loaded for as long as
+    /// the `Snek` and any symbols loaded from it are used.
+    pub unsafe fn from_raw_handle_unowned(handle: *mut c_void) -> Result<Snek, Error> {
+        let handle = NonNull::new(handle).ok_or_else(null_handle)?;
+        let mut snek = Snek::from_handle(Handle::new(handle), backend::library_path(handle).unwrap_or_default());
+
+        Arc::get_mut(&mut snek.library).expect("library is shared before it is loaded").owned = false;
+        Ok(snek)
+    }
+
+    /// Attempt to load a dynamic library from each of the given paths in turn,
+    /// returning a `Snek` instance wrapping the first one which loads.
+    ///
+    /// If none of the paths can be loaded, this will return
+    /// [`Error::CandidatesError`](enum.Error.html), holding the reason each
+    /// one failed.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::{Error, Snek};
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let candidates = vec![PathBuf::from("libexample.so.2"), PathBuf::from("libexample.so.1"), libexample];
+    /// assert!(Snek::load_any(&candidates).is_ok());
+    ///
+    /// match Snek::load_any(&candidates[..2]) {
+    ///     Err(Error::CandidatesError(candidates)) => for candidate in &candidates {
+    ///         println!("{} failed", candidate.path().display());
+    ///     },
+    ///
+    ///     _ => unreachable!()
+    /// }
+    /// # }
+    /// ```
+    pub fn load_any<I, P>(paths: I) -> Result<Snek, Error> where I: IntoIterator<Item = P>, P: AsRef<Path> {
+        LoadOptions::new().load_any(paths)
+    }
+
+    /// Attempt to load a dynamic library by its logical name, such as `foo`
+    /// for `libfoo.so`, trying each of the platform's file names for it in
+    /// turn, as listed by [`env::library_names`](env/fn.library_names.html).
+    ///
+    /// If none of the names can be loaded, this will return
+    /// [`Error::CandidatesError`](enum.Error.html), holding each name tried
+    /// and the reason it failed.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # fn main() {
+    /// // libz.dylib on macOS, and libz.so or libz.so.1 on Linux
+    /// # #[cfg(unix)]
+    /// let libz = Snek::load_name("z");
+    /// # }
+    /// ```
+    pub fn load_name(name: &str) -> Result<Snek, Error> {
+        LoadOptions::new().load_name(name)
+    }
+
+    /// Attempt to load a dynamic library by its unversioned name, falling back
+    /// to soname-versioned names if that fails, as described for
+    /// [`LoadOptions::load_versioned`](struct.LoadOptions.html#method.load_versioned).
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # fn main() {
+    /// // Loads libz.so if it exists, and otherwise libz.so.1
+    /// # #[cfg(target_os = "linux")]
+    /// let libz = Snek::load_versioned("libz.so", &[1]);
+    /// # }
+    /// ```
+    pub fn load_versioned<P>(path: P, majors: &[u32]) -> Result<Snek, Error> where P: AsRef<Path> {
+        LoadOptions::new().load_versioned(path, majors)
+    }
+
+    pub(crate) fn open_versioned(path: &Path, majors: &[u32], options: &LoadOptions) -> Result<Snek, Error> {
+        let mut majors = if majors.is_empty() {
+            env::installed_majors(path)
+        } else {
+            majors.to_vec()
+        };
+
+        majors.sort_unstable_by(|a, b| b.cmp(a));
+        majors.dedup();
+
+        let versioned = majors.iter().map(|major| {
+            let mut name = path.as_os_str().to_owned();
+            name.push(format!(".{}", major));
+            PathBuf::from(name)
+        });
+
+        Snek::open_any(Some(path.to_owned()).into_iter().chain(versioned), options)
+    }
+
+    pub(crate) fn open_any<I, P>(paths: I, options: &LoadOptions) -> Result<Snek, Error> where I: IntoIterator<Item = P>, P: AsRef<Path> {
+        let mut candidates = CandidateErrors::new();
+
+        for path in paths {
+            let path = path.as_ref();
+
+            match Snek::open(path, options) {
+                Ok(snek) => return Ok(snek),
+                Err(err) => candidates.push(path.to_owned(), err)
+            }
+        }
+
+        Err(Error::CandidatesError(candidates))
+    }
+
+    pub(crate) fn from_handle<P>(handle: Handle, path: P) -> Snek where P: Into<PathBuf> {
+        Snek {
+            library: Arc::new(Library {
+                handle,
+                fini: None,
+                unload_callbacks: UnloadCallbacks::default(),
+                closed: AtomicBool::new(false),
+                owned: true,
+                process: false,
+                pinned: AtomicBool::new(false)
+            }),
+
+            path: path.into(),
+            ascii_symbols: true,
+            resolvers: Resolvers::default(),
+            symbols: SymbolCache::new(true)
+        }
+    }
+
+    pub(crate) fn open(path: &Path, options: &LoadOptions) -> Result<Snek, Error> {
+        if options.absolute_paths && !path.is_absolute() {
+            return Err(Error::LibraryLoadError(Failure::new(
+                format!("{}: absolute paths are required, so a relative path or bare name cannot be loaded; locate the library explicitly, such as with snek::env::locate", path.display()),
+                None,
+                Class::RelativePath
+            ).with_path(path)));
+        }
+
+        let handle = backend::load_library(path, options.flags)
+            .map_err(|err| diagnose::diagnose(path, err, options.diagnose))?;
+
+        // Until the init function has succeeded there is no fini to call, so
+        // an error here just unloads the library again
+        let mut snek = Snek::from_handle(Handle::new(handle), path);
+        snek.ascii_symbols = options.ascii_symbols;
+        snek.symbols = SymbolCache::new(options.cache_symbols);
+
+        let fini = match options.fini {
+            Some(ref symbol) => Some(unsafe { mem::transmute::<*mut c_void, unsafe extern "C" fn()>(snek.symbol(symbol)?.as_ptr()) }),
+            None => None
+        };
+
+        if let Some(ref symbol) = options.init {
+            unsafe { call_init(snek.symbol(symbol)?.as_ptr(), symbol).map_err(|err| err.with_path(path))? };
+        }
+
+        set_fini(&mut snek, fini);
+        Ok(snek)
     }
 
     /// Attempt to load a symbol from the dynamic library, returning a 
     /// [`Symbol`](struct.Symbol.html) instance wrapping it.
     ///
-    /// If the load fails, this will return [`Error::SymbolLoadError`](enum.Error.html)
-    pub fn symbol<'a>(&'a self, symbol: &str) -> Result<Symbol<'a>, Error> {
-        load_symbol(self.handle, symbol).map(|result| Symbol::new(result))
+    /// If the load fails, this will return [`Error::SymbolLoadError`](enum.Error.html).
+    /// When the `suggestions` feature is enabled (as it is by default), the
+    /// library's exports are then read from its file, and up to three names
+    /// close to the requested one are added to the error message.
+    ///
+    /// The name can be any [`SymbolName`](trait.SymbolName.html), such as a
+    /// `&str` or a `&CStr`, the latter of which is passed to the platform
+    /// without being copied. The first successful lookup of each name still
+    /// copies it into the cache described below, unless that is disabled.
+    ///
+    /// In debug builds, the name is first checked to be non-empty, and free of
+    /// whitespace, control characters and (unless disabled with
+    /// [`LoadOptions::ascii_symbols`](struct.LoadOptions.html#method.ascii_symbols))
+    /// non-ASCII characters, any of which is usually a mistake in reading the
+    /// name from elsewhere. A bad name fails with an error quoting it with the
+    /// offending character escaped, for which
+    /// [`Error::is_invalid_name`](enum.Error.html#method.is_invalid_name) is
+    /// true. Release builds skip the check.
+    ///
+    /// A symbol which exists but has the null address, such as a weak symbol
+    /// which was never defined, also fails, with an error for which
+    /// [`Error::is_null_symbol`](enum.Error.html#method.is_null_symbol) is
+    /// true. Use [`nullable_symbol`](#method.nullable_symbol) to tell it apart
+    /// from a missing symbol without matching on the error.
+    ///
+    /// The address found for each name is remembered, so that looking it up
+    /// again is only a map lookup, unless disabled with
+    /// [`LoadOptions::cache_symbols`](struct.LoadOptions.html#method.cache_symbols).
+    pub fn symbol<'a, N>(&'a self, symbol: N) -> Result<Symbol<'a>, Error> where N: SymbolName {
+        // A cached name has already been checked, and resolvers must still be
+        // consulted before the library
+        if self.resolvers.is_empty() {
+            if let Some(address) = self.symbols.get(symbol.name_bytes()) {
+                return Ok(Symbol::from_non_null(address));
+            }
+        }
+
+        let symbol = symbol.c_name()?;
+
+        #[cfg(debug_assertions)]
+        validate::validate(&symbol.to_string_lossy(), self.ascii_symbols)?;
+
+        if !self.resolvers.is_empty() {
+            return self.resolve(&symbol);
+        }
+
+        match self.library_symbol(&symbol) {
+            Ok(symbol) => Ok(Symbol::from_non_null(symbol)),
+
+            #[cfg(feature = "suggestions")]
+            Err(err) => Err(suggest::suggest(self.library.handle.as_non_null(), &self.path, &symbol.to_string_lossy(), err)),
+
+            #[cfg(not(feature = "suggestions"))]
+            Err(err) => Err(err)
+        }
+    }
+
+    /// Attempt to load a function from the dynamic library, as with
+    /// [`symbol`](#method.symbol), returning it as a
+    /// [`TypedSymbol`](struct.TypedSymbol.html) of the given function pointer
+    /// type, which can be called directly.
+    ///
+    /// A type which is not the size of a pointer fails to compile, as for
+    /// [`Symbol::cast`](struct.Symbol.html#method.cast).
+    ///
+    /// # Safety
+    /// The symbol must be a function of the given type.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate libc;
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # use libc::c_int;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let snek = Snek::load(libexample).unwrap();
+    /// let add = unsafe { snek.typed_symbol::<extern "C" fn(c_int, c_int) -> c_int, _>("add") }.unwrap();
+    ///
+    /// assert_eq!((*add)(3, 7), 10);
+    /// # }
+    /// ```
+    pub unsafe fn typed_symbol<'a, F, N>(&'a self, symbol: N) -> Result<TypedSymbol<'a, F>, Error> where F: Copy, N: SymbolName {
+        self.symbol(symbol).map(|symbol| unsafe { symbol.cast() })
+    }
+
+    /// Attempt to load a function from the dynamic library, as with
+    /// [`symbol`](#method.symbol), returning it as a bare function pointer of
+    /// the given type, for a one-off call.
+    ///
+    /// A type which is not the size of a pointer fails to compile, as for
+    /// [`Symbol::cast`](struct.Symbol.html#method.cast).
+    ///
+    /// # Safety
+    /// The symbol must be a function of the given type, including its calling
+    /// convention, such as `extern "system"` for a Windows API function.
+    ///
+    /// The function pointer is not tied to the lifetime of the `Snek`, so it
+    /// must not be called once the library is unloaded, such as after the
+    /// `Snek` is dropped, when it points into memory which may since have
+    /// been reused. [`typed_symbol`](#method.typed_symbol) returns one which
+    /// borrows the `Snek` instead, and [`owned_symbol`](#method.owned_symbol)
+    /// one which keeps the library loaded.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate libc;
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # use libc::c_int;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let snek = Snek::load(libexample).unwrap();
+    ///
+    /// let add: extern "C" fn(c_int, c_int) -> c_int = unsafe { snek.func("add") }.unwrap();
+    /// let multiply: extern "system" fn(c_int, c_int) -> c_int = unsafe { snek.func("multiply") }.unwrap();
+    ///
+    /// assert_eq!(multiply(add(1, 2), 4), 12);
+    /// # }
+    /// ```
+    pub unsafe fn func<F, N>(&self, symbol: N) -> Result<F, Error> where F: Copy, N: SymbolName {
+        unsafe { self.typed_symbol::<F, N>(symbol) }.map(|function| function.get())
+    }
+
+    fn resolve<'a>(&'a self, symbol: &CStr) -> Result<Symbol<'a>, Error> {
+        let name = symbol.to_string_lossy();
+
+        if let Some(address) = self.resolvers.resolve(&name, ResolverPosition::Before) {
+            return Ok(Symbol::from_non_null(address));
+        }
+
+        let err = match self.library_symbol(symbol) {
+            Ok(address) => {
+                self.resolvers.resolved_by_library(&name);
+                return Ok(Symbol::from_non_null(address));
+            },
+
+            Err(err) => err
+        };
+
+        match self.resolvers.resolve(&name, ResolverPosition::After) {
+            Some(address) => Ok(Symbol::from_non_null(address)),
+
+            #[cfg(feature = "suggestions")]
+            None => Err(suggest::suggest(self.library.handle.as_non_null(), &self.path, &name, err)),
+
+            #[cfg(not(feature = "suggestions"))]
+            None => Err(err)
+        }
+    }
+
+    /// Look a symbol up in the library itself, remembering where it was found.
+    fn library_symbol(&self, symbol: &CStr) -> Result<NonNull<c_void>, Error> {
+        if let Some(address) = self.symbols.get(symbol.to_bytes()) {
+            return Ok(address);
+        }
+
+        let address = unsafe { backend::load_symbol(self.library.handle.as_non_null(), symbol) }?;
+        self.symbols.insert(symbol.to_bytes(), address);
+
+        Ok(address)
+    }
+
+    /// Attempt to load a particular version of a symbol from the dynamic
+    /// library, such as `pthread_cond_wait` at `GLIBC_2.3.2`, with `dlvsym`.
+    ///
+    /// glibc and the libraries built against it can export several versions
+    /// of one symbol, for binaries built against older releases, and
+    /// [`symbol`](#method.symbol) only finds the default one. The version is
+    /// the name of the version node, as shown after the `@` by
+    /// `objdump -T`. Resolvers are not consulted.
+    ///
+    /// If the symbol has no such version, this will return
+    /// [`Error::SymbolLoadError`](enum.Error.html). Symbol versions are only
+    /// supported on Linux with glibc; elsewhere this returns the same error,
+    /// for which [`Error::is_unsupported`](enum.Error.html#method.is_unsupported)
+    /// is true, rather than falling back to the unversioned symbol.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # fn main() {
+    /// # #[cfg(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64"))]
+    /// # {
+    /// let libc = Snek::load("libc.so.6").unwrap();
+    /// let current = libc.symbol_version("pthread_cond_wait", "GLIBC_2.3.2").unwrap();
+    /// let old = libc.symbol_version("pthread_cond_wait", "GLIBC_2.2.5").unwrap();
+    ///
+    /// assert_eq!(current.as_ptr(), libc.symbol("pthread_cond_wait").unwrap().as_ptr());
+    /// assert_ne!(current.as_ptr(), old.as_ptr());
+    /// # }
+    /// # }
+    /// ```
+    pub fn symbol_version<'a>(&'a self, symbol: &str, version: &str) -> Result<Symbol<'a>, Error> {
+        let name = symbol.c_name()?;
+        let version = version.c_name()?;
+
+        #[cfg(debug_assertions)]
+        validate::validate(symbol, self.ascii_symbols)?;
+
+        unsafe { backend::load_symbol_version(self.library.handle.as_non_null(), &name, &version) }.map(Symbol::from_non_null)
+    }
+
+    /// Attempt to load a symbol by its ordinal, the number a Windows library
+    /// exports it at, as `GetProcAddress` does given `MAKEINTRESOURCE(ordinal)`.
+    ///
+    /// Some libraries export functions only by ordinal, which no name can
+    /// find. Resolvers are not consulted. If the library exports nothing at
+    /// the ordinal, this will return [`Error::SymbolLoadError`](enum.Error.html),
+    /// whose message names the ordinal.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # fn main() {
+    /// let comctl32 = Snek::load("comctl32.dll").unwrap();
+    ///
+    /// // InitCommonControls, exported at ordinal 17
+    /// let init = comctl32.symbol_ordinal(17).unwrap();
+    /// # let _ = init;
+    /// # }
+    /// ```
+    #[cfg(windows)]
+    pub fn symbol_ordinal<'a>(&'a self, ordinal: u16) -> Result<Symbol<'a>, Error> {
+        unsafe { backend::load_symbol_ordinal(self.library.handle.as_non_null(), ordinal) }.map(Symbol::from_non_null)
+    }
+
+    /// Attempt to load a symbol, returning its address along with its entry
+    /// in the library's export table, which tells whether it is defined by
+    /// the library or forwarded to another, as `kernel32.dll` forwards many
+    /// of its functions to `kernelbase.dll` and `ntdll.dll`. This is only
+    /// available on Windows.
+    ///
+    /// The symbol is resolved as for [`symbol`](#method.symbol), including
+    /// the fallback to a decorated name, except that resolvers are not
+    /// consulted. If it cannot be loaded, this will return
+    /// [`Error::SymbolLoadError`](enum.Error.html), as it will for a
+    /// registered or testing symbol, which is in no export table.
+    #[cfg(windows)]
+    pub fn symbol_info<N>(&self, symbol: N) -> Result<::windows::SymbolInfo, Error> where N: SymbolName {
+        let symbol = symbol.c_name()?;
+
+        #[cfg(debug_assertions)]
+        validate::validate(&symbol.to_string_lossy(), self.ascii_symbols)?;
+
+        let handle = self.library.handle.as_non_null();
+        let address = unsafe { backend::load_symbol(handle, &symbol) }?;
+
+        let decorated = || {
+            let name = unsafe { backend::decorated_name(handle, &symbol.to_string_lossy()) }?;
+            unsafe { backend::export_info(handle, &std::ffi::CString::new(name).ok()?) }
+        };
+
+        match unsafe { backend::export_info(handle, &symbol) }.or_else(decorated) {
+            Some((ordinal, kind)) => Ok(::windows::SymbolInfo::new(address, ordinal, kind)),
+            None => Err(Error::SymbolLoadError(Failure::new("symbol is not in the library's export table", None, Class::NotFound)).with_symbol(&symbol.to_string_lossy()))
+        }
+    }
+
+    /// Attempt to load a symbol which may have the null address, returning
+    /// `None` if it does, and otherwise as for [`symbol`](#method.symbol).
+    ///
+    /// A weak symbol which nothing defines is exported with the null address,
+    /// so its users can check whether it is present, as can a symbol defined
+    /// as an absolute address of zero. On unix this is told apart from a
+    /// missing symbol by checking `dlerror()`, which is only set for the
+    /// latter. On Windows an export never has the null address, so this never
+    /// returns `None`.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let snek = Snek::load(libexample).unwrap();
+    ///
+    /// match snek.nullable_symbol("add").unwrap() {
+    ///     Some(add) => println!("add is at {:?}", add.as_ptr()),
+    ///     None => println!("add is not defined")
+    /// }
+    /// # }
+    /// ```
+    pub fn nullable_symbol<'a, N>(&'a self, symbol: N) -> Result<Option<Symbol<'a>>, Error> where N: SymbolName {
+        match self.symbol(symbol) {
+            Ok(symbol) => Ok(Some(symbol)),
+            Err(ref err) if err.is_null_symbol() => Ok(None),
+            Err(err) => Err(err)
+        }
+    }
+
+    /// Returns true if the library exports a symbol by the given name, such
+    /// as to check for an optional function before relying on it.
+    ///
+    /// This only asks the library itself, not any resolvers, and never makes
+    /// the error a failed [`symbol`](#method.symbol) would, so a missing name
+    /// costs no more than a present one. A symbol with the null address is
+    /// present, though `symbol` fails for it. A name which could not be looked
+    /// up, such as one containing a NUL byte, is never present, and the
+    /// checks debug builds make in `symbol` are skipped.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let snek = Snek::load(libexample).unwrap();
+    ///
+    /// assert!(snek.has_symbol("add"));
+    /// assert!(!snek.has_symbol("render_v2"));
+    /// # }
+    /// ```
+    pub fn has_symbol<N>(&self, symbol: N) -> bool where N: SymbolName {
+        if self.symbols.get(symbol.name_bytes()).is_some() {
+            return true;
+        }
+
+        match symbol.c_name() {
+            Ok(symbol) => unsafe { backend::has_symbol(self.library.handle.as_non_null(), &symbol) },
+            Err(_) => false
+        }
+    }
+
+    /// Add a resolver to the chain consulted by [`symbol`](#method.symbol)
+    /// and [`owned_symbol`](#method.owned_symbol), returning its index in
+    /// the chain.
+    ///
+    /// Each resolver is called with the requested name, and the first one in
+    /// the chain at the given position to return an address satisfies it, so
+    /// that resolvers placed [`Before`](enum.ResolverPosition.html) the
+    /// library override its exports, and those placed
+    /// [`After`](enum.ResolverPosition.html) it are only consulted for names
+    /// it does not export. The address is returned as an ordinary
+    /// [`Symbol`](struct.Symbol.html), and is still checked as described for
+    /// [`symbol`](#method.symbol) first. Symbols loaded through other means,
+    /// such as by a [`snek!`](macro.snek!.html) block, do not consult the
+    /// chain.
+    ///
+    /// The resolver which satisfied each name can be found with
+    /// [`resolved_by`](#method.resolved_by), and is included in the `Snek`'s
+    /// `Debug` output.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::{ResolverPosition, Snek};
+    /// # use std::os::raw::c_int;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// extern "C" fn subtract(a: c_int, b: c_int) -> c_int {
+    ///     a - b
+    /// }
+    ///
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let mut snek = Snek::load(libexample).unwrap();
+    /// snek.push_resolver(ResolverPosition::Before, Box::new(|name| match name {
+    ///     "add" => Some(subtract as *mut _),
+    ///     _ => None
+    /// }));
+    ///
+    /// let result = unsafe { snek.symbol("add").unwrap().with(|add: extern fn(c_int, c_int) -> c_int| add(3, 7)) };
+    /// assert_eq!(result, -4);
+    /// assert_eq!(snek.resolved_by("add"), Some(0));
+    /// # }
+    /// ```
+    pub fn push_resolver(&mut self, position: ResolverPosition, resolver: Resolver) -> usize {
+        self.resolvers.push(position, resolver)
+    }
+
+    /// Returns the index of the resolver which satisfied the last lookup of
+    /// the given name, as returned by [`push_resolver`](#method.push_resolver),
+    /// or `None` if it was satisfied by the library or has not been looked up.
+    pub fn resolved_by(&self, symbol: &str) -> Option<usize> {
+        self.resolvers.resolved_by(symbol)
+    }
+
+    /// Attempt to load each of the given symbols from the library, as with
+    /// [`symbol`](#method.symbol), returning them in the order they were
+    /// named.
+    ///
+    /// Every name is looked up even once one has failed, so that a library
+    /// missing several symbols reports them all at once. If any fail, this
+    /// will return [`Error::Multiple`](enum.Error.html), holding the error for
+    /// each failed name in the order they were named, each of which names its
+    /// symbol.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::{Error, Snek};
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let snek = Snek::load(libexample).unwrap();
+    /// assert_eq!(snek.symbols(["add", "ANSWER"]).unwrap().len(), 2);
+    ///
+    /// match snek.symbols(["add", "plugin_start", "plugin_stop"]) {
+    ///     Err(Error::Multiple(errors)) => for err in &errors {
+    ///         println!("{}", err);
+    ///     },
+    ///
+    ///     _ => unreachable!()
+    /// }
+    /// # }
+    /// ```
+    pub fn symbols<'a, I, N>(&'a self, symbols: I) -> Result<Vec<Symbol<'a>>, Error> where I: IntoIterator<Item = N>, N: SymbolName {
+        let mut found = Vec::new();
+        let mut errors = Vec::new();
+
+        for symbol in symbols {
+            match self.symbol(symbol) {
+                Ok(symbol) => found.push(symbol),
+                Err(err) => errors.push(err)
+            }
+        }
+
+        match Errors::from_vec(errors) {
+            Some(errors) => Err(Error::Multiple(errors)),
+            None => Ok(found)
+        }
+    }
+
+    /// Attempt to load each of the given symbols from the library, as with
+    /// [`symbols`](#method.symbols), returning them by name.
+    ///
+    /// A name which is not UTF-8 has its invalid bytes replaced in the key,
+    /// and a name given more than once is only in the map once.
+    pub fn symbol_map<'a, I, N>(&'a self, symbols: I) -> Result<HashMap<String, Symbol<'a>>, Error> where I: IntoIterator<Item = N>, N: SymbolName {
+        let symbols = symbols.into_iter().collect::<Vec<_>>();
+        let found = self.symbols(&symbols)?;
+
+        Ok(symbols.iter()
+            .map(|symbol| String::from_utf8_lossy(symbol.name_bytes()).into_owned())
+            .zip(found)
+            .collect())
+    }
+
+    /// Load every symbol the library exports whose name starts with the given
+    /// prefix, returning each with its name, in sorted order. If `strip` is
+    /// true, the prefix is removed from the returned names.
+    ///
+    /// The exports are read from the library file, as with
+    /// [`inspect::Image::exports`](inspect/struct.Image.html#method.exports).
+    /// If the file cannot be found or read, or it has no export table to read,
+    /// such as an ELF file whose section headers have been stripped, this
+    /// will return [`Error::InspectError`](enum.Error.html) rather than an
+    /// empty list, so that a library with no matching exports can be told
+    /// apart from one whose exports are unknown.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate libc;
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # use libc::c_int;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let snek = Snek::load(libexample).unwrap();
+    ///
+    /// for (name, handler) in snek.symbols_with_prefix("cmd_", true).unwrap() {
+    ///     let result = unsafe { handler.with(|handler: extern fn() -> c_int| handler()) };
+    ///     println!("{} returns {}", name, result);
+    /// }
+    /// # }
+    /// ```
+    pub fn symbols_with_prefix<'a>(&'a self, prefix: &str, strip: bool) -> Result<Vec<(String, Symbol<'a>)>, Error> {
+        let file = env::library_file(&self.path)
+            .ok_or_else(|| Error::InspectError(format!("{} not found in any search directory", self.path.display())))?;
+
+        let image = inspect::inspect_file(&file)?;
+        if image.exports().is_empty() {
+            return Err(Error::InspectError(format!("{} has no readable export table", file.display())));
+        }
+
+        image.exports().iter()
+            .filter(|name| name.starts_with(prefix))
+            .map(|name| {
+                let symbol = self.symbol(name)?;
+                let name = if strip { &name[prefix.len()..] } else { name };
+
+                Ok((name.to_owned(), symbol))
+            })
+            .collect()
+    }
+
+    /// Returns the name the library actually exports the given symbol as, if
+    /// a lookup for it only succeeds by falling back to a decorated name.
+    ///
+    /// On 32-bit Windows, functions using the `__stdcall` or `__fastcall`
+    /// conventions are often exported as `_Frobnicate@8` or `@Frobnicate@8`,
+    /// and `__cdecl` ones as `_Frobnicate`. When a symbol is not exported under
+    /// its own name, [`symbol`](#method.symbol) and
+    /// [`load_symbol`](fn.load_symbol.html) retry with a leading underscore,
+    /// and then with the one export decorating it with an argument size, if
+    /// there is exactly one. This returns `None` on every other platform, and
+    /// for a symbol exported under its own name or not at all.
+    pub fn decorated_name(&self, symbol: &str) -> Option<String> {
+        unsafe { backend::decorated_name(self.library.handle.as_non_null(), symbol) }
+    }
+
+    /// Attempt to load a symbol from the dynamic library, returning an
+    /// [`OwnedSymbol`](struct.OwnedSymbol.html) which keeps the library loaded
+    /// for as long as it exists, even once this `Snek` is dropped.
+    ///
+    /// If the load fails, this will return [`Error::SymbolLoadError`](enum.Error.html),
+    /// as for [`symbol`](#method.symbol).
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate libc;
+    /// # extern crate snek;
+    /// # use snek::{OwnedSymbol, Snek};
+    /// # use libc::c_int;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// struct Adder {
+    ///     add: OwnedSymbol
+    /// }
+    ///
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let adder = Adder { add: Snek::load(libexample).unwrap().owned_symbol("add").unwrap() };
+    ///
+    /// let result = unsafe { adder.add.with(|add: extern fn(c_int, c_int) -> c_int| add(3, 7)) };
+    /// assert_eq!(result, 10);
+    /// # }
+    /// ```
+    pub fn owned_symbol<N>(&self, symbol: N) -> Result<OwnedSymbol, Error> where N: SymbolName {
+        self.symbol(symbol).map(|symbol| OwnedSymbol::new(symbol.as_non_null(), self.library.clone()))
+    }
+
+    /// Returns the number of [`OwnedSymbol`](struct.OwnedSymbol.html)s loaded
+    /// from this library which still exist, including clones. While this is
+    /// non-zero, dropping the `Snek` does not unload the library.
+    pub fn strong_symbol_count(&self) -> usize {
+        Arc::strong_count(&self.library) - 1
+    }
+
+    /// Register a callback to run just before the library is unloaded, for
+    /// releasing anything elsewhere which points into it, such as strings or
+    /// callbacks it handed out.
+    ///
+    /// Callbacks run in reverse order of registration, before the library's
+    /// fini function, whether it is unloaded by dropping the last `Snek` or
+    /// [`OwnedSymbol`](struct.OwnedSymbol.html) sharing it, or by
+    /// [`force_close`](#method.force_close). A callback which panics does not
+    /// stop the others from running or the library from being unloaded; the
+    /// panic is caught and discarded. A library which is never unloaded, such
+    /// as a pinned module, never runs its callbacks.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let snek = Snek::load(libexample).unwrap();
+    /// snek.on_unload(Box::new(|| println!("unloading")));
+    ///
+    /// // Prints "unloading"
+    /// drop(snek);
+    /// # }
+    /// ```
+    pub fn on_unload(&self, callback: Box<dyn FnOnce() + Send>) {
+        self.library.unload_callbacks.lock().push(callback)
+    }
+
+    /// Unload the library now, even if [`OwnedSymbol`](struct.OwnedSymbol.html)s
+    /// loaded from it still exist, running its unload callbacks and calling
+    /// its fini function first.
+    ///
+    /// # Safety
+    /// Any symbol from the library which is used after this, owned or not, is
+    /// a dangling pointer into memory which may since have been reused. This
+    /// is only for cases where the library must be unloaded at a certain
+    /// point, and every user of its symbols is known to have finished, for
+    /// which [`strong_symbol_count`](#method.strong_symbol_count) can help.
+    pub unsafe fn force_close(self) {
+        let _ = self.library.close();
+    }
+
+    /// Unload the library now, as dropping the `Snek` would, but reporting
+    /// whether the platform managed to unload it.
+    ///
+    /// The library's unload callbacks and fini function are run first, as
+    /// they are when it is dropped. If the platform fails to unload it, this
+    /// will return [`Error::UnloadError`](enum.Error.html) with its message.
+    /// If [`OwnedSymbol`](struct.OwnedSymbol.html)s loaded from the library
+    /// still exist, nothing is unloaded yet, and this also returns
+    /// `Error::UnloadError`; the library is then unloaded as usual once the
+    /// last of them is dropped.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let snek = Snek::load(libexample).unwrap();
+    /// snek.close().unwrap();
+    /// # }
+    /// ```
+    pub fn close(self) -> Result<(), Error> {
+        match self.strong_symbol_count() {
+            0 => self.library.close(),
+            count => Err(Error::UnloadError(format!("{} is still used by {} owned symbols, so it will stay loaded until they are dropped", self.path.display(), count)))
+        }
+    }
+
+    /// Turn the `Snek` into a [`SharedSnek`](struct.SharedSnek.html), which
+    /// can be cloned for each part of the program using the library, and
+    /// loads symbols which keep it loaded by themselves.
+    ///
+    /// Symbols and [`OwnedSymbol`](struct.OwnedSymbol.html)s already loaded
+    /// from the `Snek` stay as valid as they were.
+    pub fn into_shared(self) -> SharedSnek {
+        SharedSnek::from(self)
+    }
+
+    /// Leak the `Snek`, so that the library is never unloaded, returning a
+    /// reference to it which lives for the rest of the process. Symbols loaded
+    /// through it are `Symbol<'static>`, so can be kept anywhere, such as in a
+    /// static or a C callback's context, without borrowing from a `Snek`.
+    ///
+    /// Unlike [`pin`](#method.pin) this only stops the `Snek` itself from
+    /// unloading the library, so another handle to it can still do so, which
+    /// `pin` can be added to prevent. Its unload callbacks and fini function
+    /// are never run, even at process exit, though the platform still runs
+    /// the library's own destructors then, as for any library still loaded;
+    /// a symbol called from another thread while the process is exiting may
+    /// find the library's state already torn down.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate libc;
+    /// # extern crate snek;
+    /// # use snek::{Snek, Symbol};
+    /// # use libc::c_int;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let add: Symbol<'static> = Snek::load(libexample).unwrap().leak().symbol("add").unwrap();
+    /// assert_eq!(unsafe { add.with(|add: extern fn(c_int, c_int) -> c_int| add(3, 7)) }, 10);
+    /// # }
+    /// ```
+    pub fn leak(self) -> &'static Snek {
+        Box::leak(Box::new(self))
+    }
+
+    /// Pin the library, so that it is never unloaded for the rest of the
+    /// process, however many times anything closes it. This is for a library
+    /// whose threads, `atexit` handlers or thread-local destructors must
+    /// outlive every user of it, which would crash once its code is unmapped.
+    ///
+    /// * On Windows, this uses `GetModuleHandleExW` with
+    ///   `GET_MODULE_HANDLE_EX_FLAG_PIN`.
+    /// * On unix, this opens the library again with `RTLD_NODELETE`, by the
+    ///   path the loader found it at rather than the one it was loaded by, and
+    ///   never closes the new reference, which alone keeps the library loaded
+    ///   on a platform without the flag.
+    ///
+    /// Once pinned, dropping the `Snek` (or forcing it closed) neither calls
+    /// its fini function nor releases its handle, as the library will not be
+    /// unloaded, so the symbols loaded from it stay valid, including those of
+    /// a [`snek!`](macro.snek!.html) struct pinned through its `AsRef<Snek>`.
+    /// The handle to the process itself is never unloaded, so is already as
+    /// good as pinned. A library whose handle is owned by other code, as with
+    /// [`from_raw_handle_unowned`](#method.from_raw_handle_unowned), is pinned
+    /// all the same, so stays loaded once its owner releases it. If the library cannot be pinned, this will return
+    /// [`Error::LibraryLoadError`](enum.Error.html), or `Error::Unsupported`
+    /// for a `Snek` made from a testing `SymbolTable`.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate libc;
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # use libc::c_int;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libexample = fixture();
+    /// let snek = Snek::load(libexample).unwrap();
+    /// let add = unsafe { snek.symbol("add").unwrap().with(|add: extern fn(c_int, c_int) -> c_int| add) };
+    ///
+    /// snek.pin().unwrap();
+    /// drop(snek);
+    /// assert_eq!(add(3, 7), 10);
+    /// # }
+    /// ```
+    pub fn pin(&self) -> Result<(), Error> {
+        if !self.library.process {
+            backend::pin(self.library.handle.as_non_null(), &self.path)?;
+        }
+
+        self.library.pinned.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Returns true if unloading a library on the current platform never
+    /// unmaps it, as with musl, whose `dlclose` does nothing, so that every
+    /// library stays loaded as though [`pin`](#method.pin)ned.
+    ///
+    /// Dropping the last `Snek` for a library still calls its fini function
+    /// and any unload callbacks, but the library's own destructors are not
+    /// run, its statics keep their values, and loading it again from the same
+    /// path returns the same copy rather than reading the file afresh. A
+    /// [`ReloadableSnek`](struct.ReloadableSnek.html) still loads each new
+    /// version, from its own shadow copy, but every previous version stays
+    /// mapped until the process exits.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # fn main() {
+    /// if Snek::unload_is_noop() {
+    ///     println!("libraries stay loaded once dropped");
+    /// }
+    /// # }
+    /// ```
+    pub const fn unload_is_noop() -> bool {
+        cfg!(all(target_os = "linux", target_env = "musl"))
+    }
+
+    /// Attempt to load a thread-local variable from the dynamic library,
+    /// returning a [`TlsData`](struct.TlsData.html) accessor which finds the
+    /// calling thread's instance of it on each access.
+    ///
+    /// The symbol is looked up once here so that a missing one fails early,
+    /// in which case this will return [`Error::SymbolLoadError`](enum.Error.html).
+    ///
+    /// # Safety
+    /// The symbol must be a thread-local variable of type `T`. Reading an
+    /// ordinary data symbol through the accessor reads the same address from
+    /// every thread, and on macOS calls into whatever the symbol holds.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate libc;
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # use libc::c_int;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # if !cfg!(all(target_os = "linux", target_arch = "x86_64")) { return; }
+    /// let snek = Snek::load(fixture()).unwrap();
+    /// let errno = unsafe { snek.tls_data::<c_int>("fixture_errno") }.unwrap();
+    ///
+    /// errno.write(5).unwrap();
+    /// assert_eq!(errno.read().unwrap(), 5);
+    /// # }
+    /// ```
+    pub unsafe fn tls_data<'a, T>(&'a self, symbol: &str) -> Result<TlsData<'a, T>, Error> {
+        self.symbol(symbol)?;
+        Ok(TlsData::new(self, symbol))
+    }
+
+    pub(crate) fn thread_local(&self, symbol: &str) -> Result<NonNull<c_void>, Error> {
+        unsafe {
+            let address = backend::load_symbol(self.library.handle.as_non_null(), &symbol.c_name()?)?;
+            backend::thread_local(self.library.handle.as_non_null(), address)
+        }
+    }
+
+    /// Attempt to read the [`plugin::Info`](plugin/struct.Info.html) structure
+    /// exported by the library under the given symbol name, usually
+    /// [`plugin::INFO_SYMBOL`](plugin/constant.INFO_SYMBOL.html).
+    ///
+    /// If the symbol cannot be loaded this will return [`Error::SymbolLoadError`](enum.Error.html),
+    /// and if it does not hold valid plugin information this will return
+    /// [`Error::PluginError`](enum.Error.html).
+    ///
+    /// # Safety
+    /// The symbol must be a data symbol at least eight bytes in size, as the
+    /// magic number and size are read from it before they can be validated.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # use snek::plugin;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let libplugin = fixture();
+    /// let snek = Snek::load(libplugin).unwrap();
+    /// let info = unsafe { snek.plugin_info(plugin::INFO_SYMBOL) }.unwrap();
+    ///
+    /// println!("{} {:?} by {}", info.name(), info.version, info.author());
+    /// # assert_eq!(info.name(), "fixture");
+    /// # }
+    /// ```
+    pub unsafe fn plugin_info(&self, symbol: &str) -> Result<Info, Error> {
+        Info::read(&self.symbol(symbol)?)
     }
 }
 
-impl Drop for Snek {
-    fn drop(&mut self) {
-        drop_library(self.handle)
+/// Attempt to load a dynamic library from the given path, as with
+/// [`Snek::load`](struct.Snek.html#method.load).
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::Snek;
+/// # use std::path::Path;
+/// # use std::convert::TryFrom;
+/// # fn main() {
+/// assert!(Snek::try_from(Path::new("libdoesnotexist.so")).is_err());
+/// # }
+/// ```
+impl<'a> TryFrom<&'a Path> for Snek {
+    type Error = Error;
+
+    fn try_from(path: &'a Path) -> Result<Snek, Error> {
+        Snek::load(path)
     }
 }
 
+/// Attempt to load a dynamic library from the given path, as with
+/// [`Snek::load`](struct.Snek.html#method.load).
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::Snek;
+/// # use std::path::PathBuf;
+/// # use std::convert::TryFrom;
+/// # fn main() {
+/// # #[cfg(target_os = "linux")]
+/// let libc = Snek::try_from(PathBuf::from("libc.so.6")).unwrap();
+/// # }
+/// ```
+impl TryFrom<PathBuf> for Snek {
+    type Error = Error;
+
+    fn try_from(path: PathBuf) -> Result<Snek, Error> {
+        Snek::load(path)
+    }
+}
+
+/// Attempt to load a dynamic library from the path in the given string, as
+/// with [`Snek::load`](struct.Snek.html#method.load).
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::Snek;
+/// # fn main() {
+/// let config = "plugin = libdoesnotexist.so";
+/// let plugin = config.splitn(2, " = ").nth(1).unwrap().parse::<Snek>();
+///
+/// assert!(plugin.is_err());
+/// # }
+/// ```
+impl FromStr for Snek {
+    type Err = Error;
+
+    fn from_str(path: &str) -> Result<Snek, Error> {
+        Snek::load(path)
+    }
+}
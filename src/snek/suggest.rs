@@ -0,0 +1,140 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/snek/suggest.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::{env, inspect, Error};
+use ::failure::Class;
+use ::snek::backend;
+
+use std::ptr::NonNull;
+use std::path::Path;
+use libc::c_void;
+
+/// The most suggestions added to an error.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// The most exports compared against the missing name, so that a library with
+/// an enormous export table does not make a failed lookup slow.
+const MAX_CANDIDATES: usize = 1 << 14;
+
+/// Extend the error from a failed symbol lookup with the names of the
+/// library's exports which are closest to the requested one, if any are close.
+/// The exports are read from the loaded library where the platform can,
+/// and otherwise from its file.
+pub fn suggest(handle: NonNull<c_void>, path: &Path, symbol: &str, err: Error) -> Error {
+    match err {
+        // A null symbol was found, so there is nothing to suggest instead
+        Error::SymbolLoadError(mut failure) if failure.class != Class::NullSymbol => {
+            let exports = match unsafe { backend::exported_names(handle, MAX_CANDIDATES) } {
+                Some(exports) => exports,
+                None => match env::library_file(path).map(inspect::inspect_file) {
+                    Some(Ok(image)) => image.exports().to_vec(),
+                    _ => return Error::SymbolLoadError(failure)
+                }
+            };
+
+            let suggestions = closest(symbol, &exports);
+            if suggestions.is_empty() {
+                return Error::SymbolLoadError(failure);
+            }
+
+            let quoted = suggestions.iter().map(|name| format!("'{}'", name)).collect::<Vec<_>>();
+            let list = match quoted.split_last() {
+                Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+                _ => quoted.join("")
+            };
+
+            failure.message = format!("{}; did you mean {}?", failure.message, list);
+            Error::SymbolLoadError(failure)
+        },
+
+        err => err
+    }
+}
+
+fn closest<'a>(symbol: &str, exports: &'a [String]) -> Vec<&'a str> {
+    let wanted = symbol.to_lowercase();
+    let threshold = (wanted.chars().count() / 4).max(1);
+
+    let mut scored = exports.iter()
+        .take(MAX_CANDIDATES)
+        .filter(|export| export.as_str() != symbol)
+        .filter_map(|export| {
+            let candidate = export.to_lowercase();
+
+            // A case-only difference is the closest possible match, and a
+            // prefix match catches a missing or extra suffix
+            let difference = (candidate.chars().count() as isize - wanted.chars().count() as isize).unsigned_abs();
+            let score = if candidate == wanted {
+                0
+            } else if candidate.starts_with(&wanted) || wanted.starts_with(&candidate) {
+                if candidate.len().min(wanted.len()) < 3 {
+                    return None;
+                }
+
+                difference
+            } else if difference <= threshold {
+                edit_distance(&wanted, &candidate, threshold)?
+            } else {
+                return None;
+            };
+
+            Some((score, export.as_str()))
+        })
+        .collect::<Vec<_>>();
+
+    scored.sort();
+    scored.into_iter().take(MAX_SUGGESTIONS).map(|(_, export)| export).collect()
+}
+
+/// Returns the edit distance between two strings, counting a transposition of
+/// adjacent characters as a single edit, or `None` if it is greater than
+/// `limit`.
+fn edit_distance(a: &str, b: &str, limit: usize) -> Option<usize> {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+
+    let mut before = vec![0; b.len() + 1];
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+
+    for i in 0..a.len() {
+        current[0] = i + 1;
+
+        for j in 0..b.len() {
+            let substitution = previous[j] + if a[i] == b[j] { 0 } else { 1 };
+            let mut distance = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+
+            if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+                distance = distance.min(before[j - 1] + 1);
+            }
+
+            current[j + 1] = distance;
+        }
+
+        // A later row can only improve on this one by a transposition, which
+        // still costs an edit, so the minimum bounds the final distance
+        if current.iter().min().map(|&min| min > limit + 1).unwrap_or(false) {
+            return None;
+        }
+
+        ::std::mem::swap(&mut before, &mut previous);
+        ::std::mem::swap(&mut previous, &mut current);
+    }
+
+    Some(previous[b.len()]).filter(|&distance| distance <= limit)
+}
@@ -20,43 +20,564 @@
 
 extern crate libc;
 
-use ::Error;
+use ::{name, AddressInfo, Error, LoadedLibrary};
+use ::diagnostics::OsError;
+use ::failure::{Class, Failure};
+use ::options::LoadFlags;
 
-use std::path::Path;
-use std::ffi::{CStr, CString};
-use libc::{c_char, c_int, c_void};
+use std::env;
+use std::fs;
+use std::mem;
+use std::cell::Cell;
+use std::ptr::{self, NonNull};
+use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::ffi::{CStr, OsStr};
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use libc::{c_char, c_int, c_void, dlclose, dlerror, dlopen, dlsym};
 
-extern {
-    fn dlopen(path: *mut c_char, mode: c_int) -> *mut c_void;
-    fn dlclose(handle: *mut c_void);
-    fn dlsym(handle: *mut c_void, symbol: *mut c_char) -> *mut c_void;
-    fn dlerror() -> *mut c_char;
+pub fn load_library<P>(path: P, flags: LoadFlags) -> Result<NonNull<c_void>, Error> where P: AsRef<Path> {
+    let binding = if flags.now { libc::RTLD_NOW } else { libc::RTLD_LAZY };
+    let visibility = if flags.global { libc::RTLD_GLOBAL } else { libc::RTLD_LOCAL };
+    let mode = flags.raw.unwrap_or(binding | visibility);
+
+    if flags.new_namespace && !cfg!(all(target_os = "linux", target_env = "gnu")) {
+        return Err(Error::Unsupported);
+    }
+
+    open_path(path.as_ref(), mode, flags.new_namespace).or_else(|err| load_fallback(path.as_ref(), mode, err))
+}
+
+fn open_path(path: &Path, mode: c_int, new_namespace: bool) -> Result<NonNull<c_void>, Error> {
+    let path_string = name::c_path(path)?;
+
+    with_loader_lock(|| {
+        let result = unsafe { open(path_string.as_ptr(), mode, new_namespace) };
+
+        NonNull::new(result).ok_or_else(|| {
+            let error = take_error();
+            let class = classify_message(error.message());
+            Error::LibraryLoadError(error.into_failure(class))
+        })
+    })
+}
+
+/// Classify a failed load from the loader's message, which is all it reports.
+/// The messages are those of glibc, musl and dyld.
+fn classify_message(message: &str) -> Class {
+    let patterns = [
+        ("wrong ELF class", Class::WrongArchitecture),
+        ("ELF file OS ABI invalid", Class::WrongArchitecture),
+        ("incompatible architecture", Class::WrongArchitecture),
+        ("missing compatible architecture", Class::WrongArchitecture),
+        ("invalid ELF header", Class::BadImage),
+        ("file too short", Class::BadImage),
+        ("not a mach-o file", Class::BadImage),
+        ("Exec format error", Class::BadImage),
+        ("Permission denied", Class::PermissionDenied),
+        ("No such file", Class::NotFound),
+        ("image not found", Class::NotFound)
+    ];
+
+    patterns.iter()
+        .find(|&&(pattern, _)| message.contains(pattern))
+        .map(|&(_, class)| class)
+        .unwrap_or(Class::Other)
+}
+
+/// Unlike other unix loaders, dyld does not search the executable's runpaths
+/// for a bare file name, and without `DYLD_FALLBACK_LIBRARY_PATH` set only
+/// searches the most basic fallback directories, so a bare name which is not
+/// found is tried as `@rpath/name`, then in each of the directories from
+/// `env::search_paths`.
+///
+/// A universal binary without a slice for the process's architecture is
+/// reported as such, rather than by dyld's message alone.
+#[cfg(target_os = "macos")]
+fn load_fallback(path: &Path, mode: c_int, err: Error) -> Result<NonNull<c_void>, Error> {
+    let err = match err {
+        Error::LibraryLoadError(failure) => Error::LibraryLoadError(explain_universal(path, failure)),
+        err => return Err(err)
+    };
+
+    if !::env::is_bare_name(path) || !err.is_not_found() {
+        return Err(err);
+    }
+
+    let candidates = Some(Path::new("@rpath").join(path)).into_iter().chain(::env::locate(path));
+
+    for candidate in candidates {
+        match open_path(&candidate, mode, false) {
+            Ok(handle) => return Ok(handle),
+
+            // A file which is found but cannot be loaded explains more than
+            // the bare name not being found
+            Err(Error::LibraryLoadError(failure)) if !candidate.starts_with("@rpath") => {
+                return Err(Error::LibraryLoadError(explain_universal(&candidate, failure)));
+            },
+
+            Err(_) => ()
+        }
+    }
+
+    Err(err)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn load_fallback(_path: &Path, _mode: c_int, err: Error) -> Result<NonNull<c_void>, Error> {
+    Err(err)
+}
+
+#[cfg(target_os = "macos")]
+fn explain_universal(path: &Path, mut failure: Failure) -> Failure {
+    use ::inspect::{self, Format, Machine};
+
+    let image = match ::env::library_file(path).map(inspect::inspect_file) {
+        Some(Ok(image)) => image,
+        _ => return failure
+    };
+
+    if image.format() == Format::MachOUniversal && !image.matches_current() {
+        let machines = image.machines().iter().map(|machine| machine.to_string()).collect::<Vec<_>>();
+        failure.message = format!("universal binary contains {} but process is {} ({})", machines.join(", "), Machine::current(), failure.message);
+        failure.class = Class::WrongArchitecture;
+    }
+
+    failure
+}
+
+/// Only glibc can load a library into a new namespace.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+unsafe fn open(path: *const c_char, mode: c_int, new_namespace: bool) -> *mut c_void {
+    if new_namespace {
+        unsafe { libc::dlmopen(libc::LM_ID_NEWLM, path, mode) }
+    } else {
+        unsafe { dlopen(path, mode) }
+    }
+}
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+unsafe fn open(path: *const c_char, mode: c_int, _new_namespace: bool) -> *mut c_void {
+    unsafe { dlopen(path, mode) }
+}
+
+/// Returns the namespace the library was loaded into, which is zero for the
+/// process's own namespace.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+pub fn namespace_id(handle: NonNull<c_void>) -> Result<i64, Error> {
+    let mut namespace: libc::Lmid_t = 0;
+
+    with_loader_lock(|| match unsafe { libc::dlinfo(handle.as_ptr(), libc::RTLD_DI_LMID, &mut namespace as *mut _ as *mut c_void) } {
+        0 => Ok(namespace as i64),
+        _ => Err(Error::LibraryLoadError(take_error().into_failure(Class::Other)))
+    })
+}
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+pub fn namespace_id(_handle: NonNull<c_void>) -> Result<i64, Error> {
+    Err(Error::Unsupported)
+}
+
+/// Returns the absolute path of the file the library was loaded from, which
+/// the loader may have found by searching for a bare file name.
+pub fn library_path(handle: NonNull<c_void>) -> Result<PathBuf, Error> {
+    let path = loaded_name(handle)?;
+
+    // The main program has no name of its own, and a library loaded by a
+    // relative path keeps it
+    let path = if path.as_os_str().is_empty() {
+        env::current_exe().map_err(|err| Error::LibraryLoadError(Failure::new(err.to_string(), err.raw_os_error(), Class::Other)))?
+    } else {
+        path
+    };
+
+    Ok(fs::canonicalize(&path).unwrap_or(path))
+}
+
+// Declared here as the `libc` crate's are deprecated
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+extern "C" {
+    fn _dyld_image_count() -> u32;
+    fn _dyld_get_image_name(index: u32) -> *const c_char;
+    fn _dyld_get_image_header(index: u32) -> *const c_void;
+}
+
+/// The prefix of glibc's `struct link_map` which is part of its ABI.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+#[repr(C)]
+struct LinkMap {
+    _l_addr: usize,
+    l_name: *const c_char
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn loaded_name(handle: NonNull<c_void>) -> Result<PathBuf, Error> {
+    let mut map: *const LinkMap = ptr::null();
+
+    with_loader_lock(|| match unsafe { libc::dlinfo(handle.as_ptr(), libc::RTLD_DI_LINKMAP, &mut map as *mut _ as *mut c_void) } {
+        0 if !map.is_null() => Ok(c_path_buf(unsafe { (*map).l_name })),
+        _ => Err(Error::LibraryLoadError(take_error().into_failure(Class::Other)))
+    })
+}
+
+/// dyld names every image it has loaded, so the library is whichever of them
+/// opens to the same handle.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+fn loaded_name(handle: NonNull<c_void>) -> Result<PathBuf, Error> {
+    let count = unsafe { _dyld_image_count() };
+    let names = (0..count).map(|index| unsafe { _dyld_get_image_name(index) });
+
+    find_loaded(handle, names.filter(|name| !name.is_null()).map(|name| unsafe { CStr::from_ptr(name) }.to_owned()))
+}
+
+/// Elsewhere the files mapped into the process are listed by procfs, so the
+/// library is whichever of them opens to the same handle.
+#[cfg(not(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos", target_os = "ios")))]
+fn loaded_name(handle: NonNull<c_void>) -> Result<PathBuf, Error> {
+    let maps = fs::read_to_string("/proc/self/maps").map_err(|_| Error::Unsupported)?;
+
+    let mut names = maps.lines()
+        .filter_map(|line| line.find('/').map(|start| &line[start..]))
+        .collect::<Vec<_>>();
+
+    names.dedup();
+    find_loaded(handle, names.into_iter().filter_map(|name| CString::new(name).ok()))
+}
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+fn find_loaded<I>(handle: NonNull<c_void>, names: I) -> Result<PathBuf, Error> where I: IntoIterator<Item = CString> {
+    with_loader_lock(|| {
+        for name in names {
+            let result = unsafe { dlopen(name.as_ptr(), libc::RTLD_LAZY | libc::RTLD_NOLOAD) };
+
+            if !result.is_null() {
+                unsafe { dlclose(result) };
+
+                if result == handle.as_ptr() {
+                    return Ok(c_path_buf(name.as_ptr()));
+                }
+            }
+        }
+
+        Err(Error::LibraryLoadError(Failure::new("library is not among the loaded images", None, Class::NotFound)))
+    })
+}
+
+fn c_path_buf(name: *const c_char) -> PathBuf {
+    if name.is_null() {
+        PathBuf::new()
+    } else {
+        PathBuf::from(OsStr::from_bytes(unsafe { CStr::from_ptr(name) }.to_bytes()))
+    }
+}
+
+/// Returns a handle to the process itself, whose lookups search the main
+/// program and every library loaded with global visibility.
+pub fn process_handle() -> Result<NonNull<c_void>, Error> {
+    with_loader_lock(|| {
+        // The program and its libraries are already loaded and relocated, so
+        // the binding mode changes nothing, but one must be given; lazy, as for
+        // the other opens of a loaded object below
+        let result = unsafe { dlopen(ptr::null(), libc::RTLD_LAZY) };
+
+        NonNull::new(result).ok_or_else(|| Error::LibraryLoadError(take_error().into_failure(Class::Other)))
+    })
 }
 
-pub fn load_library<P>(path: P) -> Result<*mut c_void, Error> where P: AsRef<Path> {
-    let path_string = CString::new(path.as_ref().to_string_lossy().as_ref()).unwrap();
-    let result = unsafe { dlopen(path_string.as_ptr() as *mut c_char, 1) };
+/// Takes another reference to a loaded library by opening the file it was
+/// loaded from again, which only counts a reference to the loaded copy. The
+/// process's own handle is never unloaded, so is returned as it is.
+pub fn add_reference(handle: NonNull<c_void>) -> Result<NonNull<c_void>, Error> {
+    if process_handle().map(|process| process == handle).unwrap_or(false) {
+        return Ok(handle);
+    }
+
+    open_existing(library_path(handle)?)
+}
+
+/// Returns a new reference to a library which is already loaded, found as
+/// `dlopen` would find it, without loading it if it is not.
+pub fn open_existing<P>(path: P) -> Result<NonNull<c_void>, Error> where P: AsRef<Path> {
+    let path_string = name::c_path(path.as_ref())?;
+
+    with_loader_lock(|| {
+        let result = unsafe { dlopen(path_string.as_ptr(), libc::RTLD_LAZY | libc::RTLD_NOLOAD) };
+
+        // A library which is not loaded is not an error to dlopen
+        NonNull::new(result).ok_or_else(|| match pending_error() {
+            Some(error) => Error::LibraryLoadError(error.into_failure(Class::Other)),
+            None => Error::LibraryLoadError(Failure::new("library is not loaded", None, Class::NotFound))
+        })
+    })
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "ios",
+          target_os = "freebsd", target_os = "dragonfly", target_os = "netbsd"))]
+const RTLD_NODELETE: c_int = libc::RTLD_NODELETE;
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "ios",
+              target_os = "freebsd", target_os = "dragonfly", target_os = "netbsd")))]
+const RTLD_NODELETE: c_int = 0;
+
+/// Pin a library by opening it again with `RTLD_NODELETE`, and never closing
+/// the new reference, which alone keeps it loaded where the flag is not
+/// supported.
+///
+/// The library is opened by the path the loader found it at, rather than the
+/// one it was asked for, which may be relative, a bare name found by searching
+/// or empty for a handle wrapped without one.
+pub fn pin(handle: NonNull<c_void>) -> Result<(), Error> {
+    let path_string = name::c_path(&library_path(handle)?)?;
+
+    with_loader_lock(|| {
+        let result = unsafe { dlopen(path_string.as_ptr(), libc::RTLD_LAZY | libc::RTLD_NOLOAD | RTLD_NODELETE) };
+
+        if !result.is_null() {
+            return Ok(());
+        }
+
+        Err(match pending_error() {
+            Some(error) => Error::LibraryLoadError(error.into_failure(Class::Other)),
+            None => Error::LibraryLoadError(Failure::new("library is not loaded", None, Class::NotFound))
+        })
+    })
+}
+
+pub unsafe fn load_symbol(handle: NonNull<c_void>, symbol: &CStr) -> Result<NonNull<c_void>, Error> {
+    with_loader_lock(|| {
+        let result = unsafe { dlsym(handle.as_ptr(), symbol.as_ptr()) };
+        symbol_result(result)
+    })
+}
+
+/// Only whether an error was set is checked, so that a symbol with the null
+/// address is told apart from a missing one without copying the message.
+pub unsafe fn has_symbol(handle: NonNull<c_void>, symbol: &CStr) -> bool {
+    with_loader_lock(|| {
+        let result = unsafe { dlsym(handle.as_ptr(), symbol.as_ptr()) };
+        !result.is_null() || unsafe { dlerror() }.is_null()
+    })
+}
+
+/// Search every object in the global scope, in load order, as for a library
+/// loaded with `RTLD_GLOBAL`.
+pub unsafe fn global_symbol(symbol: &CStr) -> Result<NonNull<c_void>, Error> {
+    with_loader_lock(|| {
+        let result = unsafe { dlsym(libc::RTLD_DEFAULT, symbol.as_ptr()) };
+        symbol_result(result)
+    })
+}
+
+/// Search the objects loaded after the one this crate is linked into, which
+/// is that of its caller, so a shim finds the definition it is interposing.
+pub unsafe fn next_symbol(symbol: &CStr) -> Result<NonNull<c_void>, Error> {
+    with_loader_lock(|| {
+        let result = unsafe { dlsym(libc::RTLD_NEXT, symbol.as_ptr()) };
+        symbol_result(result)
+    })
+}
+
+/// Only glibc versions its symbols.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+pub unsafe fn load_symbol_version(handle: NonNull<c_void>, symbol: &CStr, version: &CStr) -> Result<NonNull<c_void>, Error> {
+    with_loader_lock(|| {
+        let result = unsafe { libc::dlvsym(handle.as_ptr(), symbol.as_ptr(), version.as_ptr()) };
+        symbol_result(result)
+    })
+}
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+pub unsafe fn load_symbol_version(_handle: NonNull<c_void>, _symbol: &CStr, _version: &CStr) -> Result<NonNull<c_void>, Error> {
+    Err(Error::SymbolLoadError(Failure::new("symbol versions are not supported on this platform", None, Class::Unsupported)))
+}
+
+/// A symbol can really have the null address, such as a weak symbol which was
+/// never defined, in which case no error is set.
+fn symbol_result(result: *mut c_void) -> Result<NonNull<c_void>, Error> {
+    NonNull::new(result).ok_or_else(|| match pending_error() {
+        Some(error) => Error::SymbolLoadError(error.into_failure(Class::NotFound)),
+        None => Error::SymbolLoadError(Failure::new("symbol has the null address", None, Class::NullSymbol))
+    })
+}
+
+/// A library's exports are read from its file instead, as the dynamic symbol
+/// table mapped for it may lack the section headers needed to find it.
+pub unsafe fn exported_names(_handle: NonNull<c_void>, _limit: usize) -> Option<Vec<String>> {
+    None
+}
+
+/// Names are only decorated on 32-bit Windows.
+pub unsafe fn decorated_name(_handle: NonNull<c_void>, _symbol: &str) -> Option<String> {
+    None
+}
+
+/// Most platforms' `dlsym` already returns the calling thread's instance of a
+/// thread-local variable, allocating it if the thread has not used it yet.
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+pub unsafe fn thread_local(symbol: NonNull<c_void>) -> Result<NonNull<c_void>, Error> {
+    Ok(symbol)
+}
+
+/// On Apple platforms `dlsym` returns the variable's descriptor, whose thunk
+/// returns the calling thread's instance.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub unsafe fn thread_local(symbol: NonNull<c_void>) -> Result<NonNull<c_void>, Error> {
+    #[repr(C)]
+    struct Descriptor {
+        thunk: unsafe extern "C" fn(*mut Descriptor) -> *mut c_void,
+        key: usize,
+        offset: usize
+    }
+
+    let descriptor = symbol.as_ptr() as *mut Descriptor;
+    let result = unsafe { ((*descriptor).thunk)(descriptor) };
 
-    if result == (0 as *mut libc::c_void) {
-        let error = unsafe { CStr::from_ptr(dlerror()).to_string_lossy().into_owned() };
-        Err(Error::LibraryLoadError(error))
+    NonNull::new(result).ok_or_else(|| {
+        Error::SymbolLoadError(Failure::new("thread-local variable has no storage on this thread", None, Class::Other))
+    })
+}
+
+/// `dladdr` only reports exported symbols, and names the main program by an
+/// empty string on some platforms, in which case its path is asked for.
+pub fn resolve_address(address: *const c_void) -> Option<AddressInfo> {
+    let mut info: libc::Dl_info = unsafe { mem::zeroed() };
+    if unsafe { libc::dladdr(address, &mut info) } == 0 || info.dli_fname.is_null() {
+        return None;
+    }
+
+    let name = unsafe { CStr::from_ptr(info.dli_fname) };
+    let path = match name.to_bytes() {
+        b"" => env::current_exe().ok()?,
+        bytes => PathBuf::from(OsStr::from_bytes(bytes))
+    };
+
+    let symbol = if info.dli_sname.is_null() || info.dli_saddr.is_null() {
+        None
     } else {
-        Ok(result)
+        Some((unsafe { CStr::from_ptr(info.dli_sname) }.to_string_lossy().into_owned(), info.dli_saddr as *const c_void))
+    };
+
+    Some(AddressInfo::new(path, info.dli_fbase, symbol, address))
+}
+
+/// The loader holds its own lock while listing its objects, so nothing can
+/// be loaded or unloaded part way through. The main program is listed first,
+/// with an empty name.
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+pub fn loaded_libraries() -> Vec<LoadedLibrary> {
+    unsafe extern "C" fn push(info: *mut libc::dl_phdr_info, _size: libc::size_t, libraries: *mut c_void) -> c_int {
+        let info = unsafe { &*info };
+        let libraries = unsafe { &mut *(libraries as *mut Vec<(PathBuf, *const c_void)>) };
+
+        libraries.push((c_path_buf(info.dlpi_name), info.dlpi_addr as *const c_void));
+        0
     }
+
+    let mut libraries: Vec<(PathBuf, *const c_void)> = Vec::new();
+    unsafe { libc::dl_iterate_phdr(Some(push), &mut libraries as *mut _ as *mut c_void) };
+
+    libraries.into_iter()
+        .map(|(path, base)| if path.as_os_str().is_empty() {
+            LoadedLibrary::new(env::current_exe().unwrap_or(path), base)
+        } else {
+            LoadedLibrary::new(path, base)
+        })
+        .collect()
 }
 
-pub fn load_symbol(handle: *mut c_void, symbol: &str) -> Result<*mut c_void, Error> {
-    let string = CString::new(symbol).unwrap();
-    let result = unsafe { dlsym(handle, string.as_ptr() as *mut c_char) };
+/// dyld has no lock to hold, so each image's name is copied as soon as it is
+/// found, and one unloaded in the meantime is skipped.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub fn loaded_libraries() -> Vec<LoadedLibrary> {
+    with_loader_lock(|| {
+        (0..unsafe { _dyld_image_count() })
+            .map(|index| unsafe { (_dyld_get_image_name(index), _dyld_get_image_header(index)) })
+            .filter(|&(name, header)| !name.is_null() && !header.is_null())
+            .map(|(name, header)| LoadedLibrary::new(c_path_buf(name), header))
+            .collect()
+    })
+}
 
-    if result == (0 as *mut libc::c_void) {
-        let error = unsafe { CStr::from_ptr(dlerror()).to_string_lossy().into_owned() };
-        Err(Error::SymbolLoadError(error))
+static LOADER: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    static HOLDING_LOADER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run a loader call along with reading its error, so that no other thread's
+/// call can replace the error in between on a platform where `dlerror()` is
+/// shared by every thread, rather than per thread as with glibc, musl and
+/// macOS. Any error left by an earlier call is cleared first.
+fn with_loader_lock<F, T>(f: F) -> T where F: FnOnce() -> T {
+    holding_loader_lock(|| {
+        unsafe { dlerror() };
+        f()
+    })
+}
+
+/// Run the given function holding the loader lock. A library's initialisers
+/// run inside `dlopen`, and may load libraries of their own, so a thread which
+/// already holds the lock runs straight through.
+fn holding_loader_lock<F, T>(f: F) -> T where F: FnOnce() -> T {
+    struct Release;
+
+    impl Drop for Release {
+        fn drop(&mut self) {
+            HOLDING_LOADER.with(|holding| holding.set(false));
+        }
+    }
+
+    if HOLDING_LOADER.with(Cell::get) {
+        return f();
+    }
+
+    // A panic cannot leave the loader inconsistent, so poisoning is ignored
+    let _guard = LOADER.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    HOLDING_LOADER.with(|holding| holding.set(true));
+    let _release = Release;
+
+    f()
+}
+
+/// Returns the message from `dlerror()`, which reports no error code.
+pub fn last_error() -> OsError {
+    holding_loader_lock(take_error)
+}
+
+/// Copy the message from `dlerror()`, which must be called with the loader
+/// lock held.
+fn take_error() -> OsError {
+    pending_error().unwrap_or_else(|| OsError::new(None, "no dynamic loading error has occurred"))
+}
+
+/// As `take_error`, returning `None` if no error has occurred since the last
+/// call.
+fn pending_error() -> Option<OsError> {
+    let error = unsafe { dlerror() };
+
+    if error.is_null() {
+        None
     } else {
-        Ok(result)
+        Some(OsError::new(None, unsafe { CStr::from_ptr(error) }.to_string_lossy()))
+    }
+}
+
+/// Classify an `errno` value, such as from checking the library file.
+pub fn classify(code: i32) -> Class {
+    match code {
+        libc::ENOENT | libc::ENOTDIR => Class::NotFound,
+        libc::EACCES | libc::EPERM => Class::PermissionDenied,
+        libc::ENOEXEC => Class::BadImage,
+        libc::ENOSYS | libc::EOPNOTSUPP => Class::Unsupported,
+        _ => Class::Other
     }
 }
 
-pub fn drop_library(handle: *mut c_void) {
-    unsafe { dlclose(handle) }
+pub fn drop_library(handle: NonNull<c_void>) -> Result<(), Error> {
+    with_loader_lock(|| match unsafe { dlclose(handle.as_ptr()) } {
+        0 => Ok(()),
+        _ => Err(Error::UnloadError(take_error().message().to_owned()))
+    })
 }
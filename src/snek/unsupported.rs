@@ -0,0 +1,110 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/snek/unsupported.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+#![cfg(not(any(unix, windows)))]
+
+// The backend for platforms without dynamic libraries, on which every load
+// fails with `Error::Unsupported`
+
+use ::{AddressInfo, Error, LoadedLibrary};
+use ::diagnostics::OsError;
+use ::failure::Class;
+use ::options::LoadFlags;
+
+use std::ptr::NonNull;
+use std::path::{Path, PathBuf};
+use std::ffi::CStr;
+use libc::c_void;
+
+pub fn load_library<P>(_path: P, _flags: LoadFlags) -> Result<NonNull<c_void>, Error> where P: AsRef<Path> {
+    Err(Error::Unsupported)
+}
+
+pub fn add_reference(_handle: NonNull<c_void>) -> Result<NonNull<c_void>, Error> {
+    Err(Error::Unsupported)
+}
+
+pub fn open_existing<P>(_path: P) -> Result<NonNull<c_void>, Error> where P: AsRef<Path> {
+    Err(Error::Unsupported)
+}
+
+pub fn pin(_handle: NonNull<c_void>) -> Result<(), Error> {
+    Err(Error::Unsupported)
+}
+
+pub fn namespace_id(_handle: NonNull<c_void>) -> Result<i64, Error> {
+    Err(Error::Unsupported)
+}
+
+pub fn library_path(_handle: NonNull<c_void>) -> Result<PathBuf, Error> {
+    Err(Error::Unsupported)
+}
+
+pub fn process_handle() -> Result<NonNull<c_void>, Error> {
+    Err(Error::Unsupported)
+}
+
+pub unsafe fn load_symbol(_handle: NonNull<c_void>, _symbol: &CStr) -> Result<NonNull<c_void>, Error> {
+    Err(Error::Unsupported)
+}
+
+pub unsafe fn has_symbol(_handle: NonNull<c_void>, _symbol: &CStr) -> bool {
+    false
+}
+
+pub unsafe fn global_symbol(_symbol: &CStr) -> Result<NonNull<c_void>, Error> {
+    Err(Error::Unsupported)
+}
+
+pub unsafe fn load_symbol_version(_handle: NonNull<c_void>, _symbol: &CStr, _version: &CStr) -> Result<NonNull<c_void>, Error> {
+    Err(Error::Unsupported)
+}
+
+pub unsafe fn exported_names(_handle: NonNull<c_void>, _limit: usize) -> Option<Vec<String>> {
+    None
+}
+
+pub unsafe fn decorated_name(_handle: NonNull<c_void>, _symbol: &str) -> Option<String> {
+    None
+}
+
+pub unsafe fn thread_local(_symbol: NonNull<c_void>) -> Result<NonNull<c_void>, Error> {
+    Err(Error::Unsupported)
+}
+
+pub fn resolve_address(_address: *const c_void) -> Option<AddressInfo> {
+    None
+}
+
+pub fn loaded_libraries() -> Vec<LoadedLibrary> {
+    Vec::new()
+}
+
+/// There is no dynamic loader to fail.
+pub fn last_error() -> OsError {
+    OsError::new(None, "dynamic libraries are not supported on this platform")
+}
+
+/// There are no OS error codes to classify.
+pub fn classify(_code: i32) -> Class {
+    Class::Other
+}
+
+pub fn drop_library(_handle: NonNull<c_void>) -> Result<(), Error> {
+    Ok(())
+}
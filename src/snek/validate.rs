@@ -0,0 +1,48 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/snek/validate.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::Error;
+use ::failure::{Class, Failure};
+
+/// Check that a symbol name could plausibly be exported, so that a name read
+/// from a config file with a stray newline or a lookalike character fails
+/// with an error showing it, rather than as an undefined symbol. This is only
+/// compiled into debug builds.
+pub fn validate(symbol: &str, ascii: bool) -> Result<(), Error> {
+    let problem = if symbol.is_empty() {
+        Some("is empty".to_owned())
+    } else {
+        symbol.char_indices().filter_map(|(index, character)| if character.is_whitespace() {
+            Some(format!("contains whitespace at byte {}", index))
+        } else if character.is_control() {
+            Some(format!("contains a control character at byte {}", index))
+        } else if ascii && !character.is_ascii() {
+            Some(format!("contains the non-ASCII character '{}' at byte {}", character.escape_unicode(), index))
+        } else {
+            None
+        }).next()
+    };
+
+    match problem {
+        Some(problem) => Err(Error::SymbolLoadError(Failure::new(
+            format!("invalid symbol name \"{}\": {}", symbol.escape_default(), problem), None, Class::InvalidName
+        ))),
+
+        None => Ok(())
+    }
+}
@@ -18,83 +18,600 @@
 
 #![cfg(windows)]
 
-use ::Error;
+use ::{name, AddressInfo, Error, LoadedLibrary};
+use ::windows::ExportKind;
+use ::diagnostics::OsError;
+use ::failure::{Class, Failure};
+use ::options::LoadFlags;
+use ::win32::{self, HRESULT, DWORD, HMODULE, LPCWSTR};
 
-use std::ptr;
+use std::mem;
+use std::ptr::{self, NonNull};
 use std::slice;
-use std::path::Path;
+use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::ffi::{CStr, OsString};
+use std::os::windows::ffi::OsStringExt;
+#[cfg(target_arch = "x86")]
 use std::ffi::CString;
-use libc::c_void;
-use winapi::{self, HRESULT, DWORD, HMODULE};
-use kernel32;
-
-pub fn load_library<P>(path: P) -> Result<*mut c_void, Error> where P: AsRef<Path> {
-    let path_string = CString::new(path.as_ref().to_string_lossy().as_ref()).unwrap();
-    let module = unsafe { kernel32::LoadLibraryA(path_string.as_ptr()) };
-    
-    if module.is_null() {
-        let error = last_error_string().unwrap_or_else(|| "Unknown Error".into());
-        Err(Error::LibraryLoadError(error))
-    } else {
-        Ok(module as *mut c_void)
+use libc::{c_char, c_void};
+
+/// Windows resolves every import when a library is loaded, and a library's
+/// exports are only found through its own handle, so the portable flags are
+/// ignored. Search flags, or raw flags in their place, are passed to
+/// `LoadLibraryExW`.
+pub fn load_library<P>(path: P, flags: LoadFlags) -> Result<NonNull<c_void>, Error> where P: AsRef<Path> {
+    if flags.new_namespace {
+        return Err(Error::Unsupported);
     }
+
+    // The wide functions take any path, where the ANSI ones would replace
+    // whatever the current code page cannot represent
+    let path_string = name::wide_path(&name::extended_path(path.as_ref()))?;
+    let _mode = if flags.error_dialogs { None } else { Some(ErrorMode::quiet()) };
+
+    let module = match flags.raw {
+        Some(raw) => unsafe { win32::LoadLibraryExW(path_string.as_ptr(), ptr::null_mut(), raw) },
+        None if flags.search != 0 => unsafe { win32::LoadLibraryExW(path_string.as_ptr(), ptr::null_mut(), flags.search) },
+        None => unsafe { win32::LoadLibraryW(path_string.as_ptr()) }
+    };
+
+    NonNull::new(module as *mut c_void).ok_or_else(|| Error::LibraryLoadError(last_failure()))
 }
 
-pub fn load_symbol(handle: *mut c_void, symbol: &str) -> Result<*mut c_void, Error> {
-    let module = handle as HMODULE;
-    let string = CString::new(symbol).unwrap();
-    let result = unsafe { kernel32::GetProcAddress(module, string.as_ptr()) };
-    
-    if result.is_null() {
-        let error = last_error_string().unwrap_or_else(|| "Unknown Error".into());
-        Err(Error::SymbolLoadError(error))
-    } else {
-        Ok(result as *mut c_void)
+/// Adds the flags which stop the system showing a dialog box for a failed
+/// load to the calling thread's error mode, restoring the mode it replaced
+/// when dropped. The mode is per thread, so no other thread's is touched.
+struct ErrorMode {
+    previous: Option<DWORD>
+}
+
+impl ErrorMode {
+    fn quiet() -> ErrorMode {
+        let quiet = win32::SEM_FAILCRITICALERRORS | win32::SEM_NOOPENFILEERRORBOX;
+        let current = unsafe { win32::GetThreadErrorMode() };
+
+        // A mode which already has the flags is left alone
+        if current & quiet == quiet {
+            return ErrorMode { previous: None };
+        }
+
+        let mut previous = 0;
+        match unsafe { win32::SetThreadErrorMode(current | quiet, &mut previous) } {
+            0 => ErrorMode { previous: None },
+            _ => ErrorMode { previous: Some(previous) }
+        }
+    }
+}
+
+impl Drop for ErrorMode {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous {
+            unsafe { win32::SetThreadErrorMode(previous, ptr::null_mut()) };
+        }
+    }
+}
+
+/// Takes another reference to a loaded module, which `GetModuleHandleExW`
+/// finds from any address inside it, including the handle itself, as that
+/// is the address the module is mapped at.
+pub fn add_reference(handle: NonNull<c_void>) -> Result<NonNull<c_void>, Error> {
+    let mut module: HMODULE = ptr::null_mut();
+    match unsafe { win32::GetModuleHandleExW(GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS, handle.as_ptr() as LPCWSTR, &mut module) } {
+        0 => Err(Error::LibraryLoadError(last_failure())),
+        _ => Ok(NonNull::new(module as *mut c_void).expect("module handle is null"))
+    }
+}
+
+/// Returns a new reference to a module which is already loaded, found by its
+/// file name or path as `LoadLibrary` would, without loading it if it is not.
+pub fn open_existing<P>(path: P) -> Result<NonNull<c_void>, Error> where P: AsRef<Path> {
+    let path_string = name::wide_path(path.as_ref())?;
+    let mut module: HMODULE = ptr::null_mut();
+
+    match unsafe { win32::GetModuleHandleExW(0, path_string.as_ptr(), &mut module) } {
+        0 => Err(Error::LibraryLoadError(last_failure())),
+        _ => Ok(NonNull::new(module as *mut c_void).expect("module handle is null"))
+    }
+}
+
+/// Every module shares the process's one namespace.
+pub fn namespace_id(_handle: NonNull<c_void>) -> Result<i64, Error> {
+    Err(Error::Unsupported)
+}
+
+/// The loader keeps the full path of every module it loads, which is found
+/// with `GetModuleFileNameW`.
+pub fn library_path(handle: NonNull<c_void>) -> Result<PathBuf, Error> {
+    module_path(handle.as_ptr() as HMODULE).ok_or_else(|| Error::LibraryLoadError(last_failure()))
+}
+
+/// Returns the handle of the executable, which only finds its own exports.
+pub fn process_handle() -> Result<NonNull<c_void>, Error> {
+    let module = unsafe { win32::GetModuleHandleW(ptr::null()) };
+
+    NonNull::new(module as *mut c_void).ok_or_else(|| Error::LibraryLoadError(last_failure()))
+}
+
+pub unsafe fn load_symbol(handle: NonNull<c_void>, symbol: &CStr) -> Result<NonNull<c_void>, Error> {
+    match unsafe { find_export(handle, symbol) } {
+        Some(result) => Ok(result),
+
+        // Exported names come from C identifiers, so a name which is not
+        // UTF-8 has no decorated form
+        #[cfg(target_arch = "x86")]
+        None => {
+            let mut failure = last_failure();
+            let symbol = match symbol.to_str() {
+                Ok(symbol) => symbol,
+                Err(_) => return Err(Error::SymbolLoadError(failure))
+            };
+
+            match unsafe { decorations(handle, symbol) } {
+                Decorations::Unique(name) => if let Some(result) = unsafe { find_export(handle, &CString::new(name).unwrap()) } {
+                    return Ok(result);
+                },
+
+                Decorations::Ambiguous(names) => {
+                    failure.message = format!("{} (decorated exports {} all match)", failure.message, names.join(", "));
+                },
+
+                Decorations::None => ()
+            }
+
+            Err(Error::SymbolLoadError(failure))
+        },
+
+        #[cfg(not(target_arch = "x86"))]
+        None => Err(Error::SymbolLoadError(last_failure()))
+    }
+}
+
+/// An ordinal is passed to `GetProcAddress` in place of the name, as the low
+/// word of the pointer with the rest zero, as `MAKEINTRESOURCE` does.
+pub unsafe fn load_symbol_ordinal(handle: NonNull<c_void>, ordinal: u16) -> Result<NonNull<c_void>, Error> {
+    let module = handle.as_ptr() as HMODULE;
+    let result = unsafe { win32::GetProcAddress(module, ordinal as usize as *const c_char) };
+
+    NonNull::new(result as *mut c_void).ok_or_else(|| Error::SymbolLoadError(last_failure()))
+}
+
+/// A failed lookup sets the thread's last error code, which is never
+/// formatted here.
+pub unsafe fn has_symbol(handle: NonNull<c_void>, symbol: &CStr) -> bool {
+    unsafe { find_export(handle, symbol) }.is_some() || symbol.to_str().ok().and_then(|symbol| unsafe { decorated_name(handle, symbol) }).is_some()
+}
+
+/// Windows has no global scope, so each module of the process is tried in
+/// the order they were loaded, the executable first. A module unloaded while
+/// the list is walked just fails to export the symbol.
+pub unsafe fn global_symbol(symbol: &CStr) -> Result<NonNull<c_void>, Error> {
+    let found = process_modules().into_iter()
+        .filter_map(|module| NonNull::new(module as *mut c_void))
+        .find_map(|module| unsafe { find_export(module, symbol) });
+
+    found.ok_or_else(|| Error::SymbolLoadError(Failure::new("symbol not found in any loaded module", Some(win32::ERROR_PROC_NOT_FOUND as i32), Class::NotFound)))
+}
+
+/// Windows has no symbol versions.
+pub unsafe fn load_symbol_version(_handle: NonNull<c_void>, _symbol: &CStr, _version: &CStr) -> Result<NonNull<c_void>, Error> {
+    Err(Error::SymbolLoadError(Failure::new("symbol versions are not supported on this platform", None, Class::Unsupported)))
+}
+
+/// Returns the decorated name a lookup for the symbol falls back to, if it
+/// is not exported under its own name. Only 32-bit libraries decorate names.
+#[cfg(target_arch = "x86")]
+pub unsafe fn decorated_name(handle: NonNull<c_void>, symbol: &str) -> Option<String> {
+    if unsafe { find_export(handle, &CString::new(symbol).ok()?) }.is_some() {
+        return None;
+    }
+
+    match unsafe { decorations(handle, symbol) } {
+        Decorations::Unique(name) => Some(name),
+        _ => None
+    }
+}
+
+#[cfg(not(target_arch = "x86"))]
+pub unsafe fn decorated_name(_handle: NonNull<c_void>, _symbol: &str) -> Option<String> {
+    None
+}
+
+unsafe fn find_export(handle: NonNull<c_void>, symbol: &CStr) -> Option<NonNull<c_void>> {
+    let module = handle.as_ptr() as HMODULE;
+    let result = unsafe { win32::GetProcAddress(module, symbol.as_ptr()) };
+
+    NonNull::new(result as *mut c_void)
+}
+
+#[cfg(target_arch = "x86")]
+enum Decorations {
+    None,
+    Unique(String),
+    Ambiguous(Vec<String>)
+}
+
+/// 32-bit `__cdecl`, `__stdcall` and `__fastcall` functions are often exported
+/// under decorated names, such as `_Frobnicate`, `_Frobnicate@8` or
+/// `@Frobnicate@8`. A leading underscore alone is preferred, and otherwise
+/// the export table is searched for a single name decorating the symbol with
+/// the size of its arguments.
+#[cfg(target_arch = "x86")]
+unsafe fn decorations(handle: NonNull<c_void>, symbol: &str) -> Decorations {
+    let exports = unsafe { exports(handle.as_ptr() as *const u8, usize::MAX) };
+
+    let underscored = format!("_{}", symbol);
+    if exports.contains(&underscored) {
+        return Decorations::Unique(underscored);
+    }
+
+    let mut matches = exports.into_iter()
+        .filter(|export| is_decoration(export, symbol))
+        .collect::<Vec<_>>();
+
+    match matches.len() {
+        0 => Decorations::None,
+        1 => Decorations::Unique(matches.remove(0)),
+        _ => Decorations::Ambiguous(matches)
+    }
+}
+
+/// Returns true if the export is `_symbol@N`, `symbol@N` or `@symbol@N`.
+#[cfg(target_arch = "x86")]
+fn is_decoration(export: &str, symbol: &str) -> bool {
+    let undecorated = [Some(export), export.strip_prefix('_'), export.strip_prefix('@')];
+
+    undecorated.iter()
+        .filter_map(|&name| name)
+        .filter_map(|name| name.strip_prefix(symbol))
+        .filter_map(|rest| rest.strip_prefix('@'))
+        .any(|size| !size.is_empty() && size.bytes().all(|byte| byte.is_ascii_digit()))
+}
+
+/// Read up to the given number of names in the export table of a module
+/// mapped into memory.
+unsafe fn exports(base: *const u8, limit: usize) -> Vec<String> {
+    match unsafe { ExportTable::new(base) } {
+        Some(table) => (0..table.count.min(limit)).map(|index| table.name(index).to_string_lossy().into_owned()).collect(),
+        None => Vec::new()
+    }
+}
+
+/// The module's own export table is read, rather than its file, which is
+/// already mapped and is the one the loader picked. A module without one
+/// has nothing to suggest.
+pub unsafe fn exported_names(handle: NonNull<c_void>, limit: usize) -> Option<Vec<String>> {
+    Some(unsafe { exports(handle.as_ptr() as *const u8, limit) })
+}
+
+/// The export table of a module mapped into memory, where the headers'
+/// addresses are all relative to its base.
+struct ExportTable {
+    base: *const u8,
+
+    // The range of the export directory, which a forwarded export's address
+    // points into
+    directory: usize,
+    size: usize,
+
+    count: usize
+}
+
+impl ExportTable {
+    /// The base must be that of a module mapped into memory, which stays
+    /// mapped for as long as the table is used.
+    unsafe fn new(base: *const u8) -> Option<ExportTable> {
+        let u16_at = |offset: usize| unsafe { ptr::read_unaligned(base.add(offset) as *const u16) };
+        let u32_at = |offset: usize| unsafe { ptr::read_unaligned(base.add(offset) as *const u32) } as usize;
+
+        // "MZ", then "PE\0\0" at the offset the DOS header gives
+        if u16_at(0) != 0x5a4d || u32_at(u32_at(0x3c)) != 0x4550 {
+            return None;
+        }
+
+        // The data directories follow the optional header's fixed fields,
+        // which are longer in a 64-bit image
+        let optional = u32_at(0x3c) + 24;
+        let directories = optional + if u16_at(optional) == 0x20b { 112 } else { 96 };
+
+        let directory = u32_at(directories);
+        if directory == 0 {
+            return None;
+        }
+
+        Some(ExportTable {
+            base,
+            directory,
+            size: u32_at(directories + 4),
+            count: u32_at(directory + 24)
+        })
+    }
+
+    fn u32_at(&self, offset: usize) -> usize {
+        unsafe { ptr::read_unaligned(self.base.add(offset) as *const u32) as usize }
+    }
+
+    /// Returns the name at the given index of the table's sorted names.
+    fn name(&self, index: usize) -> &CStr {
+        let names = self.u32_at(self.directory + 32);
+        unsafe { CStr::from_ptr(self.base.add(self.u32_at(names + index * 4)) as *const c_char) }
+    }
+
+    /// Finds a named export, returning its ordinal and where it leads.
+    fn find(&self, symbol: &CStr) -> Option<(u16, ExportKind)> {
+        let index = (0..self.count).find(|&index| self.name(index) == symbol)?;
+
+        // The name's index gives the function's index, which is its ordinal
+        // less the table's first ordinal
+        let ordinals = self.u32_at(self.directory + 36);
+        let function = unsafe { ptr::read_unaligned(self.base.add(ordinals + index * 2) as *const u16) } as usize;
+        let ordinal = (self.u32_at(self.directory + 16) + function) as u16;
+
+        let functions = self.u32_at(self.directory + 28);
+        let rva = self.u32_at(functions + function * 4);
+
+        // An address inside the export directory is the name of the export
+        // it is forwarded to, such as "NTDLL.RtlAllocateHeap"
+        let kind = if rva >= self.directory && rva < self.directory + self.size {
+            let target = unsafe { CStr::from_ptr(self.base.add(rva) as *const c_char) };
+            ExportKind::Forwarded { target: target.to_string_lossy().into_owned() }
+        } else {
+            ExportKind::Address { rva: rva as u32 }
+        };
+
+        Some((ordinal, kind))
+    }
+}
+
+/// Finds a symbol in the module's export table, as exported under that name.
+pub unsafe fn export_info(handle: NonNull<c_void>, symbol: &CStr) -> Option<(u16, ExportKind)> {
+    unsafe { ExportTable::new(handle.as_ptr() as *const u8) }?.find(symbol)
+}
+
+/// A `__declspec(thread)` variable cannot be exported from a DLL, so no symbol
+/// found by `GetProcAddress` is a thread-local variable.
+pub unsafe fn thread_local(_symbol: NonNull<c_void>) -> Result<NonNull<c_void>, Error> {
+    Err(Error::Unsupported)
+}
+
+/// Classify a `GetLastError()` value.
+pub fn classify(code: i32) -> Class {
+    match code as DWORD {
+        win32::ERROR_FILE_NOT_FOUND |
+        win32::ERROR_PATH_NOT_FOUND |
+        win32::ERROR_MOD_NOT_FOUND |
+        win32::ERROR_PROC_NOT_FOUND => Class::NotFound,
+        win32::ERROR_ACCESS_DENIED |
+        win32::ERROR_SHARING_VIOLATION => Class::PermissionDenied,
+        win32::ERROR_EXE_MACHINE_TYPE_MISMATCH => Class::WrongArchitecture,
+        win32::ERROR_BAD_EXE_FORMAT |
+        win32::ERROR_BAD_FORMAT |
+        win32::ERROR_INVALID_EXE_SIGNATURE |
+        win32::ERROR_FILE_CORRUPT |
+        // Returned for an empty file
+        win32::ERROR_FILE_INVALID => Class::BadImage,
+        win32::ERROR_NOT_SUPPORTED |
+        win32::ERROR_CALL_NOT_IMPLEMENTED => Class::Unsupported,
+        _ => Class::Other
+    }
+}
+
+const GET_MODULE_HANDLE_EX_FLAG_PIN: DWORD = 0x1;
+const GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT: DWORD = 0x2;
+const GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS: DWORD = 0x4;
+
+/// Pin a module by its handle, which is also its base address.
+pub fn pin(handle: NonNull<c_void>) -> Result<(), Error> {
+    let flags = GET_MODULE_HANDLE_EX_FLAG_PIN | GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS;
+    let mut module: HMODULE = ptr::null_mut();
+
+    match unsafe { win32::GetModuleHandleExW(flags, handle.as_ptr() as LPCWSTR, &mut module) } {
+        0 => Err(Error::LibraryLoadError(last_failure())),
+        _ => Ok(())
+    }
+}
+
+/// Find the module containing an address, then the nearest symbol before it
+/// from DbgHelp, which reads the module's exports and any program database
+/// beside it.
+pub fn resolve_address(address: *const c_void) -> Option<AddressInfo> {
+    let flags = GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS | GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT;
+    let mut module: HMODULE = ptr::null_mut();
+
+    if unsafe { win32::GetModuleHandleExW(flags, address as LPCWSTR, &mut module) } == 0 {
+        return None;
+    }
+
+    let path = module_path(module)?;
+    let symbol = dbghelp::symbol_from_address(address);
+
+    Some(AddressInfo::new(path, module as *const c_void, symbol, address))
+}
+
+/// A module unloaded before its path is found is skipped.
+pub fn loaded_libraries() -> Vec<LoadedLibrary> {
+    process_modules().into_iter()
+        .filter_map(|module| module_path(module).map(|path| LoadedLibrary::new(path, module as *const c_void)))
+        .collect()
+}
+
+/// List the process's modules, growing the buffer until every handle fits as
+/// modules can be loaded between calls.
+fn process_modules() -> Vec<HMODULE> {
+    let process = unsafe { win32::GetCurrentProcess() };
+    let mut modules: Vec<HMODULE> = vec![ptr::null_mut(); 256];
+
+    loop {
+        let size = (modules.len() * mem::size_of::<HMODULE>()) as DWORD;
+        let mut needed = 0;
+
+        if unsafe { win32::K32EnumProcessModulesEx(process, modules.as_mut_ptr(), size, &mut needed, win32::LIST_MODULES_ALL) } == 0 {
+            return Vec::new();
+        }
+
+        if needed <= size {
+            modules.truncate(needed as usize / mem::size_of::<HMODULE>());
+            return modules;
+        }
+
+        modules.resize(needed as usize / mem::size_of::<HMODULE>() + 16, ptr::null_mut());
+    }
+}
+
+/// Returns the path a module was loaded from, growing the buffer until the
+/// path fits, as `GetModuleFileNameW` truncates it silently.
+fn module_path(module: HMODULE) -> Option<PathBuf> {
+    let mut buffer = vec![0u16; 260];
+
+    loop {
+        let len = unsafe { win32::GetModuleFileNameW(module, buffer.as_mut_ptr(), buffer.len() as DWORD) } as usize;
+
+        match len {
+            0 => return None,
+            len if len < buffer.len() => return Some(PathBuf::from(OsString::from_wide(&buffer[..len]))),
+            _ => buffer.resize(buffer.len() * 2, 0)
+        }
+    }
+}
+
+mod dbghelp {
+    use super::*;
+
+    const SYMOPT_UNDNAME: DWORD = 0x2;
+    const SYMOPT_DEFERRED_LOADS: DWORD = 0x4;
+    const SYMOPT_FAIL_CRITICAL_ERRORS: DWORD = 0x200;
+
+    const MAX_NAME_LEN: usize = 256;
+
+    #[repr(C)]
+    struct SymbolInfo {
+        size_of_struct: u32,
+        type_index: u32,
+        reserved: [u64; 2],
+        index: u32,
+        size: u32,
+        mod_base: u64,
+        flags: u32,
+        value: u64,
+        address: u64,
+        register: u32,
+        scope: u32,
+        tag: u32,
+        name_len: u32,
+        max_name_len: u32,
+        name: [u16; 1]
+    }
+
+    // The name is written past the end of the struct, so it is allocated
+    // with room for the longest name asked for
+    #[repr(C)]
+    struct SymbolBuffer {
+        info: SymbolInfo,
+        name: [u16; MAX_NAME_LEN]
+    }
+
+    #[link(name = "dbghelp")]
+    extern "system" {
+        fn SymSetOptions(options: DWORD) -> DWORD;
+        fn SymInitializeW(process: win32::HANDLE, search_path: LPCWSTR, invade: win32::BOOL) -> win32::BOOL;
+        fn SymRefreshModuleList(process: win32::HANDLE) -> win32::BOOL;
+        fn SymFromAddrW(process: win32::HANDLE, address: u64, displacement: *mut u64, symbol: *mut SymbolInfo) -> win32::BOOL;
+    }
+
+    /// DbgHelp is not thread safe, so every call is made holding this, which
+    /// also records whether it has been initialised for the process.
+    static DBGHELP: Mutex<bool> = Mutex::new(false);
+
+    pub fn symbol_from_address(address: *const c_void) -> Option<(String, *const c_void)> {
+        let mut initialised = DBGHELP.lock().unwrap_or_else(|err| err.into_inner());
+        let process = unsafe { win32::GetCurrentProcess() };
+
+        unsafe {
+            // Modules loaded since the last lookup are only known once the
+            // list is refreshed
+            if *initialised {
+                SymRefreshModuleList(process);
+            } else {
+                SymSetOptions(SYMOPT_UNDNAME | SYMOPT_DEFERRED_LOADS | SYMOPT_FAIL_CRITICAL_ERRORS);
+                *initialised = SymInitializeW(process, ptr::null(), win32::TRUE) != 0;
+
+                if !*initialised {
+                    return None;
+                }
+            }
+
+            let mut buffer: SymbolBuffer = mem::zeroed();
+            buffer.info.size_of_struct = mem::size_of::<SymbolInfo>() as u32;
+            buffer.info.max_name_len = MAX_NAME_LEN as u32;
+
+            let mut displacement = 0;
+            if SymFromAddrW(process, address as u64, &mut displacement, &mut buffer as *mut SymbolBuffer as *mut SymbolInfo) == 0 {
+                return None;
+            }
+
+            let len = (buffer.info.name_len as usize).min(MAX_NAME_LEN);
+            let name = (&buffer as *const SymbolBuffer as *const u8).add(mem::offset_of!(SymbolInfo, name)) as *const u16;
+            let name = slice::from_raw_parts(name, len);
+
+            Some((String::from_utf16_lossy(name), buffer.info.address as usize as *const c_void))
+        }
     }
 }
 
-pub fn drop_library(handle: *mut c_void) {
-    unsafe { kernel32::FreeLibrary(handle as HMODULE) };
+/// A pinned module is not unloaded, but `FreeLibrary` still succeeds, so this
+/// only fails for a handle which is not a loaded module.
+pub fn drop_library(handle: NonNull<c_void>) -> Result<(), Error> {
+    match unsafe { win32::FreeLibrary(handle.as_ptr() as HMODULE) } {
+        0 => Err(Error::UnloadError(last_error().message().to_owned())),
+        _ => Ok(())
+    }
 }
 
 fn hresult_from_win32(win32: DWORD) -> HRESULT {
     if win32 as HRESULT <= 0 {
         win32 as HRESULT
     } else {
-        ((win32 & 0x0000FFFF) | ((winapi::FACILITY_WIN32 as DWORD) << 16) | 0x80000000) as HRESULT
+        ((win32 & 0x0000FFFF) | ((win32::FACILITY_WIN32 as DWORD) << 16) | 0x80000000) as HRESULT
     }
 }
 
+/// Format the system's message for an error, in the user's language, without
+/// the line break it ends with.
 fn hresult_to_string(hr: HRESULT) -> Option<String> {
     unsafe {
-        let mut buffer: *mut u8 = ptr::null_mut();
-        let num_chars = kernel32::FormatMessageA(
-            winapi::FORMAT_MESSAGE_ALLOCATE_BUFFER |
-            winapi::FORMAT_MESSAGE_FROM_SYSTEM |
-            winapi::FORMAT_MESSAGE_IGNORE_INSERTS,
+        let mut buffer: *mut u16 = ptr::null_mut();
+        let num_chars = win32::FormatMessageW(
+            win32::FORMAT_MESSAGE_ALLOCATE_BUFFER |
+            win32::FORMAT_MESSAGE_FROM_SYSTEM |
+            win32::FORMAT_MESSAGE_IGNORE_INSERTS,
             ptr::null_mut(),
             hr as DWORD,
             0, // unknown lang-id, use default
-            (&mut buffer) as *mut *mut u8 as *mut i8,
+            (&mut buffer) as *mut *mut u16 as *mut u16,
             0, // minimum buffer size
             ptr::null_mut(),
         );
         if num_chars == 0 {
             return None;
         }
-        
-        let bytes = slice::from_raw_parts(buffer, num_chars as usize);
-        let message = String::from_utf8_lossy(bytes).into_owned();
-        kernel32::LocalFree(buffer as *mut _);
-        
+
+        let chars = slice::from_raw_parts(buffer, num_chars as usize);
+        let message = String::from_utf16_lossy(chars).trim_end().to_owned();
+        win32::LocalFree(buffer as *mut _);
+
         Some(message)
     }
 }
 
-fn last_error_hr() -> HRESULT {
-    hresult_from_win32(unsafe { kernel32::GetLastError() })
+/// Returns the formatted message for `GetLastError()`, ending with the code
+/// itself so that it can be searched for whatever the system's language.
+pub fn last_error() -> OsError {
+    let code = unsafe { win32::GetLastError() };
+    let message = hresult_to_string(hresult_from_win32(code)).unwrap_or_else(|| "Unknown Error".into());
+
+    OsError::new(Some(code as i32), format!("{} (os error {})", message, code))
 }
 
-fn last_error_string() -> Option<String> {
-    hresult_to_string(last_error_hr())
+fn last_failure() -> Failure {
+    last_error().into_failure(Class::Other)
 }
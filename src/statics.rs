@@ -0,0 +1,136 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/statics.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! This module provides a registry of libraries whose functions are linked
+//! statically into the program, for platforms which cannot load dynamic
+//! libraries at runtime, or programs which choose not to. This requires the
+//! `static-registry` feature.
+//!
+//! When a library is loaded, whether by [`Snek::load`](../struct.Snek.html#method.load),
+//! [`load_library`](../fn.load_library.html) or a struct generated by the
+//! [`snek!`](../macro.snek!.html) macro, a library registered under exactly
+//! the same path is used instead of asking the platform to load one, and its
+//! symbols are looked up in the table it was registered with. Paths which
+//! have not been registered are loaded by the platform as usual, so that the
+//! same code works against either.
+//!
+//! # Example
+//! ```
+//! # #[macro_use] extern crate snek;
+//! # use std::os::raw::c_int;
+//! snek! {
+//!     Example {
+//!         add: (x: c_int, y: c_int) -> c_int
+//!     }
+//! }
+//!
+//! extern "C" fn add(x: c_int, y: c_int) -> c_int {
+//!     x + y
+//! }
+//!
+//! fn main() {
+//!     register_static_library! {
+//!         "libexample.so" {
+//!             add
+//!         }
+//!     }
+//!
+//!     let example = Example::load("libexample.so").unwrap();
+//!     assert_eq!(unsafe { example.add(3, 7) }, 10);
+//! }
+//! ```
+
+use ::Error;
+use ::failure::{Class, Failure};
+
+use std::ptr::NonNull;
+use std::path::Path;
+use std::sync::Mutex;
+use libc::c_void;
+
+struct Library {
+    name: String,
+    symbols: Vec<(String, usize)>
+}
+
+// Each registered library is leaked, so that its address can serve as the
+// handle to it for the rest of the program
+static LIBRARIES: Mutex<Vec<&'static Library>> = Mutex::new(Vec::new());
+
+/// Register a statically linked library under the given path, with the given
+/// symbol names and addresses. Loading exactly that path then returns this
+/// library rather than asking the platform to load one. This is usually used
+/// through the [`register_static_library!`](../macro.register_static_library!.html)
+/// macro.
+///
+/// Registering the same path again replaces the library for later loads,
+/// while libraries which have already been loaded keep the old symbols.
+pub fn register<S>(path: S, symbols: &[(&str, *const c_void)]) where S: Into<String> {
+    let library = Box::leak(Box::new(Library {
+        name: path.into(),
+        symbols: symbols.iter().map(|&(name, address)| (name.to_owned(), address as usize)).collect()
+    }));
+
+    lock().push(library);
+}
+
+/// Returns true if a library has been registered under the given path.
+pub fn is_registered<P>(path: P) -> bool where P: AsRef<Path> {
+    find(path.as_ref()).is_some()
+}
+
+pub(crate) fn find(path: &Path) -> Option<NonNull<c_void>> {
+    lock().iter()
+        .rev()
+        .find(|library| Path::new(&library.name) == path)
+        .map(|&library| NonNull::from(library).cast())
+}
+
+/// Look up a symbol in a registered library, or return `None` if the handle
+/// is not one of them.
+pub(crate) fn symbol(handle: NonNull<c_void>, name: &str) -> Option<Result<NonNull<c_void>, Error>> {
+    let library = registered(handle)?;
+
+    // A symbol registered with the null address is present, as a weak symbol
+    // which was never defined is in a loaded library
+    Some(match library.symbols.iter().find(|&(symbol, _)| symbol == name) {
+        Some(&(_, address)) => NonNull::new(address as *mut c_void).ok_or_else(|| Error::SymbolLoadError(Failure::new(
+            format!("{}: symbol has the null address: {}", library.name, name), None, Class::NullSymbol
+        ))),
+
+        None => Err(Error::SymbolLoadError(Failure::new(
+            format!("{}: undefined symbol: {}", library.name, name), None, Class::NotFound
+        )))
+    })
+}
+
+pub(crate) fn is_static(handle: NonNull<c_void>) -> bool {
+    registered(handle).is_some()
+}
+
+fn registered(handle: NonNull<c_void>) -> Option<&'static Library> {
+    lock().iter()
+        .find(|&&library| NonNull::from(library).cast() == handle)
+        .cloned()
+}
+
+fn lock() -> ::std::sync::MutexGuard<'static, Vec<&'static Library>> {
+    // The registry is never left half updated, so a panic elsewhere while it
+    // was locked does not matter
+    LIBRARIES.lock().unwrap_or_else(|err| err.into_inner())
+}
@@ -16,8 +16,14 @@
 //  limitations under the License.
 //////////////////////////////////////////////////////////////////////////////
 
-use std::ptr;
+use ::Snek;
+use ::snek::Library;
+
+use std::mem;
+use std::ptr::{self, NonNull};
+use std::sync::Arc;
 use std::marker::PhantomData;
+use std::ops::Deref;
 use libc::c_void;
 
 /// This provides an interface around a symbol loaded from a
@@ -31,18 +37,38 @@ pub struct Symbol<'a> {
     _life: PhantomData<&'a c_void>
 }
 
+// A symbol is only an address, which means the same on every thread of the
+// process, and is only used through the unsafe `with`, whose caller vouches
+// for what is there, including whether it can be used from another thread.
+unsafe impl<'a> Send for Symbol<'a> {}
+unsafe impl<'a> Sync for Symbol<'a> {}
+
 impl<'a> Symbol<'a> {
     /// Construct a new `Symbol` wrapping a symbol. This should not be used
     /// manually, however is public to allow access from the 
     /// [`snek!`](macro.snek!.html) macro.
     pub fn new(symbol: *mut c_void) -> Symbol<'a> {
         Symbol {
-            symbol: symbol,
+            symbol,
 
             _life: PhantomData
         }
     }
 
+    pub(crate) fn from_non_null(symbol: NonNull<c_void>) -> Symbol<'a> {
+        Symbol::new(symbol.as_ptr())
+    }
+
+    /// Returns the address of the symbol.
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.symbol
+    }
+
+    // Only used on symbols this crate looked up, which are never null
+    pub(crate) fn as_non_null(&self) -> NonNull<c_void> {
+        NonNull::new(self.symbol).expect("looked up symbol is null")
+    }
+
     /// Use the symbol as if it was a certain type. There is no way of checking
     /// that the symbol is of the specified type, so this function should be used
     /// with care.
@@ -57,21 +83,288 @@ impl<'a> Symbol<'a> {
     /// # extern crate snek;
     /// # use snek::Snek;
     /// # use libc::c_int;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
     /// # fn main() {
-    /// # match Snek::load("libexample.so") {
-    /// #    Ok(snek) => match snek.symbol("add") {
-    /// #        Ok(symbol) => {
-    /// let result: c_int =  unsafe { symbol.with(|add: extern fn(c_int, c_int) -> c_int| add(3, 7)) };
-    /// #       },
-    /// #       _ => ()
-    /// #   },
-    /// #   _ => ()
+    /// # let snek = Snek::load(fixture()).unwrap();
+    /// # let symbol = snek.symbol("add").unwrap();
+    /// let result: c_int = unsafe { symbol.with(|add: extern fn(c_int, c_int) -> c_int| add(3, 7)) };
+    /// assert_eq!(result, 10);
     /// # }
+    /// ```
+    pub unsafe fn with<F, T, U>(&self, f: F) -> U where F: Fn(T) -> U {
+        let value = ptr::read(&self.symbol as *const _ as *const T);
+        f(value)
+    }
+
+    /// Treat the symbol as a function of the given function pointer type,
+    /// returning a [`TypedSymbol`](struct.TypedSymbol.html) which can be
+    /// called directly, and is still tied to the lifetime of the library.
+    ///
+    /// A type which is not the size of a pointer, and so cannot be a function
+    /// pointer, fails to compile.
+    ///
+    /// # Safety
+    /// The symbol must be a function of the given type, as for
+    /// [`with`](#method.with).
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate libc;
+    /// # extern crate snek;
+    /// # use snek::Snek;
+    /// # use libc::c_int;
+    /// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+    /// # fn main() {
+    /// # let snek = Snek::load(fixture()).unwrap();
+    /// let add = unsafe { snek.symbol("add").unwrap().cast::<extern "C" fn(c_int, c_int) -> c_int>() };
+    /// assert_eq!((*add)(3, 7), 10);
     /// # }
+    /// ```
+    pub unsafe fn cast<F>(&self) -> TypedSymbol<'a, F> where F: Copy {
+        TypedSymbol::new(self.symbol)
+    }
+}
+
+/// A symbol treated as a function of a certain function pointer type,
+/// returned from [`Symbol::cast`](struct.Symbol.html#method.cast) and
+/// [`Snek::typed_symbol`](struct.Snek.html#method.typed_symbol).
+///
+/// This dereferences to the function pointer, so it can be called as though
+/// it was one, and is tied to the lifetime of the
+/// [`Snek`](struct.Snek.html) it was loaded from, as a `Symbol` is.
+///
+/// A type which is not the size of a pointer is rejected when compiled:
+///
+/// ```compile_fail
+/// # extern crate snek;
+/// # use snek::Snek;
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+/// # fn main() {
+/// # let snek = Snek::load(fixture()).unwrap();
+/// let add = unsafe { snek.symbol("add").unwrap().cast::<[usize; 2]>() };
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct TypedSymbol<'a, F> {
+    function: F,
+
+    _life: PhantomData<&'a c_void>
+}
+
+impl<'a, F> TypedSymbol<'a, F> where F: Copy {
+    /// The symbol must be a function of the given type.
+    pub(crate) unsafe fn new(symbol: *mut c_void) -> TypedSymbol<'a, F> {
+        let () = PointerSized::<F>::ASSERT;
+
+        TypedSymbol {
+            function: unsafe { mem::transmute_copy(&symbol) },
+
+            _life: PhantomData
+        }
+    }
+
+    /// Returns the function pointer, which is not tied to the lifetime of the
+    /// library, so must not be called once it is unloaded.
+    pub fn get(&self) -> F {
+        self.function
+    }
+}
+
+impl<'a, F> Deref for TypedSymbol<'a, F> {
+    type Target = F;
+
+    fn deref(&self) -> &F {
+        &self.function
+    }
+}
+
+/// Checked when a `TypedSymbol` is made, so that a type which cannot be a
+/// function pointer fails to compile rather than being read past its end.
+struct PointerSized<F>(PhantomData<F>);
+
+impl<F> PointerSized<F> {
+    const ASSERT: () = assert!(mem::size_of::<F>() == mem::size_of::<*mut c_void>(), "a typed symbol must be a function pointer type");
+}
+
+/// The address of a symbol loaded by [`load_symbol`](fn.load_symbol.html).
+///
+/// Unlike a [`Symbol`](struct.Symbol.html), this is not tied to the lifetime
+/// of the library it was loaded from, so must not be used after the library
+/// is unloaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RawSymbol(NonNull<c_void>);
+
+// As for Symbol
+unsafe impl Send for RawSymbol {}
+unsafe impl Sync for RawSymbol {}
+
+impl RawSymbol {
+    pub(crate) fn new(symbol: NonNull<c_void>) -> RawSymbol {
+        RawSymbol(symbol)
+    }
+
+    /// Returns the address of the symbol.
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.0.as_ptr()
+    }
+}
+
+/// Anything holding the address of a symbol, which can be called with the
+/// [`call_symbol!`](macro.call_symbol!.html) macro.
+#[doc(hidden)]
+pub trait SymbolAddress {
+    fn address(&self) -> *mut c_void;
+}
+
+impl<'a> SymbolAddress for Symbol<'a> {
+    fn address(&self) -> *mut c_void {
+        self.as_ptr()
+    }
+}
+
+impl SymbolAddress for OwnedSymbol {
+    fn address(&self) -> *mut c_void {
+        self.as_ptr()
+    }
+}
+
+impl SymbolAddress for SharedSymbol {
+    fn address(&self) -> *mut c_void {
+        self.as_ptr()
+    }
+}
+
+impl SymbolAddress for RawSymbol {
+    fn address(&self) -> *mut c_void {
+        self.as_ptr()
+    }
+}
+
+impl SymbolAddress for *mut c_void {
+    fn address(&self) -> *mut c_void {
+        *self
+    }
+}
+
+impl SymbolAddress for *const c_void {
+    fn address(&self) -> *mut c_void {
+        *self as *mut c_void
+    }
+}
+
+impl<S> SymbolAddress for &S where S: SymbolAddress + ?Sized {
+    fn address(&self) -> *mut c_void {
+        (**self).address()
+    }
+}
+
+/// Cast the address of a symbol to a function pointer type. This is used by
+/// the [`call_symbol!`](macro.call_symbol!.html) macro, which only passes
+/// function pointer types.
+///
+/// # Safety
+/// The symbol must be a function of the given type.
+///
+/// # Panics
+/// This panics if the address is null, or the type is not the size of a
+/// pointer.
+#[doc(hidden)]
+pub unsafe fn cast_function<F>(symbol: &dyn SymbolAddress) -> F where F: Copy {
+    let address = symbol.address();
+
+    assert!(mem::size_of::<F>() == mem::size_of::<*mut c_void>(), "function pointer is not the size of a pointer");
+    assert!(!address.is_null(), "symbol pointer is null");
+
+    mem::transmute_copy(&address)
+}
+
+/// A symbol which keeps the library it was loaded from loaded for as long as
+/// it exists, returned from [`Snek::owned_symbol`](struct.Snek.html#method.owned_symbol).
+///
+/// Unlike a [`Symbol`](struct.Symbol.html), this is not tied to the lifetime
+/// of the [`Snek`](struct.Snek.html) it was loaded from, so it can be stored
+/// in a struct or cache which outlives it. Each one, including each clone,
+/// holds a count on the library, which is only unloaded once the `Snek` and
+/// every `OwnedSymbol` loaded from it have been dropped.
+#[derive(Debug, Clone)]
+pub struct OwnedSymbol {
+    symbol: NonNull<c_void>,
+
+    // Only held to keep the library loaded
+    _library: Arc<Library>
+}
+
+// As for Symbol, and the library is counted atomically
+unsafe impl Send for OwnedSymbol {}
+unsafe impl Sync for OwnedSymbol {}
+
+impl OwnedSymbol {
+    pub(crate) fn new(symbol: NonNull<c_void>, library: Arc<Library>) -> OwnedSymbol {
+        OwnedSymbol {
+            symbol,
+
+            _library: library
+        }
+    }
+
+    /// Returns the address of the symbol.
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.symbol.as_ptr()
+    }
+
+    /// Use the symbol as if it was a certain type, as with
+    /// [`Symbol::with`](struct.Symbol.html#method.with).
+    ///
+    /// # Safety
+    /// When calling this function, ensure the type of the symbol is actually the
+    /// type you say it is.
     pub unsafe fn with<F, T, U>(&self, f: F) -> U where F: Fn(T) -> U {
         let value = ptr::read(&self.symbol as *const _ as *const T);
         f(value)
     }
 }
 
+/// A symbol which keeps a [`SharedSnek`](struct.SharedSnek.html) loaded for
+/// as long as it exists, returned from [`SharedSnek::symbol`](struct.SharedSnek.html#method.symbol).
+///
+/// This holds a clone of the `SharedSnek`'s count, as
+/// [`OwnedSymbol`](struct.OwnedSymbol.html) does of a `Snek`'s, so the
+/// library stays loaded even once every clone of the `SharedSnek` itself has
+/// been dropped.
+#[derive(Debug, Clone)]
+pub struct SharedSymbol {
+    symbol: NonNull<c_void>,
+
+    // Only held to keep the library loaded
+    _snek: Arc<Snek>
+}
+
+// As for Symbol, and the library is counted atomically
+unsafe impl Send for SharedSymbol {}
+unsafe impl Sync for SharedSymbol {}
+
+impl SharedSymbol {
+    pub(crate) fn new(symbol: NonNull<c_void>, snek: Arc<Snek>) -> SharedSymbol {
+        SharedSymbol {
+            symbol,
 
+            _snek: snek
+        }
+    }
+
+    /// Returns the address of the symbol.
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.symbol.as_ptr()
+    }
+
+    /// Use the symbol as if it was a certain type, as with
+    /// [`Symbol::with`](struct.Symbol.html#method.with).
+    ///
+    /// # Safety
+    /// When calling this function, ensure the type of the symbol is actually the
+    /// type you say it is.
+    pub unsafe fn with<F, T, U>(&self, f: F) -> U where F: Fn(T) -> U {
+        let value = ptr::read(&self.symbol as *const _ as *const T);
+        f(value)
+    }
+}
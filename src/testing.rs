@@ -0,0 +1,167 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/testing.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! This module provides a way to stand in for a dynamic library in tests,
+//! with a table of symbols which are usually `extern "C"` test doubles
+//! defined by the tests themselves. This requires the `testing` feature.
+//!
+//! A [`SymbolTable`](struct.SymbolTable.html) can be turned into a
+//! [`Snek`](../struct.Snek.html), or into a [`Handle`](../struct.Handle.html)
+//! for a struct generated by the [`snek!`](../macro.snek!.html) macro, which
+//! then look their symbols up in the table rather than asking the platform.
+//! Nothing is loaded or unloaded, so no library needs to be built.
+//!
+//! # Example
+//! ```
+//! # #[macro_use] extern crate snek;
+//! # use std::os::raw::c_int;
+//! # use snek::testing::SymbolTable;
+//! snek! {
+//!     Example {
+//!         add: (x: c_int, y: c_int) -> c_int
+//!     }
+//! }
+//!
+//! extern "C" fn fake_add(_x: c_int, _y: c_int) -> c_int {
+//!     42
+//! }
+//!
+//! fn main() {
+//!     let handle = SymbolTable::new()
+//!         .insert("add", fake_add as *mut _)
+//!         .handle();
+//!
+//!     let example = Example::from_handle(handle).unwrap();
+//!     assert_eq!(unsafe { example.add(3, 7) }, 42);
+//! }
+//! ```
+
+use ::{Error, Handle, Snek};
+use ::failure::{Class, Failure};
+
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use libc::c_void;
+
+/// A table of symbol names and addresses which stands in for a dynamic
+/// library. See the [module documentation](index.html) for more.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    symbols: Vec<(String, usize)>,
+
+    // Shared by every copy, so that lookups through a handle are counted here
+    lookups: Arc<AtomicUsize>
+}
+
+// The addresses of the tables behind every live handle, so that they can be
+// told apart from handles returned by the platform
+static TABLES: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+impl SymbolTable {
+    /// Construct an empty `SymbolTable`.
+    pub fn new() -> SymbolTable {
+        SymbolTable::default()
+    }
+
+    /// Add a symbol to the table, replacing any existing symbol with the same
+    /// name.
+    pub fn insert<S>(&mut self, name: S, address: *mut c_void) -> &mut SymbolTable where S: Into<String> {
+        let name = name.into();
+        self.symbols.retain(|(symbol, _)| *symbol != name);
+        self.symbols.push((name, address as usize));
+        self
+    }
+
+    /// Returns the number of times a symbol has been looked up in this table,
+    /// or in any handle or `Snek` made from it or from a clone of it, whether
+    /// the symbol was found or not, such as to check that a caller caches
+    /// what it finds.
+    pub fn lookups(&self) -> usize {
+        self.lookups.load(Ordering::SeqCst)
+    }
+
+    /// Returns a [`Handle`](../struct.Handle.html) which looks its symbols up
+    /// in a copy of this table, for use with [`load_symbol`](../fn.load_symbol.html)
+    /// or the `from_handle` function of a struct generated by the
+    /// [`snek!`](../macro.snek!.html) macro.
+    ///
+    /// Passing the handle to [`drop_library`](../fn.drop_library.html) frees
+    /// the copy rather than unloading anything.
+    pub fn handle(&self) -> Handle {
+        let table = Box::into_raw(Box::new(self.clone()));
+        lock().push(table as usize);
+
+        Handle::new(NonNull::new(table as *mut c_void).unwrap())
+    }
+
+    /// Returns a [`Snek`](../struct.Snek.html) which looks its symbols up in a
+    /// copy of this table.
+    pub fn snek(&self) -> Snek {
+        Snek::from_handle(self.handle(), "<symbol table>")
+    }
+}
+
+/// Look up a symbol in a table, or return `None` if the handle is not one.
+pub(crate) fn symbol(handle: NonNull<c_void>, name: &str) -> Option<Result<NonNull<c_void>, Error>> {
+    let tables = lock();
+    if !tables.contains(&(handle.as_ptr() as usize)) {
+        return None;
+    }
+
+    // Tables are only freed once removed from the list, which is locked
+    let table = unsafe { &*(handle.as_ptr() as *const SymbolTable) };
+    table.lookups.fetch_add(1, Ordering::SeqCst);
+
+    // A symbol inserted with the null address is present, as a weak symbol
+    // which was never defined is in a loaded library
+    Some(match table.symbols.iter().find(|&(symbol, _)| symbol == name) {
+        Some(&(_, address)) => NonNull::new(address as *mut c_void).ok_or_else(|| Error::SymbolLoadError(Failure::new(
+            format!("symbol table: symbol has the null address: {}", name), None, Class::NullSymbol
+        ))),
+
+        None => Err(Error::SymbolLoadError(Failure::new(
+            format!("symbol table: undefined symbol: {}", name), None, Class::NotFound
+        )))
+    })
+}
+
+/// Returns true if the handle is a table.
+pub(crate) fn is_table(handle: NonNull<c_void>) -> bool {
+    lock().contains(&(handle.as_ptr() as usize))
+}
+
+/// Free a table, returning false if the handle is not one.
+pub(crate) fn drop_table(handle: NonNull<c_void>) -> bool {
+    let mut tables = lock();
+    let address = handle.as_ptr() as usize;
+
+    match tables.iter().position(|&table| table == address) {
+        Some(index) => {
+            tables.swap_remove(index);
+            drop(unsafe { Box::from_raw(handle.as_ptr() as *mut SymbolTable) });
+            true
+        },
+
+        None => false
+    }
+}
+
+fn lock() -> ::std::sync::MutexGuard<'static, Vec<usize>> {
+    TABLES.lock().unwrap_or_else(|err| err.into_inner())
+}
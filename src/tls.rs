@@ -0,0 +1,76 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tls.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use ::{Error, Snek};
+
+use std::ptr;
+use std::marker::PhantomData;
+
+/// An accessor for a thread-local variable exported by a dynamic library,
+/// returned from [`Snek::tls_data`](struct.Snek.html#method.tls_data).
+///
+/// Each thread has its own instance of the variable, so rather than holding
+/// one address this looks the variable up again on every access, finding the
+/// instance belonging to the calling thread:
+///
+/// * On Linux, the BSDs and other platforms using ELF, `dlsym` returns the
+///   calling thread's instance, allocating it if the thread has not used the
+///   variable before.
+/// * On macOS and iOS, `dlsym` returns the variable's descriptor, whose thunk
+///   is called to find the calling thread's instance.
+/// * On Windows, a DLL cannot export a thread-local variable, so every access
+///   returns [`Error::Unsupported`](enum.Error.html).
+///
+/// Each access costs a symbol lookup, so code which uses the variable
+/// repeatedly from one thread should keep the pointer from
+/// [`as_ptr`](#method.as_ptr), which stays valid until that thread exits.
+#[derive(Debug)]
+pub struct TlsData<'a, T> {
+    snek: &'a Snek,
+    symbol: String,
+
+    _type: PhantomData<fn() -> T>
+}
+
+impl<'a, T> TlsData<'a, T> {
+    pub(crate) fn new(snek: &'a Snek, symbol: &str) -> TlsData<'a, T> {
+        TlsData {
+            snek,
+            symbol: symbol.into(),
+
+            _type: PhantomData
+        }
+    }
+
+    /// Returns the address of the calling thread's instance of the variable.
+    ///
+    /// If it cannot be found, this will return [`Error::SymbolLoadError`](enum.Error.html).
+    pub fn as_ptr(&self) -> Result<*mut T, Error> {
+        self.snek.thread_local(&self.symbol).map(|address| address.as_ptr() as *mut T)
+    }
+
+    /// Read the calling thread's instance of the variable.
+    pub fn read(&self) -> Result<T, Error> where T: Copy {
+        self.as_ptr().map(|address| unsafe { ptr::read(address) })
+    }
+
+    /// Write to the calling thread's instance of the variable.
+    pub fn write(&self, value: T) -> Result<(), Error> where T: Copy {
+        self.as_ptr().map(|address| unsafe { ptr::write(address, value) })
+    }
+}
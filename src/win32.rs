@@ -0,0 +1,95 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/win32.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// The few parts of the Windows API used by the crate, declared here rather
+// than taken from a bindings crate. Only kernel32 is linked for these; the
+// DbgHelp and version functions are declared beside their only users.
+
+#![allow(non_camel_case_types, clippy::upper_case_acronyms)]
+
+use libc::{c_char, c_void};
+
+pub type BOOL = i32;
+pub type DWORD = u32;
+pub type UINT = u32;
+pub type HRESULT = i32;
+pub type HANDLE = *mut c_void;
+pub type HMODULE = *mut HINSTANCE__;
+pub type HLOCAL = *mut c_void;
+pub type LPCSTR = *const c_char;
+pub type LPCWSTR = *const u16;
+pub type LPWSTR = *mut u16;
+pub type FARPROC = *const c_void;
+
+// A distinct type, as with STRICT defined, so that a module handle is only
+// passed where one is expected
+pub enum HINSTANCE__ {}
+
+pub const TRUE: BOOL = 1;
+
+pub const SEM_FAILCRITICALERRORS: DWORD = 0x0001;
+pub const SEM_NOOPENFILEERRORBOX: DWORD = 0x8000;
+
+pub const FORMAT_MESSAGE_ALLOCATE_BUFFER: DWORD = 0x0000_0100;
+pub const FORMAT_MESSAGE_IGNORE_INSERTS: DWORD = 0x0000_0200;
+pub const FORMAT_MESSAGE_FROM_SYSTEM: DWORD = 0x0000_1000;
+
+pub const FACILITY_WIN32: HRESULT = 7;
+
+pub const LIST_MODULES_ALL: DWORD = 0x03;
+
+pub const ERROR_FILE_NOT_FOUND: DWORD = 2;
+pub const ERROR_PATH_NOT_FOUND: DWORD = 3;
+pub const ERROR_ACCESS_DENIED: DWORD = 5;
+pub const ERROR_BAD_FORMAT: DWORD = 11;
+pub const ERROR_SHARING_VIOLATION: DWORD = 32;
+pub const ERROR_NOT_SUPPORTED: DWORD = 50;
+pub const ERROR_CALL_NOT_IMPLEMENTED: DWORD = 120;
+pub const ERROR_MOD_NOT_FOUND: DWORD = 126;
+pub const ERROR_PROC_NOT_FOUND: DWORD = 127;
+pub const ERROR_INVALID_EXE_SIGNATURE: DWORD = 191;
+pub const ERROR_BAD_EXE_FORMAT: DWORD = 193;
+pub const ERROR_EXE_MACHINE_TYPE_MISMATCH: DWORD = 216;
+pub const ERROR_FILE_INVALID: DWORD = 1006;
+pub const ERROR_FILE_CORRUPT: DWORD = 1392;
+
+#[link(name = "kernel32")]
+extern "system" {
+    pub fn LoadLibraryW(filename: LPCWSTR) -> HMODULE;
+    pub fn LoadLibraryExW(filename: LPCWSTR, file: HANDLE, flags: DWORD) -> HMODULE;
+    pub fn FreeLibrary(module: HMODULE) -> BOOL;
+    pub fn GetProcAddress(module: HMODULE, name: LPCSTR) -> FARPROC;
+
+    pub fn GetModuleHandleW(name: LPCWSTR) -> HMODULE;
+    pub fn GetModuleHandleExW(flags: DWORD, name: LPCWSTR, module: *mut HMODULE) -> BOOL;
+    pub fn GetModuleFileNameW(module: HMODULE, filename: LPWSTR, size: DWORD) -> DWORD;
+    pub fn K32EnumProcessModulesEx(process: HANDLE, modules: *mut HMODULE, size: DWORD, needed: *mut DWORD, filter: DWORD) -> BOOL;
+
+    pub fn GetDllDirectoryW(length: DWORD, buffer: LPWSTR) -> DWORD;
+    pub fn GetSystemDirectoryW(buffer: LPWSTR, size: UINT) -> UINT;
+    pub fn GetWindowsDirectoryW(buffer: LPWSTR, size: UINT) -> UINT;
+
+    pub fn GetThreadErrorMode() -> DWORD;
+    pub fn SetThreadErrorMode(mode: DWORD, previous: *mut DWORD) -> BOOL;
+
+    pub fn GetLastError() -> DWORD;
+    pub fn FormatMessageW(flags: DWORD, source: *const c_void, message: DWORD, language: DWORD, buffer: LPWSTR, size: DWORD, arguments: *mut c_void) -> DWORD;
+    pub fn LocalFree(memory: HLOCAL) -> HLOCAL;
+
+    pub fn GetCurrentProcess() -> HANDLE;
+}
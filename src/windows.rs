@@ -0,0 +1,515 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/windows.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! This module provides access to the directories Windows searches for the
+//! dependencies of a library, as a safer alternative to adding them to
+//! `PATH`, which every thread shares with no way to change it atomically, and
+//! which is inherited by child processes.
+//!
+//! A directory added with [`DllDirectory::add`](struct.DllDirectory.html#method.add)
+//! is only searched by loads which ask for it, such as with
+//! [`LoadFlags::search_user_dirs`](../struct.LoadFlags.html#method.search_user_dirs)
+//! or [`LoadFlags::search_default_dirs`](../struct.LoadFlags.html#method.search_default_dirs),
+//! or by every load once [`set_default_search_dirs`](fn.set_default_search_dirs.html)
+//! has been called.
+//!
+//! It also provides [`load_symbol_ordinal`](fn.load_symbol_ordinal.html), to
+//! find a symbol a library exports only by its ordinal, for a raw
+//! [`Handle`](../struct.Handle.html), [`file_version`](fn.file_version.html),
+//! to read the version resource of a library file, and the
+//! [`SymbolInfo`](struct.SymbolInfo.html) returned by
+//! [`Snek::symbol_info`](../struct.Snek.html#method.symbol_info), and
+//! [`DataFileLibrary`](struct.DataFileLibrary.html), to map a library for
+//! reading its resources without running it.
+//!
+//! The DLL directory functions first appeared in Windows 8, and in an update to Windows
+//! 7, and without them return [`Error::Unsupported`](../enum.Error.html).
+//!
+//! # Example
+//! ```
+//! # extern crate snek;
+//! # use snek::{LoadFlags, Snek};
+//! # use snek::windows::DllDirectory;
+//! # fn main() {
+//! let exe = std::env::current_exe().unwrap();
+//! let libs = exe.parent().unwrap().join("libs");
+//! # std::fs::create_dir_all(&libs).unwrap();
+//!
+//! let _libs = DllDirectory::add(&libs).unwrap();
+//! let plugin = Snek::load_with_flags("plugin.dll", LoadFlags::new().search_default_dirs());
+//! # }
+//! ```
+
+use ::{Error, Handle, RawSymbol};
+use ::failure::Class;
+use ::name;
+use ::snek::backend;
+use ::win32::{self, BOOL, DWORD, HMODULE, LPCWSTR};
+
+use std::fmt;
+use std::mem;
+use std::ptr::{self, NonNull};
+use std::path::Path;
+use std::ffi::OsStr;
+use std::os::windows::ffi::OsStrExt;
+use libc::c_void;
+
+/// `AddDllDirectory`, `RemoveDllDirectory` and `SetDefaultDllDirectories`,
+/// looked up when first needed as older versions of Windows lack them.
+struct Functions {
+    add: unsafe extern "system" fn(LPCWSTR) -> *mut c_void,
+    remove: unsafe extern "system" fn(*mut c_void) -> BOOL,
+    set_default: unsafe extern "system" fn(DWORD) -> BOOL
+}
+
+fn functions() -> Result<Functions, Error> {
+    let kernel32 = name::wide_path(Path::new("kernel32.dll"))?;
+    let module = unsafe { win32::GetModuleHandleW(kernel32.as_ptr()) };
+    if module.is_null() {
+        return Err(Error::Unsupported);
+    }
+
+    let find = |symbol: &[u8]| match unsafe { win32::GetProcAddress(module, symbol.as_ptr() as *const _) } {
+        address if address.is_null() => Err(Error::Unsupported),
+        address => Ok(address)
+    };
+
+    unsafe {
+        Ok(Functions {
+            add: mem::transmute::<*const c_void, unsafe extern "system" fn(LPCWSTR) -> *mut c_void>(find(b"AddDllDirectory\0")?),
+            remove: mem::transmute::<*const c_void, unsafe extern "system" fn(*mut c_void) -> BOOL>(find(b"RemoveDllDirectory\0")?),
+            set_default: mem::transmute::<*const c_void, unsafe extern "system" fn(DWORD) -> BOOL>(find(b"SetDefaultDllDirectories\0")?)
+        })
+    }
+}
+
+/// Attempt to load a symbol from a library by its ordinal, returning its
+/// address, as described for [`Snek::symbol_ordinal`](../struct.Snek.html#method.symbol_ordinal).
+///
+/// # Safety
+/// The library must still be loaded, as for [`load_symbol`](../fn.load_symbol.html).
+pub unsafe fn load_symbol_ordinal(handle: &Handle, ordinal: u16) -> Result<RawSymbol, Error> {
+    unsafe { backend::load_symbol_ordinal(handle.as_non_null(), ordinal) }.map(RawSymbol::new)
+}
+
+fn last_failure(path: Option<&Path>) -> Error {
+    let failure = ::snek::last_error().into_failure(Class::Other);
+    let failure = match path {
+        Some(path) => failure.with_path(path),
+        None => failure
+    };
+
+    Error::LibraryLoadError(failure)
+}
+
+/// Make every library loaded by the process search only the directory of
+/// the executable, the system directory, and the directories added with
+/// [`DllDirectory::add`](struct.DllDirectory.html#method.add), rather than
+/// the legacy search order which includes the current directory and `PATH`,
+/// with `SetDefaultDllDirectories(LOAD_LIBRARY_SEARCH_DEFAULT_DIRS)`.
+///
+/// This affects every load made by the process from then on, including those
+/// made by other libraries, and cannot be undone, so is best called once at
+/// startup. Calling it again has no further effect.
+pub fn set_default_search_dirs() -> Result<(), Error> {
+    const LOAD_LIBRARY_SEARCH_DEFAULT_DIRS: DWORD = 0x1000;
+
+    let functions = functions()?;
+    match unsafe { (functions.set_default)(LOAD_LIBRARY_SEARCH_DEFAULT_DIRS) } {
+        0 => Err(last_failure(None)),
+        _ => Ok(())
+    }
+}
+
+/// Directories added to those Windows searches for the dependencies of a
+/// library, as described for the [module](index.html).
+#[derive(Debug)]
+pub struct DllDirectory {
+    _private: ()
+}
+
+impl DllDirectory {
+    /// Add a directory to those searched by loads which ask for the user
+    /// directories, with `AddDllDirectory`, until the returned guard is
+    /// dropped.
+    ///
+    /// The directory must be an absolute path, or this will return
+    /// [`Error::InvalidInput`](../enum.Error.html). If the system rejects it,
+    /// such as when it does not exist, this will return
+    /// [`Error::LibraryLoadError`](../enum.Error.html) with the system's
+    /// message.
+    pub fn add<P>(path: P) -> Result<DllDirectoryGuard, Error> where P: AsRef<Path> {
+        let path = path.as_ref();
+        if !path.is_absolute() {
+            return Err(Error::InvalidInput(format!("DLL directory {} is not an absolute path", path.display())));
+        }
+
+        let functions = functions()?;
+        let wide = name::wide_path(path)?;
+
+        match unsafe { (functions.add)(wide.as_ptr()) } {
+            cookie if cookie.is_null() => Err(last_failure(Some(path))),
+            cookie => Ok(DllDirectoryGuard {
+                cookie: cookie as usize,
+                remove: functions.remove
+            })
+        }
+    }
+}
+
+/// A directory added with [`DllDirectory::add`](struct.DllDirectory.html#method.add),
+/// which is removed from the search again when this is dropped.
+///
+/// The directories are shared by the whole process, so the guard can be sent
+/// to and dropped on any thread, and a directory added twice is searched
+/// until both guards are dropped.
+#[derive(Debug)]
+#[must_use = "the directory is removed again when the guard is dropped"]
+pub struct DllDirectoryGuard {
+    cookie: usize,
+    remove: unsafe extern "system" fn(*mut c_void) -> BOOL
+}
+
+impl Drop for DllDirectoryGuard {
+    fn drop(&mut self) {
+        unsafe { (self.remove)(self.cookie as *mut c_void) };
+    }
+}
+
+#[link(name = "version")]
+extern "system" {
+    fn GetFileVersionInfoSizeW(filename: LPCWSTR, handle: *mut DWORD) -> DWORD;
+    fn GetFileVersionInfoW(filename: LPCWSTR, handle: DWORD, len: DWORD, data: *mut c_void) -> BOOL;
+    fn VerQueryValueW(block: *const c_void, sub_block: LPCWSTR, buffer: *mut *mut c_void, len: *mut u32) -> BOOL;
+}
+
+/// The fixed part of a version resource, `VS_FIXEDFILEINFO`.
+#[repr(C)]
+struct FixedFileInfo {
+    signature: u32,
+    struct_version: u32,
+    file_version_ms: u32,
+    file_version_ls: u32,
+    product_version_ms: u32,
+    product_version_ls: u32,
+    file_flags_mask: u32,
+    file_flags: u32,
+    file_os: u32,
+    file_type: u32,
+    file_subtype: u32,
+    file_date_ms: u32,
+    file_date_ls: u32
+}
+
+const FIXED_FILE_INFO_SIGNATURE: u32 = 0xfeef_04bd;
+
+const ERROR_RESOURCE_DATA_NOT_FOUND: i32 = 1812;
+const ERROR_RESOURCE_TYPE_NOT_FOUND: i32 = 1813;
+
+/// The version of a library file, read from its `VERSIONINFO` resource by
+/// [`file_version`](fn.file_version.html) or
+/// [`Snek::file_version`](../struct.Snek.html#method.file_version).
+///
+/// The numeric versions are those of the fixed part of the resource, in the
+/// order major, minor, build, revision. The strings are taken from the
+/// resource's first translation, and are `None` if it lacks them.
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::Snek;
+/// # fn main() {
+/// let kernel32 = Snek::load("kernel32.dll").unwrap();
+/// let version = kernel32.file_version().unwrap().unwrap();
+///
+/// println!("{} {}", version.product_name().unwrap_or("kernel32"), version);
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileVersion {
+    file: [u16; 4],
+    product: [u16; 4],
+    product_name: Option<String>,
+    file_description: Option<String>
+}
+
+impl FileVersion {
+    /// Returns the file version, `FILEVERSION` in the resource script.
+    pub fn file(&self) -> [u16; 4] {
+        self.file
+    }
+
+    /// Returns the product version, `PRODUCTVERSION` in the resource script.
+    pub fn product(&self) -> [u16; 4] {
+        self.product
+    }
+
+    /// Returns the `ProductName` string.
+    pub fn product_name(&self) -> Option<&str> {
+        self.product_name.as_deref()
+    }
+
+    /// Returns the `FileDescription` string.
+    pub fn file_description(&self) -> Option<&str> {
+        self.file_description.as_deref()
+    }
+}
+
+/// Formats the file version, such as `10.0.19041.1`.
+impl fmt::Display for FileVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}.{}", self.file[0], self.file[1], self.file[2], self.file[3])
+    }
+}
+
+/// Read the version resource of the library file at the given path, with
+/// `GetFileVersionInfoW` and `VerQueryValueW`, returning `None` if it has
+/// none. The file is read, not loaded, so a bare file name is looked for as
+/// `LoadLibrary` would, and a loaded library is best found by
+/// [`Snek::file_version`](../struct.Snek.html#method.file_version).
+///
+/// If the file cannot be read, or its resource is malformed, this will
+/// return [`Error::InspectError`](../enum.Error.html) with the system's
+/// message.
+pub fn file_version<P>(path: P) -> Result<Option<FileVersion>, Error> where P: AsRef<Path> {
+    let path = path.as_ref();
+    let wide = name::wide_path(&name::extended_path(path))?;
+    let failed = || Error::InspectError(format!("{}: {}", path.display(), ::snek::last_error()));
+
+    let mut handle = 0;
+    let len = unsafe { GetFileVersionInfoSizeW(wide.as_ptr(), &mut handle) };
+    if len == 0 {
+        return match ::snek::last_error().code() {
+            Some(ERROR_RESOURCE_DATA_NOT_FOUND) | Some(ERROR_RESOURCE_TYPE_NOT_FOUND) => Ok(None),
+            _ => Err(failed())
+        };
+    }
+
+    let mut data = vec![0u8; len as usize];
+    if unsafe { GetFileVersionInfoW(wide.as_ptr(), 0, len, data.as_mut_ptr() as *mut c_void) } == 0 {
+        return Err(failed());
+    }
+
+    let fixed = match unsafe { query(&data, "\\") } {
+        Some(fixed) if fixed.len() >= mem::size_of::<FixedFileInfo>() => unsafe { ptr::read_unaligned(fixed.as_ptr() as *const FixedFileInfo) },
+        _ => return Err(Error::InspectError(format!("{}: version resource has no fixed file info", path.display())))
+    };
+
+    if fixed.signature != FIXED_FILE_INFO_SIGNATURE {
+        return Err(Error::InspectError(format!("{}: version resource has an invalid signature {:#x}", path.display(), fixed.signature)));
+    }
+
+    let split = |ms: u32, ls: u32| [(ms >> 16) as u16, ms as u16, (ls >> 16) as u16, ls as u16];
+
+    // Each translation is a language and code page, naming the block its
+    // strings are in; without one, US English in Unicode is usual
+    let translation = unsafe { query(&data, "\\VarFileInfo\\Translation") }
+        .filter(|translation| translation.len() >= 4)
+        .map(|translation| (u16::from_le_bytes([translation[0], translation[1]]), u16::from_le_bytes([translation[2], translation[3]])))
+        .unwrap_or((0x0409, 0x04b0));
+
+    let string = |key: &str| {
+        let value = unsafe { query(&data, &format!("\\StringFileInfo\\{:04x}{:04x}\\{}", translation.0, translation.1, key)) }?;
+        let units = value.chunks(2).map(|unit| u16::from_le_bytes([unit[0], *unit.get(1).unwrap_or(&0)])).take_while(|&unit| unit != 0).collect::<Vec<_>>();
+
+        Some(String::from_utf16_lossy(&units)).filter(|value| !value.is_empty())
+    };
+
+    Ok(Some(FileVersion {
+        file: split(fixed.file_version_ms, fixed.file_version_ls),
+        product: split(fixed.product_version_ms, fixed.product_version_ls),
+        product_name: string("ProductName"),
+        file_description: string("FileDescription")
+    }))
+}
+
+/// Returns the data of a version resource from the start of a value to the
+/// end, as the length `VerQueryValueW` gives is in bytes for some values and
+/// characters for others.
+unsafe fn query<'a>(data: &'a [u8], sub_block: &str) -> Option<&'a [u8]> {
+    let sub_block = OsStr::new(sub_block).encode_wide().chain(Some(0)).collect::<Vec<_>>();
+    let mut value = ptr::null_mut();
+    let mut len = 0;
+
+    if unsafe { VerQueryValueW(data.as_ptr() as *const c_void, sub_block.as_ptr(), &mut value, &mut len) } == 0 || value.is_null() {
+        return None;
+    }
+
+    let start = (value as usize).checked_sub(data.as_ptr() as usize)?;
+    data.get(start..)
+}
+
+/// A symbol's entry in the export table of the library it was loaded from,
+/// returned from [`Snek::symbol_info`](../struct.Snek.html#method.symbol_info).
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::Snek;
+/// # use snek::windows::ExportKind;
+/// # fn main() {
+/// let kernel32 = Snek::load("kernel32.dll").unwrap();
+///
+/// match *kernel32.symbol_info("HeapAlloc").unwrap().kind() {
+///     ExportKind::Forwarded { ref target } => println!("HeapAlloc is forwarded to {}", target),
+///     ExportKind::Address { rva } => println!("HeapAlloc is defined at {:#x}", rva)
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolInfo {
+    address: *mut c_void,
+    ordinal: u16,
+    kind: ExportKind
+}
+
+/// Where an export leads, as part of a [`SymbolInfo`](struct.SymbolInfo.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportKind {
+    /// The symbol is defined by the library itself, at the given address
+    /// relative to its base.
+    Address {
+        rva: u32
+    },
+
+    /// The symbol is forwarded to an export of another library, named as
+    /// `library.symbol`, or `library.#ordinal`, which `GetProcAddress`
+    /// loads the library to resolve.
+    Forwarded {
+        target: String
+    }
+}
+
+impl SymbolInfo {
+    pub(crate) fn new(address: NonNull<c_void>, ordinal: u16, kind: ExportKind) -> SymbolInfo {
+        SymbolInfo {
+            address: address.as_ptr(),
+            ordinal,
+            kind
+        }
+    }
+
+    /// Returns the address the symbol resolves to, as returned by
+    /// [`Snek::symbol`](../struct.Snek.html#method.symbol), which for a
+    /// forwarded export is in the library it is forwarded to.
+    pub fn address(&self) -> *mut c_void {
+        self.address
+    }
+
+    /// Returns the ordinal the symbol is exported at.
+    pub fn ordinal(&self) -> u16 {
+        self.ordinal
+    }
+
+    /// Returns where the export leads.
+    pub fn kind(&self) -> &ExportKind {
+        &self.kind
+    }
+
+    /// Returns the export the symbol is forwarded to, if it is forwarded.
+    pub fn forwarded_to(&self) -> Option<&str> {
+        match self.kind {
+            ExportKind::Forwarded { ref target } => Some(target),
+            ExportKind::Address { .. } => None
+        }
+    }
+}
+
+/// A library mapped for reading its resources, such as a resource-only
+/// localisation library, without running any of its code, with
+/// `LoadLibraryExW(LOAD_LIBRARY_AS_DATAFILE | LOAD_LIBRARY_AS_IMAGE_RESOURCE)`.
+///
+/// The library is mapped as an image, so that the addresses in its headers
+/// are relative to its [`base`](#method.base), but none of its imports are
+/// resolved and its entry point is never called. `GetProcAddress` cannot
+/// find anything in such a mapping, so unlike a [`Snek`](../struct.Snek.html)
+/// this has no `symbol` method. The library is unmapped when this is
+/// dropped.
+///
+/// # Example
+/// ```
+/// # extern crate snek;
+/// # use snek::windows::DataFileLibrary;
+/// # include!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/common/fixture.rs"));
+/// # fn main() {
+/// # let resources = fixture();
+/// let library = DataFileLibrary::load(resources).unwrap();
+///
+/// // The DOS header's "MZ"
+/// assert_eq!(unsafe { *library.base() }, b'M');
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct DataFileLibrary {
+    // As returned by LoadLibraryExW, with its low bits marking it as a data
+    // file mapping rather than a module, which FreeLibrary expects back
+    handle: NonNull<c_void>,
+    size: usize
+}
+
+impl DataFileLibrary {
+    /// Map the library at the given path for reading its resources.
+    ///
+    /// If the file cannot be found or is not a valid image for any
+    /// architecture, this will return [`Error::LibraryLoadError`](../enum.Error.html).
+    /// The library can be built for a different architecture to the current
+    /// process, as no code is run.
+    pub fn load<P>(path: P) -> Result<DataFileLibrary, Error> where P: AsRef<Path> {
+        const LOAD_LIBRARY_AS_DATAFILE: DWORD = 0x2;
+        const LOAD_LIBRARY_AS_IMAGE_RESOURCE: DWORD = 0x20;
+
+        let path = path.as_ref();
+        let wide = name::wide_path(&name::extended_path(path))?;
+
+        let handle = unsafe { win32::LoadLibraryExW(wide.as_ptr(), ptr::null_mut(), LOAD_LIBRARY_AS_DATAFILE | LOAD_LIBRARY_AS_IMAGE_RESOURCE) };
+        let handle = NonNull::new(handle as *mut c_void).ok_or_else(|| last_failure(Some(path)))?;
+
+        // The mapping spans the image's size from its optional header, at the
+        // same offset in 32 and 64-bit images, as its headers were checked
+        // to be valid by the loader
+        let base = mapped_base(handle);
+        let size = unsafe {
+            let headers = ptr::read_unaligned(base.add(0x3c) as *const u32) as usize;
+            ptr::read_unaligned(base.add(headers + 24 + 56) as *const u32) as usize
+        };
+
+        Ok(DataFileLibrary { handle, size })
+    }
+
+    /// Returns the address the library is mapped at, where its DOS header
+    /// starts.
+    pub fn base(&self) -> *const u8 {
+        mapped_base(self.handle)
+    }
+
+    /// Returns the size of the mapping, which is the image's size as given by
+    /// its headers.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+fn mapped_base(handle: NonNull<c_void>) -> *const u8 {
+    (handle.as_ptr() as usize & !3) as *const u8
+}
+
+impl Drop for DataFileLibrary {
+    fn drop(&mut self) {
+        unsafe { win32::FreeLibrary(self.handle.as_ptr() as HMODULE) };
+    }
+}
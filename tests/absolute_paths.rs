@@ -0,0 +1,46 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/absolute_paths.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// The setting is process-wide, so this is kept apart from the other tests,
+// in a single test, to avoid it changing their loads while they run
+
+extern crate snek;
+
+mod common;
+
+use common::fixture;
+use snek::{LoadOptions, Snek};
+
+#[test]
+fn process_default_applies_to_every_load() {
+    let current = std::env::current_dir().unwrap();
+    let relative = fixture().strip_prefix(&current).unwrap().to_owned();
+
+    snek::set_require_absolute_paths(true);
+
+    let err = Snek::load(&relative).unwrap_err();
+    assert!(err.is_relative_path(), "{:?}", err);
+    assert!(Snek::load_name("snek_test_fixture").unwrap_err().is_relative_path());
+    assert!(Snek::load(fixture()).is_ok());
+
+    // Explicit options are not affected
+    assert!(LoadOptions::new().require_absolute_paths(false).load(&relative).is_ok());
+
+    snek::set_require_absolute_paths(false);
+    assert!(Snek::load(&relative).is_ok());
+}
@@ -0,0 +1,63 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/address.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+extern crate snek;
+
+mod common;
+
+use snek::Snek;
+
+use std::env;
+use std::fs;
+
+#[cfg(any(unix, windows))]
+#[test]
+fn fixture_symbol_is_resolved() {
+    let snek = Snek::load(common::fixture()).unwrap();
+    let add = snek.symbol("add").unwrap();
+
+    let info = snek::resolve_address(add.as_ptr()).unwrap();
+    assert_eq!(fs::canonicalize(info.path()).unwrap(), fs::canonicalize(common::fixture()).unwrap());
+    assert_eq!(info.symbol(), Some("add"));
+    assert_eq!(info.symbol_address(), Some(add.as_ptr() as *const _));
+    assert_eq!(info.offset(), 0);
+    assert!(info.to_string().ends_with("!add+0x0"), "{}", info);
+}
+
+#[cfg(any(unix, windows))]
+#[test]
+fn offset_is_from_the_nearest_symbol() {
+    let snek = Snek::load(common::fixture()).unwrap();
+    let add = snek.symbol("add").unwrap();
+
+    let info = snek::resolve_address((add.as_ptr() as *const u8).wrapping_add(1) as *const _).unwrap();
+    assert_eq!(info.symbol(), Some("add"));
+    assert_eq!(info.offset(), 1);
+}
+
+#[cfg(any(unix, windows))]
+#[test]
+fn executable_address_is_resolved() {
+    let info = snek::resolve_address(executable_address_is_resolved as *const _).unwrap();
+    assert_eq!(fs::canonicalize(info.path()).unwrap(), fs::canonicalize(env::current_exe().unwrap()).unwrap());
+}
+
+#[test]
+fn unmapped_address_is_not_resolved() {
+    assert_eq!(snek::resolve_address(8 as *const _), None);
+}
@@ -0,0 +1,70 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/allocations.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// This counts every allocation made by the test binary, so it is kept apart
+// from the other tests and holds a single test.
+
+extern crate snek;
+
+mod common;
+
+use snek::{LoadOptions, Snek};
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::ffi::CStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn allocations<F>(f: F) -> usize where F: FnOnce() {
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    f();
+    ALLOCATIONS.load(Ordering::SeqCst) - before
+}
+
+#[test]
+fn cstr_lookups_only_allocate_to_cache_names() {
+    let add = CStr::from_bytes_with_nul(b"add\0").unwrap();
+
+    // The first lookup copies the name into the cache, and later ones find it
+    // there without copying it again
+    let snek = Snek::load(common::fixture()).unwrap();
+    assert!(allocations(|| assert!(snek.symbol(add).is_ok())) > 0);
+    assert_eq!(allocations(|| assert!(snek.symbol(add).is_ok())), 0);
+
+    // Without the cache, nothing is copied at all
+    let uncached = LoadOptions::new().cache_symbols(false).load(common::fixture()).unwrap();
+    assert_eq!(allocations(|| assert!(uncached.symbol(add).is_ok())), 0);
+    assert_eq!(allocations(|| assert!(uncached.symbol(add).is_ok())), 0);
+}
@@ -0,0 +1,89 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/asynch.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// This is kept apart from the other tests, as any other test holding the
+// fixture open would stop it from being unloaded.
+
+#![cfg(feature = "tokio")]
+
+extern crate snek;
+extern crate tokio;
+
+mod common;
+
+use common::count;
+use snek::{asynch, Error, LoadOptions, Snek};
+
+use std::pin::Pin;
+use std::future::Future;
+use std::task::{Context, Waker};
+use tokio::runtime::{Builder, Runtime};
+
+fn runtime() -> Runtime {
+    Builder::new_multi_thread().build().unwrap()
+}
+
+#[test]
+fn loads_on_blocking_pool() {
+    let runtime = runtime();
+
+    let snek = runtime.block_on(asynch::load(common::fixture())).unwrap();
+    assert!(snek.symbol("add").is_ok());
+
+    let snek = runtime.block_on(asynch::load_with(common::fixture(), LoadOptions::new().diagnose(false))).unwrap();
+    assert!(snek.symbol("add").is_ok());
+}
+
+#[test]
+fn load_any_reports_every_candidate() {
+    let dir = common::scratch_dir("asynch-any");
+    let candidates = vec![dir.join("libone.so"), dir.join("libtwo.so")];
+
+    match runtime().block_on(asynch::load_any(&candidates)) {
+        Err(Error::CandidatesError(errors)) => assert_eq!(errors.paths(), candidates),
+        Err(err) => panic!("unexpected error {:?}", err),
+        Ok(_) => panic!("loaded a library which does not exist")
+    }
+}
+
+#[test]
+fn dropped_load_unloads_library() {
+    let path = common::fixture_v1();
+    let snek = Snek::load(&path).unwrap();
+    assert_eq!(count(&snek), 1);
+
+    // Dropping a load which has not been polled loads nothing
+    drop(asynch::load(&path));
+
+    // Polling once starts the load, which then finishes on the blocking pool
+    // after the future is dropped; dropping the runtime waits for it
+    let runtime = runtime();
+    {
+        let _guard = runtime.enter();
+        let mut load = asynch::load(&path);
+
+        let mut context = Context::from_waker(Waker::noop());
+        let _ = Pin::new(&mut load).poll(&mut context);
+    }
+
+    drop(runtime);
+    drop(snek);
+
+    let snek = Snek::load(&path).unwrap();
+    assert_eq!(count(&snek), 1);
+}
@@ -0,0 +1,86 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/common/fixture.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// This is also included by the doctests, so it must not rely on being part of
+// the integration tests, and should stay free of inner attributes.
+
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Once;
+
+static BUILD: Once = Once::new();
+
+/// Returns the path of the default build of the `snek-test-fixture` library,
+/// building it first if this is the first call.
+pub fn fixture() -> PathBuf {
+    build();
+    library_path("fixture", "snek_test_fixture")
+}
+
+/// Returns the path of the `v1` build of the `snek-test-fixture` library,
+/// which lacks the symbols added in later versions.
+pub fn fixture_v1() -> PathBuf {
+    build();
+    library_path("fixture-v1", "snek_test_fixture")
+}
+
+/// Returns the path of the `snek-test-consumer` library, which uses the `add`
+/// function from the fixture without defining it.
+pub fn fixture_consumer() -> PathBuf {
+    build();
+    library_path("fixture-consumer", "snek_test_consumer")
+}
+
+/// Returns the path of the `snek-test-ordinal` library, which on Windows
+/// exports `ordinal_add` at ordinal 42.
+pub fn fixture_ordinal() -> PathBuf {
+    build();
+    library_path("fixture-ordinal", "snek_test_ordinal")
+}
+
+fn build() {
+    BUILD.call_once(|| {
+        cargo_build("fixture", &["-p", "snek-test-fixture"]);
+        cargo_build("fixture-v1", &["-p", "snek-test-fixture", "--features", "snek-test-fixture/v1"]);
+        cargo_build("fixture-consumer", &["-p", "snek-test-consumer"]);
+        cargo_build("fixture-ordinal", &["-p", "snek-test-ordinal"]);
+    });
+}
+
+fn cargo_build(target_dir: &str, arguments: &[&str]) {
+    let cargo = option_env!("CARGO").unwrap_or("cargo");
+    let status = Command::new(cargo)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["build", "--quiet", "--target-dir"])
+        .arg(target_root().join(target_dir))
+        .args(arguments)
+        .status()
+        .expect("failed to run cargo to build the test fixture");
+
+    assert!(status.success(), "failed to build the test fixture");
+}
+
+fn target_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("target")
+}
+
+fn library_path(target_dir: &str, crate_name: &str) -> PathBuf {
+    let name = format!("{}{}{}", DLL_PREFIX, crate_name, DLL_SUFFIX);
+    target_root().join(target_dir).join("debug").join(name)
+}
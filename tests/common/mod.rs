@@ -0,0 +1,77 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/common/mod.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// Each test binary only uses some of these helpers
+#![allow(dead_code, unused_imports)]
+
+use snek::{OwnedSymbol, Snek};
+
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+mod fixture;
+
+pub use self::fixture::{fixture, fixture_consumer, fixture_ordinal, fixture_v1};
+
+/// An empty directory for a test to write files into, which is removed along
+/// with everything in it once dropped.
+pub struct ScratchDir(PathBuf);
+
+impl Deref for ScratchDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for ScratchDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        // A library still loaded from the directory cannot be removed on
+        // Windows, such as one which was pinned
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Returns an empty directory for the given test to write files into.
+pub fn scratch_dir(name: &str) -> ScratchDir {
+    let dir = std::env::temp_dir().join(format!("snek-tests-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+
+    fs::create_dir_all(&dir).unwrap();
+    ScratchDir(dir)
+}
+
+/// Calls the fixture's `count` function, which returns the number of times it
+/// has been called since the library was loaded.
+pub fn count(snek: &Snek) -> usize {
+    unsafe { snek.symbol("count").unwrap().with(|count: extern "C" fn() -> usize| count()) }
+}
+
+/// Calls the fixture's `count` function through a symbol which holds its own
+/// reference to the library.
+pub fn owned_count(symbol: &OwnedSymbol) -> usize {
+    unsafe { symbol.with(|count: extern "C" fn() -> usize| count()) }
+}
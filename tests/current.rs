@@ -0,0 +1,60 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/current.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+extern crate snek;
+
+use snek::Snek;
+
+use std::env;
+use std::fs;
+use std::os::raw::c_int;
+
+/// Exported from the test binary by the crate's build script, or on MSVC by
+/// the linker directive below, which only this test binary carries.
+#[no_mangle]
+pub extern "C" fn snek_current_answer() -> c_int {
+    42
+}
+
+#[cfg(all(windows, target_env = "msvc"))]
+#[link_section = ".drectve"]
+#[used]
+static EXPORT_ANSWER: [u8; 29] = *b" /EXPORT:snek_current_answer ";
+
+#[test]
+fn current_resolves_functions_of_the_executable() {
+    let current = Snek::current().unwrap();
+
+    let answer = current.symbol("snek_current_answer").unwrap();
+    assert_eq!(answer.as_ptr() as *const (), snek_current_answer as *const ());
+    assert_eq!(unsafe { answer.with(|answer: extern "C" fn() -> c_int| answer()) }, 42);
+
+    // The process's handle is never released, so another is just as usable
+    drop(current);
+    assert!(Snek::current().unwrap().symbol("snek_current_answer").is_ok());
+
+    let path = Snek::current().unwrap().path().unwrap();
+    assert_eq!(fs::canonicalize(path).unwrap(), fs::canonicalize(env::current_exe().unwrap()).unwrap());
+}
+
+// The C library is loaded with global visibility, so is searched too
+#[cfg(unix)]
+#[test]
+fn current_resolves_global_libraries() {
+    assert!(Snek::current().unwrap().symbol("strlen").is_ok());
+}
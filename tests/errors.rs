@@ -0,0 +1,450 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/errors.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+extern crate libc;
+extern crate snek;
+
+mod common;
+
+use common::{fixture, scratch_dir};
+use snek::{Error, ErrorKind, Errors, LoadOptions, Snek};
+
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::thread;
+
+#[cfg(unix)]
+use std::fs;
+
+#[cfg(target_os = "linux")]
+const SYSTEM_LIBRARY: &str = "libc.so.6";
+
+#[cfg(target_os = "macos")]
+const SYSTEM_LIBRARY: &str = "/usr/lib/libSystem.B.dylib";
+
+#[cfg(windows)]
+const SYSTEM_LIBRARY: &str = "kernel32.dll";
+
+#[test]
+fn missing_path_is_not_found() {
+    let scratch = scratch_dir("missing-path");
+    let err = Snek::load(scratch.join("libmissing.so")).unwrap_err();
+
+    assert!(err.is_not_found(), "{:?}", err);
+    assert!(!err.is_permission_denied() && !err.is_bad_image() && !err.is_wrong_architecture());
+}
+
+#[test]
+fn relative_paths_can_be_rejected() {
+    let current = std::env::current_dir().unwrap();
+    let relative = fixture().strip_prefix(&current).unwrap().to_owned();
+    assert!(LoadOptions::new().load(&relative).is_ok());
+
+    let err = LoadOptions::new().require_absolute_paths(true).load(&relative).unwrap_err();
+    assert!(err.is_relative_path(), "{:?}", err);
+    assert!(!err.is_not_found());
+
+    assert!(LoadOptions::new().require_absolute_paths(true).load(fixture()).is_ok());
+}
+
+#[cfg(any(target_os = "linux", windows))]
+#[test]
+fn bare_names_can_be_rejected() {
+    assert!(LoadOptions::new().load(SYSTEM_LIBRARY).is_ok());
+
+    let err = LoadOptions::new().require_absolute_paths(true).load(SYSTEM_LIBRARY).unwrap_err();
+    assert!(err.is_relative_path(), "{:?}", err);
+    assert!(format!("{:?}", err).contains("env::locate"), "{:?}", err);
+
+    let located = snek::env::locate(SYSTEM_LIBRARY);
+    assert!(LoadOptions::new().require_absolute_paths(true).load(&located[0]).is_ok());
+}
+
+#[cfg(unix)]
+#[test]
+fn last_os_error_reports_raw_failures() {
+    let handle = unsafe { libc::dlopen(b"libsnek-missing-library.so\0".as_ptr() as *const _, libc::RTLD_NOW) };
+    assert!(handle.is_null());
+
+    let error = snek::diagnostics::last_os_error();
+    assert!(error.message().contains("libsnek-missing-library.so"), "{:?}", error);
+    assert_eq!(error.to_string(), error.message());
+    assert_eq!(error.code(), None);
+
+    // Reading the error clears it
+    assert!(!snek::diagnostics::last_os_error().message().contains("libsnek-missing-library.so"));
+}
+
+#[test]
+fn errors_format_for_users() {
+    let err = Snek::load("libsnek-missing-library.so").unwrap_err();
+    let message = err.to_string();
+    assert!(message.starts_with("failed to load library: "), "{}", message);
+    assert!(!message.contains("LibraryLoadError"), "{}", message);
+
+    let snek = Snek::load(fixture()).unwrap();
+    let message = snek.symbol("snek_missing_symbol").unwrap_err().to_string();
+    assert!(message.starts_with("failed to load symbol: "), "{}", message);
+
+    let message = Snek::load_any(["libsnek-missing-a.so", "libsnek-missing-b.so"]).unwrap_err().to_string();
+    assert!(message.starts_with("failed to load library: none of 2 candidate paths could be loaded:\n    libsnek-missing-a.so: "), "{}", message);
+
+    assert_eq!(Error::Unsupported.to_string(), "dynamic libraries are not supported on this platform");
+    assert_eq!(Error::InspectError("truncated header".into()).to_string(), "failed to inspect library: truncated header");
+}
+
+#[test]
+fn errors_name_what_failed() {
+    let scratch = scratch_dir("named");
+    let path = scratch.join("libmissing.so");
+    let err = Snek::load(&path).unwrap_err();
+    assert_eq!(err.path(), Some(path.as_path()));
+    assert_eq!(err.symbol(), None);
+    assert_eq!(err.to_string().matches("libmissing.so").count(), 1, "{}", err);
+
+    let snek = Snek::load(fixture()).unwrap();
+    let err = snek.symbol("snek_missing_symbol").unwrap_err();
+    assert_eq!(err.symbol(), Some("snek_missing_symbol"));
+    assert_eq!(err.path(), None);
+    assert!(err.to_string().contains("snek_missing_symbol"), "{}", err);
+
+    let err = LoadOptions::new().init("fixture_init_fails").load(fixture()).unwrap_err();
+    assert_eq!(err.path(), Some(fixture().as_path()));
+}
+
+#[test]
+fn errors_convert_to_boxed_errors() {
+    fn load() -> Result<Snek, Box<dyn std::error::Error>> {
+        Ok(Snek::load("libsnek-missing-library.so")?)
+    }
+
+    let err = load().unwrap_err();
+    assert!(err.to_string().starts_with("failed to load library: "), "{}", err);
+    assert!(err.downcast_ref::<Error>().unwrap().is_not_found());
+}
+
+#[test]
+fn missing_path_has_os_code() {
+    let scratch = scratch_dir("os-code");
+    let err = Snek::load(scratch.join("libmissing.so")).unwrap_err();
+
+    #[cfg(unix)]
+    assert_eq!(err.os_code(), Some(libc::ENOENT), "{:?}", err);
+
+    // ERROR_MOD_NOT_FOUND
+    #[cfg(windows)]
+    {
+        assert_eq!(err.os_code(), Some(126), "{:?}", err);
+
+        let message = match err {
+            Error::LibraryLoadError(ref failure) => failure.message(),
+            _ => panic!("{:?}", err)
+        };
+
+        assert_eq!(message, message.trim_end());
+        assert!(message.ends_with("(os error 126)"), "{:?}", message);
+    }
+
+    let err = LoadOptions::new().diagnose(false).load(scratch.join("libmissing.so")).unwrap_err();
+    assert!(err.os_code().is_some(), "{:?}", err);
+}
+
+#[test]
+fn os_error_is_the_source() {
+    use std::error::Error as StdError;
+
+    let scratch = scratch_dir("source");
+    let err = Snek::load(scratch.join("libmissing.so")).unwrap_err();
+
+    let source = err.source().and_then(|source| source.downcast_ref::<std::io::Error>()).expect("no source");
+    assert_eq!(source.raw_os_error(), err.os_code());
+
+    // A symbol has no OS error behind it on unix
+    #[cfg(unix)]
+    assert!(Snek::load(fixture()).unwrap().symbol("snek_missing_symbol").unwrap_err().source().is_none());
+}
+
+#[test]
+fn missing_bare_name_is_not_found() {
+    let err = Snek::load("libsnek-missing-library.so").unwrap_err();
+    assert!(err.is_not_found(), "{:?}", err);
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos", windows))]
+#[test]
+fn missing_symbol_is_not_found() {
+    let snek = Snek::load(SYSTEM_LIBRARY).unwrap();
+    let err = snek.symbol("snek_missing_symbol").unwrap_err();
+
+    assert!(err.is_not_found(), "{:?}", err);
+}
+
+#[cfg(debug_assertions)]
+#[test]
+fn invalid_symbol_names_are_quoted() {
+    let snek = Snek::load(fixture()).unwrap();
+    let handle = snek::load_library(fixture()).unwrap();
+
+    let cases = [
+        ("", "invalid symbol name \"\": is empty"),
+        ("add\n", "invalid symbol name \"add\\n\": contains whitespace at byte 3"),
+        ("add ", "invalid symbol name \"add \": contains whitespace at byte 3"),
+        ("\u{430}dd", "invalid symbol name \"\\u{430}dd\": contains the non-ASCII character '\\u{430}' at byte 0")
+    ];
+
+    for &(name, message) in &cases {
+        match snek.symbol(name) {
+            Err(ref err @ Error::SymbolLoadError(_)) => {
+                assert!(err.is_invalid_name(), "{:?}", err);
+                assert!(!err.is_not_found(), "{:?}", err);
+
+                if let Error::SymbolLoadError(ref failure) = *err {
+                    assert_eq!(failure.message(), message);
+                }
+            },
+
+            other => panic!("unexpected result for {:?}: {:?}", name, other.map(|symbol| symbol.as_ptr()))
+        }
+
+        assert!(unsafe { snek::load_symbol(&handle, name) }.unwrap_err().is_invalid_name());
+    }
+
+    snek::drop_library(handle);
+}
+
+// Unlike the other checks, this is made in release builds too, as the name
+// cannot be passed to the platform at all
+#[test]
+fn interior_nul_is_rejected() {
+    let snek = Snek::load(fixture()).unwrap();
+
+    for name in &["foo\0bar", "add\0\0"] {
+        assert!(snek.symbol(name).unwrap_err().is_invalid_input(), "{:?}", name);
+        assert!(snek.symbol(name.as_bytes()).unwrap_err().is_invalid_input(), "{:?}", name);
+    }
+
+    let handle = snek::load_library(fixture()).unwrap();
+    let err = unsafe { snek::load_symbol(&handle, "foo\0bar") }.unwrap_err();
+    assert_eq!(err.to_string(), "invalid input: invalid symbol name \"foo\\u{0}bar\": contains a NUL byte at byte 3");
+    snek::drop_library(handle);
+
+    assert!(snek.symbol("add\0").is_ok());
+}
+
+#[test]
+fn interior_nul_in_path_is_rejected() {
+    let err = Snek::load("foo\0bar").unwrap_err();
+    assert!(err.is_invalid_input(), "{:?}", err);
+    assert_eq!(err.to_string(), "invalid input: invalid library path \"foo\\u{0}bar\": contains a NUL byte at byte 3");
+
+    assert!(snek::load_library("foo\0bar").unwrap_err().is_invalid_input());
+    assert!(LoadOptions::new().diagnose(false).load("foo\0bar").unwrap_err().is_invalid_input());
+    assert!(Snek::load_any(["foo\0bar", "bar\0foo"]).unwrap_err().is_invalid_input());
+}
+
+#[cfg(debug_assertions)]
+#[test]
+fn non_ascii_symbol_names_can_be_allowed() {
+    let snek = LoadOptions::new().ascii_symbols(false).load(fixture()).unwrap();
+    let err = snek.symbol("\u{430}dd").unwrap_err();
+
+    assert!(err.is_not_found(), "{:?}", err);
+    assert!(snek.symbol("add ").unwrap_err().is_invalid_name());
+}
+
+#[test]
+fn load_failures_have_kinds() {
+    let dir = scratch_dir("kinds");
+    let library = |name: &str| dir.join(format!("{}{}{}", DLL_PREFIX, name, DLL_SUFFIX));
+
+    std::fs::write(library("empty"), b"").unwrap();
+    std::fs::write(library("text"), b"this is not a library\n").unwrap();
+
+    let cases = [
+        (library("missing"), ErrorKind::NotFound),
+        (library("empty"), ErrorKind::NotALibrary),
+        (library("text"), ErrorKind::NotALibrary)
+    ];
+
+    // The platform's own error is enough without inspecting the file
+    for diagnose in &[true, false] {
+        for &(ref path, kind) in &cases {
+            let err = LoadOptions::new().diagnose(*diagnose).load(path).unwrap_err();
+            assert_eq!(err.kind(), kind, "{:?}", err);
+        }
+    }
+
+    let err = Snek::load_any(&[library("missing"), library("also-missing")]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    let err = Snek::load_any(&[library("missing"), library("text")]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Other);
+
+    assert_eq!(Snek::load("foo\0bar").unwrap_err().kind(), ErrorKind::InvalidInput);
+    assert_eq!(Error::Unsupported.kind(), ErrorKind::Unsupported);
+}
+
+#[test]
+fn errors_convert_to_io_errors() {
+    let scratch = scratch_dir("io");
+    let path = scratch.join("libmissing.so");
+    let err = std::io::Error::from(Snek::load(&path).unwrap_err());
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    assert!(err.to_string().starts_with("failed to load library"), "{}", err);
+
+    let inner = err.into_inner().unwrap().downcast::<Error>().unwrap();
+    assert_eq!(inner.path(), Some(path.as_path()));
+
+    let err = std::io::Error::from(Snek::load("foo\0bar").unwrap_err());
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(err.get_ref().unwrap().downcast_ref::<Error>().unwrap().is_invalid_input());
+
+    let snek = Snek::load(fixture()).unwrap();
+    let err = std::io::Error::from(snek.symbol("snek_missing_symbol").unwrap_err());
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+    let err = std::io::Error::from(Error::RegistryError("cycle".into()));
+    assert_eq!(err.kind(), std::io::ErrorKind::Other);
+    assert_eq!(err.to_string(), "library registry error: cycle");
+}
+
+#[cfg(unix)]
+#[test]
+fn empty_file_is_bad_image() {
+    let scratch = scratch_dir("empty");
+    let path = scratch.join("libempty.so");
+    fs::write(&path, b"").unwrap();
+
+    let err = Snek::load(&path).unwrap_err();
+    assert!(err.is_bad_image(), "{:?}", err);
+}
+
+#[cfg(unix)]
+#[test]
+fn unreadable_file_is_permission_denied() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let scratch = scratch_dir("unreadable");
+    let path = scratch.join("libunreadable.so");
+    fs::write(&path, b"\x7fELF").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o000)).unwrap();
+
+    let err = Snek::load(&path).unwrap_err();
+
+    // The permission check does not apply to root, which reads the file and
+    // finds it too short to be a library
+    if unsafe { libc::geteuid() } == 0 {
+        assert!(err.is_bad_image(), "{:?}", err);
+    } else {
+        assert!(err.is_permission_denied(), "{:?}", err);
+    }
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[test]
+fn foreign_library_is_wrong_architecture() {
+    let original = snek::env::locate(SYSTEM_LIBRARY).into_iter().next().unwrap();
+    let mut data = fs::read(original).unwrap();
+
+    // Rewrite e_machine as AArch64
+    data[0x12] = 183;
+    data[0x13] = 0;
+
+    let scratch = scratch_dir("foreign");
+    let path = scratch.join("libforeign.so");
+    fs::write(&path, data).unwrap();
+
+    let err = Snek::load(&path).unwrap_err();
+    assert!(err.is_wrong_architecture(), "{:?}", err);
+}
+
+#[cfg(windows)]
+#[test]
+fn missing_module_is_not_found() {
+    let err = Snek::load("snek-missing-module.dll").unwrap_err();
+    assert!(err.is_not_found(), "{:?}", err);
+}
+
+#[cfg(any(unix, windows))]
+#[test]
+fn platform_is_supported() {
+    assert!(snek::is_supported());
+}
+
+#[test]
+fn multiple_errors_are_listed_flat() {
+    let missing = |name: &str| Snek::load(format!("libsnek-missing-{}.so", name)).unwrap_err();
+
+    let mut inner = Errors::new(missing("b"));
+    inner.push(missing("c"));
+
+    let mut errors = Errors::new(missing("a"));
+    errors.push(Error::Multiple(inner));
+    errors.push(Snek::load_any(["libsnek-missing-d.so", "libsnek-missing-e.so"]).unwrap_err());
+
+    assert_eq!(errors.iter().count(), 4);
+    assert!(errors.first().to_string().contains("libsnek-missing-a.so"));
+
+    let err = Error::Multiple(errors);
+    assert!(err.is_not_found(), "{:?}", err);
+
+    let message = err.to_string();
+    let lines = message.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "4 errors occurred:");
+    assert_eq!(lines.len(), 7, "{}", message);
+    assert!(lines[1..].iter().all(|line| line.starts_with("    ")), "{}", message);
+    assert!(lines[5].starts_with("        libsnek-missing-d.so: "), "{}", message);
+
+    match err {
+        Error::Multiple(errors) => assert_eq!(errors.into_vec().len(), 4),
+        _ => unreachable!()
+    }
+
+    assert!(Errors::from_vec(Vec::new()).is_none());
+    assert_eq!(Errors::from_vec(vec![missing("a")]).unwrap().to_string(), missing("a").to_string());
+}
+
+#[test]
+fn concurrent_load_errors_are_not_mixed() {
+    let threads = (0..16).map(|i| thread::spawn(move || {
+        let name = format!("libsnek-missing-{}.so", i);
+        let message = match Snek::load(&name).unwrap_err() {
+            Error::LibraryLoadError(failure) => failure.message().to_owned(),
+            err => panic!("{:?}", err)
+        };
+
+        (name, message)
+    })).collect::<Vec<_>>();
+
+    for thread in threads {
+        let (name, message) = thread.join().unwrap();
+        assert!(!message.is_empty(), "{} reported nothing", name);
+
+        // The platform's own message names the library, so one taken from
+        // another thread's load would name the wrong one
+        #[cfg(unix)]
+        assert!(message.contains(&name), "{} reported {}", name, message);
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn unresolved_symbols_fail_the_load() {
+    // Nothing in this binary makes the fixture's add global for it
+    let err = Snek::load(common::fixture_consumer()).unwrap_err();
+    assert!(err.to_string().contains("undefined symbol: add"), "{}", err);
+}
@@ -0,0 +1,604 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/fixture.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+extern crate libc;
+#[macro_use]
+extern crate snek;
+
+mod common;
+
+use snek::{Error, Handle, LoadOptions, ResolverPosition, Snek};
+#[cfg(feature = "testing")]
+use snek::testing::SymbolTable;
+use libc::{c_char, c_int};
+
+use std::env;
+use std::ffi::{CStr, CString};
+use std::process::Command;
+#[cfg(feature = "testing")]
+use std::ptr;
+use std::thread;
+
+#[test]
+fn loads_fixture() {
+    assert!(Snek::load(common::fixture()).is_ok());
+}
+
+#[test]
+fn calls_function() {
+    let snek = Snek::load(common::fixture()).unwrap();
+    let add = snek.symbol("add").unwrap();
+
+    assert_eq!(unsafe { add.with(|add: extern "C" fn(c_int, c_int) -> c_int| add(3, 7)) }, 10);
+}
+
+#[test]
+fn reads_string() {
+    let snek = Snek::load(common::fixture()).unwrap();
+    let hello = snek.symbol("hello").unwrap();
+    let hello = unsafe { hello.with(|hello: extern "C" fn() -> *const c_char| CStr::from_ptr(hello())) };
+
+    assert_eq!(hello.to_str().unwrap(), "hello");
+}
+
+#[test]
+fn symbol_names_can_be_any_string_type() {
+    let snek = Snek::load(common::fixture()).unwrap();
+    let add = snek.symbol("add").unwrap().as_ptr();
+
+    let name = String::from("add");
+    assert_eq!(snek.symbol(&name).unwrap().as_ptr(), add);
+    assert_eq!(snek.symbol(name).unwrap().as_ptr(), add);
+    assert_eq!(snek.symbol(CString::new("add").unwrap()).unwrap().as_ptr(), add);
+    assert_eq!(snek.symbol(CStr::from_bytes_with_nul(b"add\0").unwrap()).unwrap().as_ptr(), add);
+    assert_eq!(snek.symbol(&b"add"[..]).unwrap().as_ptr(), add);
+    assert_eq!(snek.owned_symbol("add\0").unwrap().as_ptr(), add);
+}
+
+extern "C" fn subtract(a: c_int, b: c_int) -> c_int {
+    a - b
+}
+
+#[test]
+fn resolvers_are_consulted_around_the_library() {
+    let mut snek = Snek::load(common::fixture()).unwrap();
+    let library_add = snek.symbol("add").unwrap().as_ptr();
+
+    // A null address leaves the name to the rest of the chain
+    snek.push_resolver(ResolverPosition::Before, Box::new(|_| Some(std::ptr::null_mut())));
+    let before = snek.push_resolver(ResolverPosition::Before, Box::new(|name| match name {
+        "add" => Some(subtract as *mut _),
+        _ => None
+    }));
+    let after = snek.push_resolver(ResolverPosition::After, Box::new(|name| match name {
+        "add" | "subtract" | "hello" => Some(subtract as *mut _),
+        _ => None
+    }));
+
+    let add = snek.symbol("add").unwrap();
+    assert_eq!(unsafe { add.with(|add: extern "C" fn(c_int, c_int) -> c_int| add(3, 7)) }, -4);
+    assert_eq!(snek.resolved_by("add"), Some(before));
+
+    assert_eq!(snek.symbol("subtract").unwrap().as_ptr(), subtract as *mut _);
+    assert_eq!(snek.resolved_by("subtract"), Some(after));
+
+    // The library's own export wins over a fallback
+    assert_ne!(snek.symbol("hello").unwrap().as_ptr(), subtract as *mut _);
+    assert_eq!(snek.resolved_by("hello"), None);
+
+    assert!(snek.symbol("divide").unwrap_err().is_not_found());
+    assert!(format!("{:?}", snek).contains("\"subtract\""), "{:?}", snek);
+    assert_ne!(snek.owned_symbol("add").unwrap().as_ptr(), library_add);
+}
+
+#[test]
+fn call_symbol_accepts_every_symbol_type() {
+    let snek = Snek::load(common::fixture()).unwrap();
+
+    let symbol = snek.symbol("add").unwrap();
+    let owned = snek.owned_symbol("add").unwrap();
+    let handle = snek::load_library(common::fixture()).unwrap();
+    let raw = unsafe { snek::load_symbol(&handle, "add").unwrap() };
+    let address = symbol.as_ptr();
+
+    unsafe {
+        assert_eq!(call_symbol!(symbol, fn(c_int, c_int) -> c_int, (3, 7)), 10);
+        assert_eq!(call_symbol!(&owned, fn(c_int, c_int) -> c_int, (3, 7)), 10);
+        assert_eq!(call_symbol!(raw, extern "C" fn(c_int, c_int) -> c_int, (3, 7)), 10);
+        assert_eq!(call_symbol!(address, fn(c_int, c_int,) -> c_int, (3, 7,)), 10);
+        assert_eq!(call_symbol!(snek.symbol("version").unwrap(), fn() -> c_int, ()), 2);
+    }
+
+    snek::drop_library(handle);
+}
+
+#[test]
+#[should_panic(expected = "symbol pointer is null")]
+fn call_symbol_rejects_null() {
+    let address = std::ptr::null_mut::<libc::c_void>();
+    unsafe { call_symbol!(address, fn(), ()) }
+}
+
+#[test]
+fn reads_data_symbol() {
+    let snek = Snek::load(common::fixture()).unwrap();
+    let answer = snek.symbol("ANSWER").unwrap();
+
+    assert_eq!(unsafe { *(answer.as_ptr() as *const c_int) }, 42);
+}
+
+#[test]
+fn raw_handle_round_trips() {
+    let handle = snek::load_library(common::fixture()).unwrap();
+    let raw = handle.into_raw();
+
+    let handle = unsafe { Handle::from_raw(raw) }.unwrap();
+    let answer = unsafe { snek::load_symbol(&handle, "ANSWER") }.unwrap();
+
+    assert_eq!(unsafe { *(answer.as_ptr() as *const c_int) }, 42);
+    assert!(unsafe { Handle::from_raw(::std::ptr::null_mut()) }.is_none());
+
+    snek::drop_library(handle);
+}
+
+#[cfg(unix)]
+#[test]
+fn handle_is_the_platform_handle() {
+    let handle = snek::load_library(common::fixture()).unwrap();
+    let path = CString::new(common::fixture().to_str().unwrap()).unwrap();
+
+    // Opening an already loaded library returns the same handle
+    let existing = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_LAZY | libc::RTLD_NOLOAD) };
+    assert_eq!(existing, handle.as_raw());
+
+    let answer = unsafe { libc::dlsym(existing, b"ANSWER\0".as_ptr() as *const c_char) };
+    assert_eq!(answer, unsafe { snek::load_symbol(&handle, "ANSWER") }.unwrap().as_ptr() as *mut _);
+
+    unsafe { libc::dlclose(existing) };
+    snek::drop_library(handle);
+}
+
+#[test]
+fn versions_differ() {
+    let v1 = Snek::load(common::fixture_v1()).unwrap();
+    let v2 = Snek::load(common::fixture()).unwrap();
+
+    let version = |snek: &Snek| unsafe { snek.symbol("version").unwrap().with(|version: extern "C" fn() -> c_int| version()) };
+    assert_eq!(version(&v1), 1);
+    assert_eq!(version(&v2), 2);
+
+    assert!(v2.symbol("added_in_v2").is_ok());
+}
+
+#[test]
+fn missing_symbol_fails() {
+    let snek = Snek::load(common::fixture_v1()).unwrap();
+
+    match snek.symbol("added_in_v2") {
+        Err(ref err @ Error::SymbolLoadError(_)) => assert!(err.is_not_found()),
+        other => panic!("unexpected result {:?}", other.map(|symbol| symbol.as_ptr()))
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn null_symbol_is_not_missing() {
+    let snek = Snek::load(common::fixture()).unwrap();
+
+    // A failed lookup beforehand must not be mistaken for this one failing
+    assert!(snek.symbol("added_in_v3").is_err());
+
+    match snek.symbol("NULL_SYMBOL") {
+        Err(ref err) => assert!(err.is_null_symbol() && !err.is_not_found(), "{:?}", err),
+        Ok(symbol) => panic!("NULL_SYMBOL resolved to {:?}", symbol.as_ptr())
+    }
+
+    assert!(snek.nullable_symbol("NULL_SYMBOL").unwrap().is_none());
+    assert!(snek.nullable_symbol("ANSWER").unwrap().is_some());
+    assert!(snek.nullable_symbol("added_in_v3").unwrap_err().is_not_found());
+}
+
+#[test]
+fn typed_symbols_are_called_directly() {
+    let snek = Snek::load(common::fixture()).unwrap();
+
+    let add = unsafe { snek.symbol("add").unwrap().cast::<extern "C" fn(c_int, c_int) -> c_int>() };
+    assert_eq!((*add)(2, 3), 5);
+
+    let add = unsafe { snek.typed_symbol::<extern "C" fn(c_int, c_int) -> c_int, _>("add") }.unwrap();
+    assert_eq!((add.get())(4, 5), 9);
+
+    let err = unsafe { snek.typed_symbol::<extern "C" fn(), _>("added_in_v3") }.unwrap_err();
+    assert!(err.is_not_found(), "{:?}", err);
+}
+
+#[test]
+fn functions_are_loaded_as_bare_pointers() {
+    let snek = Snek::load(common::fixture()).unwrap();
+
+    let add: extern "C" fn(c_int, c_int) -> c_int = unsafe { snek.func("add") }.unwrap();
+    assert_eq!(add(2, 3), 5);
+
+    let multiply: extern "system" fn(c_int, c_int) -> c_int = unsafe { snek.func("multiply") }.unwrap();
+    assert_eq!(multiply(2, 3), 6);
+
+    let multiply = unsafe { snek.typed_symbol::<extern "system" fn(c_int, c_int) -> c_int, _>(CString::new("multiply").unwrap()) }.unwrap();
+    assert_eq!((*multiply)(4, 5), 20);
+
+    let err = unsafe { snek.func::<extern "C" fn(), _>("added_in_v3") }.unwrap_err();
+    assert!(err.is_not_found(), "{:?}", err);
+}
+
+#[test]
+fn symbols_are_loaded_together() {
+    let snek = Snek::load(common::fixture()).unwrap();
+
+    let symbols = snek.symbols(["ANSWER", "add"]).unwrap();
+    assert_eq!(symbols[0].as_ptr(), snek.symbol("ANSWER").unwrap().as_ptr());
+    assert_eq!(symbols[1].as_ptr(), snek.symbol("add").unwrap().as_ptr());
+
+    let map = snek.symbol_map(vec![String::from("add"), String::from("ANSWER")]).unwrap();
+    assert_eq!(map.len(), 2);
+    assert_eq!(map["add"].as_ptr(), symbols[1].as_ptr());
+
+    assert!(snek.symbols(Vec::<&str>::new()).unwrap().is_empty());
+    assert!(snek.symbol_map(Vec::<&str>::new()).unwrap().is_empty());
+}
+
+#[test]
+fn symbols_report_every_missing_name() {
+    let snek = Snek::load(common::fixture()).unwrap();
+
+    let err = snek.symbols(["plugin_start", "add", "plugin_stop"]).unwrap_err();
+    assert!(err.is_not_found(), "{:?}", err);
+
+    match err {
+        Error::Multiple(errors) => {
+            let missing = errors.iter().map(|err| match *err {
+                Error::SymbolLoadError(ref failure) => failure.symbol().unwrap().to_owned(),
+                ref err => panic!("unexpected error {:?}", err)
+            }).collect::<Vec<_>>();
+
+            assert_eq!(missing, ["plugin_start", "plugin_stop"]);
+        },
+
+        err => panic!("unexpected error {:?}", err)
+    }
+
+    assert!(snek.symbol_map(["add", "plugin_start"]).is_err());
+}
+
+#[test]
+fn has_symbol_probes_without_failing() {
+    let snek = Snek::load(common::fixture()).unwrap();
+
+    assert!(snek.has_symbol("add"));
+    assert!(snek.has_symbol(CString::new("ANSWER").unwrap()));
+    assert!(!snek.has_symbol("render_v2"));
+    assert!(!snek.has_symbol("add\0\0"));
+
+    // Once found, it is found in the cache
+    snek.symbol("add").unwrap();
+    assert!(snek.has_symbol("add"));
+
+    // Present, only at the null address, even after a failed lookup
+    #[cfg(target_os = "linux")]
+    {
+        assert!(!snek.has_symbol("added_in_v3"));
+        assert!(snek.has_symbol("NULL_SYMBOL"));
+    }
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn null_table_symbol_is_not_missing() {
+    let snek = SymbolTable::new().insert("NULL_SYMBOL", ptr::null_mut()).snek();
+
+    assert!(snek.has_symbol("NULL_SYMBOL"));
+    assert!(!snek.has_symbol("added_in_v3"));
+
+    match snek.symbol("NULL_SYMBOL") {
+        Err(ref err) => assert!(err.is_null_symbol() && !err.is_not_found(), "{:?}", err),
+        Ok(symbol) => panic!("NULL_SYMBOL resolved to {:?}", symbol.as_ptr())
+    }
+
+    assert!(snek.nullable_symbol("NULL_SYMBOL").unwrap().is_none());
+    assert!(snek.nullable_symbol("added_in_v3").unwrap_err().is_not_found());
+}
+
+#[cfg(feature = "suggestions")]
+#[test]
+fn missing_symbol_suggests_close_names() {
+    let snek = Snek::load(common::fixture()).unwrap();
+
+    match snek.symbol("helo") {
+        Err(Error::SymbolLoadError(failure)) => assert!(failure.message().ends_with("did you mean 'hello'?"), "{}", failure),
+        other => panic!("unexpected result {:?}", other.map(|symbol| symbol.as_ptr()))
+    }
+}
+
+// Read from the export table of the loaded module, which an export differing
+// only in case matches most closely
+#[cfg(all(windows, feature = "suggestions"))]
+#[test]
+fn missing_symbol_suggests_exports_of_the_module() {
+    let snek = Snek::load(common::fixture()).unwrap();
+
+    let err = snek.symbol("Add").unwrap_err();
+    assert_eq!(err.os_code(), Some(127));
+    assert!(err.to_string().contains("did you mean 'add'"), "{}", err);
+}
+
+#[test]
+fn plain_names_are_not_decorated() {
+    let snek = Snek::load(common::fixture()).unwrap();
+
+    assert_eq!(snek.decorated_name("add"), None);
+    assert_eq!(snek.decorated_name("does_not_exist"), None);
+}
+
+#[cfg(all(windows, target_arch = "x86"))]
+#[test]
+fn stdcall_name_resolves_through_decoration() {
+    let snek = Snek::load(common::fixture()).unwrap();
+
+    let result = unsafe { snek.symbol("add_stdcall").unwrap().with(|add: extern "C" fn(c_int, c_int) -> c_int| add(3, 7)) };
+    assert_eq!(result, 10);
+    assert_eq!(snek.decorated_name("add_stdcall"), Some("add_stdcall@8".into()));
+}
+
+#[test]
+fn prefixed_symbols_are_found() {
+    let snek = Snek::load(common::fixture()).unwrap();
+
+    let commands = snek.symbols_with_prefix("cmd_", true).unwrap();
+    let results = commands.iter()
+        .map(|(name, symbol)| (name.as_str(), unsafe { symbol.with(|command: extern "C" fn() -> c_int| command()) }))
+        .collect::<Vec<_>>();
+    assert_eq!(results, [("start", 1), ("status", 3), ("stop", 2)]);
+
+    let names = snek.symbols_with_prefix("cmd_st", false).unwrap().into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+    assert_eq!(names, ["cmd_start", "cmd_status", "cmd_stop"]);
+
+    assert!(snek.symbols_with_prefix("no_such_prefix_", false).unwrap().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn prefixed_symbols_need_the_library_file() {
+    let scratch = common::scratch_dir("prefixed-symbols");
+    let copy = scratch.join(common::fixture().file_name().unwrap());
+    std::fs::copy(common::fixture(), &copy).unwrap();
+
+    let snek = Snek::load(&copy).unwrap();
+    std::fs::remove_file(&copy).unwrap();
+
+    match snek.symbols_with_prefix("cmd_", true) {
+        Err(Error::InspectError(_)) => (),
+        other => panic!("unexpected result {:?}", other.map(|symbols| symbols.len()))
+    }
+}
+
+#[test]
+fn missing_library_fails() {
+    let scratch = common::scratch_dir("missing-library");
+    let path = scratch.join("libmissing.so");
+
+    match Snek::load(&path) {
+        Err(ref err @ Error::LibraryLoadError(_)) => assert!(err.is_not_found()),
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn load_any_uses_first_loadable_candidate() {
+    let scratch = common::scratch_dir("load-any");
+    let missing = scratch.join("libmissing.so");
+    let snek = Snek::load_any(&[missing, common::fixture_v1(), common::fixture()]).unwrap();
+
+    assert!(snek.symbol("added_in_v2").is_err());
+}
+
+#[test]
+fn loads_concurrently() {
+    let path = common::fixture();
+
+    let threads = (0..8).map(|thread| {
+        let path = path.clone();
+
+        thread::spawn(move || for _ in 0..32 {
+            let snek = Snek::load(&path).unwrap();
+            let add = snek.symbol("add").unwrap();
+
+            assert_eq!(unsafe { add.with(|add: extern "C" fn(c_int, c_int) -> c_int| add(thread, 1)) }, thread + 1);
+        })
+    }).collect::<Vec<_>>();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
+
+#[test]
+fn panicking_function_aborts() {
+    // The panic cannot unwind into this process, so it is called from a child
+    if env::var_os("SNEK_CALL_PANICKING_FIXTURE").is_some() {
+        let snek = Snek::load(common::fixture()).unwrap();
+        unsafe { snek.symbol("panics").unwrap().with(|panics: extern "C" fn()| panics()) };
+        return;
+    }
+
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "panicking_function_aborts", "--nocapture"])
+        .env("SNEK_CALL_PANICKING_FIXTURE", "1")
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("panicked on purpose"));
+}
+
+#[cfg(unix)]
+#[test]
+fn path_is_where_the_loader_found_the_library() {
+    use std::fs;
+    use std::os::unix::fs::MetadataExt;
+
+    let fixture = common::fixture();
+    let relative = fixture.strip_prefix(env::current_dir().unwrap()).unwrap();
+    let snek = Snek::load(relative).unwrap();
+
+    let path = snek.path().unwrap();
+    assert!(path.is_absolute(), "{}", path.display());
+
+    let (found, expected) = (fs::metadata(&path).unwrap(), fs::metadata(&fixture).unwrap());
+    assert_eq!((found.dev(), found.ino()), (expected.dev(), expected.ino()));
+}
+
+#[cfg(windows)]
+#[test]
+fn loads_from_non_ascii_directory() {
+    // Outside any one ANSI code page
+    let scratch = common::scratch_dir("non-ascii");
+    let dir = scratch.join("плагины-插件");
+    std::fs::create_dir(&dir).unwrap();
+
+    let library = dir.join(common::fixture().file_name().unwrap());
+    std::fs::copy(common::fixture(), &library).unwrap();
+
+    let snek = Snek::load(&library).unwrap();
+    assert_eq!(unsafe { snek.symbol("add").unwrap().with(|add: extern "C" fn(c_int, c_int) -> c_int| add(3, 4)) }, 7);
+
+    let missing = dir.join("missing.dll");
+    let err = Snek::load(&missing).unwrap_err();
+    assert_eq!(err.path(), Some(missing.as_path()));
+    assert!(err.to_string().contains("плагины-插件"), "{}", err);
+}
+
+#[cfg(windows)]
+#[test]
+fn loads_from_long_path() {
+    let scratch = common::scratch_dir("long-path");
+    let mut dir = scratch.to_path_buf();
+    while dir.as_os_str().len() < 300 {
+        dir.push("a-directory-nested-deeply");
+    }
+
+    std::fs::create_dir_all(&dir).unwrap();
+    let library = dir.join(common::fixture().file_name().unwrap());
+    std::fs::copy(common::fixture(), &library).unwrap();
+
+    let snek = Snek::load(&library).unwrap();
+    assert_eq!(unsafe { snek.symbol("add").unwrap().with(|add: extern "C" fn(c_int, c_int) -> c_int| add(3, 4)) }, 7);
+
+    // Components which the prefix would otherwise keep are resolved first
+    let indirect = dir.join("..").join(dir.file_name().unwrap()).join(".").join(library.file_name().unwrap());
+    assert!(Snek::load(&indirect).is_ok());
+}
+
+#[cfg(windows)]
+#[test]
+fn path_is_the_file_the_loader_picked() {
+    // Found in the system directory, under whichever case it is stored with
+    let kernel32 = Snek::load("kernel32.dll").unwrap();
+    let path = kernel32.path().unwrap();
+
+    assert!(path.is_absolute(), "{}", path.display());
+    assert!(path.exists(), "{}", path.display());
+    assert_eq!(path.file_name().unwrap().to_string_lossy().to_lowercase(), "kernel32.dll");
+
+    let existing = Snek::open_existing("kernel32.dll").unwrap();
+    assert_eq!(existing.path().unwrap(), path);
+}
+
+#[cfg(windows)]
+#[test]
+fn forwarded_exports_are_told_apart() {
+    use snek::windows::ExportKind;
+
+    let snek = Snek::load(common::fixture()).unwrap();
+    let add = snek.symbol_info("add").unwrap();
+    assert!(matches!(*add.kind(), ExportKind::Address { .. }), "{:?}", add);
+    assert_eq!(add.address(), snek.symbol("add").unwrap().as_ptr());
+
+    // kernel32 forwards these to ntdll on current versions of Windows
+    let kernel32 = Snek::load("kernel32.dll").unwrap();
+    let forwarded = ["HeapAlloc", "AcquireSRWLockExclusive", "InitializeSListHead"].iter()
+        .map(|&name| kernel32.symbol_info(name).unwrap())
+        .filter_map(|info| info.forwarded_to().map(str::to_owned))
+        .collect::<Vec<_>>();
+
+    assert!(forwarded.iter().any(|target| target.to_uppercase().starts_with("NTDLL.")), "{:?}", forwarded);
+
+    assert!(snek.symbol_info("not_a_symbol").is_err());
+}
+
+#[test]
+fn symbols_are_found_with_and_without_the_cache() {
+    let cached = Snek::load(common::fixture()).unwrap();
+    let uncached = LoadOptions::new().cache_symbols(false).load(common::fixture()).unwrap();
+
+    for snek in &[cached, uncached] {
+        let first = snek.symbol("add").unwrap().as_ptr();
+        assert_eq!(snek.symbol(CString::new("add").unwrap()).unwrap().as_ptr(), first);
+        assert!(snek.symbol("add\0\0").unwrap_err().is_invalid_input());
+    }
+}
+
+#[cfg(windows)]
+#[test]
+fn data_files_are_mapped_without_being_loaded() {
+    use snek::windows::DataFileLibrary;
+
+    // A copy of its own, which nothing else in the process loads
+    let scratch = common::scratch_dir("datafile");
+    let library = scratch.join(common::fixture().file_name().unwrap());
+    std::fs::copy(common::fixture(), &library).unwrap();
+
+    let data = DataFileLibrary::load(&library).unwrap();
+    let headers = unsafe { std::slice::from_raw_parts(data.base(), 2) };
+    assert_eq!(headers, b"MZ");
+    assert!(data.size() > 0x1000, "{}", data.size());
+
+    // Mapped, but not as a module
+    assert!(Snek::open_existing(&library).unwrap_err().is_not_found());
+    drop(data);
+
+    assert!(DataFileLibrary::load(library.with_extension("missing")).unwrap_err().is_not_found());
+}
+
+#[cfg(windows)]
+#[test]
+fn file_version_is_read_from_the_version_resource() {
+    // As given in fixture/version.rc
+    let snek = Snek::load(common::fixture()).unwrap();
+    let version = snek.file_version().unwrap().expect("the fixture has a version resource");
+
+    assert_eq!(version.file(), [1, 2, 3, 4]);
+    assert_eq!(version.product(), [5, 6, 7, 8]);
+    assert_eq!(version.product_name(), Some("snek"));
+    assert_eq!(version.file_description(), Some("snek test fixture"));
+    assert_eq!(version.to_string(), "1.2.3.4");
+
+    // The consumer is built without one
+    assert_eq!(snek::windows::file_version(common::fixture_consumer()).unwrap(), None);
+
+    let scratch = common::scratch_dir("no-version");
+    let err = snek::windows::file_version(scratch.join("missing.dll")).unwrap_err();
+    assert!(err.to_string().contains("missing.dll"), "{}", err);
+}
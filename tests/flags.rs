@@ -0,0 +1,107 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/flags.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+extern crate libc;
+extern crate snek;
+
+mod common;
+
+use snek::LoadFlags;
+
+#[test]
+fn flags_are_combined() {
+    let flags = LoadFlags::new().lazy().global();
+    assert!(!flags.is_now() && flags.is_global());
+
+    let flags = flags.now().local();
+    assert_eq!(flags, LoadFlags::new());
+    assert_eq!(flags, LoadFlags::default());
+
+    assert!(!flags.shows_error_dialogs());
+    assert!(flags.error_dialogs().shows_error_dialogs());
+}
+
+// Nothing else in this binary loads the fixture globally, so it cannot have
+// been made global before this test does
+#[cfg(target_os = "linux")]
+#[test]
+fn global_symbols_are_found_without_a_handle() {
+    let find = || unsafe { libc::dlsym(libc::RTLD_DEFAULT, b"ANSWER\0".as_ptr() as *const _) };
+
+    let local = snek::Snek::load_with_flags(common::fixture(), LoadFlags::new().now()).unwrap();
+    assert!(find().is_null());
+
+    let global = snek::Snek::load_with_flags(common::fixture(), LoadFlags::new().now().global()).unwrap();
+    assert_eq!(find(), global.symbol("ANSWER").unwrap().as_ptr());
+
+    drop(local);
+    drop(global);
+}
+
+#[test]
+fn library_handles_take_flags() {
+    let handle = snek::load_library_with_flags(common::fixture(), LoadFlags::new().now()).unwrap();
+    assert!(unsafe { snek::load_symbol(&handle, "ANSWER") }.is_ok());
+
+    snek::drop_library(handle);
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+#[test]
+fn raw_flags_reach_dlopen() {
+    let snek = snek::Snek::load_with_raw_flags(common::fixture(), libc::RTLD_NOW | libc::RTLD_DEEPBIND).unwrap();
+    assert!(snek.symbol("add").is_ok());
+
+    // A mode without RTLD_NOW or RTLD_LAZY is only rejected if it is passed
+    // on untouched
+    let err = snek::load_library_with_raw_flags(common::fixture(), libc::RTLD_DEEPBIND).unwrap_err();
+    assert!(err.to_string().contains("invalid mode"), "{}", err);
+}
+
+#[cfg(windows)]
+#[test]
+fn error_mode_is_restored_after_loading() {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetThreadErrorMode() -> u32;
+        fn SetThreadErrorMode(mode: u32, previous: *mut u32) -> i32;
+    }
+
+    const SEM_FAILCRITICALERRORS: u32 = 0x1;
+    const SEM_NOGPFAULTERRORBOX: u32 = 0x2;
+    const SEM_NOOPENFILEERRORBOX: u32 = 0x8000;
+
+    // Each test has a thread of its own, so this sees no other test's mode
+    unsafe { SetThreadErrorMode(SEM_NOGPFAULTERRORBOX, std::ptr::null_mut()) };
+
+    let scratch = common::scratch_dir("error-mode");
+    let missing = scratch.join("missing.dll");
+    assert!(snek::Snek::load(&missing).unwrap_err().is_not_found());
+    assert_eq!(unsafe { GetThreadErrorMode() }, SEM_NOGPFAULTERRORBOX);
+
+    assert!(snek::Snek::load(common::fixture()).is_ok());
+    assert_eq!(unsafe { GetThreadErrorMode() }, SEM_NOGPFAULTERRORBOX);
+
+    assert!(snek::Snek::load_with_flags(&missing, LoadFlags::new().error_dialogs()).is_err());
+    assert_eq!(unsafe { GetThreadErrorMode() }, SEM_NOGPFAULTERRORBOX);
+
+    // A mode which already suppresses the dialogs is kept as it is
+    unsafe { SetThreadErrorMode(SEM_FAILCRITICALERRORS | SEM_NOOPENFILEERRORBOX, std::ptr::null_mut()) };
+    assert!(snek::Snek::load(&missing).is_err());
+    assert_eq!(unsafe { GetThreadErrorMode() }, SEM_FAILCRITICALERRORS | SEM_NOOPENFILEERRORBOX);
+}
@@ -0,0 +1,66 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/global.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+extern crate libc;
+extern crate snek;
+
+use snek::{Error, ErrorKind};
+
+#[cfg(unix)]
+use libc::{c_char, size_t};
+
+#[cfg(unix)]
+#[test]
+fn global_symbol_finds_the_c_library() {
+    assert!(snek::global_symbol("malloc").is_ok());
+
+    let strlen = snek::global_symbol("strlen").unwrap();
+    let len = unsafe { strlen.with(|strlen: extern "C" fn(*const c_char) -> size_t| strlen(b"snek\0".as_ptr() as *const c_char)) };
+    assert_eq!(len, 4);
+}
+
+// The C library is loaded after the test executable, which defines neither
+#[cfg(unix)]
+#[test]
+fn next_symbol_finds_definitions_after_the_caller() {
+    assert!(snek::next_symbol("malloc").is_ok());
+    assert_eq!(snek::next_symbol("strlen").unwrap().as_ptr(), snek::global_symbol("strlen").unwrap().as_ptr());
+}
+
+#[cfg(windows)]
+#[test]
+fn global_symbol_searches_every_module() {
+    let tick_count = snek::global_symbol("GetTickCount").unwrap();
+    let _ = unsafe { tick_count.with(|tick_count: extern "system" fn() -> u32| tick_count()) };
+}
+
+#[test]
+fn global_symbol_fails_for_missing_names() {
+    let err = snek::global_symbol("snek_defines_nothing_by_this_name").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    match err {
+        Error::SymbolLoadError(failure) => assert_eq!(failure.symbol(), Some("snek_defines_nothing_by_this_name")),
+        err => panic!("unexpected error {:?}", err)
+    }
+
+    match snek::global_symbol("not\0a name") {
+        Err(Error::InvalidInput(_)) => (),
+        result => panic!("unexpected result {:?}", result)
+    }
+}
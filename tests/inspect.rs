@@ -0,0 +1,114 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/inspect.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+extern crate snek;
+
+mod common;
+
+use common::fixture;
+use snek::inspect;
+
+use std::fs;
+use std::path::PathBuf;
+
+fn corpus() -> Vec<PathBuf> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/inspect");
+    let mut files = fs::read_dir(dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+
+    files.sort();
+    files
+}
+
+#[test]
+fn corpus_is_rejected() {
+    let files = corpus();
+    assert!(!files.is_empty());
+
+    for file in files {
+        let data = fs::read(&file).unwrap();
+        assert!(inspect::inspect(&data).is_err(), "{} was accepted", file.display());
+        assert!(inspect::inspect_file(&file).is_err(), "{} was accepted", file.display());
+    }
+}
+
+#[test]
+fn fixture_exports_are_found() {
+    let image = inspect::inspect_file(fixture()).unwrap();
+
+    assert!(image.matches_current());
+    for name in &["ANSWER", "add", "hello"] {
+        assert!(image.exports().iter().any(|export| export == name), "{} missing from {:?}", name, image.exports());
+    }
+}
+
+#[test]
+fn truncated_fixture_does_not_panic() {
+    let data = fs::read(fixture()).unwrap();
+
+    // Every prefix of the headers, then a sample of longer ones
+    let lengths = (0..4096.min(data.len())).chain((4096..data.len()).step_by(61));
+    for len in lengths {
+        let _ = inspect::inspect(&data[..len]);
+    }
+}
+
+#[test]
+fn corrupted_fixture_does_not_panic() {
+    let original = fs::read(fixture()).unwrap();
+    let mut data = original.clone();
+
+    // A fixed seed keeps any failure reproducible
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    for _ in 0..2000 {
+        data.copy_from_slice(&original);
+
+        for _ in 0..1 + next() % 8 {
+            // Bias corruption towards the headers, where the offsets and counts are
+            let limit = if next() % 4 == 0 { data.len() } else { data.len().min(1024) };
+            let offset = (next() % limit as u64) as usize;
+
+            data[offset] = match next() % 4 {
+                0 => 0,
+                1 => 0xff,
+                _ => next() as u8
+            };
+        }
+
+        let _ = inspect::inspect(&data);
+    }
+}
+
+#[test]
+fn oversized_counts_are_refused() {
+    // A Mach-O header claiming more load commands than the limit
+    let mut data = vec![0xcf, 0xfa, 0xed, 0xfe, 0x07, 0x00, 0x00, 0x01];
+    data.extend_from_slice(&[0; 24]);
+    data[16..20].copy_from_slice(&0x0010_0001_u32.to_le_bytes());
+
+    let err = inspect::inspect(&data).unwrap_err();
+    assert!(format!("{:?}", err).contains("limit"), "{:?}", err);
+}
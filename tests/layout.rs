@@ -0,0 +1,44 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/layout.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+extern crate snek;
+
+use snek::{Handle, Snek, Symbol};
+
+use std::mem::size_of;
+use std::ptr;
+
+#[test]
+fn symbol_is_pointer_sized() {
+    // Symbol::new accepts a null pointer, so there is no niche for an option
+    assert_eq!(size_of::<Symbol>(), size_of::<*mut u8>());
+    assert!(Symbol::new(ptr::null_mut()).as_ptr().is_null());
+}
+
+#[test]
+fn option_handle_is_pointer_sized() {
+    assert_eq!(size_of::<Option<Handle>>(), size_of::<*mut u8>());
+}
+
+#[test]
+fn option_snek_uses_a_niche() {
+    // A Snek also holds the path it was loaded from, so it cannot be pointer
+    // sized itself, but wrapping it in an option costs nothing
+    assert_eq!(size_of::<Option<Snek>>(), size_of::<Snek>());
+    assert_eq!(size_of::<Option<Option<Snek>>>(), size_of::<Snek>());
+}
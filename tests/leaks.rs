@@ -0,0 +1,75 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/leaks.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// This counts every allocation made by the test binary, so it is kept apart
+// from the other tests and holds a single test.
+
+extern crate snek;
+
+mod common;
+
+use snek::Snek;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct Counting;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE.fetch_add(layout.size(), Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+// Each path and name which is not already NUL terminated is copied into a C
+// string for the call, which must be freed again
+fn load_and_lookup(path: &std::path::Path) {
+    let snek = Snek::load(path).unwrap();
+    for _ in 0..100 {
+        assert!(snek.symbol("add").is_ok());
+        assert!(snek.symbol(String::from("ANSWER")).is_ok());
+        assert!(snek.symbol(&b"count\0"[..]).is_ok());
+    }
+}
+
+#[test]
+fn loads_and_lookups_do_not_leak() {
+    let path = common::fixture();
+
+    // The first load may allocate things which live for the whole process
+    load_and_lookup(&path);
+
+    let before = LIVE.load(Ordering::SeqCst);
+    for _ in 0..100 {
+        load_and_lookup(&path);
+    }
+
+    let after = LIVE.load(Ordering::SeqCst);
+    assert!(after <= before, "{} bytes leaked by 100 loads and 30000 lookups", after - before);
+}
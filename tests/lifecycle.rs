@@ -0,0 +1,300 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/lifecycle.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// The init and fini calls are counted by the fixture, so these tests are
+// kept apart from any others loading it, and take turns with each other.
+
+#[macro_use]
+extern crate snek;
+
+mod common;
+
+use common::{fixture, scratch_dir};
+use snek::{LoadOptions, Registry, Snek, Symbol};
+
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::os::raw::c_int;
+
+static TURN: Mutex<()> = Mutex::new(());
+
+snek! {
+    Lifecycle {
+        add: (a: c_int, b: c_int) -> c_int
+    }
+
+    init fixture_init;
+    fini fixture_fini;
+}
+
+snek! {
+    FailingInit {
+        add: (a: c_int, b: c_int) -> c_int
+    }
+
+    init fixture_init_fails;
+    fini fixture_fini;
+}
+
+snek! {
+    MissingFini {
+        add: (a: c_int, b: c_int) -> c_int
+    }
+
+    init fixture_init;
+    fini fixture_shutdown;
+}
+
+/// Returns the number of init and fini calls so far. The observer keeps the
+/// library loaded, so the counts survive the others unloading it.
+fn calls(observer: &Snek) -> (usize, usize) {
+    let count = |symbol| unsafe { observer.symbol(symbol).unwrap().with(|count: extern "C" fn() -> usize| count()) };
+    (count("init_calls"), count("fini_calls"))
+}
+
+#[test]
+fn options_call_init_and_fini() {
+    let _turn = TURN.lock().unwrap();
+    let observer = Snek::load(fixture()).unwrap();
+    let (inits, finis) = calls(&observer);
+
+    let snek = LoadOptions::new().init("fixture_init").fini("fixture_fini").load(fixture()).unwrap();
+    assert_eq!(calls(&observer), (inits + 1, finis));
+
+    drop(snek);
+    assert_eq!(calls(&observer), (inits + 1, finis + 1));
+}
+
+#[test]
+fn failing_init_fails_load_without_fini() {
+    let _turn = TURN.lock().unwrap();
+    let observer = Snek::load(fixture()).unwrap();
+    let (inits, finis) = calls(&observer);
+
+    let err = LoadOptions::new().init("fixture_init_fails").fini("fixture_fini").load(fixture()).unwrap_err();
+    assert!(format!("{:?}", err).contains("fixture_init_fails returned 3"), "{:?}", err);
+
+    let err = FailingInit::load(fixture()).err().unwrap();
+    assert!(format!("{:?}", err).contains("fixture_init_fails returned 3"), "{:?}", err);
+
+    assert_eq!(calls(&observer), (inits, finis));
+}
+
+#[test]
+fn missing_fini_fails_load_before_init() {
+    let _turn = TURN.lock().unwrap();
+    let observer = Snek::load(fixture()).unwrap();
+    let (inits, finis) = calls(&observer);
+
+    let err = LoadOptions::new().init("fixture_init").fini("fixture_shutdown").load(fixture()).unwrap_err();
+    assert!(err.is_not_found(), "{:?}", err);
+
+    let err = MissingFini::load(fixture()).err().unwrap();
+    assert!(err.is_not_found(), "{:?}", err);
+
+    assert_eq!(calls(&observer), (inits, finis));
+}
+
+#[test]
+fn macro_calls_init_and_fini() {
+    let _turn = TURN.lock().unwrap();
+    let observer = Snek::load(fixture()).unwrap();
+    let (inits, finis) = calls(&observer);
+
+    let lifecycle = Lifecycle::load(fixture()).unwrap();
+    assert_eq!(unsafe { lifecycle.add(3, 7) }, 10);
+    assert_eq!(calls(&observer), (inits + 1, finis));
+
+    drop(lifecycle);
+    assert_eq!(calls(&observer), (inits + 1, finis + 1));
+}
+
+#[test]
+fn unload_callbacks_run_in_reverse_before_fini() {
+    let _turn = TURN.lock().unwrap();
+    let observer = Snek::load(fixture()).unwrap();
+    let (_, finis) = calls(&observer);
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let snek = LoadOptions::new().init("fixture_init").fini("fixture_fini").load(fixture()).unwrap();
+
+    for name in &["first", "second"] {
+        let log = log.clone();
+        snek.on_unload(Box::new(move || {
+            // The fini function has not been called yet
+            let observer = Snek::load(fixture()).unwrap();
+            log.lock().unwrap().push((*name, calls(&observer).1));
+        }));
+    }
+
+    // A panicking callback does not stop the others
+    snek.on_unload(Box::new(|| panic!("callback failed")));
+
+    drop(snek);
+    assert_eq!(*log.lock().unwrap(), [("second", finis), ("first", finis)]);
+    assert_eq!(calls(&observer).1, finis + 1);
+}
+
+#[test]
+fn registry_exposes_unload_callbacks() {
+    let _turn = TURN.lock().unwrap();
+    let unloaded = Arc::new(Mutex::new(false));
+
+    let mut registry = Registry::new();
+    registry.load("fixture", fixture(), &[]).unwrap();
+
+    let flag = unloaded.clone();
+    registry.on_unload("fixture", Box::new(move || *flag.lock().unwrap() = true)).unwrap();
+    assert!(registry.on_unload("missing", Box::new(|| ())).is_err());
+
+    registry.unload("fixture").unwrap();
+    assert!(*unloaded.lock().unwrap());
+}
+
+#[test]
+fn existing_library_is_referenced_not_loaded() {
+    // A copy of its own, which nothing else in the process loads
+    let scratch = scratch_dir("existing");
+    let library = scratch.join(fixture().file_name().unwrap());
+    fs::copy(fixture(), &library).unwrap();
+
+    let err = Snek::open_existing(&library).unwrap_err();
+    assert!(err.is_not_found(), "{:?}", err);
+
+    let loaded = Snek::load(&library).unwrap();
+    let existing = Snek::open_existing(&library).unwrap();
+    let add = existing.symbol("add").unwrap();
+    assert_eq!(add.as_ptr(), loaded.symbol("add").unwrap().as_ptr());
+
+    // Each holds its own reference, so dropping either leaves the other usable
+    drop(loaded);
+    assert_eq!(unsafe { add.with(|add: extern "C" fn(c_int, c_int) -> c_int| add(3, 4)) }, 7);
+
+    drop(existing);
+    assert_eq!(Snek::open_existing(&library).is_ok(), Snek::unload_is_noop());
+}
+
+#[test]
+fn loaded_libraries_follow_loads_and_unloads() {
+    // A copy of its own, which nothing else in the process loads
+    let scratch = scratch_dir("listed");
+    let library = fs::canonicalize(&scratch).unwrap().join(fixture().file_name().unwrap());
+    fs::copy(fixture(), &library).unwrap();
+
+    let listed = || snek::loaded_libraries().into_iter().find(|loaded| fs::canonicalize(loaded.path()).ok().as_ref() == Some(&library));
+    assert_eq!(listed(), None);
+
+    let snek = Snek::load(&library).unwrap();
+    let add = snek.symbol("add").unwrap();
+    assert_eq!(listed().map(|loaded| loaded.base()), snek::resolve_address(add.as_ptr()).map(|info| info.base()));
+
+    drop(snek);
+    assert_eq!(listed().is_some(), Snek::unload_is_noop());
+}
+
+#[test]
+fn raw_handles_are_wrapped_without_stealing_them() {
+    // A copy of its own, which nothing else in the process loads
+    let scratch = scratch_dir("raw-handle");
+    let library = scratch.join(fixture().file_name().unwrap());
+    fs::copy(fixture(), &library).unwrap();
+
+    let handle = snek::load_library(&library).unwrap();
+    let add = unsafe { snek::load_symbol(&handle, "add") }.unwrap();
+
+    // Each releases only what it took, so the original handle stays loaded
+    let referenced = unsafe { Snek::from_raw_handle_ref(handle.as_raw()) }.unwrap();
+    assert_eq!(referenced.symbol("add").unwrap().as_ptr(), add.as_ptr());
+    drop(referenced);
+
+    let unowned = unsafe { Snek::from_raw_handle_unowned(handle.as_raw()) }.unwrap();
+    assert_eq!(unowned.symbol("add").unwrap().as_ptr(), add.as_ptr());
+    drop(unowned);
+
+    let add = unsafe { std::mem::transmute::<*mut std::os::raw::c_void, extern "C" fn(c_int, c_int) -> c_int>(add.as_ptr()) };
+    assert_eq!(add(3, 4), 7);
+
+    snek::drop_library(handle);
+    assert_eq!(Snek::open_existing(&library).is_ok(), Snek::unload_is_noop());
+
+    let err = unsafe { Snek::from_raw_handle_ref(std::ptr::null_mut()) }.unwrap_err();
+    assert!(err.is_invalid_input(), "{:?}", err);
+}
+
+#[test]
+fn raw_handles_round_trip_through_a_snek() {
+    // A copy of its own, which nothing else in the process loads
+    let scratch = scratch_dir("round-trip");
+    let library = scratch.join(fixture().file_name().unwrap());
+    fs::copy(fixture(), &library).unwrap();
+
+    // Neither the Snek nor its owned symbol unloads a handle it gave up
+    let snek = Snek::load(&library).unwrap();
+    let add = snek.owned_symbol("add").unwrap();
+    let handle = snek.into_raw_handle();
+    drop(add);
+
+    let snek = unsafe { Snek::from_raw_handle(handle) }.unwrap();
+    let add = snek.symbol("add").unwrap();
+    assert_eq!(unsafe { add.with(|add: extern "C" fn(c_int, c_int) -> c_int| add(3, 4)) }, 7);
+    assert_eq!(fs::canonicalize(snek.path().unwrap()).unwrap(), fs::canonicalize(&library).unwrap());
+
+    // Only the one reference was taken, so it is released just once
+    snek.close().unwrap();
+    assert_eq!(Snek::open_existing(&library).is_ok(), Snek::unload_is_noop());
+
+    let err = unsafe { Snek::from_raw_handle(std::ptr::null_mut()) }.unwrap_err();
+    assert!(err.is_invalid_input(), "{:?}", err);
+}
+
+#[test]
+fn leaked_libraries_outlive_their_snek() {
+    // A copy of its own, which nothing else in the process loads
+    let scratch = scratch_dir("leaked");
+    let library = scratch.join(fixture().file_name().unwrap());
+    fs::copy(fixture(), &library).unwrap();
+
+    let add: Symbol<'static> = {
+        let snek = Snek::load(&library).unwrap();
+        snek.leak().symbol("add").unwrap()
+    };
+
+    assert_eq!(unsafe { add.with(|add: extern "C" fn(c_int, c_int) -> c_int| add(3, 4)) }, 7);
+    assert!(Snek::open_existing(&library).is_ok());
+}
+
+#[test]
+fn shared_libraries_unload_after_every_clone_and_symbol() {
+    // A copy of its own, which nothing else in the process loads
+    let scratch = scratch_dir("shared");
+    let library = scratch.join(fixture().file_name().unwrap());
+    fs::copy(fixture(), &library).unwrap();
+
+    let shared = Snek::load(&library).unwrap().into_shared();
+    let clone = shared.clone();
+    let add = clone.symbol("add").unwrap().clone();
+    assert_eq!(shared.strong_count(), 3);
+
+    drop(shared);
+    drop(clone);
+    assert_eq!(unsafe { add.with(|add: extern "C" fn(c_int, c_int) -> c_int| add(3, 4)) }, 7);
+    assert!(Snek::open_existing(&library).is_ok());
+
+    drop(add);
+    assert_eq!(Snek::open_existing(&library).is_ok(), Snek::unload_is_noop());
+}
@@ -0,0 +1,50 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/link.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// This is kept apart from the other tests, as making the fixture's symbols
+// global changes what other libraries loaded by the process resolve against.
+
+#![cfg(unix)]
+
+extern crate snek;
+
+mod common;
+
+use snek::LinkGroup;
+
+use std::os::raw::c_int;
+
+#[test]
+fn consumer_resolves_against_provider() {
+    let mut group = LinkGroup::new();
+
+    // Nothing provides the consumer's symbol yet, and the load says which
+    let err = group.load_consumer(common::fixture_consumer()).unwrap_err();
+    let message = format!("{:?}", err);
+    assert!(message.contains("needs symbol add") && message.contains("no providers are loaded"), "{}", message);
+    assert!(group.consumers().is_empty());
+
+    group.load_provider(common::fixture()).unwrap();
+
+    let consumer = group.load_consumer(common::fixture_consumer()).unwrap();
+    let add_twice = consumer.symbol("add_twice").unwrap();
+    assert_eq!(unsafe { add_twice.with(|f: extern "C" fn(c_int, c_int) -> c_int| f(3, 4)) }, 14);
+
+    assert_eq!(group.providers().len(), 1);
+    assert_eq!(group.consumers().len(), 1);
+}
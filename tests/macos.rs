@@ -0,0 +1,79 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/macos.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+#![cfg(target_os = "macos")]
+
+extern crate snek;
+
+mod common;
+
+use common::{fixture, scratch_dir};
+use snek::Snek;
+
+use std::env;
+use std::fs;
+
+#[test]
+fn libraries_are_named_as_dylibs() {
+    assert_eq!(fixture().extension().unwrap(), "dylib");
+    assert_eq!(snek::env::library_names("example")[0].to_str(), Some("libexample.dylib"));
+}
+
+#[test]
+fn path_is_the_dyld_image() {
+    let snek = Snek::load(fixture()).unwrap();
+    assert_eq!(snek.path().unwrap(), fs::canonicalize(fixture()).unwrap());
+}
+
+#[test]
+fn bare_names_fall_back_to_the_search_paths() {
+    let dir = scratch_dir("fallback");
+    fs::copy(fixture(), dir.join("libsnekfallback.dylib")).unwrap();
+
+    // dyld only reads its variables at startup, so only the crate's own
+    // fallback sees this
+    env::set_var("DYLD_FALLBACK_LIBRARY_PATH", &dir);
+
+    let snek = Snek::load("libsnekfallback.dylib").unwrap();
+    assert!(snek.symbol("add").is_ok());
+    assert_eq!(snek.path().unwrap(), fs::canonicalize(dir.join("libsnekfallback.dylib")).unwrap());
+}
+
+#[test]
+fn universal_binary_without_our_slice_is_explained() {
+    // A universal binary holding a single slice for the other architecture
+    let (cpu_type, cpu_subtype) = if cfg!(target_arch = "aarch64") { (0x0100_0007_u32, 3_u32) } else { (0x0100_000c, 0) };
+
+    let mut data = Vec::new();
+    for word in &[0xcafe_babe_u32, 1, cpu_type, cpu_subtype, 4096, 32, 12] {
+        data.extend_from_slice(&word.to_be_bytes());
+    }
+
+    data.resize(4096, 0);
+    for word in &[0xfeed_facf_u32, cpu_type, cpu_subtype, 6, 0, 0, 0, 0] {
+        data.extend_from_slice(&word.to_le_bytes());
+    }
+
+    let scratch = scratch_dir("universal");
+    let library = scratch.join("libuniversal.dylib");
+    fs::write(&library, &data).unwrap();
+
+    let err = Snek::load(&library).unwrap_err();
+    assert_eq!(err.kind(), snek::ErrorKind::WrongArchitecture, "{:?}", err);
+    assert!(err.to_string().contains("universal binary contains"), "{}", err);
+}
@@ -0,0 +1,342 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/macro.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+#[macro_use]
+extern crate snek;
+
+mod common;
+
+use std::ffi::CStr;
+use std::str::Utf8Error;
+use std::ptr::NonNull;
+use std::os::raw::{c_char, c_int, c_void};
+
+snek! {
+    Fixture {
+        add: (a: c_int, b: c_int) -> c_int,
+        hello: () -> *const c_char,
+        version: () -> c_int
+    }
+}
+
+snek! {
+    FixtureTable {
+        add: (a: c_int, b: c_int) -> c_int,
+        version: () -> c_int
+    }
+
+    vtable FixtureVtable;
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Pair {
+    a: c_int,
+    b: c_int
+}
+
+unsafe impl snek::FfiSafe for Pair {}
+
+// Only checked, never loaded, as the fixture has no such functions
+snek! {
+    #[deny_non_ffi]
+    Strict {
+        add: (a: c_int, b: c_int) -> c_int,
+        hello: () -> *const c_char,
+        callback: (f: Option<extern "C" fn(c_int) -> c_int>, data: *mut c_void) -> (),
+        swap: (pair: Pair, out: NonNull<Pair>) -> bool,
+        named: (name: &str as *const c_char) -> (),
+        name: () -> *mut c_char as String => free_name
+    }
+
+    vtable StrictVtable;
+}
+
+snek! {
+    Strings {
+        string_length: (string: &str as *const c_char) -> usize,
+        add: (a: c_int, b: c_int) -> c_int
+    }
+
+    vtable StringsVtable;
+}
+
+snek! {
+    #[fallible_strings]
+    FallibleStrings {
+        string_length: (string: &str as *const c_char) -> usize,
+        add: (a: c_int, b: c_int) -> c_int
+    }
+}
+
+snek! {
+    Names {
+        name_of: (id: c_int) -> *mut c_char as String => free_string,
+        hello: () -> *const c_char as String,
+        freed_strings: () -> usize
+    }
+}
+
+snek! {
+    OptionalNames {
+        name_of: (id: c_int) -> *mut c_char as Option<String> => free_string
+    }
+}
+
+snek! {
+    StrictNames {
+        name_of: (id: c_int) -> *mut c_char as Result<String, Utf8Error> => free_string
+    }
+
+    vtable StrictNamesVtable;
+}
+
+snek! {
+    #[load_error(CheckedLoadError)]
+    Checked {
+        version: () -> c_int,
+        added_in_v2: () -> c_int
+    }
+}
+
+snek! {
+    #[load_error(FailingInitError)]
+    FailingInit {
+        add: (a: c_int, b: c_int) -> c_int
+    }
+
+    init fixture_init_fails;
+}
+
+// The attribute order must not matter, as load_self returns the generated error
+#[cfg(unix)]
+snek! {
+    #[self_process]
+    #[load_error(CheckedHostError)]
+    CheckedHost {
+        strlen: (s: *const c_char) -> usize
+    }
+}
+
+snek! {
+    FixtureV2 {
+        version: () -> c_int,
+        added_in_v2: () -> c_int
+    }
+}
+
+// The C library is linked into every unix test binary, and is found through
+// the process handle as it is loaded with global visibility
+#[cfg(unix)]
+snek! {
+    #[self_process]
+    #[deny_non_ffi]
+    Host {
+        strlen: (s: *const c_char) -> usize
+    }
+}
+
+#[test]
+fn loads_and_calls_symbols() {
+    let fixture = Fixture::load(common::fixture()).unwrap();
+
+    unsafe {
+        assert_eq!(fixture.add(3, 7), 10);
+        assert_eq!(CStr::from_ptr(fixture.hello()).to_str().unwrap(), "hello");
+        assert_eq!(fixture.version(), 2);
+    }
+}
+
+#[test]
+fn missing_symbol_fails_load() {
+    assert!(FixtureV2::load(common::fixture()).is_ok());
+
+    match FixtureV2::load(common::fixture_v1()) {
+        Err(ref err @ snek::Error::SymbolLoadError(_)) => assert!(err.is_not_found()),
+        Err(err) => panic!("unexpected error {:?}", err),
+        Ok(_) => panic!("loaded a symbol missing from the library")
+    }
+}
+
+#[test]
+fn missing_library_fails_load() {
+    let scratch = common::scratch_dir("macro-missing");
+    let path = scratch.join("libmissing.so");
+
+    match Fixture::load(path) {
+        Err(ref err @ snek::Error::LibraryLoadError(_)) => assert!(err.is_not_found()),
+        Err(err) => panic!("unexpected error {:?}", err),
+        Ok(_) => panic!("loaded a library which does not exist")
+    }
+}
+
+#[test]
+fn vtable_holds_symbols_in_declaration_order() {
+    let fixture = FixtureTable::load(common::fixture()).unwrap();
+    let vtable = fixture.vtable();
+
+    unsafe {
+        assert_eq!((vtable.add)(3, 7), 10);
+        assert_eq!((vtable.version)(), 2);
+
+        let fields = &vtable as *const FixtureVtable as *const usize;
+        assert_eq!(*fields, vtable.add as usize);
+        assert_eq!(*fields.offset(1), vtable.version as usize);
+    }
+}
+
+#[test]
+fn strict_block_loads_like_any_other() {
+    let err = Strict::load(common::fixture()).err().unwrap();
+    assert!(err.is_not_found(), "{:?}", err);
+}
+
+#[test]
+fn extra_symbols_come_from_the_same_library() {
+    let fixture = FixtureV2::load(common::fixture()).unwrap();
+    let hello = fixture.extra_symbol("hello").unwrap();
+
+    unsafe {
+        let greeting = hello.with(|hello: extern "C" fn() -> *const c_char| CStr::from_ptr(hello()));
+        assert_eq!(greeting.to_str().unwrap(), "hello");
+        assert_eq!(fixture.added_in_v2(), 2);
+    }
+
+    let snek: &snek::Snek = fixture.as_ref();
+    assert_eq!(snek.symbol("version").unwrap().as_ptr(), fixture.extra_symbol("version").unwrap().as_ptr());
+    assert!(fixture.extra_symbol("does_not_exist").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn self_process_resolves_against_the_process() {
+    let host = Host::load_self().unwrap();
+    assert_eq!(unsafe { host.strlen(b"host\0".as_ptr() as *const c_char) }, 4);
+
+    // Dropping the struct must leave the process handle usable
+    drop(host);
+    let host = Host::load_self().unwrap();
+    assert_eq!(unsafe { host.strlen(b"\0".as_ptr() as *const c_char) }, 0);
+    assert!(host.extra_symbol("strcmp").is_ok());
+}
+
+#[test]
+fn string_arguments_are_converted() {
+    let strings = Strings::load(common::fixture()).unwrap();
+
+    unsafe {
+        assert_eq!(strings.string_length("hello"), 5);
+        assert_eq!(strings.string_length(&String::from("snek")), 4);
+        assert_eq!(strings.add(3, 7), 10);
+        assert_eq!((strings.vtable().string_length)(b"vtable\0".as_ptr() as *const c_char), 6);
+    }
+}
+
+#[test]
+#[should_panic(expected = "string argument string contains a NUL byte")]
+fn string_arguments_with_nul_panic() {
+    let strings = Strings::load(common::fixture()).unwrap();
+    unsafe { strings.string_length("hel\0lo") };
+}
+
+#[test]
+fn fallible_string_arguments_return_errors() {
+    let strings = FallibleStrings::load(common::fixture()).unwrap();
+
+    unsafe {
+        assert_eq!(strings.string_length("hello"), Ok(5));
+        assert_eq!(strings.string_length("hel\0lo").unwrap_err().nul_position(), 3);
+        assert_eq!(strings.add(3, 7), Ok(10));
+    }
+}
+
+#[test]
+fn returned_strings_are_copied_and_freed() {
+    let names = Names::load(common::fixture()).unwrap();
+    let optional = OptionalNames::load(common::fixture()).unwrap();
+    let strict = StrictNames::load(common::fixture()).unwrap();
+
+    unsafe {
+        let freed = names.freed_strings();
+        assert_eq!(names.name_of(1), "one");
+        assert_eq!(names.name_of(0), "");
+        assert_eq!(names.name_of(2), "tw\u{fffd}o");
+        assert_eq!(optional.name_of(0), None);
+        assert_eq!(optional.name_of(1), Some("one".into()));
+        assert!(strict.name_of(2).is_err());
+        assert_eq!(strict.name_of(1), Ok("one".into()));
+        assert_eq!(names.freed_strings(), freed + 5);
+
+        assert_eq!(names.hello(), "hello");
+
+        // The vtable holds the function itself, returning the raw pointer
+        let name = (strict.vtable().name_of)(1);
+        assert_eq!(CStr::from_ptr(name).to_str(), Ok("one"));
+    }
+}
+
+#[test]
+fn load_errors_name_their_stage() {
+    assert!(Checked::load(common::fixture()).is_ok());
+
+    match Checked::load(common::fixture_v1()) {
+        Err(err @ CheckedLoadError::Symbol { .. }) => {
+            assert!(err.to_string().starts_with("failed to load symbol added_in_v2: "), "{}", err);
+            assert!(std::error::Error::source(&err).is_some());
+
+            if let CheckedLoadError::Symbol { name, ref source } = err {
+                assert_eq!(name, "added_in_v2");
+                assert_eq!(source.symbol(), Some("added_in_v2"));
+                assert!(source.is_not_found());
+            }
+        },
+
+        other => panic!("unexpected result {:?}", other.err())
+    }
+
+    let scratch = common::scratch_dir("macro-load-error");
+    match Checked::load(scratch.join("libmissing.so")) {
+        Err(ref load_err @ CheckedLoadError::Library(ref err)) => {
+            assert!(err.is_not_found());
+
+            // The library error's own source, the OS error, is passed through
+            let source = std::error::Error::source(load_err).and_then(|source| source.downcast_ref::<std::io::Error>());
+            assert_eq!(source.and_then(|source| source.raw_os_error()), err.os_code());
+        },
+
+        other => panic!("unexpected result {:?}", other.err())
+    }
+
+    match FailingInit::load(common::fixture()) {
+        Err(err @ FailingInitError::InitFailed(3)) => {
+            assert_eq!(err.to_string(), "init function returned 3");
+
+            let err: snek::Error = err.into();
+            assert!(matches!(err, snek::Error::LibraryLoadError(_)));
+        },
+
+        other => panic!("unexpected result {:?}", other.err())
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn load_errors_apply_to_self_process() {
+    let result: Result<CheckedHost, CheckedHostError> = CheckedHost::load_self();
+    assert!(result.is_ok());
+}
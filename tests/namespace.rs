@@ -0,0 +1,54 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/namespace.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+extern crate snek;
+
+mod common;
+
+use snek::Snek;
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+#[test]
+fn namespaces_are_isolated() {
+    let call = |snek: &Snek, name: &str| unsafe { snek.symbol(name).unwrap().with(|f: extern "C" fn() -> std::os::raw::c_int| f()) };
+
+    let v1 = Snek::load_in_new_namespace(common::fixture_v1()).unwrap();
+    let v2 = Snek::load_in_new_namespace(common::fixture()).unwrap();
+    assert_eq!(call(&v1, "version"), 1);
+    assert_eq!(call(&v2, "version"), 2);
+
+    let base = Snek::load(common::fixture()).unwrap();
+    assert_eq!(base.namespace_id().unwrap(), 0);
+    assert!(v1.namespace_id().unwrap() > 0);
+    assert!(v2.namespace_id().unwrap() > 0);
+    assert_ne!(v1.namespace_id().unwrap(), v2.namespace_id().unwrap());
+
+    // The same file in two namespaces is two copies, with their own state
+    assert_ne!(base.symbol("count").unwrap().as_ptr(), v2.symbol("count").unwrap().as_ptr());
+
+    drop(v1);
+    assert_eq!(call(&v2, "version"), 2);
+    assert_eq!(call(&base, "version"), 2);
+}
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+#[test]
+fn namespaces_are_unsupported() {
+    assert!(Snek::load_in_new_namespace(common::fixture()).unwrap_err().is_unsupported());
+    assert!(Snek::load(common::fixture()).unwrap().namespace_id().unwrap_err().is_unsupported());
+}
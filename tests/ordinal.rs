@@ -0,0 +1,45 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/ordinal.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+#![cfg(windows)]
+
+extern crate snek;
+
+mod common;
+
+use common::fixture_ordinal;
+use snek::Snek;
+
+use std::os::raw::c_int;
+
+#[test]
+fn symbols_are_found_by_ordinal() {
+    let snek = Snek::load(fixture_ordinal()).unwrap();
+
+    let ordinal_add = snek.symbol_ordinal(42).unwrap();
+    assert_eq!(unsafe { ordinal_add.with(|f: extern "C" fn(c_int, c_int) -> c_int| f(3, 4)) }, 7);
+    assert_eq!(ordinal_add.as_ptr(), snek.symbol("ordinal_add").unwrap().as_ptr());
+
+    let handle = snek::load_library(fixture_ordinal()).unwrap();
+    let raw = unsafe { snek::windows::load_symbol_ordinal(&handle, 42) }.unwrap();
+    assert_eq!(raw.as_ptr(), ordinal_add.as_ptr());
+    snek::drop_library(handle);
+
+    let err = snek.symbol_ordinal(43).unwrap_err();
+    assert!(err.to_string().contains("ordinal 43"), "{}", err);
+}
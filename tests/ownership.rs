@@ -0,0 +1,119 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/ownership.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// This is kept apart from the other tests, as any other test holding the
+// fixture open would stop it from being unloaded, and the tests here take
+// turns for the same reason.
+
+extern crate snek;
+
+mod common;
+
+use common::{fixture, owned_count};
+use snek::Snek;
+
+use std::sync::Mutex;
+
+static TURN: Mutex<()> = Mutex::new(());
+
+#[test]
+fn owned_symbol_keeps_library_loaded() {
+    let _turn = TURN.lock().unwrap();
+
+    let snek = Snek::load(fixture()).unwrap();
+    let symbol = snek.owned_symbol("count").unwrap();
+    assert_eq!(owned_count(&symbol), 1);
+
+    // The library stays loaded, so the count carries on
+    drop(snek);
+    assert_eq!(owned_count(&symbol), 2);
+
+    // Until the last symbol is dropped
+    drop(symbol);
+    let snek = Snek::load(fixture()).unwrap();
+    assert_eq!(owned_count(&snek.owned_symbol("count").unwrap()), 1);
+}
+
+#[test]
+fn strong_symbol_count_includes_clones() {
+    let _turn = TURN.lock().unwrap();
+
+    let snek = Snek::load(fixture()).unwrap();
+    assert_eq!(snek.strong_symbol_count(), 0);
+
+    let add = snek.owned_symbol("add").unwrap();
+    let hello = snek.owned_symbol("hello").unwrap();
+    let copy = add.clone();
+    assert_eq!(snek.strong_symbol_count(), 3);
+
+    drop(add);
+    drop(hello);
+    assert_eq!(snek.strong_symbol_count(), 1);
+    assert!(!copy.as_ptr().is_null());
+
+    // A borrowed symbol holds no count
+    let _symbol = snek.symbol("add").unwrap();
+    drop(copy);
+    assert_eq!(snek.strong_symbol_count(), 0);
+}
+
+#[test]
+fn force_close_unloads_once() {
+    let _turn = TURN.lock().unwrap();
+
+    let snek = Snek::load(fixture()).unwrap();
+    let symbol = snek.owned_symbol("count").unwrap();
+    assert_eq!(owned_count(&symbol), 1);
+
+    unsafe { snek.force_close() };
+
+    let snek = Snek::load(fixture()).unwrap();
+    let fresh = snek.owned_symbol("count").unwrap();
+    assert_eq!(owned_count(&fresh), 1);
+
+    // Dropping the last symbol from the closed library must not unload the
+    // library again from under the new handle
+    drop(symbol);
+    assert_eq!(owned_count(&fresh), 2);
+}
+
+#[test]
+fn close_unloads_and_reports_outstanding_symbols() {
+    let _turn = TURN.lock().unwrap();
+
+    let snek = Snek::load(fixture()).unwrap();
+    assert_eq!(owned_count(&snek.owned_symbol("count").unwrap()), 1);
+    snek.close().unwrap();
+
+    // The count starting again shows the library was unloaded
+    let snek = Snek::load(fixture()).unwrap();
+    let symbol = snek.owned_symbol("count").unwrap();
+    assert_eq!(owned_count(&symbol), 1);
+
+    match snek.close() {
+        Err(ref err @ snek::Error::UnloadError(_)) => assert!(err.to_string().contains("1 owned symbols"), "{}", err),
+        other => panic!("unexpected result {:?}", other)
+    }
+
+    // Still loaded until the symbol is dropped
+    assert_eq!(owned_count(&symbol), 2);
+    drop(symbol);
+
+    let snek = Snek::load(fixture()).unwrap();
+    assert_eq!(owned_count(&snek.owned_symbol("count").unwrap()), 1);
+}
@@ -0,0 +1,105 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/pin.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// A pinned fixture stays loaded for the rest of the process, so this is kept
+// apart from the other tests.
+#![cfg(any(unix, windows))]
+
+#[macro_use]
+extern crate snek;
+
+mod common;
+
+use common::count;
+use snek::{Handle, Snek};
+
+use std::fs;
+use std::os::raw::c_int;
+
+snek! {
+    Adder {
+        add: (a: c_int, b: c_int) -> c_int
+    }
+}
+
+#[test]
+fn pinned_module_stays_loaded() {
+    let snek = Snek::load(common::fixture()).unwrap();
+    assert_eq!(count(&snek), 1);
+
+    snek.pin().unwrap();
+    drop(snek);
+
+    // Without the pin, this would load the fixture afresh
+    let snek = Snek::load(common::fixture()).unwrap();
+    assert_eq!(count(&snek), 2);
+    assert!(snek.pin().is_ok());
+}
+
+#[test]
+fn pinned_symbols_outlive_the_snek() {
+    // A copy of its own, so that the count above is not disturbed
+    let scratch = common::scratch_dir("pinned");
+    let library = scratch.join(common::fixture().file_name().unwrap());
+    fs::copy(common::fixture(), &library).unwrap();
+
+    let snek = Snek::load(&library).unwrap();
+    let add = unsafe { snek.symbol("add").unwrap().with(|add: extern "C" fn(c_int, c_int) -> c_int| add) };
+
+    snek.pin().unwrap();
+    drop(snek);
+
+    assert_eq!(add(3, 4), 7);
+    assert!(Snek::open_existing(&library).is_ok());
+}
+
+#[test]
+fn pinned_unowned_handle_outlives_its_owner() {
+    let scratch = common::scratch_dir("pinned-unowned");
+    let library = scratch.join(common::fixture().file_name().unwrap());
+    fs::copy(common::fixture(), &library).unwrap();
+
+    let handle = Snek::load(&library).unwrap().into_raw_handle();
+
+    let unowned = unsafe { Snek::from_raw_handle_unowned(handle) }.unwrap();
+    unowned.pin().unwrap();
+    drop(unowned);
+
+    // The owner releasing its handle must not unload the library
+    drop(unsafe { Snek::from_raw_handle(handle) }.unwrap());
+    assert!(Snek::open_existing(&library).is_ok());
+}
+
+#[test]
+fn struct_from_handle_pins_its_own_library() {
+    let scratch = common::scratch_dir("pinned-struct");
+    let library = scratch.join(common::fixture().file_name().unwrap());
+    fs::copy(common::fixture(), &library).unwrap();
+
+    // A struct made from a handle has no path of its own to open again
+    let handle = snek::load_library(&library).unwrap();
+    let raw = handle.as_raw();
+
+    let adder = Adder::from_handle(handle).unwrap();
+    adder.as_ref().pin().unwrap();
+    drop(adder);
+
+    // Pinned, the struct leaves its reference open, so release it here
+    snek::drop_library(unsafe { Handle::from_raw(raw) }.unwrap());
+    assert!(Snek::open_existing(&library).is_ok());
+}
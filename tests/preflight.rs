@@ -0,0 +1,78 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/preflight.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+extern crate snek;
+
+mod common;
+
+use common::{fixture, scratch_dir};
+use snek::{inspect, PreflightMethod, PreflightOptions};
+
+use std::fs;
+
+#[test]
+fn fixture_is_loadable() {
+    let loaded = PreflightOptions::new().require("add").run(fixture()).unwrap();
+    assert_eq!(loaded.method(), PreflightMethod::Loaded);
+    assert!(loaded.is_loadable(), "{:?}", loaded);
+    assert_eq!(loaded.machine(), Some(inspect::Machine::current()));
+
+    let inspected = PreflightOptions::new().load(false).require("add").run(fixture()).unwrap();
+    assert_eq!(inspected.method(), PreflightMethod::Inspected);
+    assert!(inspected.is_loadable(), "{:?}", inspected);
+    assert!(inspected.missing_dependencies().is_empty());
+}
+
+#[test]
+fn missing_exports_are_listed() {
+    for &load in &[true, false] {
+        let report = PreflightOptions::new()
+            .load(load)
+            .require("add")
+            .require("not_a_symbol")
+            .run(fixture())
+            .unwrap();
+
+        assert!(!report.is_loadable());
+        assert_eq!(report.missing_exports(), ["not_a_symbol"]);
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn unresolved_symbols_fail_the_load() {
+    // The consumer only loads once something else has defined add
+    let report = snek::preflight(common::fixture_consumer()).unwrap();
+
+    assert!(!report.is_loadable());
+    assert!(report.error().is_some());
+}
+
+#[test]
+fn unreadable_files_are_reported() {
+    let scratch = scratch_dir("preflight");
+    let file = scratch.join("empty.so");
+    fs::write(&file, b"").unwrap();
+
+    let report = snek::preflight(&file).unwrap();
+    assert!(!report.is_loadable());
+    assert!(report.error().is_some());
+    assert_eq!(report.machine(), None);
+
+    assert!(PreflightOptions::new().load(false).run(&file).is_err());
+}
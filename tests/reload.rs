@@ -0,0 +1,42 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/reload.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+extern crate snek;
+
+mod common;
+
+use common::fixture;
+use snek::ReloadableSnek;
+
+#[test]
+fn shadow_copies_are_made_in_a_private_directory() {
+    let library = ReloadableSnek::load(fixture()).unwrap();
+    assert!(library.library().symbol("add").is_ok());
+
+    let dir = library.loaded_path().parent().unwrap().to_owned();
+    assert_ne!(dir, std::env::temp_dir());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(dir.metadata().unwrap().permissions().mode() & 0o777, 0o700);
+    }
+
+    drop(library);
+    assert!(!dir.exists());
+}
@@ -0,0 +1,65 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/renamed.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// The macros must only refer to the crate through `$crate`, so that they work
+// when the crate is imported under another name and without `#[macro_use]`.
+
+extern crate snek as dyn_load;
+
+include!("common/fixture.rs");
+
+use std::os::raw::{c_char, c_int};
+use std::str::Utf8Error;
+
+dyn_load::snek! {
+    Example {
+        add: (x: c_int, y: c_int) -> c_int
+    }
+}
+
+dyn_load::snek! {
+    #[deny_non_ffi]
+    #[load_error(ExampleLoadError)]
+    Checked {
+        add: (x: c_int, y: c_int) -> c_int,
+        name_of: (id: c_int) -> *mut c_char as Result<String, Utf8Error> => free_string
+    }
+
+    init fixture_init;
+    vtable CheckedVtable;
+}
+
+dyn_load::snek! {
+    #[fallible_strings]
+    FallibleStrings {
+        string_length: (string: &str as *const c_char) -> usize
+    }
+}
+
+#[test]
+fn macro_works_through_a_renamed_crate() {
+    let example = Example::load(fixture()).unwrap();
+    assert_eq!(unsafe { example.add(3, 7) }, 10);
+
+    let checked = Checked::load(fixture()).unwrap();
+    assert_eq!(unsafe { checked.add(3, 7) }, 10);
+    assert!(unsafe { checked.name_of(1) }.is_ok());
+
+    let strings = FallibleStrings::load(fixture()).unwrap();
+    assert_eq!(unsafe { strings.string_length("snek") }.unwrap(), 4);
+}
@@ -0,0 +1,111 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/scan.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+extern crate libc;
+extern crate snek;
+
+mod common;
+
+use common::scratch_dir;
+use snek::{scan_dir, ScanOptions};
+
+use std::fs;
+
+fn names(results: &[snek::ScanResult]) -> Vec<String> {
+    results.iter().map(|result| result.path.file_name().unwrap().to_string_lossy().into_owned()).collect()
+}
+
+#[test]
+fn question_mark_matches_a_multibyte_character() {
+    let dir = scratch_dir("scan-multibyte");
+    for name in &["libé.so", "libab.so", "lib.so"] {
+        fs::write(dir.join(name), b"").unwrap();
+    }
+
+    let results = scan_dir(&dir, ScanOptions::new().extensions(&[]).pattern("lib?.so"));
+    assert_eq!(names(&results), ["libé.so"]);
+}
+
+// Other unix filesystems may not allow names which are not valid UTF-8
+#[cfg(target_os = "linux")]
+#[test]
+fn question_mark_matches_a_byte_which_is_not_unicode() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = scratch_dir("scan-bytes");
+    fs::write(dir.join(OsStr::from_bytes(b"lib\xff.so")), b"").unwrap();
+    fs::write(dir.join(OsStr::from_bytes(b"lib\xff\xfe.so")), b"").unwrap();
+
+    let results = scan_dir(&dir, ScanOptions::new().extensions(&[]).pattern("lib?.so"));
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path.file_name().unwrap().as_bytes(), b"lib\xff.so");
+}
+
+#[test]
+fn many_stars_do_not_backtrack_exponentially() {
+    let dir = scratch_dir("scan-stars");
+    let name = "a".repeat(100);
+    fs::write(dir.join(&name), b"").unwrap();
+    fs::write(dir.join(format!("{}b", name)), b"").unwrap();
+
+    let results = scan_dir(&dir, ScanOptions::new().extensions(&[]).pattern("a*a*a*a*a*a*a*a*a*a*a*a*b"));
+    assert_eq!(names(&results), [format!("{}b", name)]);
+}
+
+#[cfg(unix)]
+#[test]
+fn unreadable_directory_is_ordered_and_stops_the_scan() {
+    use common::fixture;
+    use std::env::consts::DLL_EXTENSION;
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = scratch_dir("scan-unreadable");
+    fs::copy(fixture(), dir.join(format!("a.{}", DLL_EXTENSION))).unwrap();
+    fs::write(dir.join(format!("c.{}", DLL_EXTENSION)), b"").unwrap();
+
+    let unreadable = dir.join("b");
+    fs::create_dir(&unreadable).unwrap();
+    fs::set_permissions(&unreadable, fs::Permissions::from_mode(0o000)).unwrap();
+
+    let results = scan_dir(&dir, ScanOptions::new().depth(1).stop_on_error(true));
+    fs::set_permissions(&unreadable, fs::Permissions::from_mode(0o700)).unwrap();
+
+    // Root can read the directory anyway, so the scan stops at the next file
+    // instead, which is no library
+    let failed = if unsafe { libc::geteuid() } == 0 {
+        format!("c.{}", DLL_EXTENSION)
+    } else {
+        "b".into()
+    };
+
+    assert_eq!(names(&results), [format!("a.{}", DLL_EXTENSION), failed]);
+    assert!(results[0].result.is_ok());
+    assert!(results[1].result.is_err());
+}
+
+#[test]
+fn missing_directory_is_a_failed_result() {
+    let scratch = scratch_dir("scan-missing");
+    let dir = scratch.join("missing");
+
+    let results = scan_dir(&dir, &ScanOptions::new());
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, dir);
+    assert!(results[0].result.is_err());
+}
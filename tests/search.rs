@@ -0,0 +1,94 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/search.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// The consumer imports add from the fixture on Windows, and a module which
+// is already loaded satisfies the import whatever the search order, so this
+// is kept apart from the other tests, none of which load the fixture, and
+// these tests take turns so that neither sees the fixture the other loaded.
+
+#![cfg(windows)]
+
+extern crate snek;
+
+mod common;
+
+use common::{fixture, fixture_consumer, scratch_dir};
+use snek::{LoadFlags, Snek};
+use snek::windows::DllDirectory;
+
+use std::fs;
+use std::os::raw::c_int;
+use std::sync::Mutex;
+
+static TURN: Mutex<()> = Mutex::new(());
+
+#[test]
+fn private_dependencies_need_the_search_flags() {
+    let _turn = TURN.lock().unwrap_or_else(|err| err.into_inner());
+
+    // The consumer and the fixture it depends on, together in a directory
+    // which is in no search path
+    let dir = scratch_dir("search");
+    let consumer = dir.join(fixture_consumer().file_name().unwrap());
+    fs::copy(fixture_consumer(), &consumer).unwrap();
+    fs::copy(fixture(), dir.join(fixture().file_name().unwrap())).unwrap();
+
+    let err = Snek::load(&consumer).unwrap_err();
+    assert!(err.is_not_found() || err.kind() == snek::ErrorKind::MissingDependency, "{:?}", err);
+
+    for flags in &[LoadFlags::new().search_dll_load_dir().search_default_dirs(), LoadFlags::new().altered_search_path()] {
+        let snek = Snek::load_with_flags(&consumer, *flags).unwrap();
+        let add_twice = snek.symbol("add_twice").unwrap();
+        assert_eq!(unsafe { add_twice.with(|f: extern "C" fn(c_int, c_int) -> c_int| f(3, 4)) }, 14);
+    }
+
+    // The altered search path cannot be combined with the new search flags,
+    // which the system rejects with ERROR_INVALID_PARAMETER
+    let flags = LoadFlags::new().altered_search_path().search_dll_load_dir();
+    let err = Snek::load_with_flags(&consumer, flags).unwrap_err();
+    assert!(err.to_string().contains("(os error 87)"), "{}", err);
+}
+
+#[test]
+fn dll_directories_are_searched_until_the_guard_is_dropped() {
+    let _turn = TURN.lock().unwrap_or_else(|err| err.into_inner());
+
+    // The consumer in one directory, and the fixture it depends on only in
+    // another
+    let consumer_dir = scratch_dir("dll-directory-consumer");
+    let fixture_dir = scratch_dir("dll-directory-fixture");
+    let consumer = consumer_dir.join(fixture_consumer().file_name().unwrap());
+    fs::copy(fixture_consumer(), &consumer).unwrap();
+    fs::copy(fixture(), fixture_dir.join(fixture().file_name().unwrap())).unwrap();
+
+    let flags = LoadFlags::new().search_default_dirs();
+
+    let guard = DllDirectory::add(&fixture_dir).unwrap();
+    let snek = Snek::load_with_flags(&consumer, flags).unwrap();
+    let add_twice = snek.symbol("add_twice").unwrap();
+    assert_eq!(unsafe { add_twice.with(|f: extern "C" fn(c_int, c_int) -> c_int| f(3, 4)) }, 14);
+    drop(snek);
+
+    // The guard can be dropped on any thread
+    std::thread::spawn(move || drop(guard)).join().unwrap();
+
+    assert!(Snek::load_with_flags(&consumer, flags).is_err());
+
+    let err = DllDirectory::add("relative").unwrap_err();
+    assert_eq!(err.kind(), snek::ErrorKind::InvalidInput);
+}
@@ -0,0 +1,85 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/statics.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+#![cfg(feature = "static-registry")]
+
+#[macro_use]
+extern crate snek;
+
+mod common;
+
+use snek::{statics, Snek};
+
+use std::os::raw::c_int;
+
+snek! {
+    Fixture {
+        add: (a: c_int, b: c_int) -> c_int,
+        version: () -> c_int
+    }
+}
+
+extern "C" fn add(a: c_int, b: c_int) -> c_int {
+    a + b
+}
+
+extern "C" fn version() -> c_int {
+    -1
+}
+
+#[test]
+fn macro_wrapper_uses_registered_library() {
+    register_static_library! {
+        "libsnek-static-macro.so" {
+            add,
+            version
+        }
+    }
+
+    let fixture = Fixture::load("libsnek-static-macro.so").unwrap();
+    unsafe {
+        assert_eq!(fixture.add(3, 7), 10);
+        assert_eq!(fixture.version(), -1);
+    }
+
+    // Unregistered paths are still loaded by the platform
+    let fixture = Fixture::load(common::fixture()).unwrap();
+    assert_eq!(unsafe { fixture.version() }, 2);
+}
+
+#[test]
+fn missing_static_symbol_is_not_found() {
+    register_static_library! {
+        "libsnek-static-missing.so" {
+            add
+        }
+    }
+
+    assert!(statics::is_registered("libsnek-static-missing.so"));
+    assert!(Fixture::load("libsnek-static-missing.so").is_err());
+
+    let snek = Snek::load("libsnek-static-missing.so").unwrap();
+    let err = snek.symbol("version").unwrap_err();
+    assert!(err.is_not_found(), "{:?}", err);
+}
+
+#[test]
+fn unregistered_path_is_not_found() {
+    assert!(!statics::is_registered("libsnek-static-unregistered.so"));
+    assert!(Snek::load("libsnek-static-unregistered.so").unwrap_err().is_not_found());
+}
@@ -0,0 +1,117 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/testing.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+#![cfg(feature = "testing")]
+
+#[macro_use]
+extern crate snek;
+
+use snek::ResolverPosition;
+use snek::testing::SymbolTable;
+
+use std::os::raw::c_int;
+
+snek! {
+    Example {
+        add: (a: c_int, b: c_int) -> c_int,
+        version: () -> c_int
+    }
+}
+
+extern "C" fn fake_add(a: c_int, b: c_int) -> c_int {
+    a * b
+}
+
+extern "C" fn fake_version() -> c_int {
+    7
+}
+
+static ANSWER: c_int = 42;
+
+#[test]
+fn macro_struct_uses_table() {
+    let handle = SymbolTable::new()
+        .insert("add", fake_add as *mut _)
+        .insert("version", fake_version as *mut _)
+        .handle();
+
+    let example = Example::from_handle(handle).unwrap();
+    unsafe {
+        assert_eq!(example.add(3, 7), 21);
+        assert_eq!(example.version(), 7);
+    }
+}
+
+#[test]
+fn missing_symbol_is_not_found() {
+    let handle = SymbolTable::new().insert("add", fake_add as *mut _).handle();
+
+    match Example::from_handle(handle) {
+        Err(ref err @ snek::Error::SymbolLoadError(_)) => assert!(err.is_not_found()),
+        Err(err) => panic!("unexpected error {:?}", err),
+        Ok(_) => panic!("loaded a symbol missing from the table")
+    }
+}
+
+#[test]
+fn snek_uses_table() {
+    let mut table = SymbolTable::new();
+    table.insert("ANSWER", &ANSWER as *const c_int as *mut _);
+
+    let snek = table.snek();
+    let answer = snek.symbol("ANSWER").unwrap();
+    assert_eq!(unsafe { *(answer.as_ptr() as *const c_int) }, 42);
+
+    // Each Snek has its own copy, freed when it is dropped
+    let second = table.snek();
+    drop(snek);
+    assert!(second.symbol("ANSWER").is_ok());
+    assert!(second.symbol("add").unwrap_err().is_not_found());
+}
+
+#[test]
+fn snek_asks_the_table_once_per_name() {
+    let mut table = SymbolTable::new();
+    table.insert("add", fake_add as *mut _);
+
+    let mut snek = table.snek();
+    for _ in 0..3 {
+        assert_eq!(snek.symbol("add").unwrap().as_ptr(), fake_add as *mut _);
+    }
+
+    assert_eq!(snek.symbol(&b"add\0"[..]).unwrap().as_ptr(), fake_add as *mut _);
+    assert_eq!(table.lookups(), 1);
+
+    // Missing names are asked for again
+    assert!(snek.symbol("version").is_err());
+    assert!(snek.symbol("version").is_err());
+    assert_eq!(table.lookups(), 3);
+
+    // Resolvers are still consulted first, but the table's answer is kept
+    snek.push_resolver(ResolverPosition::Before, Box::new(|_| None));
+    assert!(snek.symbol("add").is_ok());
+    assert_eq!(table.lookups(), 3);
+}
+
+#[test]
+fn table_cannot_be_pinned() {
+    let snek = SymbolTable::new().insert("add", fake_add as *mut _).snek();
+
+    assert!(snek.pin().unwrap_err().is_unsupported());
+    assert!(snek.symbol("add").is_ok());
+}
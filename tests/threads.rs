@@ -0,0 +1,82 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/threads.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+extern crate snek;
+
+mod common;
+
+use common::fixture;
+use snek::{Handle, OwnedSymbol, RawSymbol, SharedSnek, SharedSymbol, Snek, Symbol};
+
+use std::thread;
+use std::os::raw::c_int;
+
+fn assert_send_sync<T>() where T: Send + Sync {}
+
+#[test]
+fn libraries_and_symbols_are_send_and_sync() {
+    assert_send_sync::<Snek>();
+    assert_send_sync::<SharedSnek>();
+    assert_send_sync::<Handle>();
+    assert_send_sync::<Symbol<'static>>();
+    assert_send_sync::<OwnedSymbol>();
+    assert_send_sync::<SharedSymbol>();
+    assert_send_sync::<RawSymbol>();
+}
+
+#[test]
+fn libraries_loaded_on_one_thread_are_called_from_another() {
+    let snek = thread::spawn(|| Snek::load(fixture()).unwrap()).join().unwrap();
+
+    let add = snek.symbol("add").unwrap();
+    assert_eq!(unsafe { add.with(|add: extern "C" fn(c_int, c_int) -> c_int| add(3, 4)) }, 7);
+
+    // Borrowed symbols are resolved and called on each thread in turn
+    thread::scope(|scope| {
+        for offset in 0..4 {
+            let snek = &snek;
+            scope.spawn(move || {
+                let add = snek.symbol("add").unwrap();
+                assert_eq!(unsafe { add.with(|add: extern "C" fn(c_int, c_int) -> c_int| add(offset, 4)) }, offset + 4);
+            });
+        }
+    });
+
+    // And the last owned symbol unloads the library on whichever thread drops it
+    let add = snek.owned_symbol("add").unwrap();
+    drop(snek);
+    thread::spawn(move || assert_eq!(unsafe { add.with(|add: extern "C" fn(c_int, c_int) -> c_int| add(1, 2)) }, 3)).join().unwrap();
+}
+
+#[test]
+fn shared_libraries_are_shared_between_threads() {
+    let shared = Snek::load(fixture()).unwrap().into_shared();
+
+    let workers = (0..4).map(|offset| {
+        let shared = shared.clone();
+        thread::spawn(move || {
+            let add = shared.symbol("add").unwrap();
+            unsafe { add.with(|add: extern "C" fn(c_int, c_int) -> c_int| add(offset, 10)) }
+        })
+    }).collect::<Vec<_>>();
+
+    drop(shared);
+
+    let sums = workers.into_iter().map(|worker| worker.join().unwrap()).collect::<Vec<_>>();
+    assert_eq!(sums, [10, 11, 12, 13]);
+}
@@ -0,0 +1,98 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/tls.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// The fixture only exports a thread-local variable where it can define one
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+extern crate snek;
+
+mod common;
+
+use common::fixture;
+use snek::Snek;
+
+use std::thread;
+use std::sync::{Arc, Barrier};
+use std::os::raw::c_int;
+
+fn set_errno(snek: &Snek, value: c_int) {
+    unsafe { snek.symbol("set_errno").unwrap().with(|set: extern "C" fn(c_int)| set(value)) }
+}
+
+#[test]
+fn each_thread_sees_its_own_instance() {
+    const THREADS: usize = 4;
+    let barrier = Arc::new(Barrier::new(THREADS));
+
+    let threads = (0..THREADS).map(|index| {
+        let barrier = barrier.clone();
+
+        thread::spawn(move || {
+            let snek = Snek::load(fixture()).unwrap();
+            let errno = unsafe { snek.tls_data::<c_int>("fixture_errno") }.unwrap();
+            assert_eq!(errno.read().unwrap(), 0);
+
+            // Written by the library, then read through the accessor
+            set_errno(&snek, index as c_int + 1);
+            barrier.wait();
+            assert_eq!(errno.read().unwrap(), index as c_int + 1);
+
+            // Written through the accessor, then read back once every thread has
+            // written its own
+            errno.write(index as c_int + 100).unwrap();
+            barrier.wait();
+            assert_eq!(errno.read().unwrap(), index as c_int + 100);
+
+            let address = errno.as_ptr().unwrap() as usize;
+            barrier.wait();
+            address
+        })
+    }).collect::<Vec<_>>();
+
+    let mut addresses = threads.into_iter().map(|thread| thread.join().unwrap()).collect::<Vec<_>>();
+    addresses.sort();
+    addresses.dedup();
+    assert_eq!(addresses.len(), THREADS);
+}
+
+#[test]
+fn instance_is_not_shared_with_new_threads() {
+    let snek = Snek::load(fixture()).unwrap();
+    let errno = unsafe { snek.tls_data::<c_int>("fixture_errno") }.unwrap();
+
+    set_errno(&snek, 7);
+    let here = errno.as_ptr().unwrap() as usize;
+
+    // A new thread starts with its own zeroed instance
+    let (there, value) = thread::scope(|scope| scope.spawn(|| {
+        let snek = Snek::load(fixture()).unwrap();
+        let errno = unsafe { snek.tls_data::<c_int>("fixture_errno") }.unwrap();
+        (errno.as_ptr().unwrap() as usize, errno.read().unwrap())
+    }).join().unwrap());
+
+    assert_ne!(here, there);
+    assert_eq!(value, 0);
+    assert_eq!(errno.read().unwrap(), 7);
+}
+
+#[test]
+fn missing_variable_fails_early() {
+    let snek = Snek::load(fixture()).unwrap();
+    let err = unsafe { snek.tls_data::<c_int>("fixture_errnoo") }.unwrap_err();
+    assert!(err.is_not_found(), "{:?}", err);
+}
@@ -0,0 +1,83 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/unload.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// This is kept apart from the other tests, as any other test holding the
+// fixture open would stop it from being unloaded.
+
+#[macro_use]
+extern crate snek;
+
+mod common;
+
+use snek::Snek;
+
+use std::os::raw::c_int;
+
+snek! {
+    FixtureV2 {
+        version: () -> c_int,
+        added_in_v2: () -> c_int
+    }
+}
+
+fn count(snek: &Snek) -> usize {
+    unsafe { snek.symbol("count").unwrap().with(|count: extern "C" fn() -> usize| count()) }
+}
+
+#[test]
+fn drop_unloads_library() {
+    let path = common::fixture();
+
+    let snek = Snek::load(&path).unwrap();
+    assert_eq!(count(&snek), 1);
+    assert_eq!(count(&snek), 2);
+
+    // A second handle keeps the library loaded when the first is dropped
+    let second = Snek::load(&path).unwrap();
+    drop(snek);
+    assert_eq!(count(&second), 3);
+
+    drop(second);
+
+    // Without unloading, the same copy is found again
+    let snek = Snek::load(&path).unwrap();
+    assert_eq!(count(&snek), if Snek::unload_is_noop() { 4 } else { 1 });
+}
+
+#[test]
+fn failed_macro_load_unloads_library() {
+    let path = common::fixture_v1();
+
+    let snek = Snek::load(&path).unwrap();
+    assert_eq!(count(&snek), 1);
+
+    // Each of these opens the library before failing to find a symbol
+    for _ in 0..4 {
+        assert!(FixtureV2::load(&path).is_err());
+    }
+
+    drop(snek);
+
+    let snek = Snek::load(&path).unwrap();
+    assert_eq!(count(&snek), if Snek::unload_is_noop() { 2 } else { 1 });
+}
+
+#[test]
+fn unload_is_only_a_noop_on_musl() {
+    assert_eq!(Snek::unload_is_noop(), cfg!(target_env = "musl"));
+}
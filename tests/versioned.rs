@@ -0,0 +1,124 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/versioned.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+extern crate snek;
+
+mod common;
+
+use common::ScratchDir;
+
+use snek::{env, Error, Snek};
+
+use std::fs;
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::os::raw::c_int;
+use std::path::{Path, PathBuf};
+
+/// Returns the unversioned path of a library installed in a scratch directory
+/// as the first version of the fixture under `.so.1` and the second under
+/// `.so.2`, without the unversioned name itself, along with the directory.
+fn installed(test: &str) -> (ScratchDir, PathBuf) {
+    let dir = common::scratch_dir(test);
+    let path = dir.join(format!("{}versioned{}", DLL_PREFIX, DLL_SUFFIX));
+
+    fs::copy(common::fixture_v1(), with_major(&path, 1)).unwrap();
+    fs::copy(common::fixture(), with_major(&path, 2)).unwrap();
+    (dir, path)
+}
+
+fn with_major(path: &Path, major: u32) -> PathBuf {
+    PathBuf::from(format!("{}.{}", path.display(), major))
+}
+
+fn version(snek: &Snek) -> c_int {
+    unsafe { snek.symbol("version").unwrap().with(|version: extern "C" fn() -> c_int| version()) }
+}
+
+#[test]
+fn exact_name_is_not_versioned() {
+    let (_dir, path) = installed("exact");
+    assert!(Snek::load(&path).is_err());
+}
+
+#[test]
+fn given_majors_are_tried_highest_first() {
+    let (_dir, path) = installed("given");
+
+    assert_eq!(version(&Snek::load_versioned(&path, &[1]).unwrap()), 1);
+    assert_eq!(version(&Snek::load_versioned(&path, &[1, 2, 3]).unwrap()), 2);
+}
+
+#[test]
+fn installed_majors_are_found_without_a_list() {
+    let (_dir, path) = installed("scanned");
+    assert_eq!(version(&Snek::load_versioned(&path, &[]).unwrap()), 2);
+}
+
+#[test]
+fn exact_name_is_preferred() {
+    let (_dir, path) = installed("preferred");
+    fs::copy(common::fixture_v1(), &path).unwrap();
+
+    assert_eq!(version(&Snek::load_versioned(&path, &[2]).unwrap()), 1);
+}
+
+#[test]
+fn failure_lists_every_name_tried() {
+    let (_dir, path) = installed("failure");
+
+    match Snek::load_versioned(&path, &[4, 3]) {
+        Err(Error::CandidatesError(candidates)) => {
+            let tried = candidates.iter().map(|candidate| candidate.path().to_owned()).collect::<Vec<_>>();
+            assert_eq!(tried, vec![path.clone(), with_major(&path, 4), with_major(&path, 3)]);
+        },
+
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+#[test]
+fn logical_name_falls_back_to_soname() {
+    // libc.so is a linker script installed for development, so only
+    // libc.so.6 can actually be loaded
+    let names = env::library_names("c");
+    assert_eq!(names[0], PathBuf::from("libc.so"));
+    assert!(names.contains(&PathBuf::from("libc.so.6")), "{:?}", names);
+
+    assert!(Snek::load_name("c").is_ok());
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+fn logical_name_tries_dylibs_before_frameworks() {
+    let names = env::library_names("z");
+    assert_eq!(names.first(), Some(&PathBuf::from("libz.dylib")));
+    assert_eq!(&names[names.len() - 2..], &[PathBuf::from("libz.so"), PathBuf::from("z.framework/z")]);
+}
+
+#[test]
+fn logical_name_failure_lists_every_name_tried() {
+    match Snek::load_name("snekdoesnotexist") {
+        Err(Error::CandidatesError(candidates)) => {
+            let tried = candidates.iter().map(|candidate| candidate.path().to_owned()).collect::<Vec<_>>();
+            assert_eq!(tried, env::library_names("snekdoesnotexist"));
+        },
+
+        other => panic!("unexpected result {:?}", other)
+    }
+}
@@ -0,0 +1,51 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-snek/tests/versions.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+extern crate snek;
+
+mod common;
+
+use snek::Snek;
+
+#[cfg(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64"))]
+#[test]
+fn versioned_symbols_are_found() {
+    let libc = Snek::load("libc.so.6").unwrap();
+
+    let current = libc.symbol_version("pthread_cond_wait", "GLIBC_2.3.2").unwrap();
+    let old = libc.symbol_version("pthread_cond_wait", "GLIBC_2.2.5").unwrap();
+    assert_eq!(current.as_ptr(), libc.symbol("pthread_cond_wait").unwrap().as_ptr());
+    assert_ne!(current.as_ptr(), old.as_ptr());
+
+    let err = libc.symbol_version("pthread_cond_wait", "GLIBC_0.0").unwrap_err();
+    assert!(err.is_not_found(), "{:?}", err);
+    assert_eq!(err.symbol(), Some("pthread_cond_wait"));
+
+    let handle = snek::load_library("libc.so.6").unwrap();
+    assert_eq!(unsafe { snek::load_symbol_version(&handle, "pthread_cond_wait", "GLIBC_2.2.5") }.unwrap().as_ptr(), old.as_ptr());
+    snek::drop_library(handle);
+}
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+#[test]
+fn versioned_symbols_are_unsupported() {
+    let snek = Snek::load(common::fixture()).unwrap();
+
+    let err = snek.symbol_version("add", "V1").unwrap_err();
+    assert!(err.is_unsupported(), "{:?}", err);
+}